@@ -0,0 +1,75 @@
+CREATE TABLE accounts (
+  id INTEGER PRIMARY KEY NOT NULL,
+  code TEXT NOT NULL UNIQUE,
+  name TEXT NOT NULL,
+  kana TEXT NOT NULL DEFAULT '',
+  account_type TEXT NOT NULL
+    CHECK (account_type IN ('asset', 'liability', 'equity', 'revenue', 'expense')),
+  tax_category TEXT NOT NULL DEFAULT 'out_of_scope'
+    CHECK (tax_category IN ('taxable_10', 'reduced_8', 'exempt', 'non_taxable', 'out_of_scope')),
+  active BOOLEAN NOT NULL DEFAULT 1,
+  parent_id INTEGER REFERENCES accounts (id),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX accounts_parent_id ON accounts (parent_id);
+
+-- Default chart of accounts for a sole proprietor filing a blue return.
+INSERT INTO accounts (code, name, kana, account_type, tax_category) VALUES
+  ('101', '現金', 'ゲンキン', 'asset', 'out_of_scope'),
+  ('111', '普通預金', 'フツウヨキン', 'asset', 'out_of_scope'),
+  ('112', '当座預金', 'トウザヨキン', 'asset', 'out_of_scope'),
+  ('113', '定期預金', 'テイキヨキン', 'asset', 'out_of_scope'),
+  ('121', '受取手形', 'ウケトリテガタ', 'asset', 'out_of_scope'),
+  ('122', '売掛金', 'ウリカケキン', 'asset', 'out_of_scope'),
+  ('131', '商品', 'ショウヒン', 'asset', 'out_of_scope'),
+  ('141', '前払金', 'マエバライキン', 'asset', 'out_of_scope'),
+  ('142', '貸付金', 'カシツケキン', 'asset', 'out_of_scope'),
+  ('143', '仮払金', 'カリバライキン', 'asset', 'out_of_scope'),
+  ('144', '仮払消費税', 'カリバライショウヒゼイ', 'asset', 'out_of_scope'),
+  ('151', '建物', 'タテモノ', 'asset', 'out_of_scope'),
+  ('152', '建物附属設備', 'タテモノフゾクセツビ', 'asset', 'out_of_scope'),
+  ('153', '機械装置', 'キカイソウチ', 'asset', 'out_of_scope'),
+  ('154', '車両運搬具', 'シャリョウウンパング', 'asset', 'out_of_scope'),
+  ('155', '工具器具備品', 'コウグキグビヒン', 'asset', 'out_of_scope'),
+  ('156', '一括償却資産', 'イッカツショウキャクシサン', 'asset', 'out_of_scope'),
+  ('157', '土地', 'トチ', 'asset', 'out_of_scope'),
+  ('161', '敷金', 'シキキン', 'asset', 'out_of_scope'),
+  ('191', '事業主貸', 'ジギョウヌシカシ', 'asset', 'out_of_scope'),
+  ('201', '支払手形', 'シハライテガタ', 'liability', 'out_of_scope'),
+  ('202', '買掛金', 'カイカケキン', 'liability', 'out_of_scope'),
+  ('211', '借入金', 'カリイレキン', 'liability', 'out_of_scope'),
+  ('212', '未払金', 'ミバライキン', 'liability', 'out_of_scope'),
+  ('213', '前受金', 'マエウケキン', 'liability', 'out_of_scope'),
+  ('214', '預り金', 'アズカリキン', 'liability', 'out_of_scope'),
+  ('215', '仮受消費税', 'カリウケショウヒゼイ', 'liability', 'out_of_scope'),
+  ('216', '未払消費税等', 'ミバライショウヒゼイトウ', 'liability', 'out_of_scope'),
+  ('291', '事業主借', 'ジギョウヌシカリ', 'equity', 'out_of_scope'),
+  ('301', '元入金', 'モトイレキン', 'equity', 'out_of_scope'),
+  ('401', '売上高', 'ウリアゲダカ', 'revenue', 'taxable_10'),
+  ('402', '家事消費等', 'カジショウヒトウ', 'revenue', 'taxable_10'),
+  ('411', '雑収入', 'ザツシュウニュウ', 'revenue', 'taxable_10'),
+  ('501', '仕入高', 'シイレダカ', 'expense', 'taxable_10'),
+  ('511', '租税公課', 'ソゼイコウカ', 'expense', 'out_of_scope'),
+  ('512', '荷造運賃', 'ニヅクリウンチン', 'expense', 'taxable_10'),
+  ('513', '水道光熱費', 'スイドウコウネツヒ', 'expense', 'taxable_10'),
+  ('514', '旅費交通費', 'リョヒコウツウヒ', 'expense', 'taxable_10'),
+  ('515', '通信費', 'ツウシンヒ', 'expense', 'taxable_10'),
+  ('516', '広告宣伝費', 'コウコクセンデンヒ', 'expense', 'taxable_10'),
+  ('517', '接待交際費', 'セッタイコウサイヒ', 'expense', 'taxable_10'),
+  ('518', '損害保険料', 'ソンガイホケンリョウ', 'expense', 'non_taxable'),
+  ('519', '修繕費', 'シュウゼンヒ', 'expense', 'taxable_10'),
+  ('520', '消耗品費', 'ショウモウヒンヒ', 'expense', 'taxable_10'),
+  ('521', '減価償却費', 'ゲンカショウキャクヒ', 'expense', 'out_of_scope'),
+  ('522', '福利厚生費', 'フクリコウセイヒ', 'expense', 'taxable_10'),
+  ('523', '給料賃金', 'キュウリョウチンギン', 'expense', 'out_of_scope'),
+  ('524', '外注工賃', 'ガイチュウコウチン', 'expense', 'taxable_10'),
+  ('525', '利子割引料', 'リシワリビキリョウ', 'expense', 'non_taxable'),
+  ('526', '地代家賃', 'チダイヤチン', 'expense', 'taxable_10'),
+  ('527', '貸倒金', 'カシダオレキン', 'expense', 'taxable_10'),
+  ('528', '支払手数料', 'シハライテスウリョウ', 'expense', 'taxable_10'),
+  ('529', '新聞図書費', 'シンブントショヒ', 'expense', 'taxable_10'),
+  ('530', '会議費', 'カイギヒ', 'expense', 'taxable_10'),
+  ('531', '研修費', 'ケンシュウヒ', 'expense', 'taxable_10'),
+  ('599', '雑費', 'ザッピ', 'expense', 'taxable_10');
@@ -0,0 +1,24 @@
+CREATE TABLE journal_entries (
+  id INTEGER PRIMARY KEY NOT NULL,
+  entry_date DATE NOT NULL,
+  memo TEXT NOT NULL DEFAULT '',
+  voided_at DATETIME,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX journal_entries_entry_date ON journal_entries (entry_date);
+
+-- Amounts are whole yen; each line is either a debit or a credit.
+CREATE TABLE journal_lines (
+  id INTEGER PRIMARY KEY NOT NULL,
+  entry_id INTEGER NOT NULL REFERENCES journal_entries (id) ON DELETE CASCADE,
+  line_no INTEGER NOT NULL,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  side TEXT NOT NULL CHECK (side IN ('debit', 'credit')),
+  amount BIGINT NOT NULL CHECK (amount > 0),
+  description TEXT NOT NULL DEFAULT '',
+  UNIQUE (entry_id, line_no)
+);
+
+CREATE INDEX journal_lines_account_id ON journal_lines (account_id);
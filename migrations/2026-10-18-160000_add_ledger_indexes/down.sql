@@ -0,0 +1,3 @@
+DROP INDEX journal_entries_posted_date;
+DROP INDEX journal_lines_account_entry;
+CREATE INDEX journal_lines_account_id ON journal_lines (account_id);
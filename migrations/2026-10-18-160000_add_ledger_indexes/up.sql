@@ -0,0 +1,7 @@
+-- Covering index for per-account balances and ledgers: lookups by account
+-- never need to visit the table rows.
+DROP INDEX journal_lines_account_id;
+CREATE INDEX journal_lines_account_entry ON journal_lines (account_id, entry_id, side, amount);
+
+-- Ledgers only read posted (non-void) entries.
+CREATE INDEX journal_entries_posted_date ON journal_entries (entry_date, id) WHERE voided_at IS NULL;
@@ -0,0 +1,3 @@
+DROP INDEX journal_entries_opening_date;
+ALTER TABLE journal_entries DROP COLUMN kind;
+DROP TABLE fiscal_years;
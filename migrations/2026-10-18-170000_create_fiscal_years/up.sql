@@ -0,0 +1,20 @@
+CREATE TABLE fiscal_years (
+  id INTEGER PRIMARY KEY NOT NULL,
+  start_date DATE NOT NULL UNIQUE,
+  end_date DATE NOT NULL,
+  state TEXT NOT NULL DEFAULT 'open' CHECK (state IN ('open', 'closed')),
+  closing_entry_id INTEGER REFERENCES journal_entries (id),
+  opening_entry_id INTEGER REFERENCES journal_entries (id),
+  closed_at DATETIME,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (start_date <= end_date)
+);
+
+-- Closing entries (決算振替) are kept for the audit trail only; opening
+-- entries (開始仕訳) restart balances at the beginning of a fiscal year.
+ALTER TABLE journal_entries ADD COLUMN kind TEXT NOT NULL DEFAULT 'regular'
+  CHECK (kind IN ('regular', 'closing', 'opening'));
+
+CREATE INDEX journal_entries_opening_date ON journal_entries (entry_date)
+  WHERE kind = 'opening' AND voided_at IS NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE journal_lines DROP COLUMN tax_included;
+ALTER TABLE journal_lines DROP COLUMN tax_amount;
+ALTER TABLE journal_lines DROP COLUMN tax_category;
+DROP TABLE tax_settings;
@@ -0,0 +1,19 @@
+-- Single-row table. 税込経理 (inclusive) is the default so that existing
+-- books keep their amounts; switch to 税抜経理 (exclusive) to have tax split
+-- into 仮払消費税 / 仮受消費税.
+CREATE TABLE tax_settings (
+  id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+  method TEXT NOT NULL DEFAULT 'inclusive' CHECK (method IN ('inclusive', 'exclusive')),
+  rounding TEXT NOT NULL DEFAULT 'floor' CHECK (rounding IN ('floor', 'round', 'ceil')),
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO tax_settings (id) VALUES (1);
+
+-- Lines recorded before tax tracking are left out of scope. `tax_included`
+-- tells whether `amount` contains `tax_amount` (税込) or the tax was split
+-- off into its own line (税抜).
+ALTER TABLE journal_lines ADD COLUMN tax_category TEXT NOT NULL DEFAULT 'out_of_scope'
+  CHECK (tax_category IN ('taxable_10', 'reduced_8', 'exempt', 'non_taxable', 'out_of_scope'));
+ALTER TABLE journal_lines ADD COLUMN tax_amount BIGINT NOT NULL DEFAULT 0 CHECK (tax_amount >= 0);
+ALTER TABLE journal_lines ADD COLUMN tax_included BOOLEAN NOT NULL DEFAULT 0;
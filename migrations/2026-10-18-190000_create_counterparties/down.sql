@@ -0,0 +1,3 @@
+DROP INDEX journal_lines_counterparty;
+ALTER TABLE journal_lines DROP COLUMN counterparty_id;
+DROP TABLE counterparties;
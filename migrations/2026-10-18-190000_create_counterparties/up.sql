@@ -0,0 +1,19 @@
+CREATE TABLE counterparties (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  -- 登録番号: "T" followed by 13 digits.
+  registration_number TEXT UNIQUE,
+  address TEXT NOT NULL DEFAULT '',
+  -- 適格請求書発行事業者
+  qualified BOOLEAN NOT NULL DEFAULT 0,
+  valid_from DATE,
+  valid_until DATE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (valid_from IS NULL OR valid_until IS NULL OR valid_from <= valid_until)
+);
+
+ALTER TABLE journal_lines ADD COLUMN counterparty_id INTEGER REFERENCES counterparties (id);
+
+CREATE INDEX journal_lines_counterparty ON journal_lines (counterparty_id)
+  WHERE counterparty_id IS NOT NULL;
@@ -0,0 +1,5 @@
+DROP TABLE import_rules;
+DROP INDEX staged_transactions_batch;
+DROP TABLE staged_transactions;
+DROP TABLE import_batches;
+DROP TABLE import_profiles;
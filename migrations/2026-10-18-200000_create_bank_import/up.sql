@@ -0,0 +1,56 @@
+-- How to read one institution's statement CSV. Columns are 0-based.
+CREATE TABLE import_profiles (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL UNIQUE,
+  -- The bank or credit card account the statement belongs to.
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  encoding TEXT NOT NULL DEFAULT 'utf-8' CHECK (encoding IN ('utf-8', 'shift_jis')),
+  skip_rows INTEGER NOT NULL DEFAULT 1 CHECK (skip_rows >= 0),
+  date_column INTEGER NOT NULL,
+  date_format TEXT NOT NULL DEFAULT '%Y/%m/%d',
+  description_column INTEGER NOT NULL,
+  withdrawal_column INTEGER NOT NULL,
+  deposit_column INTEGER,
+  balance_column INTEGER,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE import_batches (
+  id INTEGER PRIMARY KEY NOT NULL,
+  profile_id INTEGER NOT NULL REFERENCES import_profiles (id),
+  file_name TEXT NOT NULL,
+  imported_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Statement rows waiting to be reviewed and posted.
+CREATE TABLE staged_transactions (
+  id INTEGER PRIMARY KEY NOT NULL,
+  batch_id INTEGER NOT NULL REFERENCES import_batches (id),
+  row_no INTEGER NOT NULL,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  transaction_date DATE NOT NULL,
+  description TEXT NOT NULL,
+  withdrawal BIGINT NOT NULL DEFAULT 0 CHECK (withdrawal >= 0),
+  deposit BIGINT NOT NULL DEFAULT 0 CHECK (deposit >= 0),
+  balance BIGINT,
+  status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'posted', 'ignored')),
+  entry_id INTEGER REFERENCES journal_entries (id),
+  CHECK ((withdrawal > 0) <> (deposit > 0))
+);
+
+CREATE INDEX staged_transactions_batch ON staged_transactions (batch_id, row_no);
+
+-- Conditions are ANDed; a rule without conditions matches everything.
+CREATE TABLE import_rules (
+  id INTEGER PRIMARY KEY NOT NULL,
+  -- Lower numbers are tried first.
+  priority INTEGER NOT NULL DEFAULT 100,
+  description_contains TEXT,
+  min_amount BIGINT,
+  max_amount BIGINT,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  tax_category TEXT
+    CHECK (tax_category IN ('taxable_10', 'reduced_8', 'exempt', 'non_taxable', 'out_of_scope')),
+  counterparty_id INTEGER REFERENCES counterparties (id),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
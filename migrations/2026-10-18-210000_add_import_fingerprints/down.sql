@@ -0,0 +1,4 @@
+DROP INDEX skipped_duplicates_batch;
+DROP TABLE skipped_duplicates;
+DROP INDEX staged_transactions_fingerprint;
+ALTER TABLE staged_transactions DROP COLUMN fingerprint;
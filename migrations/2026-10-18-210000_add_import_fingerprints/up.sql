@@ -0,0 +1,21 @@
+-- Identifies a statement row across overlapping files: account, date,
+-- signed amount, normalized description and its occurrence among
+-- identical rows of the same file. Rows staged before this migration have
+-- none.
+ALTER TABLE staged_transactions ADD COLUMN fingerprint TEXT;
+
+CREATE UNIQUE INDEX staged_transactions_fingerprint ON staged_transactions (fingerprint);
+
+-- Rows left out of a batch because they had been imported before.
+CREATE TABLE skipped_duplicates (
+  id INTEGER PRIMARY KEY NOT NULL,
+  batch_id INTEGER NOT NULL REFERENCES import_batches (id),
+  row_no INTEGER NOT NULL,
+  transaction_date DATE NOT NULL,
+  description TEXT NOT NULL,
+  withdrawal BIGINT NOT NULL,
+  deposit BIGINT NOT NULL,
+  duplicate_of INTEGER NOT NULL REFERENCES staged_transactions (id)
+);
+
+CREATE INDEX skipped_duplicates_batch ON skipped_duplicates (batch_id, row_no);
@@ -0,0 +1,2 @@
+ALTER TABLE fiscal_years DROP COLUMN depreciation_entry_id;
+DROP TABLE fixed_assets;
@@ -0,0 +1,21 @@
+-- Depreciable assets (減価償却資産). Depreciation is posted directly
+-- against `account_id` (直接法).
+CREATE TABLE fixed_assets (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  -- 面積又は数量 as printed on the schedule, e.g. '1台'.
+  quantity TEXT NOT NULL DEFAULT '',
+  acquired_on DATE NOT NULL,
+  cost BIGINT NOT NULL CHECK (cost > 0),
+  useful_life INTEGER NOT NULL CHECK (useful_life BETWEEN 2 AND 50),
+  method TEXT NOT NULL CHECK (method IN ('straight_line', 'declining_balance')),
+  -- 事業専用割合 in percent; the rest of the depreciation goes to 事業主貸.
+  business_ratio INTEGER NOT NULL DEFAULT 100 CHECK (business_ratio BETWEEN 0 AND 100),
+  disposed_on DATE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (disposed_on IS NULL OR disposed_on >= acquired_on)
+);
+
+ALTER TABLE fiscal_years ADD COLUMN depreciation_entry_id INTEGER REFERENCES journal_entries (id);
@@ -0,0 +1,24 @@
+-- Allows the special treatments for small assets: full expensing under
+-- the small-amount rule (少額減価償却資産) and 3-year equal depreciation
+-- (一括償却資産). SQLite cannot alter a CHECK constraint, so the table is
+-- rebuilt.
+CREATE TABLE fixed_assets_new (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  quantity TEXT NOT NULL DEFAULT '',
+  acquired_on DATE NOT NULL,
+  cost BIGINT NOT NULL CHECK (cost > 0),
+  useful_life INTEGER NOT NULL CHECK (useful_life BETWEEN 2 AND 50),
+  method TEXT NOT NULL
+    CHECK (method IN ('straight_line', 'declining_balance', 'small_amount', 'lump_sum')),
+  business_ratio INTEGER NOT NULL DEFAULT 100 CHECK (business_ratio BETWEEN 0 AND 100),
+  disposed_on DATE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (disposed_on IS NULL OR disposed_on >= acquired_on)
+);
+
+INSERT INTO fixed_assets_new SELECT * FROM fixed_assets;
+DROP TABLE fixed_assets;
+ALTER TABLE fixed_assets_new RENAME TO fixed_assets;
@@ -0,0 +1,3 @@
+ALTER TABLE fiscal_years DROP COLUMN apportionment_entry_id;
+DROP INDEX apportionment_rules_key;
+DROP TABLE apportionment_rules;
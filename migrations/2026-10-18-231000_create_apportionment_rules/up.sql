@@ -0,0 +1,16 @@
+-- 家事按分: the business share of an expense account, optionally only for
+-- one counterparty, from `effective_from` until the next rule for the same
+-- account and counterparty.
+CREATE TABLE apportionment_rules (
+  id INTEGER PRIMARY KEY NOT NULL,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  counterparty_id INTEGER REFERENCES counterparties (id),
+  business_ratio INTEGER NOT NULL CHECK (business_ratio BETWEEN 0 AND 100),
+  effective_from DATE NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX apportionment_rules_key
+  ON apportionment_rules (account_id, IFNULL(counterparty_id, 0), effective_from);
+
+ALTER TABLE fiscal_years ADD COLUMN apportionment_entry_id INTEGER REFERENCES journal_entries (id);
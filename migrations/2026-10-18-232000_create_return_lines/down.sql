@@ -0,0 +1 @@
+DROP TABLE return_lines;
@@ -0,0 +1,44 @@
+-- Line of the blue-return 決算書 each top-level revenue and expense account
+-- is reported on. Accounts without a row go to 売上(収入)金額 if revenue
+-- and to a line of their own name if expense.
+CREATE TABLE return_lines (
+  account_id INTEGER PRIMARY KEY NOT NULL REFERENCES accounts (id),
+  line TEXT NOT NULL CHECK (line IN (
+    'sales', 'household_consumption', 'misc_income', 'purchases',
+    'taxes_and_dues', 'packing_and_freight', 'utilities', 'travel',
+    'communication', 'advertising', 'entertainment', 'insurance', 'repairs',
+    'supplies', 'depreciation', 'welfare', 'wages', 'outsourcing', 'interest',
+    'rent', 'bad_debts', 'other', 'miscellaneous'
+  )),
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO return_lines (account_id, line)
+SELECT id, CASE code
+    WHEN '401' THEN 'sales'
+    WHEN '402' THEN 'household_consumption'
+    WHEN '411' THEN 'misc_income'
+    WHEN '501' THEN 'purchases'
+    WHEN '511' THEN 'taxes_and_dues'
+    WHEN '512' THEN 'packing_and_freight'
+    WHEN '513' THEN 'utilities'
+    WHEN '514' THEN 'travel'
+    WHEN '515' THEN 'communication'
+    WHEN '516' THEN 'advertising'
+    WHEN '517' THEN 'entertainment'
+    WHEN '518' THEN 'insurance'
+    WHEN '519' THEN 'repairs'
+    WHEN '520' THEN 'supplies'
+    WHEN '521' THEN 'depreciation'
+    WHEN '522' THEN 'welfare'
+    WHEN '523' THEN 'wages'
+    WHEN '524' THEN 'outsourcing'
+    WHEN '525' THEN 'interest'
+    WHEN '526' THEN 'rent'
+    WHEN '527' THEN 'bad_debts'
+    WHEN '599' THEN 'miscellaneous'
+  END
+FROM accounts
+WHERE code IN ('401', '402', '411', '501', '511', '512', '513', '514', '515',
+  '516', '517', '518', '519', '520', '521', '522', '523', '524', '525', '526',
+  '527', '599');
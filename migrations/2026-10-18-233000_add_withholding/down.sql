@@ -0,0 +1 @@
+ALTER TABLE counterparties DROP COLUMN withholding;
@@ -0,0 +1,5 @@
+-- 源泉徴収: 'payments' when income tax is withheld from what we pay the
+-- counterparty (報酬・料金 to an individual), 'receipts' when the
+-- counterparty withholds it from what it pays us.
+ALTER TABLE counterparties ADD COLUMN withholding TEXT NOT NULL DEFAULT 'none'
+  CHECK (withholding IN ('none', 'payments', 'receipts'));
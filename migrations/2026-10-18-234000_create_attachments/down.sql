@@ -0,0 +1,9 @@
+DROP TRIGGER attachment_history_no_delete;
+DROP TRIGGER attachment_history_no_update;
+DROP TRIGGER attachments_fixed_content;
+DROP TRIGGER attachments_no_delete;
+DROP INDEX attachment_history_attachment;
+DROP TABLE attachment_history;
+DROP INDEX attachments_entry;
+DROP INDEX attachments_transaction_date;
+DROP TABLE attachments;
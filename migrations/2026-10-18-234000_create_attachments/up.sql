@@ -0,0 +1,58 @@
+-- Evidence kept under 電子帳簿保存法: scanned receipts, PDF invoices and the
+-- like. The file itself is stored outside the database, named by its
+-- SHA-256, so the hash here identifies exactly the content kept.
+CREATE TABLE attachments (
+  id INTEGER PRIMARY KEY NOT NULL,
+  sha256 TEXT NOT NULL CHECK (length(sha256) = 64),
+  file_name TEXT NOT NULL,
+  content_type TEXT NOT NULL DEFAULT 'application/octet-stream',
+  size BIGINT NOT NULL CHECK (size >= 0),
+  -- Search keys: 取引年月日, 取引金額 and 取引先.
+  transaction_date DATE NOT NULL,
+  amount BIGINT NOT NULL CHECK (amount >= 0),
+  counterparty_id INTEGER REFERENCES counterparties (id),
+  entry_id INTEGER REFERENCES journal_entries (id),
+  description TEXT NOT NULL DEFAULT '',
+  deleted_at DATETIME,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX attachments_transaction_date ON attachments (transaction_date);
+CREATE INDEX attachments_entry ON attachments (entry_id) WHERE entry_id IS NOT NULL;
+
+-- Every version of each attachment record (訂正削除の履歴), written with
+-- the change that produced it.
+CREATE TABLE attachment_history (
+  id INTEGER PRIMARY KEY NOT NULL,
+  attachment_id INTEGER NOT NULL REFERENCES attachments (id),
+  action TEXT NOT NULL CHECK (action IN ('created', 'updated', 'deleted')),
+  reason TEXT NOT NULL DEFAULT '',
+  -- The record as it was after the change, as JSON.
+  snapshot TEXT NOT NULL,
+  recorded_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX attachment_history_attachment ON attachment_history (attachment_id, id);
+
+-- Deleting only marks a record; the stored content and the history of
+-- every record are never changed.
+CREATE TRIGGER attachments_no_delete BEFORE DELETE ON attachments
+BEGIN
+  SELECT RAISE(ABORT, 'attachments cannot be deleted');
+END;
+
+CREATE TRIGGER attachments_fixed_content BEFORE UPDATE OF sha256, size ON attachments
+BEGIN
+  SELECT RAISE(ABORT, 'attachment content cannot be changed');
+END;
+
+CREATE TRIGGER attachment_history_no_update BEFORE UPDATE ON attachment_history
+BEGIN
+  SELECT RAISE(ABORT, 'attachment history is append-only');
+END;
+
+CREATE TRIGGER attachment_history_no_delete BEFORE DELETE ON attachment_history
+BEGIN
+  SELECT RAISE(ABORT, 'attachment history is append-only');
+END;
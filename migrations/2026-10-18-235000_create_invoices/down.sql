@@ -0,0 +1,4 @@
+DROP TABLE invoice_lines;
+DROP INDEX invoices_counterparty;
+DROP TABLE invoices;
+DROP TABLE business_profile;
@@ -0,0 +1,51 @@
+-- Single-row table: the business as it appears on the documents it issues.
+CREATE TABLE business_profile (
+  id INTEGER PRIMARY KEY NOT NULL CHECK (id = 1),
+  name TEXT NOT NULL DEFAULT '',
+  address TEXT NOT NULL DEFAULT '',
+  -- 適格請求書発行事業者の登録番号: "T" followed by 13 digits.
+  registration_number TEXT,
+  -- 振込先, printed on invoices.
+  bank_details TEXT NOT NULL DEFAULT '',
+  invoice_prefix TEXT NOT NULL DEFAULT 'INV-',
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO business_profile (id) VALUES (1);
+
+-- Invoices to clients. Numbers are given out on issue, so drafts that are
+-- never issued leave no gaps in the sequence.
+CREATE TABLE invoices (
+  id INTEGER PRIMARY KEY NOT NULL,
+  sequence INTEGER UNIQUE,
+  number TEXT UNIQUE,
+  counterparty_id INTEGER NOT NULL REFERENCES counterparties (id),
+  issue_date DATE NOT NULL,
+  due_date DATE NOT NULL,
+  status TEXT NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'issued', 'paid', 'void')),
+  notes TEXT NOT NULL DEFAULT '',
+  -- 売掛金 posted on issue, and its settlement.
+  entry_id INTEGER REFERENCES journal_entries (id),
+  payment_entry_id INTEGER REFERENCES journal_entries (id),
+  paid_on DATE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (issue_date <= due_date),
+  CHECK ((sequence IS NULL) = (number IS NULL))
+);
+
+CREATE INDEX invoices_counterparty ON invoices (counterparty_id);
+
+-- Prices are before tax (税抜); tax is worked out once per rate over the
+-- whole invoice.
+CREATE TABLE invoice_lines (
+  id INTEGER PRIMARY KEY NOT NULL,
+  invoice_id INTEGER NOT NULL REFERENCES invoices (id),
+  line_no INTEGER NOT NULL,
+  description TEXT NOT NULL,
+  quantity INTEGER NOT NULL DEFAULT 1 CHECK (quantity > 0),
+  unit_price BIGINT NOT NULL CHECK (unit_price >= 0),
+  tax_category TEXT NOT NULL DEFAULT 'taxable_10'
+    CHECK (tax_category IN ('taxable_10', 'reduced_8', 'exempt', 'non_taxable', 'out_of_scope')),
+  UNIQUE (invoice_id, line_no)
+);
@@ -0,0 +1,2 @@
+DROP INDEX open_item_matches_payment_line_id;
+DROP TABLE open_item_matches;
@@ -0,0 +1,14 @@
+-- Payments applied to receivables and payables. An open item is a line on
+-- 売掛金, 買掛金 or 未払金 with a counterparty that increases the balance;
+-- a payment is a line on the same account and counterparty that decreases
+-- it. A payment may be split over several items and an item paid in parts.
+CREATE TABLE open_item_matches (
+  id INTEGER PRIMARY KEY NOT NULL,
+  item_line_id INTEGER NOT NULL REFERENCES journal_lines (id),
+  payment_line_id INTEGER NOT NULL REFERENCES journal_lines (id),
+  amount BIGINT NOT NULL CHECK (amount > 0),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (item_line_id, payment_line_id)
+);
+
+CREATE INDEX open_item_matches_payment_line_id ON open_item_matches (payment_line_id);
@@ -0,0 +1,3 @@
+DROP TABLE recurring_occurrences;
+DROP TABLE recurring_entry_lines;
+DROP TABLE recurring_entries;
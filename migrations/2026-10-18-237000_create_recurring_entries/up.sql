@@ -0,0 +1,54 @@
+-- Journal entries that repeat, such as rent and subscriptions. `day` is the
+-- day of the month for monthly and yearly schedules, moved back to the last
+-- day in shorter months; `month` is the month of yearly ones.
+CREATE TABLE recurring_entries (
+  id INTEGER PRIMARY KEY NOT NULL,
+  name TEXT NOT NULL,
+  memo TEXT NOT NULL DEFAULT '',
+  schedule TEXT NOT NULL CHECK (schedule IN ('monthly', 'end_of_month', 'yearly')),
+  day INTEGER CHECK (day BETWEEN 1 AND 31),
+  month INTEGER CHECK (month BETWEEN 1 AND 12),
+  start_date DATE NOT NULL,
+  end_date DATE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (end_date IS NULL OR start_date <= end_date),
+  CHECK (
+    (schedule = 'monthly' AND day IS NOT NULL AND month IS NULL)
+    OR (schedule = 'end_of_month' AND day IS NULL AND month IS NULL)
+    OR (schedule = 'yearly' AND day IS NOT NULL AND month IS NOT NULL)
+  )
+);
+
+-- Lines as they are given to a journal entry.
+CREATE TABLE recurring_entry_lines (
+  id INTEGER PRIMARY KEY NOT NULL,
+  recurring_entry_id INTEGER NOT NULL REFERENCES recurring_entries (id) ON DELETE CASCADE,
+  line_no INTEGER NOT NULL,
+  account_id INTEGER NOT NULL REFERENCES accounts (id),
+  side TEXT NOT NULL CHECK (side IN ('debit', 'credit')),
+  amount BIGINT NOT NULL CHECK (amount > 0),
+  description TEXT NOT NULL DEFAULT '',
+  tax_category TEXT
+    CHECK (tax_category IN ('taxable_10', 'reduced_8', 'exempt', 'non_taxable', 'out_of_scope')),
+  tax_included BOOLEAN NOT NULL DEFAULT 1,
+  counterparty_id INTEGER REFERENCES counterparties (id),
+  UNIQUE (recurring_entry_id, line_no)
+);
+
+-- Occurrences that were posted, skipped or changed. Each date is posted at
+-- most once. `entry_date` and `lines` (JSON, as for a journal entry)
+-- replace the template's for that occurrence only.
+CREATE TABLE recurring_occurrences (
+  id INTEGER PRIMARY KEY NOT NULL,
+  recurring_entry_id INTEGER NOT NULL REFERENCES recurring_entries (id) ON DELETE CASCADE,
+  due_date DATE NOT NULL,
+  status TEXT NOT NULL CHECK (status IN ('due', 'posted', 'skipped')),
+  entry_date DATE,
+  lines TEXT,
+  entry_id INTEGER REFERENCES journal_entries (id),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (recurring_entry_id, due_date),
+  CHECK ((status = 'posted') = (entry_id IS NOT NULL))
+);
@@ -0,0 +1,131 @@
+//! Content-addressed storage of attachment files.
+//!
+//! Files are named by the SHA-256 of their content and kept in an
+//! `attachments` directory beside the database file, spread over
+//! subdirectories by the first two hex digits. A stored file is never
+//! rewritten: the same content always maps onto the same path, and content
+//! that differs gets a path of its own.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::Text;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// Name of the directory beside the database file.
+pub const DIRECTORY: &str = "attachments";
+
+/// Lower-case hex SHA-256 of `bytes`.
+pub fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    root: PathBuf,
+}
+
+#[derive(QueryableByName)]
+struct DatabaseFile {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    file: String,
+}
+
+impl FileStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FileStore { root: root.into() }
+    }
+
+    /// The store beside the database file `connection` is open on.
+    pub fn for_connection(connection: &mut SqliteConnection) -> Result<Self> {
+        let databases = sql_query("PRAGMA database_list").load::<DatabaseFile>(connection)?;
+        match databases.iter().find(|database| database.name == "main") {
+            Some(database) if !database.file.is_empty() => {
+                let dir = Path::new(&database.file)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""));
+                Ok(FileStore::new(dir.join(DIRECTORY)))
+            }
+            _ => Err(Error::Validation(
+                "attachments need a database file to be stored beside".to_string(),
+            )),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Where the content with hash `sha256` is kept.
+    pub fn path(&self, sha256: &str) -> PathBuf {
+        self.root.join(&sha256[..2]).join(sha256)
+    }
+
+    /// Stores `bytes` unless the same content is there already and returns
+    /// its hash. The file is written under a temporary name and renamed,
+    /// so a crash never leaves a partial file under the final name.
+    pub fn put(&self, bytes: &[u8]) -> Result<String> {
+        let hash = sha256(bytes);
+        let path = self.path(&hash);
+        if path.is_file() {
+            return Ok(hash);
+        }
+        let dir = path.parent().expect("stored files are in a subdirectory");
+        fs::create_dir_all(dir)?;
+        let temp = dir.join(format!("{}.tmp", hash));
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        let mut permissions = file.metadata()?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&temp, permissions)?;
+        fs::rename(&temp, &path)?;
+        Ok(hash)
+    }
+
+    /// Reads the content with hash `sha256`, checking that it still hashes
+    /// to it.
+    pub fn read(&self, sha256: &str) -> Result<Vec<u8>> {
+        let path = self.path(sha256);
+        let bytes = fs::read(&path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                Error::Integrity(format!("attachment file {} is missing", path.display()))
+            }
+            _ => Error::Io(e),
+        })?;
+        if self::sha256(&bytes) != sha256 {
+            return Err(Error::Integrity(format!(
+                "attachment file {} does not match its hash",
+                path.display()
+            )));
+        }
+        Ok(bytes)
+    }
+}
+
+/// Content type of the common evidence formats, going by the extension.
+pub fn content_type(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "tif" | "tiff" => "image/tiff",
+        "xml" => "application/xml",
+        "csv" => "text/csv",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,219 @@
+//! Online backup and restore of the SQLite database.
+//!
+//! Copies are taken with SQLite's online backup API, so they are consistent
+//! even while the server keeps writing to the source database.
+
+use std::ffi::{c_int, CStr, CString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+use chrono::Local;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::Text;
+use libsqlite3_sys as ffi;
+
+use crate::error::{Error, Result};
+
+/// Number of pages copied per backup step. Locks are released between steps
+/// so that writers are not blocked for the whole copy.
+const PAGES_PER_STEP: c_int = 256;
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+const BUSY_TIMEOUT_MS: c_int = 5_000;
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S%3f";
+
+struct Handle(*mut ffi::sqlite3);
+
+impl Handle {
+    fn open(path: &Path, flags: c_int) -> Result<Self> {
+        let c_path = to_c_path(path)?;
+        let mut db = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_open_v2(c_path.as_ptr(), &mut db, flags, ptr::null()) };
+        let handle = Handle(db);
+        if rc != ffi::SQLITE_OK {
+            return Err(handle.error(rc));
+        }
+        unsafe { ffi::sqlite3_busy_timeout(db, BUSY_TIMEOUT_MS) };
+        Ok(handle)
+    }
+
+    fn error(&self, code: c_int) -> Error {
+        let message = unsafe {
+            if self.0.is_null() {
+                CStr::from_ptr(ffi::sqlite3_errstr(code))
+            } else {
+                CStr::from_ptr(ffi::sqlite3_errmsg(self.0))
+            }
+        };
+        Error::Sqlite {
+            code,
+            message: message.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_close(self.0) };
+    }
+}
+
+fn to_c_path(path: &Path) -> Result<CString> {
+    path.to_str()
+        .and_then(|s| CString::new(s).ok())
+        .ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid database path: {}", path.display()),
+            ))
+        })
+}
+
+/// Copies the whole `main` database of `src` into `dst` page by page.
+fn copy(src: &Handle, dst: &Handle) -> Result<()> {
+    let main = CString::new("main").unwrap();
+    let backup = unsafe { ffi::sqlite3_backup_init(dst.0, main.as_ptr(), src.0, main.as_ptr()) };
+    if backup.is_null() {
+        return Err(dst.error(unsafe { ffi::sqlite3_errcode(dst.0) }));
+    }
+
+    loop {
+        match unsafe { ffi::sqlite3_backup_step(backup, PAGES_PER_STEP) } {
+            ffi::SQLITE_OK => continue,
+            ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => thread::sleep(RETRY_INTERVAL),
+            // Any other code (including SQLITE_DONE) ends the copy; a failure
+            // is reported again by sqlite3_backup_finish.
+            _ => break,
+        }
+    }
+
+    let rc = unsafe { ffi::sqlite3_backup_finish(backup) };
+    if rc != ffi::SQLITE_OK {
+        return Err(dst.error(rc));
+    }
+    Ok(())
+}
+
+fn require_file(path: &Path) -> Result<()> {
+    if path.is_file() {
+        Ok(())
+    } else {
+        Err(Error::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("database file not found: {}", path.display()),
+        )))
+    }
+}
+
+#[derive(QueryableByName)]
+struct IntegrityCheck {
+    #[diesel(sql_type = Text)]
+    integrity_check: String,
+}
+
+/// Runs `PRAGMA integrity_check` against the database at `path`.
+pub fn verify(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    require_file(path)?;
+
+    let mut connection = SqliteConnection::establish(&path.to_string_lossy())?;
+    let rows = sql_query("PRAGMA integrity_check").load::<IntegrityCheck>(&mut connection)?;
+    match rows.as_slice() {
+        [row] if row.integrity_check == "ok" => Ok(()),
+        _ => Err(Error::Integrity(
+            rows.into_iter()
+                .map(|row| row.integrity_check)
+                .collect::<Vec<_>>()
+                .join("; "),
+        )),
+    }
+}
+
+/// Writes a consistent copy of `database` to `dest` and verifies the result.
+pub fn backup(database: impl AsRef<Path>, dest: impl AsRef<Path>) -> Result<()> {
+    let (database, dest) = (database.as_ref(), dest.as_ref());
+    require_file(database)?;
+
+    let src = Handle::open(database, ffi::SQLITE_OPEN_READONLY)?;
+    let dst = Handle::open(dest, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE)?;
+    copy(&src, &dst)?;
+    drop(dst);
+
+    verify(dest)
+}
+
+/// Replaces the contents of `database` with the backup at `source`.
+///
+/// The backup is verified before anything is written, and the restored
+/// database is verified again afterwards.
+pub fn restore(source: impl AsRef<Path>, database: impl AsRef<Path>) -> Result<()> {
+    let (source, database) = (source.as_ref(), database.as_ref());
+    verify(source)?;
+
+    let src = Handle::open(source, ffi::SQLITE_OPEN_READONLY)?;
+    let dst = Handle::open(
+        database,
+        ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+    )?;
+    copy(&src, &dst)?;
+    drop(dst);
+
+    verify(database)
+}
+
+/// Backs up `database` into `dir` under a timestamped file name and removes
+/// the oldest backups so that at most `keep` remain.
+pub fn backup_rotated(
+    database: impl AsRef<Path>,
+    dir: impl AsRef<Path>,
+    keep: usize,
+) -> Result<PathBuf> {
+    let (database, dir) = (database.as_ref(), dir.as_ref());
+    let prefix = backup_prefix(database);
+    fs::create_dir_all(dir)?;
+
+    let dest = dir.join(format!(
+        "{}{}.db",
+        prefix,
+        Local::now().format(TIMESTAMP_FORMAT)
+    ));
+    backup(database, &dest)?;
+
+    let mut backups = list_backups(database, dir)?;
+    let excess = backups.len().saturating_sub(keep.max(1));
+    for old in backups.drain(..excess) {
+        fs::remove_file(old)?;
+    }
+    Ok(dest)
+}
+
+/// Lists the timestamped backups of `database` in `dir`, oldest first.
+pub fn list_backups(database: impl AsRef<Path>, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let prefix = backup_prefix(database.as_ref());
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_backup = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with(&prefix) && name.ends_with(".db"))
+            .unwrap_or(false);
+        if is_backup && path.is_file() {
+            backups.push(path);
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+fn backup_prefix(database: &Path) -> String {
+    let stem = database
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "database".to_string());
+    format!("{}-", stem)
+}
@@ -0,0 +1,311 @@
+//! Depreciation of fixed assets under the statutory rules for assets
+//! acquired from April 2012: 定額法 at `1 / useful life` and 200% 定率法
+//! switching to the revised rate once the regular amount falls below the
+//! guaranteed amount (償却保証額). Amounts are prorated by the months the
+//! asset was in use during the year, a started month counting as a whole
+//! one, and an asset is never depreciated below a memorandum value of 1 yen.
+//!
+//! Small assets may instead be expensed in full in the year of acquisition
+//! (少額減価償却資産, 租税特別措置法第28条の2) or written off in three equal
+//! parts regardless of the months in use or a disposal (一括償却資産).
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::models::{DepreciationMethod, FixedAsset};
+
+/// Costs must stay below this for the small-amount rule.
+pub const SMALL_AMOUNT_COST_LIMIT: i64 = 300_000;
+/// Total cost of small-amount assets that may be expensed in a year of 12
+/// months.
+pub const SMALL_AMOUNT_ANNUAL_CAP: i64 = 3_000_000;
+/// Costs must stay below this for 3-year equal depreciation.
+pub const LUMP_SUM_COST_LIMIT: i64 = 200_000;
+
+/// 償却率, 改定償却率 and 保証率 of 200% 定率法 for useful lives of 2 to 20
+/// years (別表第十), the first two in thousandths and the last in
+/// hundred-thousandths.
+const DECLINING_BALANCE: [(i64, i64, i64); 19] = [
+    (1000, 0, 0),
+    (667, 1000, 11089),
+    (500, 1000, 12499),
+    (400, 500, 10800),
+    (333, 334, 9911),
+    (286, 334, 8680),
+    (250, 334, 7909),
+    (222, 250, 7126),
+    (200, 250, 6552),
+    (182, 200, 5992),
+    (167, 200, 5566),
+    (154, 167, 5180),
+    (143, 167, 4854),
+    (133, 143, 4565),
+    (125, 143, 4294),
+    (118, 125, 4038),
+    (111, 112, 3884),
+    (105, 112, 3693),
+    (100, 112, 3486),
+];
+
+/// Rates for depreciating over `useful_life` years with `method`, if the
+/// statutory tables have them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rates {
+    /// 償却率 in thousandths.
+    rate: i64,
+    /// 改定償却率 in thousandths; 0 if the method never switches.
+    revised_rate: i64,
+    /// 保証率 in hundred-thousandths.
+    guarantee_rate: i64,
+}
+
+fn rates(method: DepreciationMethod, useful_life: i32) -> Option<Rates> {
+    match method {
+        // 別表第八: 1 / useful life rounded up to three decimals.
+        DepreciationMethod::StraightLine if (2..=50).contains(&useful_life) => Some(Rates {
+            rate: (1000 + useful_life as i64 - 1) / useful_life as i64,
+            revised_rate: 0,
+            guarantee_rate: 0,
+        }),
+        DepreciationMethod::DecliningBalance => DECLINING_BALANCE
+            .get(usize::try_from(useful_life).ok()?.checked_sub(2)?)
+            .map(|&(rate, revised_rate, guarantee_rate)| Rates {
+                rate,
+                revised_rate,
+                guarantee_rate,
+            }),
+        DepreciationMethod::StraightLine => None,
+        // The statutory useful life is only kept for the record.
+        DepreciationMethod::SmallAmount | DepreciationMethod::LumpSum
+            if (2..=50).contains(&useful_life) =>
+        {
+            Some(Rates {
+                rate: match method {
+                    DepreciationMethod::LumpSum => 333,
+                    _ => 1000,
+                },
+                revised_rate: 0,
+                guarantee_rate: 0,
+            })
+        }
+        DepreciationMethod::SmallAmount | DepreciationMethod::LumpSum => None,
+    }
+}
+
+/// Checks that the statutory tables cover `useful_life` for `method`.
+pub fn check_useful_life(method: DepreciationMethod, useful_life: i32) -> Result<()> {
+    table_rates(method, useful_life).map(|_| ())
+}
+
+/// Checks that `cost` qualifies for the special treatment `method` is.
+pub fn check_cost(method: DepreciationMethod, cost: i64) -> Result<()> {
+    let limit = match method {
+        DepreciationMethod::SmallAmount => SMALL_AMOUNT_COST_LIMIT,
+        DepreciationMethod::LumpSum => LUMP_SUM_COST_LIMIT,
+        DepreciationMethod::StraightLine | DepreciationMethod::DecliningBalance => return Ok(()),
+    };
+    if cost >= limit {
+        return Err(Error::Validation(format!(
+            "{} applies to costs under {} yen: {}",
+            method.label(),
+            limit,
+            cost
+        )));
+    }
+    Ok(())
+}
+
+/// The small-amount cap for the year from `from` to `to`, reduced by
+/// months for a year shorter than 12 months.
+pub fn small_amount_cap(from: NaiveDate, to: NaiveDate) -> i64 {
+    let months = (month_index(to) - month_index(from) + 1).clamp(0, 12) as i64;
+    SMALL_AMOUNT_ANNUAL_CAP * months / 12
+}
+
+fn table_rates(method: DepreciationMethod, useful_life: i32) -> Result<Rates> {
+    rates(method, useful_life).ok_or_else(|| {
+        Error::Validation(format!(
+            "no {} rate for a useful life of {} years",
+            method.label(),
+            useful_life
+        ))
+    })
+}
+
+/// One asset's depreciation for one year, as on the depreciation schedule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct YearDepreciation {
+    /// 未償却残高 at the start of the year.
+    pub opening_book_value: i64,
+    /// 償却の基礎になる金額: the cost under 定額法, the book value at the
+    /// start of the year or the revised cost (改定取得価額) under 定率法.
+    pub basis: i64,
+    /// 償却率, or 改定償却率 once `revised`, in thousandths.
+    pub rate: i64,
+    pub revised: bool,
+    /// 償却保証額 under 定率法.
+    pub guaranteed_amount: Option<i64>,
+    /// 本年中の償却期間 in months out of 12.
+    pub months: u32,
+    /// 本年分の償却費合計.
+    pub depreciation: i64,
+    /// 本年分の必要経費算入額: the business-use share of `depreciation`.
+    pub business_portion: i64,
+    /// 未償却残高 at the end of the year, before any disposal.
+    pub closing_book_value: i64,
+    /// Whether the asset was disposed of during the year, taking
+    /// `closing_book_value` off the books.
+    pub disposed: bool,
+}
+
+/// Works out the depreciation of `asset` for the year from `from` to `to`,
+/// replaying earlier years in 12-month steps back to the acquisition. None
+/// if the asset was not held during the year.
+pub fn depreciate(
+    asset: &FixedAsset,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Option<YearDepreciation>> {
+    let rates = table_rates(asset.method, asset.useful_life)?;
+    // 一括償却資産 keep being written off after a disposal.
+    let disposed_before = asset.method != DepreciationMethod::LumpSum
+        && asset.disposed_on.is_some_and(|date| date < from);
+    if asset.acquired_on > to || disposed_before {
+        return Ok(None);
+    }
+
+    let year = Months::new(12);
+    let mut start = from;
+    let mut earlier = Vec::new();
+    while start > asset.acquired_on {
+        start = start
+            .checked_sub_months(year)
+            .ok_or_else(|| Error::Validation("acquisition date is too early".to_string()))?;
+        earlier.push(start);
+    }
+    if matches!(
+        asset.method,
+        DepreciationMethod::SmallAmount | DepreciationMethod::LumpSum
+    ) {
+        return Ok(written_off(asset, rates, earlier.len(), from, to));
+    }
+
+    let mut state = State {
+        book_value: asset.cost,
+        revised_basis: None,
+    };
+    for start in earlier.into_iter().rev() {
+        let end = start
+            .checked_add_months(year)
+            .and_then(|date| date.pred_opt())
+            .unwrap_or(to);
+        state.year(asset, rates, start, end);
+    }
+    Ok(Some(state.year(asset, rates, from, to)))
+}
+
+/// A small-amount asset in its year of acquisition, or a lump-sum asset in
+/// the first three years, `elapsed` being the number of earlier years.
+fn written_off(
+    asset: &FixedAsset,
+    rates: Rates,
+    elapsed: usize,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Option<YearDepreciation> {
+    let (opening_book_value, depreciation, months) = match asset.method {
+        DepreciationMethod::SmallAmount if elapsed == 0 => {
+            let held_to = asset.disposed_on.map_or(to, |date| date.min(to));
+            let months = month_index(held_to) - month_index(asset.acquired_on.max(from)) + 1;
+            (asset.cost, asset.cost, months.clamp(0, 12) as u32)
+        }
+        DepreciationMethod::LumpSum if elapsed < 3 => {
+            let share = ceil_div(asset.cost, 3);
+            let opening = (asset.cost - share * elapsed as i64).max(0);
+            (opening, share.min(opening), 12)
+        }
+        _ => return None,
+    };
+    Some(YearDepreciation {
+        opening_book_value,
+        basis: asset.cost,
+        rate: rates.rate,
+        revised: false,
+        guaranteed_amount: None,
+        months,
+        depreciation,
+        business_portion: depreciation * asset.business_ratio as i64 / 100,
+        closing_book_value: opening_book_value - depreciation,
+        disposed: false,
+    })
+}
+
+/// The book value carried from one year to the next.
+struct State {
+    book_value: i64,
+    /// 改定取得価額, once 定率法 has switched to the revised rate.
+    revised_basis: Option<i64>,
+}
+
+impl State {
+    fn year(
+        &mut self,
+        asset: &FixedAsset,
+        rates: Rates,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> YearDepreciation {
+        let held_to = asset.disposed_on.map_or(to, |date| date.min(to));
+        let months = (month_index(held_to) - month_index(asset.acquired_on.max(from)) + 1)
+            .clamp(0, 12) as u32;
+        let opening_book_value = self.book_value;
+
+        let (basis, rate, guaranteed_amount) = match asset.method {
+            DepreciationMethod::DecliningBalance => {
+                let guaranteed = asset.cost * rates.guarantee_rate / 100_000;
+                if self.revised_basis.is_none()
+                    && rates.revised_rate > 0
+                    && ceil_div(self.book_value * rates.rate, 1000) < guaranteed
+                {
+                    self.revised_basis = Some(self.book_value);
+                }
+                match self.revised_basis {
+                    Some(basis) => (basis, rates.revised_rate, Some(guaranteed)),
+                    None => (self.book_value, rates.rate, Some(guaranteed)),
+                }
+            }
+            _ => (asset.cost, rates.rate, None),
+        };
+        let depreciation = ceil_div(basis * rate * months as i64, 12_000)
+            .min(self.book_value - 1)
+            .max(0);
+        self.book_value -= depreciation;
+        let disposed = asset.disposed_on.is_some_and(|date| date <= to);
+        let closing_book_value = self.book_value;
+        if disposed {
+            self.book_value = 0;
+        }
+
+        YearDepreciation {
+            opening_book_value,
+            basis,
+            rate,
+            revised: self.revised_basis.is_some(),
+            guaranteed_amount: guaranteed_amount.filter(|_| rates.guarantee_rate > 0),
+            months,
+            depreciation,
+            business_portion: depreciation * asset.business_ratio as i64 / 100,
+            closing_book_value,
+            disposed,
+        }
+    }
+}
+
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+fn ceil_div(numerator: i64, denominator: i64) -> i64 {
+    (numerator + denominator - 1).div_euclid(denominator)
+}
@@ -0,0 +1,54 @@
+use std::io;
+
+use chrono::NaiveDate;
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Database(#[from] diesel::result::Error),
+    #[error(transparent)]
+    Connection(#[from] diesel::ConnectionError),
+    #[error(transparent)]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error("sqlite error ({code}): {message}")]
+    Sqlite { code: i32, message: String },
+    #[error("integrity check failed: {0}")]
+    Integrity(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("journal entry is unbalanced: debit {debit} != credit {credit}")]
+    Unbalanced { debit: i64, credit: i64 },
+    #[error("{date} is in the closed fiscal year {start} - {end}")]
+    PeriodClosed {
+        date: NaiveDate,
+        start: NaiveDate,
+        end: NaiveDate,
+    },
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{resource} {id} was modified: expected version {expected}, found {current}")]
+    Conflict {
+        resource: &'static str,
+        id: i32,
+        expected: i32,
+        current: i32,
+    },
+}
+
+impl Error {
+    /// Whether a foreign key refused the change, typically deleting a row
+    /// that others still refer to.
+    pub fn is_foreign_key_violation(&self) -> bool {
+        matches!(
+            self,
+            Error::Database(DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _))
+        )
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,304 @@
+//! Deterministic sample data for tests and local load testing.
+//!
+//! All values are drawn from a seeded ChaCha RNG, so the same seed and
+//! configuration always produce the same rows.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::{insert_into, prelude::*};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::error::{Error, Result};
+use crate::models::{Category, Post, PostTag, Tag};
+
+/// SQLite limits the number of bound parameters per statement, so large
+/// inserts are split into chunks of this many rows.
+const BATCH_SIZE: usize = 500;
+
+const CATEGORY_NAMES: &[&str] = &[
+    "News", "Tech", "Life", "Travel", "Food", "Business", "Finance", "Health", "Sports", "Music",
+    "Books", "Movies",
+];
+const FIRST_NAMES: &[&str] = &[
+    "Alice", "Bob", "Carol", "Dave", "Eve", "Frank", "Grace", "Heidi", "Ivan", "Judy", "Ken",
+    "Mallory", "Naoko", "Oscar", "Peggy", "Sakura", "Taro", "Trent", "Victor", "Yuki",
+];
+const LAST_NAMES: &[&str] = &[
+    "Sato",
+    "Suzuki",
+    "Takahashi",
+    "Tanaka",
+    "Watanabe",
+    "Ito",
+    "Yamamoto",
+    "Nakamura",
+    "Smith",
+    "Johnson",
+    "Brown",
+    "Miller",
+];
+const WORDS: &[&str] = &[
+    "account",
+    "annual",
+    "balance",
+    "budget",
+    "cash",
+    "deduction",
+    "expense",
+    "filing",
+    "income",
+    "invoice",
+    "ledger",
+    "monthly",
+    "receipt",
+    "report",
+    "return",
+    "revenue",
+    "review",
+    "savings",
+    "summary",
+    "tax",
+    "update",
+    "weekly",
+    "notes",
+    "guide",
+    "tips",
+    "plan",
+    "checklist",
+    "record",
+];
+const TAG_NAMES: &[&str] = &[
+    "rust",
+    "sqlite",
+    "diesel",
+    "tax",
+    "bookkeeping",
+    "howto",
+    "release",
+    "draft",
+    "question",
+    "announcement",
+    "japan",
+    "freelance",
+    "tips",
+    "beginner",
+    "advanced",
+];
+
+#[derive(Debug, Clone)]
+pub struct SeedConfig {
+    pub seed: u64,
+    pub categories: usize,
+    pub authors: usize,
+    pub posts: usize,
+    pub tags: usize,
+    pub max_tags_per_post: usize,
+}
+
+impl Default for SeedConfig {
+    fn default() -> Self {
+        SeedConfig {
+            seed: 42,
+            categories: 8,
+            authors: 20,
+            posts: 200,
+            tags: 15,
+            max_tags_per_post: 3,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SeedSummary {
+    pub categories: usize,
+    pub posts: usize,
+    pub tags: usize,
+    pub post_tags: usize,
+}
+
+/// Generates rows without touching the database.
+pub struct Fixtures {
+    rng: ChaCha8Rng,
+    base: NaiveDateTime,
+}
+
+impl Fixtures {
+    pub fn new(seed: u64) -> Self {
+        Fixtures {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            // A fixed origin keeps timestamps reproducible across runs.
+            base: NaiveDate::from_ymd_opt(2023, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        }
+    }
+
+    pub fn category(&self, index: usize) -> Category {
+        let name = numbered(CATEGORY_NAMES, index);
+        Category {
+            id: None,
+            description: Some(format!("Posts about {}", name.to_lowercase())),
+            name,
+        }
+    }
+
+    pub fn tag(&self, index: usize) -> Tag {
+        Tag {
+            id: None,
+            name: numbered(TAG_NAMES, index),
+        }
+    }
+
+    pub fn author(&mut self) -> String {
+        format!(
+            "{} {}",
+            FIRST_NAMES.choose(&mut self.rng).unwrap(),
+            LAST_NAMES.choose(&mut self.rng).unwrap()
+        )
+    }
+
+    pub fn sentence(&mut self, words: usize) -> String {
+        let mut sentence = (0..words)
+            .map(|_| *WORDS.choose(&mut self.rng).unwrap())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if let Some(first) = sentence.get_mut(0..1) {
+            first.make_ascii_uppercase();
+        }
+        sentence
+    }
+
+    /// Builds a post. Roughly one in ten posts has no category or author,
+    /// and like counts follow a long-tailed distribution.
+    pub fn post(&mut self, category_ids: &[i32], authors: &[String]) -> Post {
+        let created_at = self.base + Duration::minutes(self.rng.gen_range(0..60 * 24 * 365));
+        let updated_at = created_at + Duration::minutes(self.rng.gen_range(0..60 * 24 * 30));
+        let paragraphs = self.rng.gen_range(1..=4);
+        let body = (0..paragraphs)
+            .map(|_| {
+                let words = self.rng.gen_range(8..30);
+                format!("{}.", self.sentence(words))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        Post {
+            id: None,
+            title: {
+                let words = self.rng.gen_range(3..8);
+                self.sentence(words)
+            },
+            body,
+            category_id: self.maybe(0.9, |f| category_ids.choose(&mut f.rng).copied()),
+            author: self.maybe(0.9, |f| authors.choose(&mut f.rng).cloned()),
+            published: self.rng.gen_bool(0.8),
+            good_count: (self.rng.gen::<f64>().powi(4) * 1000.0) as i32,
+            created_at,
+            updated_at,
+            version: 0,
+        }
+    }
+
+    /// Picks up to `max` distinct tags for a post.
+    pub fn tags_for(&mut self, post_id: i32, tag_ids: &[i32], max: usize) -> Vec<PostTag> {
+        let count = self.rng.gen_range(0..=max.min(tag_ids.len()));
+        tag_ids
+            .choose_multiple(&mut self.rng, count)
+            .map(|&tag_id| PostTag { post_id, tag_id })
+            .collect()
+    }
+
+    fn maybe<T>(&mut self, probability: f64, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.rng.gen_bool(probability) {
+            f(self)
+        } else {
+            None
+        }
+    }
+}
+
+fn numbered(names: &[&str], index: usize) -> String {
+    match index / names.len() {
+        0 => names[index].to_string(),
+        round => format!("{} {}", names[index % names.len()], round + 1),
+    }
+}
+
+/// Inserts generated categories, tags, posts and post tags in a single
+/// transaction and returns how many rows of each were written.
+pub fn seed(connection: &mut SqliteConnection, config: &SeedConfig) -> Result<SeedSummary> {
+    use crate::schema::{category, post_tags, posts, tags};
+
+    let mut fixtures = Fixtures::new(config.seed);
+    connection.transaction::<_, Error, _>(|connection| {
+        let last_category = category::table
+            .select(diesel::dsl::max(category::id))
+            .first::<Option<i32>>(connection)?;
+        let new_categories = (0..config.categories)
+            .map(|i| fixtures.category(i))
+            .collect::<Vec<_>>();
+        insert_into(category::table)
+            .values(&new_categories)
+            .execute(connection)?;
+        let category_ids = category::table
+            .filter(category::id.gt(last_category.unwrap_or(0)))
+            .select(category::id.assume_not_null())
+            .load::<i32>(connection)?;
+
+        let last_tag = tags::table
+            .select(diesel::dsl::max(tags::id))
+            .first::<Option<i32>>(connection)?;
+        let existing_tags = tags::table.select(tags::name).load::<String>(connection)?;
+        let new_tags = (0..)
+            .map(|i| fixtures.tag(i))
+            .filter(|tag| !existing_tags.contains(&tag.name))
+            .take(config.tags)
+            .collect::<Vec<_>>();
+        insert_into(tags::table)
+            .values(&new_tags)
+            .execute(connection)?;
+        let tag_ids = tags::table
+            .filter(tags::id.gt(last_tag.unwrap_or(0)))
+            .select(tags::id.assume_not_null())
+            .load::<i32>(connection)?;
+
+        let authors = (0..config.authors)
+            .map(|_| fixtures.author())
+            .collect::<Vec<_>>();
+
+        let last_post = posts::table
+            .select(diesel::dsl::max(posts::id))
+            .first::<Option<i32>>(connection)?;
+        let new_posts = (0..config.posts)
+            .map(|_| fixtures.post(&category_ids, &authors))
+            .collect::<Vec<_>>();
+        for chunk in new_posts.chunks(BATCH_SIZE) {
+            insert_into(posts::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+        let post_ids = posts::table
+            .filter(posts::id.gt(last_post.unwrap_or(0)))
+            .order_by(posts::id)
+            .select(posts::id.assume_not_null())
+            .load::<i32>(connection)?;
+
+        let new_post_tags = post_ids
+            .iter()
+            .flat_map(|&post_id| fixtures.tags_for(post_id, &tag_ids, config.max_tags_per_post))
+            .collect::<Vec<_>>();
+        for chunk in new_post_tags.chunks(BATCH_SIZE) {
+            insert_into(post_tags::table)
+                .values(chunk)
+                .execute(connection)?;
+        }
+
+        Ok(SeedSummary {
+            categories: category_ids.len(),
+            posts: post_ids.len(),
+            tags: tag_ids.len(),
+            post_tags: new_post_tags.len(),
+        })
+    })
+}
@@ -0,0 +1,42 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+
+use super::{ApiError, AppState};
+use crate::models::NewApportionmentRule;
+use crate::repository::ApportionmentRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/rules", get(list_rules).post(create_rule))
+        .route("/rules/:id", delete(delete_rule))
+}
+
+async fn list_rules(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let rules = state
+        .run(|connection| ApportionmentRepository::new(connection).list_rules())
+        .await?;
+    Ok(Json(rules))
+}
+
+async fn create_rule(
+    State(state): State<AppState>,
+    Json(new_rule): Json<NewApportionmentRule>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rule = state
+        .run(move |connection| ApportionmentRepository::new(connection).create_rule(&new_rule))
+        .await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+async fn delete_rule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .run(move |connection| ApportionmentRepository::new(connection).delete_rule(id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,129 @@
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{ApiError, AppState};
+use crate::models::{default_content_type, AttachmentChanges, NewAttachment};
+use crate::repository::{AttachmentQuery, AttachmentRepository};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(search).post(create))
+        .route("/:id", get(show).patch(update).delete(delete))
+        .route("/:id/content", get(content))
+        .route("/:id/history", get(history))
+}
+
+/// The record of an uploaded file; the content type comes from the
+/// request's `Content-Type`.
+#[derive(Deserialize)]
+pub struct UploadParams {
+    file_name: String,
+    transaction_date: NaiveDate,
+    amount: i64,
+    counterparty_id: Option<i32>,
+    entry_id: Option<i32>,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReasonParams {
+    #[serde(default)]
+    reason: String,
+}
+
+async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<AttachmentQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let attachments = state
+        .run(move |connection| AttachmentRepository::new(connection).search(&query))
+        .await?;
+    Ok(Json(attachments))
+}
+
+/// Takes the file as the raw request body.
+async fn create(
+    State(state): State<AppState>,
+    Query(params): Query<UploadParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let new_attachment = NewAttachment {
+        file_name: params.file_name,
+        content_type: headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map_or_else(default_content_type, str::to_string),
+        transaction_date: params.transaction_date,
+        amount: params.amount,
+        counterparty_id: params.counterparty_id,
+        entry_id: params.entry_id,
+        description: params.description,
+    };
+    let attachment = state
+        .run(move |connection| AttachmentRepository::new(connection).create(&new_attachment, &body))
+        .await?;
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let attachment = state
+        .run(move |connection| AttachmentRepository::new(connection).find(id))
+        .await?;
+    Ok(Json(attachment))
+}
+
+async fn content(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (attachment, content) = state
+        .run(move |connection| AttachmentRepository::new(connection).content(id))
+        .await?;
+    Ok(([(header::CONTENT_TYPE, attachment.content_type)], content))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<ReasonParams>,
+    Json(changes): Json<AttachmentChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let attachment = state
+        .run(move |connection| {
+            AttachmentRepository::new(connection).update(id, &changes, &params.reason)
+        })
+        .await?;
+    Ok(Json(attachment))
+}
+
+async fn delete(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<ReasonParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let attachment = state
+        .run(move |connection| AttachmentRepository::new(connection).delete(id, &params.reason))
+        .await?;
+    Ok(Json(attachment))
+}
+
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let history = state
+        .run(move |connection| AttachmentRepository::new(connection).history(id))
+        .await?;
+    Ok(Json(history))
+}
@@ -0,0 +1,29 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+
+use super::{ApiError, AppState};
+use crate::models::BusinessProfileChanges;
+use crate::repository::BusinessProfileRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(show).patch(update))
+}
+
+async fn show(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let profile = state
+        .run(|connection| BusinessProfileRepository::new(connection).get())
+        .await?;
+    Ok(Json(profile))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Json(changes): Json<BusinessProfileChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let profile = state
+        .run(move |connection| BusinessProfileRepository::new(connection).update(&changes))
+        .await?;
+    Ok(Json(profile))
+}
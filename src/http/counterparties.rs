@@ -0,0 +1,63 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+
+use super::{ApiError, AppState};
+use crate::models::{CounterpartyChanges, NewCounterparty};
+use crate::repository::CounterpartyRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:id", get(show).patch(update).delete(delete))
+}
+
+async fn list(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let counterparties = state
+        .run(|connection| CounterpartyRepository::new(connection).list())
+        .await?;
+    Ok(Json(counterparties))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let counterparty = state
+        .run(move |connection| CounterpartyRepository::new(connection).find(id))
+        .await?;
+    Ok(Json(counterparty))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(new_counterparty): Json<NewCounterparty>,
+) -> Result<impl IntoResponse, ApiError> {
+    let counterparty = state
+        .run(move |connection| CounterpartyRepository::new(connection).create(&new_counterparty))
+        .await?;
+    Ok((StatusCode::CREATED, Json(counterparty)))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(changes): Json<CounterpartyChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let counterparty = state
+        .run(move |connection| CounterpartyRepository::new(connection).update(id, &changes))
+        .await?;
+    Ok(Json(counterparty))
+}
+
+async fn delete(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .run(move |connection| CounterpartyRepository::new(connection).delete(id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
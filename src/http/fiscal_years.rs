@@ -0,0 +1,77 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use super::{ApiError, AppState};
+use crate::models::NewFiscalYear;
+use crate::repository::{ApportionmentRepository, FiscalYearRepository, FixedAssetRepository};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:id/close", post(close))
+        .route("/:id/reopen", post(reopen))
+        .route("/:id/depreciation", post(post_depreciation))
+        .route("/:id/apportionment", post(post_apportionment))
+}
+
+async fn list(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let years = state
+        .run(|connection| FiscalYearRepository::new(connection).list())
+        .await?;
+    Ok(Json(years))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(new_year): Json<NewFiscalYear>,
+) -> Result<impl IntoResponse, ApiError> {
+    let year = state
+        .run(move |connection| FiscalYearRepository::new(connection).create(&new_year))
+        .await?;
+    Ok((StatusCode::CREATED, Json(year)))
+}
+
+async fn close(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let year = state
+        .run(move |connection| FiscalYearRepository::new(connection).close(id))
+        .await?;
+    Ok(Json(year))
+}
+
+async fn reopen(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let year = state
+        .run(move |connection| FiscalYearRepository::new(connection).reopen(id))
+        .await?;
+    Ok(Json(year))
+}
+
+/// Posts the year-end depreciation entry ahead of closing.
+async fn post_depreciation(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let entry = state
+        .run(move |connection| FixedAssetRepository::new(connection).post_depreciation(id))
+        .await?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Posts the year-end 家事按分 entry ahead of closing.
+async fn post_apportionment(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let entry = state
+        .run(move |connection| ApportionmentRepository::new(connection).post_adjustments(id))
+        .await?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
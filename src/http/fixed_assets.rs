@@ -0,0 +1,75 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{ApiError, AppState};
+use crate::models::{FixedAssetChanges, NewFixedAsset};
+use crate::repository::FixedAssetRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/small-amount", get(small_amount))
+        .route("/:id", get(show).patch(update))
+}
+
+#[derive(Deserialize)]
+pub struct SmallAmountParams {
+    date: NaiveDate,
+}
+
+async fn list(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let assets = state
+        .run(|connection| FixedAssetRepository::new(connection).list())
+        .await?;
+    Ok(Json(assets))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let asset = state
+        .run(move |connection| FixedAssetRepository::new(connection).find(id))
+        .await?;
+    Ok(Json(asset))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(new_asset): Json<NewFixedAsset>,
+) -> Result<impl IntoResponse, ApiError> {
+    let asset = state
+        .run(move |connection| FixedAssetRepository::new(connection).create(&new_asset))
+        .await?;
+    Ok((StatusCode::CREATED, Json(asset)))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(changes): Json<FixedAssetChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let asset = state
+        .run(move |connection| FixedAssetRepository::new(connection).update(id, &changes))
+        .await?;
+    Ok(Json(asset))
+}
+
+/// Small-amount assets acquired in the fiscal year containing `date`
+/// against the annual cap.
+async fn small_amount(
+    State(state): State<AppState>,
+    Query(params): Query<SmallAmountParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let usage = state
+        .run(move |connection| {
+            FixedAssetRepository::new(connection).small_amount_usage_on(params.date)
+        })
+        .await?;
+    Ok(Json(usage))
+}
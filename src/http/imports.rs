@@ -0,0 +1,179 @@
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use super::{ApiError, AppState};
+use crate::models::{NewImportProfile, NewImportRule, NewJournalEntry, StagedStatus};
+use crate::repository::{ImportRepository, StagedQuery};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/profiles", get(list_profiles).post(create_profile))
+        .route("/profiles/:id/statements", post(import_statement))
+        .route("/batches", get(list_batches))
+        .route("/batches/:id", delete(undo_batch))
+        .route("/batches/:id/skipped", get(skipped))
+        .route("/rules", get(list_rules).post(create_rule))
+        .route("/rules/:id", delete(delete_rule))
+        .route("/transactions", get(transactions))
+        .route("/transactions/:id/post", post(post_transaction))
+        .route("/transactions/:id/ignore", post(ignore_transaction))
+        .route("/proposals", get(proposals))
+}
+
+#[derive(Deserialize)]
+pub struct StatementParams {
+    #[serde(default)]
+    file_name: String,
+}
+
+#[derive(Deserialize)]
+pub struct TransactionParams {
+    batch_id: Option<i32>,
+    status: Option<StagedStatus>,
+}
+
+#[derive(Deserialize)]
+pub struct ProposalParams {
+    batch_id: Option<i32>,
+}
+
+async fn list_profiles(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let profiles = state
+        .run(|connection| ImportRepository::new(connection).list_profiles())
+        .await?;
+    Ok(Json(profiles))
+}
+
+async fn create_profile(
+    State(state): State<AppState>,
+    Json(new_profile): Json<NewImportProfile>,
+) -> Result<impl IntoResponse, ApiError> {
+    let profile = state
+        .run(move |connection| ImportRepository::new(connection).create_profile(&new_profile))
+        .await?;
+    Ok((StatusCode::CREATED, Json(profile)))
+}
+
+/// Takes the statement file as the raw request body.
+async fn import_statement(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<StatementParams>,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    let summary = state
+        .run(move |connection| {
+            ImportRepository::new(connection).import(id, &params.file_name, &body)
+        })
+        .await?;
+    Ok((StatusCode::CREATED, Json(summary)))
+}
+
+async fn list_batches(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let batches = state
+        .run(|connection| ImportRepository::new(connection).list_batches())
+        .await?;
+    Ok(Json(batches))
+}
+
+async fn skipped(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let skipped = state
+        .run(move |connection| ImportRepository::new(connection).skipped(id))
+        .await?;
+    Ok(Json(skipped))
+}
+
+/// Undoes a whole import, voiding the entries posted from it.
+async fn undo_batch(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let undone = state
+        .run(move |connection| ImportRepository::new(connection).undo_batch(id))
+        .await?;
+    Ok(Json(undone))
+}
+
+async fn list_rules(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let rules = state
+        .run(|connection| ImportRepository::new(connection).list_rules())
+        .await?;
+    Ok(Json(rules))
+}
+
+async fn create_rule(
+    State(state): State<AppState>,
+    Json(new_rule): Json<NewImportRule>,
+) -> Result<impl IntoResponse, ApiError> {
+    let rule = state
+        .run(move |connection| ImportRepository::new(connection).create_rule(&new_rule))
+        .await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+async fn delete_rule(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .run(move |connection| ImportRepository::new(connection).delete_rule(id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn transactions(
+    State(state): State<AppState>,
+    Query(params): Query<TransactionParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let transactions = state
+        .run(move |connection| {
+            ImportRepository::new(connection).transactions(&StagedQuery {
+                batch_id: params.batch_id,
+                status: params.status,
+            })
+        })
+        .await?;
+    Ok(Json(transactions))
+}
+
+async fn proposals(
+    State(state): State<AppState>,
+    Query(params): Query<ProposalParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let proposals = state
+        .run(move |connection| ImportRepository::new(connection).proposals(params.batch_id))
+        .await?;
+    Ok(Json(proposals))
+}
+
+/// Posts the proposed entry, or the reviewed entry given as the body.
+async fn post_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    entry: Option<Json<NewJournalEntry>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let transaction = state
+        .run(move |connection| {
+            ImportRepository::new(connection).post(id, entry.as_ref().map(|Json(entry)| entry))
+        })
+        .await?;
+    Ok(Json(transaction))
+}
+
+async fn ignore_transaction(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let transaction = state
+        .run(move |connection| ImportRepository::new(connection).ignore(id))
+        .await?;
+    Ok(Json(transaction))
+}
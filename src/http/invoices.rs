@@ -0,0 +1,119 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use super::reports::DocumentFormat;
+use super::{ApiError, AppState};
+use crate::models::{InvoicePayment, NewInvoice};
+use crate::repository::{InvoiceQuery, InvoiceRepository};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:id", get(show).put(update))
+        .route("/:id/issue", post(issue))
+        .route("/:id/pay", post(pay))
+        .route("/:id/void", post(void))
+        .route("/:id/document", get(document))
+}
+
+#[derive(Deserialize)]
+pub struct DocumentParams {
+    #[serde(default)]
+    format: DocumentFormat,
+}
+
+async fn list(
+    State(state): State<AppState>,
+    Query(query): Query<InvoiceQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoices = state
+        .run(move |connection| InvoiceRepository::new(connection).list(&query))
+        .await?;
+    Ok(Json(invoices))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoice = state
+        .run(move |connection| InvoiceRepository::new(connection).find(id))
+        .await?;
+    Ok(Json(invoice))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(new_invoice): Json<NewInvoice>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoice = state
+        .run(move |connection| InvoiceRepository::new(connection).create(&new_invoice))
+        .await?;
+    Ok((StatusCode::CREATED, Json(invoice)))
+}
+
+/// Replaces a draft.
+async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(new_invoice): Json<NewInvoice>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoice = state
+        .run(move |connection| InvoiceRepository::new(connection).update(id, &new_invoice))
+        .await?;
+    Ok(Json(invoice))
+}
+
+async fn issue(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoice = state
+        .run(move |connection| InvoiceRepository::new(connection).issue(id))
+        .await?;
+    Ok(Json(invoice))
+}
+
+async fn pay(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(payment): Json<InvoicePayment>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoice = state
+        .run(move |connection| InvoiceRepository::new(connection).pay(id, &payment))
+        .await?;
+    Ok(Json(invoice))
+}
+
+async fn void(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let invoice = state
+        .run(move |connection| InvoiceRepository::new(connection).void(id))
+        .await?;
+    Ok(Json(invoice))
+}
+
+async fn document(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<DocumentParams>,
+) -> Result<Response, ApiError> {
+    let document = state
+        .run(move |connection| InvoiceRepository::new(connection).document(id))
+        .await?;
+    Ok(match params.format {
+        DocumentFormat::Json => Json(document).into_response(),
+        DocumentFormat::Html => Html(document.to_html()).into_response(),
+        DocumentFormat::Pdf => (
+            [(header::CONTENT_TYPE, "application/pdf")],
+            document.to_pdf(),
+        )
+            .into_response(),
+    })
+}
@@ -0,0 +1,226 @@
+//! JSON HTTP API.
+//!
+//! Handlers borrow a pooled connection on a blocking thread and reuse the
+//! repositories, so the HTTP layer only translates requests and errors.
+
+pub mod apportionment;
+pub mod attachments;
+pub mod business_profile;
+pub mod counterparties;
+pub mod fiscal_years;
+pub mod fixed_assets;
+pub mod imports;
+pub mod invoices;
+pub mod open_items;
+pub mod posts;
+pub mod recurring_entries;
+pub mod reports;
+pub mod return_lines;
+pub mod tax_settings;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use diesel::r2d2::{ConnectionManager, CustomizeConnection};
+use diesel::result::{DatabaseErrorKind, Error::DatabaseError};
+use diesel::{prelude::*, sql_query};
+use serde_json::json;
+
+use crate::error::{Error, Result};
+
+pub type Pool = diesel::r2d2::Pool<ConnectionManager<SqliteConnection>>;
+
+#[derive(Debug)]
+struct SqlitePragmas;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(
+        &self,
+        connection: &mut SqliteConnection,
+    ) -> std::result::Result<(), diesel::r2d2::Error> {
+        sql_query("PRAGMA busy_timeout = 5000")
+            .execute(connection)
+            .and_then(|_| crate::enable_foreign_keys(connection))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+pub fn pool(database_url: &str) -> Result<Pool> {
+    Ok(diesel::r2d2::Pool::builder()
+        .connection_timeout(Duration::from_secs(5))
+        .connection_customizer(Box::new(SqlitePragmas))
+        .build(ConnectionManager::new(database_url))?)
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pool: Pool,
+}
+
+impl AppState {
+    /// Runs `f` with a pooled connection on the blocking thread pool.
+    pub async fn run<T, F>(&self, f: F) -> std::result::Result<T, ApiError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut SqliteConnection) -> Result<T> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || f(&mut *pool.get()?))
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))?
+            .map_err(ApiError::from)
+    }
+}
+
+pub fn router(pool: Pool) -> Router {
+    Router::new()
+        .nest("/apportionment", apportionment::router())
+        .nest("/attachments", attachments::router())
+        .nest("/business-profile", business_profile::router())
+        .nest("/counterparties", counterparties::router())
+        .nest("/fiscal-years", fiscal_years::router())
+        .nest("/fixed-assets", fixed_assets::router())
+        .nest("/imports", imports::router())
+        .nest("/invoices", invoices::router())
+        .nest("/open-items", open_items::router())
+        .nest("/posts", posts::router())
+        .nest("/recurring-entries", recurring_entries::router())
+        .nest("/reports", reports::router())
+        .nest("/return-lines", return_lines::router())
+        .nest("/tax-settings", tax_settings::router())
+        .with_state(AppState { pool })
+}
+
+pub async fn serve(pool: Pool, addr: SocketAddr) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(pool)).await?;
+    Ok(())
+}
+
+/// An error rendered as a JSON body with a matching status code.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: serde_json::Value,
+    etag: Option<i32>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            body: json!({ "error": message.into() }),
+            etag: None,
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// Adds `value` to the body under `key`, e.g. the current state of a
+    /// resource that could not be changed.
+    pub fn with(mut self, key: &str, value: serde_json::Value) -> Self {
+        self.body[key] = value;
+        self
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        let message = e.to_string();
+        match e {
+            Error::NotFound(_) | Error::Database(diesel::result::Error::NotFound) => {
+                ApiError::new(StatusCode::NOT_FOUND, message)
+            }
+            Error::Validation(_) | Error::Unbalanced { .. } | Error::PeriodClosed { .. } => {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, message)
+            }
+            Error::Conflict { current, .. } => {
+                let mut error = ApiError::new(StatusCode::CONFLICT, message);
+                error.etag = Some(current);
+                error
+            }
+            // The row is still referenced, or would duplicate a unique key.
+            Error::Database(DatabaseError(
+                DatabaseErrorKind::ForeignKeyViolation | DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => ApiError::new(StatusCode::CONFLICT, message),
+            _ => ApiError::internal(message),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, Json(self.body)).into_response();
+        if let Some(version) = self.etag {
+            response.headers_mut().insert(header::ETAG, etag(version));
+        }
+        response
+    }
+}
+
+/// Strong entity tag for a row version.
+pub fn etag(version: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{}\"", version)).unwrap()
+}
+
+/// Versions accepted through `If-Match`.
+pub enum IfMatch {
+    /// `*`, which matches whatever version is current.
+    Any,
+    /// A list of strong entity tags, any of which may match.
+    Versions(Vec<i32>),
+}
+
+impl IfMatch {
+    /// The version to write against: `current` if the header matches it,
+    /// otherwise the first version listed, which then conflicts.
+    pub fn version(&self, current: i32) -> i32 {
+        match self {
+            IfMatch::Versions(versions) if !versions.contains(&current) => versions[0],
+            _ => current,
+        }
+    }
+}
+
+/// Parses `If-Match`. Weak tags never match under the strong comparison
+/// the header requires (RFC 9110), so they fail the precondition outright.
+pub fn if_match(headers: &HeaderMap) -> std::result::Result<IfMatch, ApiError> {
+    let invalid = || ApiError::new(StatusCode::BAD_REQUEST, "invalid If-Match header");
+    let value = headers
+        .get(header::IF_MATCH)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::PRECONDITION_REQUIRED,
+                "If-Match header is required",
+            )
+        })?
+        .to_str()
+        .map_err(|_| invalid())?
+        .trim();
+    if value == "*" {
+        return Ok(IfMatch::Any);
+    }
+    value
+        .split(',')
+        .map(|tag| {
+            let tag = tag.trim();
+            if tag.starts_with("W/") {
+                return Err(ApiError::new(
+                    StatusCode::PRECONDITION_FAILED,
+                    "If-Match needs strong entity tags",
+                ));
+            }
+            tag.strip_prefix('"')
+                .and_then(|tag| tag.strip_suffix('"'))
+                .and_then(|version| version.parse().ok())
+                .ok_or_else(invalid)
+        })
+        .collect::<std::result::Result<_, _>>()
+        .map(IfMatch::Versions)
+}
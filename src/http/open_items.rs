@@ -0,0 +1,82 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+
+use super::{ApiError, AppState};
+use crate::models::NewOpenItemMatch;
+use crate::repository::{OpenItemQuery, OpenItemRepository};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(outstanding))
+        .route("/lines/:id", get(show))
+        .route("/lines/:id/matches", get(matches))
+        .route("/matches", post(create_match))
+        .route("/matches/:id", delete(delete_match))
+        .route("/auto-match", post(auto_match))
+}
+
+/// Open items per counterparty, e.g. `?kind=payable&as_of=2024-12-31`.
+async fn outstanding(
+    State(state): State<AppState>,
+    Query(query): Query<OpenItemQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let open = state
+        .run(move |connection| OpenItemRepository::new(connection).outstanding(&query))
+        .await?;
+    Ok(Json(open))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let open = state
+        .run(move |connection| OpenItemRepository::new(connection).find(id))
+        .await?;
+    Ok(Json(open))
+}
+
+async fn matches(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let matches = state
+        .run(move |connection| OpenItemRepository::new(connection).matches(id))
+        .await?;
+    Ok(Json(matches))
+}
+
+async fn create_match(
+    State(state): State<AppState>,
+    Json(new_match): Json<NewOpenItemMatch>,
+) -> Result<impl IntoResponse, ApiError> {
+    let matched = state
+        .run(move |connection| OpenItemRepository::new(connection).match_payment(&new_match))
+        .await?;
+    Ok((StatusCode::CREATED, Json(matched)))
+}
+
+async fn delete_match(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .run(move |connection| OpenItemRepository::new(connection).unmatch(id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Applies unapplied payments to the oldest items, with the same
+/// conditions as listing.
+async fn auto_match(
+    State(state): State<AppState>,
+    Query(query): Query<OpenItemQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let matches = state
+        .run(move |connection| OpenItemRepository::new(connection).auto_match(&query))
+        .await?;
+    Ok(Json(matches))
+}
@@ -0,0 +1,175 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{etag, if_match, ApiError, AppState};
+use crate::error::Error;
+use crate::models::{NewPost, PostChanges};
+use crate::repository::{PostFilter, PostRepository};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/:id", get(show).patch(update).delete(delete))
+        .route("/bulk", post(bulk_create))
+        .route("/bulk/publish", post(bulk_publish))
+        .route("/bulk/recategorize", post(bulk_recategorize))
+        .route("/bulk/delete", post(bulk_delete))
+}
+
+#[derive(Deserialize)]
+pub struct ListParams {
+    published: Option<bool>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+async fn list(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let posts = state
+        .run(move |connection| {
+            PostRepository::new(connection).list(params.published, params.limit, params.offset)
+        })
+        .await?;
+    Ok(Json(posts))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(new_post): Json<NewPost>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = state
+        .run(move |connection| PostRepository::new(connection).create(&new_post))
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        [(header::ETAG, etag(post.version))],
+        Json(post),
+    ))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let post = state
+        .run(move |connection| PostRepository::new(connection).find(id))
+        .await?;
+    Ok(([(header::ETAG, etag(post.version))], Json(post)))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    Json(changes): Json<PostChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let expected = if_match(&headers)?;
+    let post = versioned(&state, id, move |repository| {
+        let version = expected.version(repository.find(id)?.version);
+        repository.update(id, version, &changes)
+    })
+    .await?;
+    Ok(([(header::ETAG, etag(post.version))], Json(post)))
+}
+
+async fn delete(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let expected = if_match(&headers)?;
+    versioned(&state, id, move |repository| {
+        let version = expected.version(repository.find(id)?.version);
+        repository.delete(id, version)
+    })
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Runs `f` against post `id`. A version conflict is answered with the
+/// post as it is now under `current`, so the client can merge and retry.
+async fn versioned<T, F>(state: &AppState, id: i32, f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PostRepository) -> crate::Result<T> + Send + 'static,
+{
+    let outcome = state
+        .run(move |connection| {
+            let mut repository = PostRepository::new(connection);
+            match f(&mut repository) {
+                Err(e @ Error::Conflict { .. }) => Ok(Err((e, repository.find(id)?))),
+                result => result.map(Ok),
+            }
+        })
+        .await?;
+    outcome.map_err(|(e, current)| ApiError::from(e).with("current", json!(current)))
+}
+
+#[derive(Deserialize)]
+pub struct PublishRequest {
+    ids: Vec<i32>,
+    published: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RecategorizeRequest {
+    ids: Vec<i32>,
+    category_id: Option<i32>,
+}
+
+async fn bulk_create(
+    State(state): State<AppState>,
+    Json(new_posts): Json<Vec<NewPost>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let items = state
+        .run(move |connection| PostRepository::new(connection).bulk_create(&new_posts))
+        .await?;
+    Ok((StatusCode::CREATED, Json(items)))
+}
+
+async fn bulk_publish(
+    State(state): State<AppState>,
+    Json(request): Json<PublishRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let items = state
+        .run(move |connection| {
+            PostRepository::new(connection).bulk_set_published(&request.ids, request.published)
+        })
+        .await?;
+    Ok(Json(items))
+}
+
+async fn bulk_recategorize(
+    State(state): State<AppState>,
+    Json(request): Json<RecategorizeRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let items = state
+        .run(move |connection| {
+            PostRepository::new(connection).bulk_recategorize(&request.ids, request.category_id)
+        })
+        .await?;
+    Ok(Json(items))
+}
+
+async fn bulk_delete(
+    State(state): State<AppState>,
+    Json(filter): Json<PostFilter>,
+) -> Result<impl IntoResponse, ApiError> {
+    let items = state
+        .run(move |connection| PostRepository::new(connection).bulk_delete(&filter))
+        .await?;
+    Ok(Json(items))
+}
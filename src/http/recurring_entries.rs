@@ -0,0 +1,139 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{ApiError, AppState};
+use crate::models::{NewRecurringEntry, OccurrenceChanges};
+use crate::repository::RecurringEntryRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list).post(create))
+        .route("/run", post(run))
+        .route("/:id", get(show).put(update).delete(delete))
+        .route("/:id/occurrences", get(occurrences))
+        .route("/:id/occurrences/:date", put(modify).delete(restore))
+        .route("/:id/occurrences/:date/skip", post(skip))
+}
+
+#[derive(Deserialize)]
+pub struct OccurrenceParams {
+    #[serde(default)]
+    from: Option<NaiveDate>,
+    to: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct RunParams {
+    to: NaiveDate,
+}
+
+async fn list(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let entries = state
+        .run(|connection| RecurringEntryRepository::new(connection).list())
+        .await?;
+    Ok(Json(entries))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    let entry = state
+        .run(move |connection| RecurringEntryRepository::new(connection).find(id))
+        .await?;
+    Ok(Json(entry))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(new_entry): Json<NewRecurringEntry>,
+) -> Result<impl IntoResponse, ApiError> {
+    let entry = state
+        .run(move |connection| RecurringEntryRepository::new(connection).create(&new_entry))
+        .await?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Replaces a template; occurrences already posted are kept.
+async fn update(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Json(new_entry): Json<NewRecurringEntry>,
+) -> Result<impl IntoResponse, ApiError> {
+    let entry = state
+        .run(move |connection| RecurringEntryRepository::new(connection).update(id, &new_entry))
+        .await?;
+    Ok(Json(entry))
+}
+
+async fn delete(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .run(move |connection| RecurringEntryRepository::new(connection).delete(id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Occurrences up to `to`, e.g. `?from=2024-01-01&to=2024-12-31`.
+async fn occurrences(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<OccurrenceParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let occurrences = state
+        .run(move |connection| {
+            RecurringEntryRepository::new(connection).occurrences(id, params.from, params.to)
+        })
+        .await?;
+    Ok(Json(occurrences))
+}
+
+async fn modify(
+    State(state): State<AppState>,
+    Path((id, date)): Path<(i32, NaiveDate)>,
+    Json(changes): Json<OccurrenceChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let occurrence = state
+        .run(move |connection| RecurringEntryRepository::new(connection).modify(id, date, &changes))
+        .await?;
+    Ok(Json(occurrence))
+}
+
+async fn skip(
+    State(state): State<AppState>,
+    Path((id, date)): Path<(i32, NaiveDate)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let occurrence = state
+        .run(move |connection| RecurringEntryRepository::new(connection).skip(id, date))
+        .await?;
+    Ok(Json(occurrence))
+}
+
+/// Undoes skipping or changing an occurrence.
+async fn restore(
+    State(state): State<AppState>,
+    Path((id, date)): Path<(i32, NaiveDate)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let occurrence = state
+        .run(move |connection| RecurringEntryRepository::new(connection).restore(id, date))
+        .await?;
+    Ok(Json(occurrence))
+}
+
+/// Posts every occurrence due up to `to` that is not posted yet.
+async fn run(
+    State(state): State<AppState>,
+    Query(params): Query<RunParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let posted = state
+        .run(move |connection| RecurringEntryRepository::new(connection).materialize(params.to))
+        .await?;
+    Ok(Json(posted))
+}
@@ -0,0 +1,214 @@
+use axum::extract::{Query, State};
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::{ApiError, AppState};
+use crate::models::OpenItemKind;
+use crate::report::{
+    AgingReport, ApportionmentReport, BalanceSheet, BlueReturn, ConsumptionTaxReport,
+    DepreciationSchedule, IncomeStatement, PaymentRecordSummary, Period, Statement, TrialBalance,
+    WithholdingLedger,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/trial-balance", get(trial_balance))
+        .route("/income-statement", get(income_statement))
+        .route("/balance-sheet", get(balance_sheet))
+        .route("/consumption-tax", get(consumption_tax))
+        .route("/depreciation", get(depreciation))
+        .route("/apportionment", get(apportionment))
+        .route("/blue-return", get(blue_return))
+        .route("/withholding", get(withholding))
+        .route("/payment-records", get(payment_records))
+        .route("/aging", get(aging))
+}
+
+/// Inclusive reporting period, e.g. `?from=2024-01-01&to=2024-12-31`.
+#[derive(Deserialize)]
+pub struct PeriodParams {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct StatementParams {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(default)]
+    format: Format,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Csv,
+    Html,
+}
+
+async fn trial_balance(
+    State(state): State<AppState>,
+    Query(params): Query<PeriodParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| {
+            TrialBalance::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(Json(report))
+}
+
+async fn income_statement(
+    State(state): State<AppState>,
+    Query(params): Query<StatementParams>,
+) -> Result<Response, ApiError> {
+    let report = state
+        .run(move |connection| {
+            IncomeStatement::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    render(&report, params.format)
+}
+
+async fn balance_sheet(
+    State(state): State<AppState>,
+    Query(params): Query<StatementParams>,
+) -> Result<Response, ApiError> {
+    let report = state
+        .run(move |connection| {
+            BalanceSheet::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    render(&report, params.format)
+}
+
+async fn consumption_tax(
+    State(state): State<AppState>,
+    Query(params): Query<PeriodParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| {
+            ConsumptionTaxReport::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(Json(report))
+}
+
+async fn depreciation(
+    State(state): State<AppState>,
+    Query(params): Query<PeriodParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| {
+            DepreciationSchedule::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(Json(report))
+}
+
+async fn apportionment(
+    State(state): State<AppState>,
+    Query(params): Query<PeriodParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| {
+            ApportionmentReport::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(Json(report))
+}
+
+async fn withholding(
+    State(state): State<AppState>,
+    Query(params): Query<PeriodParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| {
+            WithholdingLedger::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(Json(report))
+}
+
+async fn payment_records(
+    State(state): State<AppState>,
+    Query(params): Query<PeriodParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| {
+            PaymentRecordSummary::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(Json(report))
+}
+
+/// `?as_of=2024-12-31&kind=payable`; `kind` defaults to receivables.
+#[derive(Deserialize)]
+pub struct AgingParams {
+    as_of: NaiveDate,
+    #[serde(default)]
+    kind: OpenItemKind,
+}
+
+async fn aging(
+    State(state): State<AppState>,
+    Query(params): Query<AgingParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = state
+        .run(move |connection| AgingReport::generate(connection, params.kind, params.as_of))
+        .await?;
+    Ok(Json(report))
+}
+
+#[derive(Deserialize)]
+pub struct BlueReturnParams {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(default)]
+    format: DocumentFormat,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    #[default]
+    Json,
+    Html,
+    Pdf,
+}
+
+async fn blue_return(
+    State(state): State<AppState>,
+    Query(params): Query<BlueReturnParams>,
+) -> Result<Response, ApiError> {
+    let report = state
+        .run(move |connection| {
+            BlueReturn::generate(connection, Period::new(params.from, params.to)?)
+        })
+        .await?;
+    Ok(match params.format {
+        DocumentFormat::Json => Json(report).into_response(),
+        DocumentFormat::Html => Html(report.to_html()).into_response(),
+        DocumentFormat::Pdf => {
+            ([(header::CONTENT_TYPE, "application/pdf")], report.to_pdf()).into_response()
+        }
+    })
+}
+
+fn render(statement: &impl Statement, format: Format) -> Result<Response, ApiError> {
+    Ok(match format {
+        Format::Json => Json(statement).into_response(),
+        Format::Csv => {
+            let mut csv = Vec::new();
+            statement.write_csv(&mut csv)?;
+            ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv).into_response()
+        }
+        Format::Html => Html(statement.to_html()).into_response(),
+    })
+}
@@ -0,0 +1,49 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use serde::Deserialize;
+
+use super::{ApiError, AppState};
+use crate::models::ReturnLine;
+use crate::repository::ReturnLineRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list))
+        .route("/:account_id", put(set).delete(clear))
+}
+
+#[derive(Deserialize)]
+struct LineBody {
+    line: ReturnLine,
+}
+
+async fn list(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let mappings = state
+        .run(|connection| ReturnLineRepository::new(connection).list())
+        .await?;
+    Ok(Json(mappings))
+}
+
+async fn set(
+    State(state): State<AppState>,
+    Path(account_id): Path<i32>,
+    Json(body): Json<LineBody>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mapping = state
+        .run(move |connection| ReturnLineRepository::new(connection).set(account_id, body.line))
+        .await?;
+    Ok(Json(mapping))
+}
+
+async fn clear(
+    State(state): State<AppState>,
+    Path(account_id): Path<i32>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .run(move |connection| ReturnLineRepository::new(connection).clear(account_id))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,29 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+
+use super::{ApiError, AppState};
+use crate::models::TaxSettingsChanges;
+use crate::repository::TaxSettingsRepository;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(show).patch(update))
+}
+
+async fn show(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let settings = state
+        .run(|connection| TaxSettingsRepository::new(connection).get())
+        .await?;
+    Ok(Json(settings))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Json(changes): Json<TaxSettingsChanges>,
+) -> Result<impl IntoResponse, ApiError> {
+    let settings = state
+        .run(move |connection| TaxSettingsRepository::new(connection).update(&changes))
+        .await?;
+    Ok(Json(settings))
+}
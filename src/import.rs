@@ -0,0 +1,166 @@
+//! Reading bank and credit card statement CSVs according to an
+//! [`ImportProfile`]. Parsed rows are staged for review by
+//! [`ImportRepository`](crate::repository::ImportRepository).
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use crate::error::{Error, Result};
+use crate::models::{normalize_text, Encoding, ImportProfile};
+
+/// One transaction read from a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementRow {
+    /// Position of the record in the file, header rows included and blank
+    /// lines not, starting at 1.
+    pub row_no: i32,
+    pub date: NaiveDate,
+    pub description: String,
+    pub withdrawal: i64,
+    pub deposit: i64,
+    pub balance: Option<i64>,
+}
+
+/// Decodes and parses a statement. Blank rows and rows without an amount,
+/// such as carried-forward balances, are left out; any other row that
+/// cannot be read fails the whole file.
+pub fn parse_statement(profile: &ImportProfile, bytes: &[u8]) -> Result<Vec<StatementRow>> {
+    let text = decode(profile.encoding, bytes)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    let records = (1..).zip(reader.records());
+    for (row_no, record) in records.skip(profile.skip_rows.max(0) as usize) {
+        let record = record?;
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let field = |column: i32| record.get(column as usize).unwrap_or("").trim();
+        let invalid = |what: &str, value: &str| {
+            Error::Validation(format!("row {}: invalid {}: {:?}", row_no, what, value))
+        };
+
+        let withdrawal = parse_amount(field(profile.withdrawal_column))
+            .ok_or_else(|| invalid("withdrawal", field(profile.withdrawal_column)))?;
+        let deposit = match profile.deposit_column {
+            Some(column) => {
+                parse_amount(field(column)).ok_or_else(|| invalid("deposit", field(column)))?
+            }
+            None => 0,
+        };
+        let (withdrawal, deposit) = match (withdrawal, deposit) {
+            (w, 0) if w < 0 => (0, -w),
+            (0, d) if d < 0 => (-d, 0),
+            (w, d) if w >= 0 && d >= 0 => (w, d),
+            _ => {
+                return Err(invalid(
+                    "amounts",
+                    &record.iter().collect::<Vec<_>>().join(","),
+                ))
+            }
+        };
+        if withdrawal == 0 && deposit == 0 {
+            continue;
+        }
+        if withdrawal > 0 && deposit > 0 {
+            return Err(Error::Validation(format!(
+                "row {}: both a withdrawal and a deposit",
+                row_no
+            )));
+        }
+
+        let date = field(profile.date_column);
+        let date = NaiveDate::parse_from_str(date, &profile.date_format)
+            .map_err(|_| invalid("date", date))?;
+        let balance = match profile.balance_column {
+            Some(column) if !field(column).is_empty() => {
+                Some(parse_amount(field(column)).ok_or_else(|| invalid("balance", field(column)))?)
+            }
+            _ => None,
+        };
+        rows.push(StatementRow {
+            row_no,
+            date,
+            description: field(profile.description_column).to_string(),
+            withdrawal,
+            deposit,
+            balance,
+        });
+    }
+    Ok(rows)
+}
+
+/// Keys identifying each of `rows` from a statement for `account_id`
+/// independently of the file they came from: account, date, signed amount,
+/// normalized description and the occurrence of that combination within
+/// the file, starting at 1. Genuinely repeated transactions thus get
+/// distinct keys, while a re-imported file maps onto the same ones.
+pub fn fingerprints(account_id: i32, rows: &[StatementRow]) -> Vec<String> {
+    let mut occurrences = HashMap::new();
+    rows.iter()
+        .map(|row| {
+            let key = format!(
+                "{}|{}|{}|{}",
+                account_id,
+                row.date,
+                row.deposit - row.withdrawal,
+                normalize_description(&row.description)
+            );
+            let occurrence = occurrences.entry(key.clone()).or_insert(0);
+            *occurrence += 1;
+            format!("{}|{}", key, occurrence)
+        })
+        .collect()
+}
+
+/// Width- and case-folded description with runs of whitespace collapsed.
+fn normalize_description(description: &str) -> String {
+    normalize_text(description)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode(encoding: Encoding, bytes: &[u8]) -> Result<Cow<'_, str>> {
+    let (encoding, name) = match encoding {
+        Encoding::Utf8 => (encoding_rs::UTF_8, "UTF-8"),
+        Encoding::ShiftJis => (encoding_rs::SHIFT_JIS, "Shift_JIS"),
+    };
+    // Strips a byte order mark if there is one.
+    let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+    if had_errors {
+        return Err(Error::Validation(format!(
+            "statement is not valid {}",
+            name
+        )));
+    }
+    Ok(text)
+}
+
+/// Whole yen from text like `1,234`, `￥1,234`, `-500` or `1,234円`.
+/// Empty text is zero.
+fn parse_amount(text: &str) -> Option<i64> {
+    let cleaned = text
+        .chars()
+        .filter(|c| {
+            !matches!(
+                c,
+                ',' | '，' | '¥' | '￥' | '\\' | '円' | ' ' | '\u{3000}' | '+'
+            )
+        })
+        .map(|c| match c {
+            '０'..='９' => char::from_u32(c as u32 - '０' as u32 + '0' as u32).unwrap_or(c),
+            '－' | '−' | '▲' | '△' => '-',
+            c => c,
+        })
+        .collect::<String>();
+    match cleaned.as_str() {
+        "" | "-" => Some(0),
+        cleaned => cleaned.parse().ok(),
+    }
+}
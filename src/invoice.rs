@@ -0,0 +1,294 @@
+//! Invoices as issued: totals per tax rate and the printable 適格請求書.
+//!
+//! Prices are before tax, and the tax of each rate is worked out once over
+//! the invoice's lines at that rate and rounded once, as the invoice system
+//! requires. The receivable posted on issue splits sales by rate the same
+//! way, so the books carry exactly the tax shown on the invoice.
+
+use serde::Serialize;
+
+use crate::models::{
+    BusinessProfile, Counterparty, Invoice, InvoiceLine, Rounding, TaxCategory, Withholding,
+};
+use crate::pdf;
+use crate::report::statement::escape_html;
+use crate::report::yen;
+use crate::withholding::withholding_tax;
+
+/// Lines at one tax rate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateTotal {
+    pub tax_category: TaxCategory,
+    /// Before tax.
+    pub base: i64,
+    pub tax: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceTotals {
+    /// In the order of [`TaxCategory::ALL`], for the categories used.
+    pub rates: Vec<RateTotal>,
+    pub subtotal: i64,
+    pub tax: i64,
+    pub total: i64,
+    /// Income tax the client withholds, for counterparties with
+    /// withholding on receipts.
+    pub withholding: i64,
+    /// What the client is to pay: the total less withholding.
+    pub amount_due: i64,
+}
+
+/// Totals of `lines`, with tax rounded by `rounding` and withholding by
+/// the client's rule.
+pub fn totals(
+    lines: &[InvoiceLine],
+    rounding: Rounding,
+    withholding: Withholding,
+) -> InvoiceTotals {
+    let rates = TaxCategory::ALL
+        .iter()
+        .filter_map(|&tax_category| {
+            let ours = lines
+                .iter()
+                .filter(|line| line.tax_category == tax_category)
+                .collect::<Vec<_>>();
+            if ours.is_empty() {
+                return None;
+            }
+            let base = ours.iter().map(|line| line.amount()).sum::<i64>();
+            Some(RateTotal {
+                tax_category,
+                base,
+                tax: rounding.divide(base as i128 * tax_category.rate() as i128, 100),
+            })
+        })
+        .collect::<Vec<_>>();
+    let subtotal = rates.iter().map(|rate| rate.base).sum::<i64>();
+    let tax = rates.iter().map(|rate| rate.tax).sum::<i64>();
+    let total = subtotal + tax;
+    let withholding = match withholding {
+        Withholding::Receipts => withholding_tax(total),
+        Withholding::None | Withholding::Payments => 0,
+    };
+    InvoiceTotals {
+        rates,
+        subtotal,
+        tax,
+        total,
+        withholding,
+        amount_due: total - withholding,
+    }
+}
+
+/// How a line's rate is printed; reduced-rate items are marked with ※.
+pub fn rate_label(tax_category: TaxCategory) -> &'static str {
+    match tax_category {
+        TaxCategory::Taxable10 => "10%",
+        TaxCategory::Reduced8 => "8%※",
+        TaxCategory::Exempt => "免税",
+        TaxCategory::NonTaxable => "非課税",
+        TaxCategory::OutOfScope => "不課税",
+    }
+}
+
+/// Everything printed on an invoice.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceDocument {
+    pub issuer: BusinessProfile,
+    pub client: Counterparty,
+    pub invoice: Invoice,
+    pub lines: Vec<InvoiceLine>,
+    pub totals: InvoiceTotals,
+}
+
+/// A table of the document.
+struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    left: usize,
+}
+
+impl InvoiceDocument {
+    /// 適格請求書 when the issuer has a registration number.
+    pub fn title(&self) -> &'static str {
+        match self.issuer.registration_number {
+            Some(_) => "適格請求書",
+            None => "請求書",
+        }
+    }
+
+    /// A self-contained HTML document for one A4 page.
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>{title} {number}</title>
+<style>
+  @page {{ size: A4; margin: 15mm; }}
+  body {{ font-family: "Hiragino Mincho ProN", "Yu Mincho", serif; font-size: 10pt; }}
+  h1 {{ font-size: 16pt; text-align: center; letter-spacing: 0.5em; }}
+  p.client {{ font-size: 13pt; border-bottom: 1px solid #333; }}
+  p {{ margin: 0.2em 0; }}
+  table {{ width: 100%; border-collapse: collapse; margin: 1em 0; }}
+  th, td {{ border: 1px solid #333; padding: 2px 6px; }}
+  td.amount {{ text-align: right; font-variant-numeric: tabular-nums; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<p class="client">{client} 御中</p>
+"#,
+            title = self.title(),
+            number = escape_html(&self.number()),
+            client = escape_html(&self.client.name),
+        );
+        for line in self.header() {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(&line)));
+        }
+        for table in self.tables() {
+            html.push_str("<table>\n<thead><tr>");
+            for header in &table.headers {
+                html.push_str(&format!("<th>{}</th>", escape_html(header)));
+            }
+            html.push_str("</tr></thead>\n<tbody>\n");
+            for row in &table.rows {
+                html.push_str("<tr>");
+                for (i, cell) in row.iter().enumerate() {
+                    match i < table.left {
+                        true => html.push_str(&format!("<td>{}</td>", escape_html(cell))),
+                        false => html
+                            .push_str(&format!("<td class=\"amount\">{}</td>", escape_html(cell))),
+                    }
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+        for line in self.footer() {
+            html.push_str(&format!("<p>{}</p>\n", escape_html(&line)));
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// The same content as a PDF file.
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut document = pdf::Document::new(&format!("{} {}", self.title(), self.number()));
+        document.title(self.title());
+        document.heading(&format!("{} 御中", self.client.name));
+        for line in self.header() {
+            document.paragraph(&line);
+        }
+        for table in self.tables() {
+            document.table(&table.headers, &table.rows, table.left);
+        }
+        for line in self.footer() {
+            document.paragraph(&line);
+        }
+        document.finish()
+    }
+
+    fn number(&self) -> String {
+        self.invoice
+            .number
+            .clone()
+            .unwrap_or_else(|| "(下書き)".to_string())
+    }
+
+    /// Number, dates and the issuer.
+    fn header(&self) -> Vec<String> {
+        let issuer = &self.issuer;
+        let mut lines = vec![
+            format!("請求書番号: {}", self.number()),
+            format!("発行日: {}", self.invoice.issue_date),
+            format!("お支払期限: {}", self.invoice.due_date),
+            format!("ご請求金額: {}円", yen(self.totals.amount_due)),
+            String::new(),
+            issuer.name.clone(),
+        ];
+        if !issuer.address.is_empty() {
+            lines.push(issuer.address.clone());
+        }
+        if let Some(number) = &issuer.registration_number {
+            lines.push(format!("登録番号: {}", number));
+        }
+        lines
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        let items = Table {
+            headers: vec!["品目", "数量", "単価", "金額", "税率"],
+            rows: self
+                .lines
+                .iter()
+                .map(|line| {
+                    vec![
+                        line.description.clone(),
+                        line.quantity.to_string(),
+                        yen(line.unit_price),
+                        yen(line.amount()),
+                        rate_label(line.tax_category).to_string(),
+                    ]
+                })
+                .collect(),
+            left: 1,
+        };
+        let mut rates = self
+            .totals
+            .rates
+            .iter()
+            .map(|rate| {
+                vec![
+                    format!("{}対象", rate_label(rate.tax_category)),
+                    yen(rate.base),
+                    yen(rate.tax),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rates.push(vec![
+            "合計".to_string(),
+            yen(self.totals.subtotal),
+            yen(self.totals.tax),
+        ]);
+        let mut summary = vec![vec!["合計(税込)".to_string(), yen(self.totals.total)]];
+        if self.totals.withholding > 0 {
+            summary.push(vec![
+                "源泉徴収税額".to_string(),
+                yen(-self.totals.withholding),
+            ]);
+            summary.push(vec!["差引請求額".to_string(), yen(self.totals.amount_due)]);
+        }
+        vec![
+            items,
+            Table {
+                headers: vec!["税率区分", "税抜金額", "消費税額"],
+                rows: rates,
+                left: 1,
+            },
+            Table {
+                headers: vec!["", "金額"],
+                rows: summary,
+                left: 1,
+            },
+        ]
+    }
+
+    /// Notes on reduced-rate items, payment details and free text.
+    fn footer(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self
+            .lines
+            .iter()
+            .any(|line| line.tax_category == TaxCategory::Reduced8)
+        {
+            lines.push("※は軽減税率対象".to_string());
+        }
+        if !self.issuer.bank_details.is_empty() {
+            lines.push(format!("お振込先: {}", self.issuer.bank_details));
+        }
+        lines.extend(self.invoice.notes.lines().map(str::to_string));
+        lines
+    }
+}
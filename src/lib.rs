@@ -1,15 +1,50 @@
+pub mod attachment;
+pub mod backup;
+pub mod depreciation;
+pub mod error;
+pub mod fixtures;
+pub mod http;
+pub mod import;
+pub mod invoice;
 pub mod models;
+pub mod pdf;
+pub mod recurring;
+pub mod report;
+pub mod repository;
 pub mod schema;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod withholding;
 
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
 use dotenvy::dotenv;
 use std::env;
 
-pub fn establish_connection() -> SqliteConnection {
+pub use error::{Error, Result};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
+
+pub fn database_url() -> String {
     dotenv().ok();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    SqliteConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+    env::var("DATABASE_URL").expect("DATABASE_URL must be set")
+}
+
+pub fn establish_connection() -> SqliteConnection {
+    let database_url = database_url();
+    let mut connection = SqliteConnection::establish(&database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
+    enable_foreign_keys(&mut connection)
+        .unwrap_or_else(|_| panic!("Error enabling foreign keys on {}", database_url));
+    connection
+}
+
+/// Makes SQLite enforce `REFERENCES` and `ON DELETE CASCADE`, which it
+/// ignores unless turned on for each connection.
+pub fn enable_foreign_keys(connection: &mut SqliteConnection) -> QueryResult<()> {
+    diesel::sql_query("PRAGMA foreign_keys = ON")
+        .execute(connection)
+        .map(|_| ())
 }
@@ -1,29 +1,1271 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
 use self::models::*;
-use diesel::{insert_into, prelude::*};
+use chrono::{Datelike, Local, NaiveDate};
+use clap::{Parser, Subcommand, ValueEnum};
 use new_tax_account_backend::*;
 
-fn main() {
-    use self::schema::posts::dsl::*;
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Business-use ratios of expenses (家事按分)
+    #[command(subcommand)]
+    Apportionment(ApportionmentCommand),
+    /// Evidence files kept under 電子帳簿保存法
+    #[command(subcommand)]
+    Attachment(AttachmentCommand),
+    /// Show or change our name, address and registration number on invoices
+    BusinessProfile {
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        address: Option<String>,
+        /// 登録番号, e.g. T7000012050002; an empty value clears it
+        #[arg(long)]
+        registration_number: Option<String>,
+        /// 振込先 printed on invoices
+        #[arg(long)]
+        bank_details: Option<String>,
+        #[arg(long)]
+        invoice_prefix: Option<String>,
+    },
+    /// Customers and suppliers
+    #[command(subcommand)]
+    Counterparty(CounterpartyCommand),
+    /// Database maintenance
+    #[command(subcommand)]
+    Db(DbCommand),
+    /// Fiscal years and year-end closing
+    #[command(subcommand)]
+    FiscalYear(FiscalYearCommand),
+    /// Fixed asset register (固定資産台帳)
+    #[command(subcommand)]
+    FixedAsset(FixedAssetCommand),
+    /// Bank and credit card statement imports
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Invoices to clients (適格請求書)
+    #[command(subcommand)]
+    Invoice(InvoiceCommand),
+    /// Receivables and payables per counterparty, and the payments applied
+    #[command(subcommand)]
+    OpenItem(OpenItemCommand),
+    /// Bulk operations on posts
+    #[command(subcommand)]
+    Posts(PostsCommand),
+    /// Entries that repeat on a schedule, such as rent
+    #[command(subcommand)]
+    Recurring(RecurringCommand),
+    /// Accounting reports
+    #[command(subcommand)]
+    Report(ReportCommand),
+    /// Lines of the blue-return 決算書 accounts are reported on
+    #[command(subcommand)]
+    ReturnLine(ReturnLineCommand),
+    /// Show or change how consumption tax is booked
+    TaxSettings {
+        #[arg(long, value_parser = parse_text::<TaxMethod>)]
+        method: Option<TaxMethod>,
+        #[arg(long, value_parser = parse_text::<Rounding>)]
+        rounding: Option<Rounding>,
+    },
+    /// Start the HTTP API server
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:3000")]
+        addr: SocketAddr,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Take an online backup of the database.
+    ///
+    /// If PATH is a directory, a timestamped backup is written into it and
+    /// only the newest `--keep` backups are retained.
+    Backup {
+        path: PathBuf,
+        #[arg(long, default_value_t = 7)]
+        keep: usize,
+    },
+    /// Replace the database with a verified backup
+    Restore { path: PathBuf },
+    /// Run an integrity check (defaults to the configured database)
+    Verify { path: Option<PathBuf> },
+    /// Insert deterministic sample data
+    Seed {
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+        #[arg(long, default_value_t = 8)]
+        categories: usize,
+        #[arg(long, default_value_t = 20)]
+        authors: usize,
+        #[arg(long, default_value_t = 200)]
+        posts: usize,
+        #[arg(long, default_value_t = 15)]
+        tags: usize,
+        #[arg(long, default_value_t = 3)]
+        max_tags_per_post: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApportionmentCommand {
+    /// List ratios
+    List,
+    /// Add a ratio from a JSON file (`-` reads standard input)
+    AddRule { file: PathBuf },
+    /// Delete a ratio
+    DeleteRule { id: i32 },
+}
+
+#[derive(Subcommand)]
+enum AttachmentCommand {
+    /// Store a file with the date, amount and counterparty it is searched by
+    Add {
+        file: PathBuf,
+        /// 取引年月日
+        #[arg(long)]
+        date: NaiveDate,
+        /// 取引金額
+        #[arg(long)]
+        amount: i64,
+        #[arg(long)]
+        counterparty: Option<i32>,
+        /// Journal entry the file is evidence for
+        #[arg(long)]
+        entry: Option<i32>,
+        #[arg(long, default_value = "")]
+        description: String,
+    },
+    /// Search by transaction date, amount range and counterparty
+    Search {
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        #[arg(long)]
+        min_amount: Option<i64>,
+        #[arg(long)]
+        max_amount: Option<i64>,
+        #[arg(long)]
+        counterparty: Option<i32>,
+        #[arg(long)]
+        entry: Option<i32>,
+        #[arg(long)]
+        include_deleted: bool,
+    },
+    /// Write the stored content to a file
+    Get {
+        id: i32,
+        #[arg(long)]
+        output: PathBuf,
+    },
+    /// Link an attachment to a journal entry
+    Link {
+        id: i32,
+        #[arg(long)]
+        entry: i32,
+        #[arg(long, default_value = "")]
+        reason: String,
+    },
+    /// Correct the record from a JSON file of changes (`-` reads standard input)
+    Update {
+        id: i32,
+        file: PathBuf,
+        #[arg(long, default_value = "")]
+        reason: String,
+    },
+    /// Mark an attachment deleted; the file and its history are kept
+    Delete {
+        id: i32,
+        #[arg(long, default_value = "")]
+        reason: String,
+    },
+    /// Show every version of the record
+    History { id: i32 },
+    /// Check stored files against their hashes and records against their history
+    Verify,
+}
+
+#[derive(Subcommand)]
+enum InvoiceCommand {
+    /// List invoices
+    List {
+        #[arg(long, value_parser = parse_text::<InvoiceStatus>)]
+        status: Option<InvoiceStatus>,
+        #[arg(long)]
+        counterparty: Option<i32>,
+    },
+    /// Add a draft from a JSON file (`-` reads standard input)
+    Create { file: PathBuf },
+    /// Replace a draft from a JSON file (`-` reads standard input)
+    Update { id: i32, file: PathBuf },
+    /// Number a draft and post the receivable
+    Issue { id: i32 },
+    /// Record a payment, in full or in part, and apply it to the receivable
+    Pay {
+        id: i32,
+        #[arg(long)]
+        date: NaiveDate,
+        /// Code of the account paid into (defaults to 普通預金)
+        #[arg(long)]
+        account: Option<String>,
+        /// Amount received (defaults to what is left to pay)
+        #[arg(long)]
+        amount: Option<i64>,
+    },
+    /// Cancel a draft or unpaid invoice, voiding its receivable
+    Void { id: i32 },
+    /// Print the invoice; `pdf` is written to standard output
+    Document {
+        id: i32,
+        #[arg(long, value_enum, default_value_t = DocumentFormat::Html)]
+        format: DocumentFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum OpenItemCommand {
+    /// Open items and unapplied payments per counterparty
+    List {
+        #[arg(long, value_parser = parse_text::<OpenItemKind>, default_value = "receivable")]
+        kind: OpenItemKind,
+        #[arg(long)]
+        counterparty: Option<i32>,
+        /// Only lines dated up to this day (YYYY-MM-DD)
+        #[arg(long)]
+        as_of: Option<NaiveDate>,
+    },
+    /// Apply a payment line to an item line, in full or in part
+    Match {
+        item_line: i32,
+        payment_line: i32,
+        /// Defaults to as much as both have left
+        #[arg(long)]
+        amount: Option<i64>,
+    },
+    /// Remove a match
+    Unmatch { id: i32 },
+    /// Apply unapplied payments to the oldest open items
+    AutoMatch {
+        #[arg(long, value_parser = parse_text::<OpenItemKind>, default_value = "receivable")]
+        kind: OpenItemKind,
+        #[arg(long)]
+        counterparty: Option<i32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecurringCommand {
+    /// List templates
+    List,
+    /// Show a template with its lines
+    Get { id: i32 },
+    /// Create a template from a JSON file (`-` reads standard input)
+    Create { file: PathBuf },
+    /// Replace a template; occurrences already posted are kept
+    Update { id: i32, file: PathBuf },
+    /// Delete a template that has never been posted
+    Delete { id: i32 },
+    /// Occurrences of a template and whether they are posted
+    Occurrences {
+        id: i32,
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Defaults to the end of the current year
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Leave out one occurrence
+    Skip { id: i32, date: NaiveDate },
+    /// Change the date or lines of one occurrence from a JSON file
+    Modify {
+        id: i32,
+        date: NaiveDate,
+        file: PathBuf,
+    },
+    /// Undo skipping or changing one occurrence
+    Restore { id: i32, date: NaiveDate },
+    /// Post every occurrence due up to a date that is not posted yet
+    Run {
+        /// Defaults to today
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReturnLineCommand {
+    /// List accounts mapped to a line
+    List,
+    /// Report an account on a line, e.g. `set 528 other`
+    Set {
+        /// Account code
+        code: String,
+        #[arg(value_parser = parse_text::<ReturnLine>)]
+        line: ReturnLine,
+    },
+    /// Put an account back on its default line
+    Clear {
+        /// Account code
+        code: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CounterpartyCommand {
+    /// List counterparties
+    List,
+    /// Add a counterparty
+    Create {
+        #[arg(long)]
+        name: String,
+        /// 登録番号 of a qualified invoice issuer, e.g. T7000012050002
+        #[arg(long)]
+        registration_number: Option<String>,
+        #[arg(long, default_value = "")]
+        address: String,
+        #[arg(long)]
+        qualified: bool,
+        #[arg(long)]
+        valid_from: Option<NaiveDate>,
+        #[arg(long)]
+        valid_until: Option<NaiveDate>,
+        /// Income tax withheld at source: none, payments or receipts
+        #[arg(long, value_parser = parse_text::<Withholding>, default_value = "none")]
+        withholding: Withholding,
+    },
+}
+
+#[derive(Subcommand)]
+enum FiscalYearCommand {
+    /// List fiscal years
+    List,
+    /// Add a fiscal year
+    Create {
+        #[arg(long)]
+        start: NaiveDate,
+        #[arg(long)]
+        end: NaiveDate,
+    },
+    /// Close a year: post the closing entry and next year's opening entry
+    Close { id: i32 },
+    /// Reopen a closed year, voiding its closing and opening entries
+    Reopen { id: i32 },
+    /// Post the year-end depreciation entry (also done on closing)
+    Depreciate { id: i32 },
+    /// Post the year-end 家事按分 entry (also done on closing)
+    Apportion { id: i32 },
+}
+
+#[derive(Subcommand)]
+enum FixedAssetCommand {
+    /// List fixed assets
+    List,
+    /// Add an asset from a JSON file (`-` reads standard input)
+    Create { file: PathBuf },
+    /// Record the disposal of an asset
+    Dispose {
+        id: i32,
+        #[arg(long)]
+        date: NaiveDate,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportCommand {
+    /// Add an institution's column mapping from a JSON file (`-` reads standard input)
+    AddProfile { file: PathBuf },
+    /// Add a matching rule from a JSON file (`-` reads standard input)
+    AddRule { file: PathBuf },
+    /// Stage the rows of a statement CSV
+    Statement {
+        #[arg(long)]
+        profile: i32,
+        file: PathBuf,
+    },
+    /// List import batches, latest first
+    Batches,
+    /// Show the rows of a batch skipped as already imported
+    Skipped { batch: i32 },
+    /// Remove a batch and void the entries posted from it
+    Undo { batch: i32 },
+    /// Show the entries proposed for pending transactions
+    Review {
+        #[arg(long)]
+        batch: Option<i32>,
+    },
+    /// Post pending transactions as proposed
+    Post { ids: Vec<i32> },
+    /// Mark pending transactions as not to be posted
+    Ignore { ids: Vec<i32> },
+}
+
+#[derive(Subcommand)]
+enum PostsCommand {
+    /// Create posts from a JSON array (`-` reads standard input)
+    Create { file: PathBuf },
+    /// Publish the given posts
+    Publish { ids: Vec<i32> },
+    /// Unpublish the given posts
+    Unpublish { ids: Vec<i32> },
+    /// Move posts to a category (omit --category to clear it)
+    Recategorize {
+        #[arg(long)]
+        category: Option<i32>,
+        ids: Vec<i32>,
+    },
+    /// Delete posts matching all of the given conditions
+    Delete {
+        #[arg(long = "id")]
+        ids: Vec<i32>,
+        #[arg(long)]
+        category: Option<i32>,
+        #[arg(long)]
+        author: Option<String>,
+        #[arg(long)]
+        published: Option<bool>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReportCommand {
+    /// Trial balance (合計残高試算表) for a period, by default the current year
+    TrialBalance {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Profit and loss statement (損益計算書) compared with the prior year
+    IncomeStatement {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Balance sheet (貸借対照表) at the start and end of the period
+    BalanceSheet {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Consumption tax by rate (消費税集計表) with the figures for filing
+    ConsumptionTax {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Business and private shares of apportioned expenses (家事按分)
+    Apportionment {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// 預り金 ledger of income tax withheld from payments (源泉所得税)
+    Withholding {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Yearly payments and tax withheld per payee (支払調書)
+    PaymentRecords {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Open receivables or payables by age (年齢表)
+    Aging {
+        /// Defaults to today (YYYY-MM-DD)
+        #[arg(long)]
+        as_of: Option<NaiveDate>,
+        #[arg(long, value_parser = parse_text::<OpenItemKind>, default_value = "receivable")]
+        kind: OpenItemKind,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+    /// Blue-return financial statements (青色申告決算書) for a fiscal year
+    BlueReturn {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = DocumentFormat::Table)]
+        format: DocumentFormat,
+    },
+    /// Depreciation schedule (減価償却費の計算) for a fiscal year
+    Depreciation {
+        #[command(flatten)]
+        period: PeriodArgs,
+        #[arg(long, value_enum, default_value_t = Format::Table)]
+        format: Format,
+    },
+}
+
+#[derive(clap::Args)]
+struct PeriodArgs {
+    /// First day of the period (YYYY-MM-DD)
+    #[arg(long)]
+    from: Option<NaiveDate>,
+    /// Last day of the period (YYYY-MM-DD)
+    #[arg(long)]
+    to: Option<NaiveDate>,
+}
+
+impl PeriodArgs {
+    /// Missing bounds default to the current calendar year.
+    fn resolve(&self) -> (NaiveDate, NaiveDate) {
+        let year = Local::now().year();
+        (
+            self.from
+                .unwrap_or_else(|| NaiveDate::from_ymd_opt(year, 1, 1).unwrap()),
+            self.to
+                .unwrap_or_else(|| NaiveDate::from_ymd_opt(year, 12, 31).unwrap()),
+        )
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+    Html,
+}
+
+/// Output of printable documents; `pdf` is written to standard output.
+#[derive(Clone, Copy, ValueEnum)]
+enum DocumentFormat {
+    Table,
+    Json,
+    Html,
+    Pdf,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Some(Command::Apportionment(command)) => run_apportionment(command),
+        Some(Command::Attachment(command)) => run_attachment(command),
+        Some(Command::BusinessProfile {
+            name,
+            address,
+            registration_number,
+            bank_details,
+            invoice_prefix,
+        }) => run_business_profile(BusinessProfileChanges {
+            name,
+            address,
+            registration_number: registration_number
+                .map(|number| Some(number).filter(|number| !number.is_empty())),
+            bank_details,
+            invoice_prefix,
+        }),
+        Some(Command::Counterparty(command)) => run_counterparty(command),
+        Some(Command::Db(command)) => run_db(command),
+        Some(Command::FiscalYear(command)) => run_fiscal_year(command),
+        Some(Command::FixedAsset(command)) => run_fixed_asset(command),
+        Some(Command::Import(command)) => run_import(command),
+        Some(Command::Invoice(command)) => run_invoice(command),
+        Some(Command::OpenItem(command)) => run_open_item(command),
+        Some(Command::Posts(command)) => run_posts(command),
+        Some(Command::Recurring(command)) => run_recurring(command),
+        Some(Command::Report(command)) => run_report(command),
+        Some(Command::ReturnLine(command)) => run_return_line(command),
+        Some(Command::TaxSettings { method, rounding }) => {
+            run_tax_settings(TaxSettingsChanges { method, rounding })
+        }
+        Some(Command::Serve { addr }) => serve(addr),
+        None => run_report(ReportCommand::TrialBalance {
+            period: PeriodArgs {
+                from: None,
+                to: None,
+            },
+            format: Format::Table,
+        }),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn serve(addr: SocketAddr) -> Result<()> {
+    let pool = http::pool(&database_url())?;
+    println!("listening on http://{}", addr);
+    tokio::runtime::Runtime::new()?.block_on(http::serve(pool, addr))
+}
+
+fn run_apportionment(command: ApportionmentCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::ApportionmentRepository::new(connection);
+    let json = match command {
+        ApportionmentCommand::List => serde_json::to_string_pretty(&repository.list_rules()?),
+        ApportionmentCommand::AddRule { file } => {
+            serde_json::to_string_pretty(&repository.create_rule(&read_json(&file)?)?)
+        }
+        ApportionmentCommand::DeleteRule { id } => {
+            repository.delete_rule(id)?;
+            return Ok(());
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+fn run_return_line(command: ReturnLineCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let json = match command {
+        ReturnLineCommand::List => {
+            serde_json::to_string_pretty(&repository::ReturnLineRepository::new(connection).list()?)
+        }
+        ReturnLineCommand::Set { code, line } => {
+            let account = repository::AccountRepository::new(connection).find_by_code(&code)?;
+            serde_json::to_string_pretty(
+                &repository::ReturnLineRepository::new(connection).set(account.id, line)?,
+            )
+        }
+        ReturnLineCommand::Clear { code } => {
+            let account = repository::AccountRepository::new(connection).find_by_code(&code)?;
+            return repository::ReturnLineRepository::new(connection).clear(account.id);
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+fn run_attachment(command: AttachmentCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::AttachmentRepository::new(connection);
+    let json = match command {
+        AttachmentCommand::Add {
+            file,
+            date,
+            amount,
+            counterparty,
+            entry,
+            description,
+        } => {
+            let content = std::fs::read(&file)?;
+            let file_name = file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            serde_json::to_string_pretty(&repository.create(
+                &NewAttachment {
+                    content_type: attachment::content_type(&file_name).to_string(),
+                    file_name,
+                    transaction_date: date,
+                    amount,
+                    counterparty_id: counterparty,
+                    entry_id: entry,
+                    description,
+                },
+                &content,
+            )?)
+        }
+        AttachmentCommand::Search {
+            from,
+            to,
+            min_amount,
+            max_amount,
+            counterparty,
+            entry,
+            include_deleted,
+        } => serde_json::to_string_pretty(&repository.search(&repository::AttachmentQuery {
+            from,
+            to,
+            min_amount,
+            max_amount,
+            counterparty_id: counterparty,
+            entry_id: entry,
+            include_deleted,
+        })?),
+        AttachmentCommand::Get { id, output } => {
+            let (attachment, content) = repository.content(id)?;
+            std::fs::write(&output, content)?;
+            serde_json::to_string_pretty(&attachment)
+        }
+        AttachmentCommand::Link { id, entry, reason } => {
+            serde_json::to_string_pretty(&repository.update(
+                id,
+                &AttachmentChanges {
+                    entry_id: Some(Some(entry)),
+                    ..Default::default()
+                },
+                &reason,
+            )?)
+        }
+        AttachmentCommand::Update { id, file, reason } => {
+            serde_json::to_string_pretty(&repository.update(id, &read_json(&file)?, &reason)?)
+        }
+        AttachmentCommand::Delete { id, reason } => {
+            serde_json::to_string_pretty(&repository.delete(id, &reason)?)
+        }
+        AttachmentCommand::History { id } => serde_json::to_string_pretty(&repository.history(id)?),
+        AttachmentCommand::Verify => {
+            let count = repository.verify()?;
+            println!("{} attachments: ok", count);
+            return Ok(());
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+fn run_counterparty(command: CounterpartyCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::CounterpartyRepository::new(connection);
+    let json = match command {
+        CounterpartyCommand::List => serde_json::to_string_pretty(&repository.list()?),
+        CounterpartyCommand::Create {
+            name,
+            registration_number,
+            address,
+            qualified,
+            valid_from,
+            valid_until,
+            withholding,
+        } => serde_json::to_string_pretty(&repository.create(&NewCounterparty {
+            name,
+            registration_number,
+            address,
+            qualified,
+            valid_from,
+            valid_until,
+            withholding,
+        })?),
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
 
+fn run_fiscal_year(command: FiscalYearCommand) -> Result<()> {
     let connection = &mut establish_connection();
+    let mut repository = repository::FiscalYearRepository::new(connection);
+    let json = match command {
+        FiscalYearCommand::List => serde_json::to_string_pretty(&repository.list()?),
+        FiscalYearCommand::Create { start, end } => {
+            serde_json::to_string_pretty(&repository.create(&NewFiscalYear {
+                start_date: start,
+                end_date: end,
+            })?)
+        }
+        FiscalYearCommand::Close { id } => serde_json::to_string_pretty(&repository.close(id)?),
+        FiscalYearCommand::Reopen { id } => serde_json::to_string_pretty(&repository.reopen(id)?),
+        FiscalYearCommand::Depreciate { id } => serde_json::to_string_pretty(
+            &repository::FixedAssetRepository::new(connection).post_depreciation(id)?,
+        ),
+        FiscalYearCommand::Apportion { id } => serde_json::to_string_pretty(
+            &repository::ApportionmentRepository::new(connection).post_adjustments(id)?,
+        ),
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
 
-    insert_into(posts)
-        .values((title.eq("test title"), body.eq("tes"), published.eq(true)))
-        .execute(connection)
-        .unwrap();
+fn run_fixed_asset(command: FixedAssetCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::FixedAssetRepository::new(connection);
+    let json = match command {
+        FixedAssetCommand::List => serde_json::to_string_pretty(&repository.list()?),
+        FixedAssetCommand::Create { file } => {
+            let asset = repository.create(&read_json(&file)?)?;
+            if asset.method == DepreciationMethod::SmallAmount {
+                let usage = repository.small_amount_usage_on(asset.acquired_on)?;
+                if let Some(warning) = usage.warning() {
+                    eprintln!("warning: {}", warning);
+                }
+            }
+            serde_json::to_string_pretty(&asset)
+        }
+        FixedAssetCommand::Dispose { id, date } => {
+            serde_json::to_string_pretty(&repository.update(
+                id,
+                &FixedAssetChanges {
+                    disposed_on: Some(Some(date)),
+                    ..Default::default()
+                },
+            )?)
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+fn run_business_profile(changes: BusinessProfileChanges) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::BusinessProfileRepository::new(connection);
+    let unchanged = changes.name.is_none()
+        && changes.address.is_none()
+        && changes.registration_number.is_none()
+        && changes.bank_details.is_none()
+        && changes.invoice_prefix.is_none();
+    let profile = match unchanged {
+        true => repository.get()?,
+        false => repository.update(&changes)?,
+    };
+    println!("{}", serde_json::to_string_pretty(&profile).unwrap());
+    Ok(())
+}
+
+fn run_invoice(command: InvoiceCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let json = match command {
+        InvoiceCommand::List {
+            status,
+            counterparty,
+        } => serde_json::to_string_pretty(&repository::InvoiceRepository::new(connection).list(
+            &repository::InvoiceQuery {
+                status,
+                counterparty_id: counterparty,
+            },
+        )?),
+        InvoiceCommand::Create { file } => serde_json::to_string_pretty(
+            &repository::InvoiceRepository::new(connection).create(&read_json(&file)?)?,
+        ),
+        InvoiceCommand::Update { id, file } => serde_json::to_string_pretty(
+            &repository::InvoiceRepository::new(connection).update(id, &read_json(&file)?)?,
+        ),
+        InvoiceCommand::Issue { id } => {
+            serde_json::to_string_pretty(&repository::InvoiceRepository::new(connection).issue(id)?)
+        }
+        InvoiceCommand::Pay {
+            id,
+            date,
+            account,
+            amount,
+        } => {
+            let account_id = match account {
+                Some(code) => Some(
+                    repository::AccountRepository::new(connection)
+                        .find_by_code(&code)?
+                        .id,
+                ),
+                None => None,
+            };
+            serde_json::to_string_pretty(&repository::InvoiceRepository::new(connection).pay(
+                id,
+                &InvoicePayment {
+                    paid_on: date,
+                    account_id,
+                    amount,
+                },
+            )?)
+        }
+        InvoiceCommand::Void { id } => {
+            serde_json::to_string_pretty(&repository::InvoiceRepository::new(connection).void(id)?)
+        }
+        InvoiceCommand::Document { id, format } => {
+            let document = repository::InvoiceRepository::new(connection).document(id)?;
+            match format {
+                DocumentFormat::Table => {
+                    return Err(Error::Validation(
+                        "the invoice has no table output".to_string(),
+                    ))
+                }
+                DocumentFormat::Json => serde_json::to_string_pretty(&document),
+                DocumentFormat::Html => {
+                    print!("{}", document.to_html());
+                    return Ok(());
+                }
+                DocumentFormat::Pdf => {
+                    std::io::Write::write_all(&mut std::io::stdout().lock(), &document.to_pdf())?;
+                    return Ok(());
+                }
+            }
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+fn run_open_item(command: OpenItemCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::OpenItemRepository::new(connection);
+    let json = match command {
+        OpenItemCommand::List {
+            kind,
+            counterparty,
+            as_of,
+        } => serde_json::to_string_pretty(&repository.outstanding(&repository::OpenItemQuery {
+            kind,
+            counterparty_id: counterparty,
+            as_of,
+        })?),
+        OpenItemCommand::Match {
+            item_line,
+            payment_line,
+            amount,
+        } => serde_json::to_string_pretty(&repository.match_payment(&NewOpenItemMatch {
+            item_line_id: item_line,
+            payment_line_id: payment_line,
+            amount,
+        })?),
+        OpenItemCommand::Unmatch { id } => {
+            repository.unmatch(id)?;
+            return Ok(());
+        }
+        OpenItemCommand::AutoMatch { kind, counterparty } => {
+            serde_json::to_string_pretty(&repository.auto_match(&repository::OpenItemQuery {
+                kind,
+                counterparty_id: counterparty,
+                as_of: None,
+            })?)
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
 
-    let results = posts
-        .filter(published.eq(true))
-        .limit(5)
-        .select(Post::as_select())
-        .load(connection)
-        .expect("Error loading posts");
+fn run_recurring(command: RecurringCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::RecurringEntryRepository::new(connection);
+    let today = Local::now().date_naive();
+    let json = match command {
+        RecurringCommand::List => serde_json::to_string_pretty(&repository.list()?),
+        RecurringCommand::Get { id } => serde_json::to_string_pretty(&repository.find(id)?),
+        RecurringCommand::Create { file } => {
+            serde_json::to_string_pretty(&repository.create(&read_json(&file)?)?)
+        }
+        RecurringCommand::Update { id, file } => {
+            serde_json::to_string_pretty(&repository.update(id, &read_json(&file)?)?)
+        }
+        RecurringCommand::Delete { id } => {
+            repository.delete(id)?;
+            return Ok(());
+        }
+        RecurringCommand::Occurrences { id, from, to } => {
+            let end_of_year = NaiveDate::from_ymd_opt(today.year(), 12, 31).unwrap();
+            serde_json::to_string_pretty(&repository.occurrences(
+                id,
+                from,
+                to.unwrap_or(end_of_year),
+            )?)
+        }
+        RecurringCommand::Skip { id, date } => {
+            serde_json::to_string_pretty(&repository.skip(id, date)?)
+        }
+        RecurringCommand::Modify { id, date, file } => {
+            serde_json::to_string_pretty(&repository.modify(id, date, &read_json(&file)?)?)
+        }
+        RecurringCommand::Restore { id, date } => {
+            serde_json::to_string_pretty(&repository.restore(id, date)?)
+        }
+        RecurringCommand::Run { to } => {
+            serde_json::to_string_pretty(&repository.materialize(to.unwrap_or(today))?)
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+fn run_tax_settings(changes: TaxSettingsChanges) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::TaxSettingsRepository::new(connection);
+    let settings = match (changes.method, changes.rounding) {
+        (None, None) => repository.get()?,
+        _ => repository.update(&changes)?,
+    };
+    println!("{}", serde_json::to_string_pretty(&settings).unwrap());
+    Ok(())
+}
+
+/// Parses one of the labels of a text enum, e.g. `exclusive`.
+fn parse_text<T: std::str::FromStr<Err = String>>(value: &str) -> std::result::Result<T, String> {
+    value.parse()
+}
+
+fn run_import(command: ImportCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::ImportRepository::new(connection);
+    let json = match command {
+        ImportCommand::AddProfile { file } => {
+            serde_json::to_string_pretty(&repository.create_profile(&read_json(&file)?)?)
+        }
+        ImportCommand::AddRule { file } => {
+            serde_json::to_string_pretty(&repository.create_rule(&read_json(&file)?)?)
+        }
+        ImportCommand::Statement { profile, file } => {
+            let bytes = std::fs::read(&file)?;
+            let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+            serde_json::to_string_pretty(&repository.import(profile, &file_name, &bytes)?)
+        }
+        ImportCommand::Batches => serde_json::to_string_pretty(&repository.list_batches()?),
+        ImportCommand::Skipped { batch } => {
+            serde_json::to_string_pretty(&repository.skipped(batch)?)
+        }
+        ImportCommand::Undo { batch } => {
+            serde_json::to_string_pretty(&repository.undo_batch(batch)?)
+        }
+        ImportCommand::Review { batch } => {
+            serde_json::to_string_pretty(&repository.proposals(batch)?)
+        }
+        ImportCommand::Post { ids } => {
+            let posted = ids
+                .iter()
+                .map(|&id| repository.post(id, None))
+                .collect::<Result<Vec<_>>>()?;
+            serde_json::to_string_pretty(&posted)
+        }
+        ImportCommand::Ignore { ids } => {
+            let ignored = ids
+                .iter()
+                .map(|&id| repository.ignore(id))
+                .collect::<Result<Vec<_>>>()?;
+            serde_json::to_string_pretty(&ignored)
+        }
+    };
+    println!("{}", json.unwrap());
+    Ok(())
+}
+
+/// Reads JSON from a file, or from standard input for `-`.
+fn read_json<T: serde::de::DeserializeOwned>(file: &std::path::Path) -> Result<T> {
+    let json = if file.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(file)?
+    };
+    serde_json::from_str(&json).map_err(|e| Error::Validation(e.to_string()))
+}
+
+fn run_posts(command: PostsCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    let mut repository = repository::PostRepository::new(connection);
+    let items = match command {
+        PostsCommand::Create { file } => {
+            let new_posts: Vec<NewPost> = read_json(&file)?;
+            repository.bulk_create(&new_posts)?
+        }
+        PostsCommand::Publish { ids } => repository.bulk_set_published(&ids, true)?,
+        PostsCommand::Unpublish { ids } => repository.bulk_set_published(&ids, false)?,
+        PostsCommand::Recategorize { category, ids } => {
+            repository.bulk_recategorize(&ids, category)?
+        }
+        PostsCommand::Delete {
+            ids,
+            category,
+            author,
+            published,
+        } => repository.bulk_delete(&repository::PostFilter {
+            ids: (!ids.is_empty()).then_some(ids),
+            category_id: category,
+            author,
+            published,
+        })?,
+    };
+    println!("{}", serde_json::to_string_pretty(&items).unwrap());
+    Ok(())
+}
+
+fn run_db(command: DbCommand) -> Result<()> {
+    let database = PathBuf::from(database_url());
+    match command {
+        DbCommand::Backup { path, keep } if path.is_dir() => {
+            let dest = backup::backup_rotated(&database, &path, keep)?;
+            println!("backup written to {}", dest.display());
+        }
+        DbCommand::Backup { path, .. } => {
+            backup::backup(&database, &path)?;
+            println!("backup written to {}", path.display());
+        }
+        DbCommand::Restore { path } => {
+            backup::restore(&path, &database)?;
+            println!("restored {} from {}", database.display(), path.display());
+        }
+        DbCommand::Verify { path } => {
+            let path = path.unwrap_or(database);
+            backup::verify(&path)?;
+            println!("{}: ok", path.display());
+        }
+        DbCommand::Seed {
+            seed,
+            categories,
+            authors,
+            posts,
+            tags,
+            max_tags_per_post,
+        } => {
+            let config = fixtures::SeedConfig {
+                seed,
+                categories,
+                authors,
+                posts,
+                tags,
+                max_tags_per_post,
+            };
+            let summary = fixtures::seed(&mut establish_connection(), &config)?;
+            println!(
+                "inserted {} categories, {} tags, {} posts, {} post tags",
+                summary.categories, summary.tags, summary.posts, summary.post_tags
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_report(command: ReportCommand) -> Result<()> {
+    let connection = &mut establish_connection();
+    match command {
+        ReportCommand::TrialBalance { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::TrialBalance::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the trial balance has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+        ReportCommand::IncomeStatement { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::IncomeStatement::generate(connection, report::Period::new(from, to)?)?;
+            print_statement(&report, format)?;
+        }
+        ReportCommand::BalanceSheet { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::BalanceSheet::generate(connection, report::Period::new(from, to)?)?;
+            print_statement(&report, format)?;
+        }
+        ReportCommand::ConsumptionTax { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::ConsumptionTaxReport::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the consumption tax report has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+        ReportCommand::Apportionment { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::ApportionmentReport::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the apportionment report has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+        ReportCommand::Withholding { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::WithholdingLedger::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the withholding ledger has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+        ReportCommand::PaymentRecords { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::PaymentRecordSummary::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the payment record summary has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+        ReportCommand::Aging {
+            as_of,
+            kind,
+            format,
+        } => {
+            let as_of = as_of.unwrap_or_else(|| Local::now().date_naive());
+            let report = report::AgingReport::generate(connection, kind, as_of)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the aging report has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+        ReportCommand::BlueReturn { period, format } => {
+            let (from, to) = period.resolve();
+            let report = report::BlueReturn::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                DocumentFormat::Table => print!("{}", report.to_table()),
+                DocumentFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&report).unwrap())
+                }
+                DocumentFormat::Html => print!("{}", report.to_html()),
+                DocumentFormat::Pdf => {
+                    std::io::Write::write_all(&mut std::io::stdout().lock(), &report.to_pdf())?
+                }
+            }
+        }
+        ReportCommand::Depreciation { period, format } => {
+            let (from, to) = period.resolve();
+            let report =
+                report::DepreciationSchedule::generate(connection, report::Period::new(from, to)?)?;
+            match format {
+                Format::Table => print!("{}", report.to_table()),
+                Format::Csv => report.write_csv(std::io::stdout().lock())?,
+                Format::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Format::Html => {
+                    return Err(Error::Validation(
+                        "the depreciation schedule has no HTML output".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
 
-    println!("Displaying {} posts", results.len());
-    for post in results {
-        println!(
-            "id:{:?}, title:{}, body: {}, published: {}",
-            post.id, post.title, post.body, post.published
-        );
+fn print_statement(statement: &impl report::Statement, format: Format) -> Result<()> {
+    match format {
+        Format::Table => print!("{}", statement.to_table()),
+        Format::Csv => statement.write_csv(std::io::stdout().lock())?,
+        Format::Json => println!("{}", serde_json::to_string_pretty(statement).unwrap()),
+        Format::Html => print!("{}", statement.to_html()),
     }
+    Ok(())
 }
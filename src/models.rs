@@ -1,7 +1,67 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
 use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::sqlite::{Sqlite, SqliteValue};
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Default, Queryable, Selectable, Insertable)]
+/// Declares a fieldless enum stored in a TEXT column. Each variant maps to
+/// the given label both in the database and in JSON.
+macro_rules! text_enum {
+    ($(#[$meta:meta])* pub enum $name:ident { $($(#[$vmeta:meta])* $variant:ident => $label:literal,)+ }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+        #[diesel(sql_type = Text)]
+        pub enum $name {
+            $($(#[$vmeta])* #[serde(rename = $label)] $variant,)+
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)+];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $label,)+
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($label => Ok($name::$variant),)+
+                    _ => Err(format!("unknown {}: {}", stringify!($name), s)),
+                }
+            }
+        }
+
+        impl ToSql<Text, Sqlite> for $name {
+            fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+                out.set_value(self.as_str());
+                Ok(IsNull::No)
+            }
+        }
+
+        impl FromSql<Text, Sqlite> for $name {
+            fn from_sql(value: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+                let text = <String as FromSql<Text, Sqlite>>::from_sql(value)?;
+                Ok(text.parse()?)
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Default, Queryable, Selectable, Insertable, Serialize)]
 #[diesel(table_name = crate::schema::posts)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct Post {
@@ -14,4 +74,1301 @@ pub struct Post {
     pub good_count: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Incremented on every update; used for optimistic concurrency control.
+    pub version: i32,
+}
+
+/// A post to insert. `None` is written as `NULL` rather than left to the
+/// column default, so several posts fit in one multi-row `INSERT`.
+#[derive(Debug, Clone, Default, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::posts)]
+#[diesel(treat_none_as_default_value = false)]
+pub struct NewPost {
+    pub title: String,
+    pub body: String,
+    pub category_id: Option<i32>,
+    pub author: Option<String>,
+    #[serde(default)]
+    pub published: bool,
+}
+
+/// Fields to change on a post. `None` leaves a field untouched; for the
+/// nullable columns `Some(None)` (JSON `null`) clears the value.
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::posts)]
+pub struct PostChanges {
+    pub title: Option<String>,
+    pub body: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub category_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub author: Option<Option<String>>,
+    pub published: Option<bool>,
+}
+
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Default, Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::category)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Category {
+    pub id: Option<i32>,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Default, Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Tag {
+    pub id: Option<i32>,
+    pub name: String,
+}
+
+#[derive(Queryable, Selectable, Insertable)]
+#[diesel(table_name = crate::schema::post_tags)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct PostTag {
+    pub post_id: i32,
+    pub tag_id: i32,
+}
+
+text_enum! {
+    pub enum AccountType {
+        Asset => "asset",
+        Liability => "liability",
+        Equity => "equity",
+        Revenue => "revenue",
+        Expense => "expense",
+    }
+}
+
+impl AccountType {
+    /// Heading used in reports, e.g. 資産.
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountType::Asset => "資産",
+            AccountType::Liability => "負債",
+            AccountType::Equity => "純資産",
+            AccountType::Revenue => "収益",
+            AccountType::Expense => "費用",
+        }
+    }
+
+    /// The side on which the account normally carries its balance.
+    pub fn normal_side(&self) -> Side {
+        match self {
+            AccountType::Asset | AccountType::Expense => Side::Debit,
+            AccountType::Liability | AccountType::Equity | AccountType::Revenue => Side::Credit,
+        }
+    }
+
+    /// Balance from debit and credit totals, positive on the normal side.
+    pub fn balance(&self, debit: i64, credit: i64) -> i64 {
+        match self.normal_side() {
+            Side::Debit => debit - credit,
+            Side::Credit => credit - debit,
+        }
+    }
+}
+
+text_enum! {
+    /// Consumption tax treatment (消費税区分).
+    pub enum TaxCategory {
+        /// 課税 10%
+        Taxable10 => "taxable_10",
+        /// 課税 8% (軽減税率)
+        Reduced8 => "reduced_8",
+        /// 免税 (exports)
+        Exempt => "exempt",
+        /// 非課税
+        NonTaxable => "non_taxable",
+        /// 不課税
+        OutOfScope => "out_of_scope",
+    }
+}
+
+impl TaxCategory {
+    /// Tax rate in percent; zero for categories that carry no tax.
+    pub fn rate(&self) -> i64 {
+        match self {
+            TaxCategory::Taxable10 => 10,
+            TaxCategory::Reduced8 => 8,
+            TaxCategory::Exempt | TaxCategory::NonTaxable | TaxCategory::OutOfScope => 0,
+        }
+    }
+}
+
+text_enum! {
+    /// How consumption tax is booked.
+    pub enum TaxMethod {
+        /// 税込経理: amounts include tax, which is only recorded per line.
+        Inclusive => "inclusive",
+        /// 税抜経理: tax is split into 仮払消費税 / 仮受消費税.
+        Exclusive => "exclusive",
+    }
+}
+
+text_enum! {
+    /// Rounding of tax amounts below one yen.
+    pub enum Rounding {
+        Floor => "floor",
+        Round => "round",
+        Ceil => "ceil",
+    }
+}
+
+impl Rounding {
+    /// `numerator / denominator` rounded to whole yen. The denominator must
+    /// be positive. `Round` rounds halves away from zero, so a negative
+    /// amount such as a return rounds to the negation of its sale.
+    pub fn divide(&self, numerator: i128, denominator: i128) -> i64 {
+        let (n, d) = (numerator, denominator);
+        let quotient = match self {
+            Rounding::Floor => n.div_euclid(d),
+            Rounding::Ceil => -(-n).div_euclid(d),
+            Rounding::Round => n.signum() * (2 * n.abs() + d).div_euclid(2 * d),
+        };
+        quotient as i64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Serialize)]
+#[diesel(table_name = crate::schema::tax_settings)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TaxSettings {
+    pub method: TaxMethod,
+    pub rounding: Rounding,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::tax_settings)]
+pub struct TaxSettingsChanges {
+    pub method: Option<TaxMethod>,
+    pub rounding: Option<Rounding>,
+}
+
+/// The business as it appears on the documents it issues, kept in a
+/// single row.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Serialize)]
+#[diesel(table_name = crate::schema::business_profile)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct BusinessProfile {
+    pub name: String,
+    pub address: String,
+    /// 適格請求書発行事業者の登録番号, e.g. `T7000012050002`.
+    pub registration_number: Option<String>,
+    /// 振込先 printed on invoices.
+    pub bank_details: String,
+    /// Put before the sequence number of each invoice issued.
+    pub invoice_prefix: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Fields to change on the business profile; `Some(None)` clears the
+/// registration number.
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::business_profile)]
+pub struct BusinessProfileChanges {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub registration_number: Option<Option<String>>,
+    pub bank_details: Option<String>,
+    pub invoice_prefix: Option<String>,
+}
+
+/// Codes of seeded accounts that the application posts to or reports on by
+/// itself.
+pub mod account_codes {
+    /// 普通預金
+    pub const ORDINARY_DEPOSITS: &str = "111";
+    /// 売掛金
+    pub const ACCOUNTS_RECEIVABLE: &str = "122";
+    /// 商品
+    pub const INVENTORY: &str = "131";
+    /// 仮払消費税
+    pub const INPUT_TAX: &str = "144";
+    /// 事業主貸
+    pub const OWNER_DRAWINGS: &str = "191";
+    /// 買掛金
+    pub const ACCOUNTS_PAYABLE: &str = "202";
+    /// 未払金
+    pub const ACCRUED_PAYABLES: &str = "212";
+    /// 預り金
+    pub const WITHHOLDING: &str = "214";
+    /// 仮受消費税
+    pub const OUTPUT_TAX: &str = "215";
+    /// 事業主借
+    pub const OWNER_CONTRIBUTIONS: &str = "291";
+    /// 元入金
+    pub const CAPITAL: &str = "301";
+    /// 売上高
+    pub const SALES: &str = "401";
+    /// 仕入高
+    pub const PURCHASES: &str = "501";
+    /// 減価償却費
+    pub const DEPRECIATION: &str = "521";
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::accounts)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Account {
+    pub id: i32,
+    pub code: String,
+    pub name: String,
+    pub kana: String,
+    pub account_type: AccountType,
+    pub tax_category: TaxCategory,
+    pub active: bool,
+    pub parent_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::accounts)]
+pub struct NewAccount {
+    pub code: String,
+    pub name: String,
+    #[serde(default)]
+    pub kana: String,
+    pub account_type: AccountType,
+    pub tax_category: TaxCategory,
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::accounts)]
+pub struct AccountChanges {
+    pub name: Option<String>,
+    pub kana: Option<String>,
+    pub tax_category: Option<TaxCategory>,
+    pub active: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub parent_id: Option<Option<i32>>,
+}
+
+text_enum! {
+    pub enum Side {
+        Debit => "debit",
+        Credit => "credit",
+    }
+}
+
+text_enum! {
+    pub enum EntryKind {
+        Regular => "regular",
+        /// 決算振替: zeroes the year's revenue and expenses into 元入金.
+        Closing => "closing",
+        /// 開始仕訳: balances brought forward into a fiscal year.
+        Opening => "opening",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::journal_entries)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct JournalEntry {
+    pub id: i32,
+    pub entry_date: NaiveDate,
+    pub memo: String,
+    pub voided_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub kind: EntryKind,
+}
+
+/// One debit or credit of a journal entry. Amounts are whole yen.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Associations, Serialize)]
+#[diesel(belongs_to(JournalEntry, foreign_key = entry_id))]
+#[diesel(table_name = crate::schema::journal_lines)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct JournalLine {
+    pub id: i32,
+    pub entry_id: i32,
+    pub line_no: i32,
+    pub account_id: i32,
+    pub side: Side,
+    pub amount: i64,
+    pub description: String,
+    pub tax_category: TaxCategory,
+    /// Consumption tax on the line, part of `amount` when `tax_included`.
+    pub tax_amount: i64,
+    pub tax_included: bool,
+    pub counterparty_id: Option<i32>,
+}
+
+impl JournalLine {
+    /// The amount before consumption tax.
+    pub fn base_amount(&self) -> i64 {
+        match self.tax_included {
+            true => self.amount - self.tax_amount,
+            false => self.amount,
+        }
+    }
+
+    /// The amount with consumption tax, whether or not it was split off.
+    pub fn gross_amount(&self) -> i64 {
+        self.base_amount() + self.tax_amount
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JournalEntryWithLines {
+    #[serde(flatten)]
+    pub entry: JournalEntry,
+    pub lines: Vec<JournalLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewJournalEntry {
+    pub entry_date: NaiveDate,
+    #[serde(default)]
+    pub memo: String,
+    pub lines: Vec<NewJournalLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewJournalLine {
+    pub account_id: i32,
+    pub side: Side,
+    pub amount: i64,
+    #[serde(default)]
+    pub description: String,
+    /// Defaults to the account's tax category.
+    #[serde(default)]
+    pub tax_category: Option<TaxCategory>,
+    /// Whether `amount` includes consumption tax (税込) or is the amount
+    /// before tax (税抜).
+    #[serde(default = "tax_included")]
+    pub tax_included: bool,
+    #[serde(default)]
+    pub counterparty_id: Option<i32>,
+}
+
+fn tax_included() -> bool {
+    true
+}
+
+impl NewJournalLine {
+    pub fn debit(account_id: i32, amount: i64) -> Self {
+        NewJournalLine::new(account_id, Side::Debit, amount)
+    }
+
+    pub fn credit(account_id: i32, amount: i64) -> Self {
+        NewJournalLine::new(account_id, Side::Credit, amount)
+    }
+
+    fn new(account_id: i32, side: Side, amount: i64) -> Self {
+        NewJournalLine {
+            account_id,
+            side,
+            amount,
+            description: String::new(),
+            tax_category: None,
+            tax_included: true,
+            counterparty_id: None,
+        }
+    }
+
+    pub fn tax(mut self, tax_category: TaxCategory) -> Self {
+        self.tax_category = Some(tax_category);
+        self
+    }
+
+    /// Marks `amount` as the amount before tax.
+    pub fn excluding_tax(mut self) -> Self {
+        self.tax_included = false;
+        self
+    }
+
+    pub fn counterparty(mut self, counterparty_id: i32) -> Self {
+        self.counterparty_id = Some(counterparty_id);
+        self
+    }
+}
+
+text_enum! {
+    /// Income tax withheld at source (源泉徴収) on amounts exchanged with a
+    /// counterparty.
+    #[derive(Default)]
+    pub enum Withholding {
+        #[default]
+        None => "none",
+        /// Withheld from remuneration we pay them (報酬・料金 paid to an
+        /// individual) and held as 預り金 until it is paid to the tax office.
+        Payments => "payments",
+        /// Withheld by them from remuneration they pay us; it is prepaid
+        /// income tax of the owner, booked to 事業主貸.
+        Receipts => "receipts",
+    }
+}
+
+impl Withholding {
+    /// Lines tax is withheld from: the account type of the remuneration
+    /// and the side on which it counts positive.
+    pub fn remuneration(&self) -> Option<(AccountType, Side)> {
+        match self {
+            Withholding::None => None,
+            Withholding::Payments => Some((AccountType::Expense, Side::Debit)),
+            Withholding::Receipts => Some((AccountType::Revenue, Side::Credit)),
+        }
+    }
+
+    /// Code of the account the withheld tax is booked to.
+    pub fn account_code(&self) -> Option<&'static str> {
+        match self {
+            Withholding::None => None,
+            Withholding::Payments => Some(account_codes::WITHHOLDING),
+            Withholding::Receipts => Some(account_codes::OWNER_DRAWINGS),
+        }
+    }
+}
+
+/// A customer or supplier (取引先).
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::counterparties)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Counterparty {
+    pub id: i32,
+    pub name: String,
+    /// 適格請求書発行事業者の登録番号, e.g. `T7000012050002`.
+    pub registration_number: Option<String>,
+    pub address: String,
+    pub qualified: bool,
+    /// Period of the registration; open-ended when `None`.
+    pub valid_from: Option<NaiveDate>,
+    pub valid_until: Option<NaiveDate>,
+    pub withholding: Withholding,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Counterparty {
+    /// Whether invoices dated `date` are qualified invoices (適格請求書).
+    pub fn qualified_on(&self, date: NaiveDate) -> bool {
+        self.qualified
+            && self.registration_number.is_some()
+            && self.valid_from.is_none_or(|from| from <= date)
+            && self.valid_until.is_none_or(|until| date <= until)
+    }
+}
+
+#[derive(Debug, Clone, Default, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::counterparties)]
+pub struct NewCounterparty {
+    pub name: String,
+    pub registration_number: Option<String>,
+    #[serde(default)]
+    pub address: String,
+    #[serde(default)]
+    pub qualified: bool,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_until: Option<NaiveDate>,
+    #[serde(default)]
+    pub withholding: Withholding,
+}
+
+/// Fields to change on a counterparty; `Some(None)` clears a nullable one.
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::counterparties)]
+pub struct CounterpartyChanges {
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub registration_number: Option<Option<String>>,
+    pub address: Option<String>,
+    pub qualified: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub valid_from: Option<Option<NaiveDate>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub valid_until: Option<Option<NaiveDate>>,
+    pub withholding: Option<Withholding>,
+}
+
+text_enum! {
+    pub enum FiscalYearState {
+        Open => "open",
+        Closed => "closed",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::fiscal_years)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FiscalYear {
+    pub id: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub state: FiscalYearState,
+    pub closing_entry_id: Option<i32>,
+    pub opening_entry_id: Option<i32>,
+    pub closed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// The year-end depreciation entry, once posted.
+    pub depreciation_entry_id: Option<i32>,
+    /// The year-end 家事按分 entry, once posted.
+    pub apportionment_entry_id: Option<i32>,
+}
+
+impl FiscalYear {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start_date <= date && date <= self.end_date
+    }
+}
+
+#[derive(Debug, Clone, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::fiscal_years)]
+pub struct NewFiscalYear {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+}
+
+text_enum! {
+    /// Character encoding of a statement file.
+    pub enum Encoding {
+        Utf8 => "utf-8",
+        /// Used by most Japanese banks and card companies.
+        ShiftJis => "shift_jis",
+    }
+}
+
+/// Column mapping for one institution's statement CSV. Column numbers
+/// start at 0.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::import_profiles)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ImportProfile {
+    pub id: i32,
+    pub name: String,
+    /// The bank or credit card account the statement belongs to.
+    pub account_id: i32,
+    pub encoding: Encoding,
+    /// Header lines before the first transaction.
+    pub skip_rows: i32,
+    pub date_column: i32,
+    /// A chrono format string such as `%Y/%m/%d`.
+    pub date_format: String,
+    pub description_column: i32,
+    /// Money going out; for a credit card, the amount charged.
+    pub withdrawal_column: i32,
+    /// Money coming in. Without it, negative withdrawals are deposits.
+    pub deposit_column: Option<i32>,
+    pub balance_column: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::import_profiles)]
+pub struct NewImportProfile {
+    pub name: String,
+    pub account_id: i32,
+    pub encoding: Encoding,
+    pub skip_rows: i32,
+    pub date_column: i32,
+    pub date_format: String,
+    pub description_column: i32,
+    pub withdrawal_column: i32,
+    pub deposit_column: Option<i32>,
+    pub balance_column: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::import_batches)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ImportBatch {
+    pub id: i32,
+    pub profile_id: i32,
+    pub file_name: String,
+    pub imported_at: NaiveDateTime,
+}
+
+text_enum! {
+    pub enum StagedStatus {
+        Pending => "pending",
+        Posted => "posted",
+        Ignored => "ignored",
+    }
+}
+
+/// A statement row waiting for review. Exactly one of `withdrawal` and
+/// `deposit` is non-zero.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Associations, Serialize)]
+#[diesel(belongs_to(ImportBatch, foreign_key = batch_id))]
+#[diesel(table_name = crate::schema::staged_transactions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct StagedTransaction {
+    pub id: i32,
+    pub batch_id: i32,
+    /// Position of the record in the file, starting at 1.
+    pub row_no: i32,
+    pub account_id: i32,
+    pub transaction_date: NaiveDate,
+    pub description: String,
+    pub withdrawal: i64,
+    pub deposit: i64,
+    pub balance: Option<i64>,
+    pub status: StagedStatus,
+    pub entry_id: Option<i32>,
+    /// Key for recognising the row in overlapping statements; see
+    /// [`fingerprints`](crate::import::fingerprints).
+    pub fingerprint: Option<String>,
+}
+
+impl StagedTransaction {
+    /// The amount moved, whichever direction.
+    pub fn amount(&self) -> i64 {
+        self.withdrawal.max(self.deposit)
+    }
+}
+
+/// A statement row left out of a batch because it had been imported
+/// before as `duplicate_of`.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::skipped_duplicates)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct SkippedDuplicate {
+    pub id: i32,
+    pub batch_id: i32,
+    pub row_no: i32,
+    pub transaction_date: NaiveDate,
+    pub description: String,
+    pub withdrawal: i64,
+    pub deposit: i64,
+    pub duplicate_of: i32,
+}
+
+/// Proposes the other side of an entry for statement rows it matches.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::import_rules)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ImportRule {
+    pub id: i32,
+    /// Rules are tried in ascending priority, then by id.
+    pub priority: i32,
+    pub description_contains: Option<String>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    pub account_id: i32,
+    /// Defaults to the account's tax category.
+    pub tax_category: Option<TaxCategory>,
+    pub counterparty_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ImportRule {
+    /// Whether every condition of the rule holds for `transaction`.
+    /// Descriptions are compared ignoring case and full-width forms.
+    pub fn matches(&self, transaction: &StagedTransaction) -> bool {
+        let amount = transaction.amount();
+        self.description_contains.as_deref().is_none_or(|text| {
+            normalize_text(&transaction.description).contains(&normalize_text(text))
+        }) && self.min_amount.is_none_or(|min| min <= amount)
+            && self.max_amount.is_none_or(|max| amount <= max)
+    }
+}
+
+#[derive(Debug, Clone, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::import_rules)]
+pub struct NewImportRule {
+    #[serde(default = "default_priority")]
+    pub priority: i32,
+    pub description_contains: Option<String>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    pub account_id: i32,
+    pub tax_category: Option<TaxCategory>,
+    pub counterparty_id: Option<i32>,
+}
+
+fn default_priority() -> i32 {
+    100
+}
+
+/// Folds full-width ASCII and ideographic spaces to their half-width forms
+/// and lowercases, so that `ＡＭＡＺＯＮ` and `Amazon` compare equal.
+pub fn normalize_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            c => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+text_enum! {
+    /// 償却方法.
+    pub enum DepreciationMethod {
+        /// 定額法
+        StraightLine => "straight_line",
+        /// 定率法 (200% declining balance)
+        DecliningBalance => "declining_balance",
+        /// 少額減価償却資産: expensed in full in the year of acquisition
+        /// (under 300,000 yen, up to 3 million yen a year).
+        SmallAmount => "small_amount",
+        /// 一括償却資産: a third of the cost each year for three years
+        /// (under 200,000 yen).
+        LumpSum => "lump_sum",
+    }
+}
+
+impl DepreciationMethod {
+    /// Name printed on the depreciation schedule, e.g. 定額法.
+    pub fn label(&self) -> &'static str {
+        match self {
+            DepreciationMethod::StraightLine => "定額法",
+            DepreciationMethod::DecliningBalance => "定率法",
+            DepreciationMethod::SmallAmount => "少額減価償却",
+            DepreciationMethod::LumpSum => "一括償却",
+        }
+    }
+}
+
+/// An asset in the fixed asset register (固定資産台帳).
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::fixed_assets)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FixedAsset {
+    pub id: i32,
+    pub name: String,
+    /// The asset account depreciation is credited to, e.g. 工具器具備品.
+    pub account_id: i32,
+    pub quantity: String,
+    pub acquired_on: NaiveDate,
+    pub cost: i64,
+    /// 耐用年数 from the statutory table.
+    pub useful_life: i32,
+    pub method: DepreciationMethod,
+    /// 事業専用割合 in percent.
+    pub business_ratio: i32,
+    pub disposed_on: Option<NaiveDate>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::fixed_assets)]
+pub struct NewFixedAsset {
+    pub name: String,
+    pub account_id: i32,
+    #[serde(default)]
+    pub quantity: String,
+    pub acquired_on: NaiveDate,
+    pub cost: i64,
+    pub useful_life: i32,
+    pub method: DepreciationMethod,
+    #[serde(default = "full_business_use")]
+    pub business_ratio: i32,
+}
+
+fn full_business_use() -> i32 {
+    100
+}
+
+/// Fields to change on a fixed asset; `Some(None)` clears the disposal.
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::fixed_assets)]
+pub struct FixedAssetChanges {
+    pub name: Option<String>,
+    pub quantity: Option<String>,
+    pub business_ratio: Option<i32>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub disposed_on: Option<Option<NaiveDate>>,
+}
+
+/// A 家事按分 ratio: the business share of an expense account, or of what
+/// is paid to one counterparty on it, from `effective_from` on.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::apportionment_rules)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ApportionmentRule {
+    pub id: i32,
+    pub account_id: i32,
+    pub counterparty_id: Option<i32>,
+    /// Business share in percent; the rest is private.
+    pub business_ratio: i32,
+    pub effective_from: NaiveDate,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::apportionment_rules)]
+pub struct NewApportionmentRule {
+    pub account_id: i32,
+    #[serde(default)]
+    pub counterparty_id: Option<i32>,
+    pub business_ratio: i32,
+    pub effective_from: NaiveDate,
+}
+
+text_enum! {
+    /// Line of the blue-return 決算書 (一般用) an account is reported on.
+    pub enum ReturnLine {
+        /// 売上(収入)金額
+        Sales => "sales",
+        /// 家事消費等, part of 売上(収入)金額.
+        HouseholdConsumption => "household_consumption",
+        /// 雑収入, part of 売上(収入)金額.
+        MiscIncome => "misc_income",
+        /// 仕入金額, as part of 売上原価.
+        Purchases => "purchases",
+        TaxesAndDues => "taxes_and_dues",
+        PackingAndFreight => "packing_and_freight",
+        Utilities => "utilities",
+        Travel => "travel",
+        Communication => "communication",
+        Advertising => "advertising",
+        Entertainment => "entertainment",
+        Insurance => "insurance",
+        Repairs => "repairs",
+        Supplies => "supplies",
+        Depreciation => "depreciation",
+        Welfare => "welfare",
+        Wages => "wages",
+        Outsourcing => "outsourcing",
+        Interest => "interest",
+        Rent => "rent",
+        BadDebts => "bad_debts",
+        /// One of the blank expense lines, labelled with the account name.
+        Other => "other",
+        Miscellaneous => "miscellaneous",
+    }
+}
+
+impl ReturnLine {
+    /// Name printed on the form; empty for [`ReturnLine::Other`].
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReturnLine::Sales => "売上(収入)金額",
+            ReturnLine::HouseholdConsumption => "家事消費等",
+            ReturnLine::MiscIncome => "雑収入",
+            ReturnLine::Purchases => "仕入金額",
+            ReturnLine::TaxesAndDues => "租税公課",
+            ReturnLine::PackingAndFreight => "荷造運賃",
+            ReturnLine::Utilities => "水道光熱費",
+            ReturnLine::Travel => "旅費交通費",
+            ReturnLine::Communication => "通信費",
+            ReturnLine::Advertising => "広告宣伝費",
+            ReturnLine::Entertainment => "接待交際費",
+            ReturnLine::Insurance => "損害保険料",
+            ReturnLine::Repairs => "修繕費",
+            ReturnLine::Supplies => "消耗品費",
+            ReturnLine::Depreciation => "減価償却費",
+            ReturnLine::Welfare => "福利厚生費",
+            ReturnLine::Wages => "給料賃金",
+            ReturnLine::Outsourcing => "外注工賃",
+            ReturnLine::Interest => "利子割引料",
+            ReturnLine::Rent => "地代家賃",
+            ReturnLine::BadDebts => "貸倒金",
+            ReturnLine::Other => "",
+            ReturnLine::Miscellaneous => "雑費",
+        }
+    }
+
+    /// The account type whose accounts may be reported on this line.
+    pub fn account_type(&self) -> AccountType {
+        match self {
+            ReturnLine::Sales | ReturnLine::HouseholdConsumption | ReturnLine::MiscIncome => {
+                AccountType::Revenue
+            }
+            _ => AccountType::Expense,
+        }
+    }
+}
+
+/// The 決算書 line an account is reported on.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::return_lines)]
+#[diesel(primary_key(account_id))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ReturnLineMapping {
+    pub account_id: i32,
+    pub line: ReturnLine,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A piece of evidence (証憑) kept as a file, with the keys it is searched
+/// by under 電子帳簿保存法.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize, Deserialize)]
+#[diesel(table_name = crate::schema::attachments)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Attachment {
+    pub id: i32,
+    /// SHA-256 of the content, which also names the stored file.
+    pub sha256: String,
+    pub file_name: String,
+    pub content_type: String,
+    /// Size of the content in bytes.
+    pub size: i64,
+    /// 取引年月日
+    pub transaction_date: NaiveDate,
+    /// 取引金額
+    pub amount: i64,
+    /// 取引先
+    pub counterparty_id: Option<i32>,
+    /// The journal entry the attachment is evidence for.
+    pub entry_id: Option<i32>,
+    pub description: String,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// What is recorded about a file as it is stored; the hash and size are
+/// taken from the content.
+#[derive(Debug, Clone, Default, Insertable, Deserialize)]
+#[diesel(table_name = crate::schema::attachments)]
+pub struct NewAttachment {
+    pub file_name: String,
+    #[serde(default = "default_content_type")]
+    pub content_type: String,
+    pub transaction_date: NaiveDate,
+    pub amount: i64,
+    #[serde(default)]
+    pub counterparty_id: Option<i32>,
+    #[serde(default)]
+    pub entry_id: Option<i32>,
+    #[serde(default)]
+    pub description: String,
+}
+
+pub fn default_content_type() -> String {
+    "application/octet-stream".to_string()
+}
+
+/// Fields to change on an attachment; `Some(None)` clears a nullable one.
+/// The content itself cannot be changed.
+#[derive(Debug, Clone, Default, AsChangeset, Deserialize)]
+#[diesel(table_name = crate::schema::attachments)]
+pub struct AttachmentChanges {
+    pub file_name: Option<String>,
+    pub transaction_date: Option<NaiveDate>,
+    pub amount: Option<i64>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub counterparty_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub entry_id: Option<Option<i32>>,
+    pub description: Option<String>,
+}
+
+text_enum! {
+    pub enum AttachmentAction {
+        Created => "created",
+        Updated => "updated",
+        Deleted => "deleted",
+    }
+}
+
+/// One row of an attachment's modification history.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::attachment_history)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct AttachmentHistory {
+    pub id: i32,
+    pub attachment_id: i32,
+    pub action: AttachmentAction,
+    pub reason: String,
+    /// The [`Attachment`] after the change, as JSON.
+    pub snapshot: String,
+    pub recorded_at: NaiveDateTime,
+}
+
+text_enum! {
+    #[derive(Default)]
+    pub enum InvoiceStatus {
+        #[default]
+        Draft => "draft",
+        /// Numbered, with the receivable posted.
+        Issued => "issued",
+        /// Settled, with the payment posted.
+        Paid => "paid",
+        Void => "void",
+    }
+}
+
+/// An invoice to a client. `number` is given out on issue.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::invoices)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Invoice {
+    pub id: i32,
+    pub sequence: Option<i32>,
+    pub number: Option<String>,
+    pub counterparty_id: i32,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub status: InvoiceStatus,
+    pub notes: String,
+    /// The receivable posted on issue.
+    pub entry_id: Option<i32>,
+    /// The payment that settled the receivable in full.
+    pub payment_entry_id: Option<i32>,
+    pub paid_on: Option<NaiveDate>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Associations, Serialize)]
+#[diesel(belongs_to(Invoice))]
+#[diesel(table_name = crate::schema::invoice_lines)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct InvoiceLine {
+    pub id: i32,
+    pub invoice_id: i32,
+    pub line_no: i32,
+    pub description: String,
+    pub quantity: i32,
+    /// Before tax (税抜).
+    pub unit_price: i64,
+    pub tax_category: TaxCategory,
+}
+
+impl InvoiceLine {
+    /// Quantity times unit price, before tax.
+    pub fn amount(&self) -> i64 {
+        self.quantity as i64 * self.unit_price
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InvoiceWithLines {
+    #[serde(flatten)]
+    pub invoice: Invoice,
+    pub lines: Vec<InvoiceLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewInvoice {
+    pub counterparty_id: i32,
+    pub issue_date: NaiveDate,
+    pub due_date: NaiveDate,
+    #[serde(default)]
+    pub notes: String,
+    pub lines: Vec<NewInvoiceLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewInvoiceLine {
+    pub description: String,
+    #[serde(default = "one")]
+    pub quantity: i32,
+    /// Before tax (税抜).
+    pub unit_price: i64,
+    #[serde(default = "standard_rate")]
+    pub tax_category: TaxCategory,
+}
+
+/// A payment received against an invoice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoicePayment {
+    pub paid_on: NaiveDate,
+    /// The account paid into; defaults to 普通預金.
+    #[serde(default)]
+    pub account_id: Option<i32>,
+    /// Defaults to what is left to pay.
+    #[serde(default)]
+    pub amount: Option<i64>,
+}
+
+fn one() -> i32 {
+    1
+}
+
+fn standard_rate() -> TaxCategory {
+    TaxCategory::Taxable10
+}
+
+text_enum! {
+    /// Which side of the business an open item is on.
+    #[derive(Default)]
+    pub enum OpenItemKind {
+        /// 売掛金
+        #[default]
+        Receivable => "receivable",
+        /// 買掛金 and 未払金
+        Payable => "payable",
+    }
+}
+
+impl OpenItemKind {
+    /// Codes of the accounts whose lines are tracked as open items.
+    pub fn account_codes(&self) -> &'static [&'static str] {
+        match self {
+            OpenItemKind::Receivable => &[account_codes::ACCOUNTS_RECEIVABLE],
+            OpenItemKind::Payable => &[
+                account_codes::ACCOUNTS_PAYABLE,
+                account_codes::ACCRUED_PAYABLES,
+            ],
+        }
+    }
+
+    /// The side on which an item increases the balance; payments are on
+    /// the other.
+    pub fn item_side(&self) -> Side {
+        match self {
+            OpenItemKind::Receivable => Side::Debit,
+            OpenItemKind::Payable => Side::Credit,
+        }
+    }
+
+    /// Heading used in reports, e.g. 売掛金.
+    pub fn label(&self) -> &'static str {
+        match self {
+            OpenItemKind::Receivable => "売掛金",
+            OpenItemKind::Payable => "買掛金・未払金",
+        }
+    }
+}
+
+/// A payment applied to an open item.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::open_item_matches)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct OpenItemMatch {
+    pub id: i32,
+    pub item_line_id: i32,
+    pub payment_line_id: i32,
+    pub amount: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewOpenItemMatch {
+    pub item_line_id: i32,
+    pub payment_line_id: i32,
+    /// Defaults to as much as both lines have left.
+    #[serde(default)]
+    pub amount: Option<i64>,
+}
+
+text_enum! {
+    /// When a recurring entry falls due.
+    pub enum Schedule {
+        /// Every month on `day`, or on the last day of shorter months.
+        Monthly => "monthly",
+        /// The last day of every month.
+        EndOfMonth => "end_of_month",
+        /// Every year on `day` of `month`.
+        Yearly => "yearly",
+    }
+}
+
+text_enum! {
+    pub enum OccurrenceStatus {
+        /// Not posted yet, possibly with changes.
+        Due => "due",
+        Posted => "posted",
+        Skipped => "skipped",
+    }
+}
+
+/// A journal entry that repeats on a schedule, such as rent.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = crate::schema::recurring_entries)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecurringEntry {
+    pub id: i32,
+    pub name: String,
+    /// Memo of the entries posted; defaults to the name.
+    pub memo: String,
+    pub schedule: Schedule,
+    pub day: Option<i32>,
+    pub month: Option<i32>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Associations, Serialize)]
+#[diesel(belongs_to(RecurringEntry))]
+#[diesel(table_name = crate::schema::recurring_entry_lines)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecurringEntryLine {
+    pub id: i32,
+    pub recurring_entry_id: i32,
+    pub line_no: i32,
+    pub account_id: i32,
+    pub side: Side,
+    pub amount: i64,
+    pub description: String,
+    pub tax_category: Option<TaxCategory>,
+    pub tax_included: bool,
+    pub counterparty_id: Option<i32>,
+}
+
+impl From<&RecurringEntryLine> for NewJournalLine {
+    fn from(line: &RecurringEntryLine) -> Self {
+        NewJournalLine {
+            account_id: line.account_id,
+            side: line.side,
+            amount: line.amount,
+            description: line.description.clone(),
+            tax_category: line.tax_category,
+            tax_included: line.tax_included,
+            counterparty_id: line.counterparty_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecurringEntryWithLines {
+    #[serde(flatten)]
+    pub recurring_entry: RecurringEntry,
+    pub lines: Vec<RecurringEntryLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewRecurringEntry {
+    pub name: String,
+    #[serde(default)]
+    pub memo: String,
+    pub schedule: Schedule,
+    #[serde(default)]
+    pub day: Option<i32>,
+    #[serde(default)]
+    pub month: Option<i32>,
+    pub start_date: NaiveDate,
+    #[serde(default)]
+    pub end_date: Option<NaiveDate>,
+    pub lines: Vec<NewJournalLine>,
+}
+
+/// An occurrence that was posted, skipped or changed. `lines` holds the
+/// changed lines as JSON.
+#[derive(Debug, Clone, PartialEq, Queryable, Selectable, Identifiable, Associations)]
+#[diesel(belongs_to(RecurringEntry))]
+#[diesel(table_name = crate::schema::recurring_occurrences)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecurringOccurrence {
+    pub id: i32,
+    pub recurring_entry_id: i32,
+    pub due_date: NaiveDate,
+    pub status: OccurrenceStatus,
+    pub entry_date: Option<NaiveDate>,
+    pub lines: Option<String>,
+    pub entry_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Changes to one occurrence; what is left out stays as in the template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OccurrenceChanges {
+    /// Post on this date instead of the due date.
+    #[serde(default)]
+    pub entry_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub lines: Option<Vec<NewJournalLine>>,
 }
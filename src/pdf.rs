@@ -0,0 +1,250 @@
+//! Minimal PDF output for printable documents: titles, text and tables on
+//! A4 pages. Text is set in HeiseiMin-W3, one of the standard Japanese
+//! fonts PDF viewers provide, so no font has to be embedded; viewers
+//! without it substitute another Mincho face.
+
+use crate::report::display_width;
+
+/// A4 in points.
+pub const PAGE_WIDTH: f32 = 595.28;
+pub const PAGE_HEIGHT: f32 = 841.89;
+/// 15 mm, as in the `@page` rule of the HTML reports.
+const MARGIN: f32 = 42.52;
+const TABLE_FONT_SIZE: f32 = 9.0;
+/// Tables are set smaller than this rather than overflowing the page.
+const MIN_FONT_SIZE: f32 = 5.0;
+const CELL_PADDING: f32 = 4.0;
+
+/// A document being laid out top to bottom, breaking pages as needed.
+pub struct Document {
+    title: String,
+    pages: Vec<String>,
+    /// Content stream of the last page.
+    content: String,
+    /// Baseline position on the last page.
+    y: f32,
+}
+
+impl Document {
+    pub fn new(title: &str) -> Self {
+        Document {
+            title: title.to_string(),
+            pages: Vec::new(),
+            content: String::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    /// Starts a new page unless the current one is still empty.
+    pub fn page_break(&mut self) {
+        if !self.content.is_empty() {
+            self.pages.push(std::mem::take(&mut self.content));
+        }
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    /// A centred title in 14 point.
+    pub fn title(&mut self, text: &str) {
+        let size = 14.0;
+        self.advance(size * 1.8);
+        let x = (PAGE_WIDTH - text_width(text, size)) / 2.0;
+        self.text(x, self.y, size, text);
+        self.y -= size * 0.8;
+    }
+
+    /// A section heading in 11 point.
+    pub fn heading(&mut self, text: &str) {
+        let size = 11.0;
+        self.advance(size * 2.0);
+        self.text(MARGIN, self.y, size, text);
+        self.y -= size * 0.6;
+    }
+
+    /// A line of body text in 9 point.
+    pub fn paragraph(&mut self, text: &str) {
+        let size = TABLE_FONT_SIZE;
+        self.advance(size * 1.6);
+        self.text(MARGIN, self.y, size, text);
+    }
+
+    /// Ruled table like [`text_table`](crate::report::text_table): the first
+    /// `left` columns are left-aligned and the rest right-aligned, and an
+    /// empty row is drawn as a rule. Columns are sized to their contents and
+    /// the font is reduced if the table would be wider than the page. The
+    /// header is repeated after a page break.
+    pub fn table(&mut self, headers: &[&str], rows: &[Vec<String>], left: usize) {
+        let mut widths = headers
+            .iter()
+            .map(|header| display_width(header))
+            .collect::<Vec<_>>();
+        for row in rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(display_width(cell));
+            }
+        }
+        let available = PAGE_WIDTH - 2.0 * MARGIN;
+        let padding = 2.0 * CELL_PADDING * widths.len() as f32;
+        let columns = widths.iter().sum::<usize>() as f32;
+        let size =
+            ((available - padding) * 2.0 / columns.max(1.0)).clamp(MIN_FONT_SIZE, TABLE_FONT_SIZE);
+        let widths = widths
+            .iter()
+            .map(|width| *width as f32 * size / 2.0 + 2.0 * CELL_PADDING)
+            .collect::<Vec<_>>();
+        let row_height = size * 1.7;
+
+        let header = headers
+            .iter()
+            .map(|header| header.to_string())
+            .collect::<Vec<_>>();
+        self.advance(row_height * 0.5);
+        self.table_row(&header, &widths, headers.len(), size, row_height);
+        self.rule(&widths, 0.8);
+        for row in rows {
+            if row.is_empty() {
+                self.rule(&widths, 0.5);
+                continue;
+            }
+            if self.y - row_height < MARGIN {
+                self.page_break();
+                self.table_row(&header, &widths, headers.len(), size, row_height);
+                self.rule(&widths, 0.8);
+            }
+            self.table_row(row, &widths, left, size, row_height);
+        }
+        self.rule(&widths, 0.8);
+    }
+
+    /// The finished file.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.page_break();
+        if self.pages.is_empty() {
+            self.pages.push(String::new());
+        }
+
+        // Objects 1 to 5 are fixed; each page adds a page and a content
+        // stream object.
+        let page_ids = (0..self.pages.len()).map(|i| 6 + 2 * i).collect::<Vec<_>>();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids
+                    .iter()
+                    .map(|id| format!("{} 0 R", id))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                page_ids.len()
+            ),
+            "<< /Type /Font /Subtype /Type0 /BaseFont /HeiseiMin-W3 \
+             /Encoding /UniJIS-UCS2-HW-H /DescendantFonts [4 0 R] >>"
+                .to_string(),
+            // Half-width CIDs are 500 units wide, everything else 1000.
+            "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /HeiseiMin-W3 \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 2 >> \
+             /FontDescriptor 5 0 R /DW 1000 /W [231 389 500 631 631 500] >>"
+                .to_string(),
+            "<< /Type /FontDescriptor /FontName /HeiseiMin-W3 /Flags 6 \
+             /FontBBox [-123 -257 1001 910] /ItalicAngle 0 /Ascent 723 /Descent -241 \
+             /CapHeight 709 /StemV 69 >>"
+                .to_string(),
+        ];
+        for (page, content) in page_ids.iter().zip(&self.pages) {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+        objects.push(format!("<< /Title <{}> >>", utf16_hex(&self.title, true)));
+
+        let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+        }
+        let xref = pdf.len();
+        let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            trailer.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        trailer.push_str(&format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            objects.len(),
+            xref
+        ));
+        pdf.extend_from_slice(trailer.as_bytes());
+        pdf
+    }
+
+    /// Moves down by `height`, breaking the page if it does not fit.
+    fn advance(&mut self, height: f32) {
+        if self.y - height < MARGIN {
+            self.page_break();
+        }
+        self.y -= height;
+    }
+
+    fn text(&mut self, x: f32, y: f32, size: f32, text: &str) {
+        self.content.push_str(&format!(
+            "BT /F1 {:.1} Tf {:.2} {:.2} Td <{}> Tj ET\n",
+            size,
+            x,
+            y,
+            utf16_hex(text, false)
+        ));
+    }
+
+    fn table_row(&mut self, cells: &[String], widths: &[f32], left: usize, size: f32, height: f32) {
+        self.advance(height);
+        let baseline = self.y + height * 0.3;
+        let mut x = MARGIN;
+        for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+            let cell_x = if i < left {
+                x + CELL_PADDING
+            } else {
+                x + width - CELL_PADDING - text_width(cell, size)
+            };
+            self.text(cell_x, baseline, size, cell);
+            x += width;
+        }
+    }
+
+    /// A horizontal rule under the last row, as wide as the table.
+    fn rule(&mut self, widths: &[f32], line_width: f32) {
+        let right = MARGIN + widths.iter().sum::<f32>();
+        self.content.push_str(&format!(
+            "{:.1} w {:.2} {:.2} m {:.2} {:.2} l S\n",
+            line_width, MARGIN, self.y, right, self.y
+        ));
+    }
+}
+
+/// Width of `text` in points, taking full-width characters to be one em and
+/// others half of one.
+fn text_width(text: &str, size: f32) -> f32 {
+    display_width(text) as f32 * size / 2.0
+}
+
+/// `text` as UTF-16BE in hex. The font's encoding only covers the basic
+/// multilingual plane, so other characters are replaced with `?`.
+fn utf16_hex(text: &str, bom: bool) -> String {
+    let mut hex = String::with_capacity(text.len() * 4 + 4);
+    if bom {
+        hex.push_str("FEFF");
+    }
+    for c in text.chars() {
+        let unit = u16::try_from(c as u32).unwrap_or(b'?' as u16);
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex
+}
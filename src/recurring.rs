@@ -0,0 +1,70 @@
+//! When recurring entries fall due. A day of the month that a month does
+//! not have, such as the 31st in April or 29 February in most years, moves
+//! back to the last day of that month.
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::error::{Error, Result};
+use crate::models::{RecurringEntry, Schedule};
+
+/// The dates `entry` falls due from `from` to `to`, both inclusive, within
+/// its start and end dates.
+pub fn due_dates(entry: &RecurringEntry, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    let from = from.max(entry.start_date);
+    let to = entry.end_date.map_or(to, |end| end.min(to));
+    let mut dates = Vec::new();
+    let mut month = from.with_day(1);
+    while let Some(first) = month.filter(|first| *first <= to) {
+        if let Some(date) = due_in(entry, first).filter(|date| (from..=to).contains(date)) {
+            dates.push(date);
+        }
+        month = first.checked_add_months(Months::new(1));
+    }
+    dates
+}
+
+/// Whether `entry` falls due on `date`.
+pub fn is_due(entry: &RecurringEntry, date: NaiveDate) -> bool {
+    !due_dates(entry, date, date).is_empty()
+}
+
+/// Checks that the day and month suit the schedule.
+pub fn check_schedule(schedule: Schedule, day: Option<i32>, month: Option<i32>) -> Result<()> {
+    let valid = match schedule {
+        Schedule::Monthly => month.is_none() && day.is_some_and(|day| (1..=31).contains(&day)),
+        Schedule::EndOfMonth => day.is_none() && month.is_none(),
+        Schedule::Yearly => {
+            day.is_some_and(|day| (1..=31).contains(&day))
+                && month.is_some_and(|month| (1..=12).contains(&month))
+        }
+    };
+    match valid {
+        true => Ok(()),
+        false => Err(Error::Validation(format!(
+            "a {} schedule takes {}",
+            schedule,
+            match schedule {
+                Schedule::Monthly => "a day from 1 to 31 and no month",
+                Schedule::EndOfMonth => "neither a day nor a month",
+                Schedule::Yearly => "a day from 1 to 31 and a month from 1 to 12",
+            }
+        ))),
+    }
+}
+
+/// The due date of `entry` in the month starting on `first`, if any.
+fn due_in(entry: &RecurringEntry, first: NaiveDate) -> Option<NaiveDate> {
+    let last = first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())?;
+    let on_day = |day: i32| first.with_day(day as u32).unwrap_or(last);
+    match entry.schedule {
+        Schedule::Monthly => entry.day.map(on_day),
+        Schedule::EndOfMonth => Some(last),
+        Schedule::Yearly => entry
+            .month
+            .filter(|&month| month as u32 == first.month())
+            .and(entry.day)
+            .map(on_day),
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::Serialize;
+
+use super::{text_table, yen};
+use crate::error::Result;
+use crate::models::OpenItemKind;
+use crate::repository::{OpenItemQuery, OpenItemRepository};
+
+/// Age ranges in days since the item's date: 0-30, 31-60, 61-90 and over 90.
+pub const BUCKETS: [&str; 4] = ["0-30", "31-60", "61-90", "90+"];
+
+/// What is outstanding with one counterparty, by age.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgingRow {
+    pub counterparty_id: i32,
+    pub name: String,
+    /// Outstanding in each of [`BUCKETS`].
+    pub buckets: [i64; 4],
+    pub outstanding: i64,
+    /// Payments not yet applied to items.
+    pub unapplied: i64,
+    pub balance: i64,
+}
+
+/// 年齢表: open receivables or payables as of a date, per counterparty and
+/// by how long they have been open.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgingReport {
+    pub as_of: NaiveDate,
+    pub kind: OpenItemKind,
+    pub rows: Vec<AgingRow>,
+    pub total_buckets: [i64; 4],
+    pub total_outstanding: i64,
+    pub total_unapplied: i64,
+    pub total_balance: i64,
+}
+
+impl AgingReport {
+    pub fn generate(
+        connection: &mut SqliteConnection,
+        kind: OpenItemKind,
+        as_of: NaiveDate,
+    ) -> Result<AgingReport> {
+        let open = OpenItemRepository::new(connection).outstanding(&OpenItemQuery {
+            kind,
+            counterparty_id: None,
+            as_of: Some(as_of),
+        })?;
+        let rows = open
+            .into_iter()
+            .map(|counterparty| {
+                let mut buckets = [0; 4];
+                for item in &counterparty.items {
+                    buckets[bucket((as_of - item.entry_date).num_days())] += item.remaining;
+                }
+                AgingRow {
+                    counterparty_id: counterparty.counterparty_id,
+                    name: counterparty.name,
+                    buckets,
+                    outstanding: counterparty.outstanding,
+                    unapplied: counterparty.unapplied,
+                    balance: counterparty.balance,
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut total_buckets = [0; 4];
+        for row in &rows {
+            for (total, amount) in total_buckets.iter_mut().zip(row.buckets) {
+                *total += amount;
+            }
+        }
+        Ok(AgingReport {
+            as_of,
+            kind,
+            total_buckets,
+            total_outstanding: rows.iter().map(|row| row.outstanding).sum(),
+            total_unapplied: rows.iter().map(|row| row.unapplied).sum(),
+            total_balance: rows.iter().map(|row| row.balance).sum(),
+            rows,
+        })
+    }
+
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        let mut headers = vec!["counterparty"];
+        headers.extend(BUCKETS);
+        headers.extend(["outstanding", "unapplied", "balance"]);
+        csv.write_record(&headers)?;
+        for row in &self.rows {
+            let mut record = vec![row.name.clone()];
+            record.extend(row.buckets.iter().map(i64::to_string));
+            record.extend([row.outstanding, row.unapplied, row.balance].map(|n| n.to_string()));
+            csv.write_record(&record)?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    pub fn to_table(&self) -> String {
+        let row = |name: &str, buckets: &[i64; 4], rest: [i64; 3]| {
+            let mut cells = vec![name.to_string()];
+            cells.extend(buckets.iter().map(|&amount| yen(amount)));
+            cells.extend(rest.map(yen));
+            cells
+        };
+        let mut rows = self
+            .rows
+            .iter()
+            .map(|r| row(&r.name, &r.buckets, [r.outstanding, r.unapplied, r.balance]))
+            .collect::<Vec<_>>();
+        rows.push(Vec::new());
+        rows.push(row(
+            "合計",
+            &self.total_buckets,
+            [
+                self.total_outstanding,
+                self.total_unapplied,
+                self.total_balance,
+            ],
+        ));
+        let labels = BUCKETS.map(|bucket| format!("{}日", bucket));
+        let mut headers = vec!["取引先"];
+        headers.extend(labels.iter().map(String::as_str));
+        headers.extend(["未決済計", "未消込入出金", "残高"]);
+        format!(
+            "{}年齢表 {}現在\n\n{}",
+            self.kind.label(),
+            self.as_of,
+            text_table(&headers, &rows, 1)
+        )
+    }
+}
+
+/// Index into [`BUCKETS`] of an item open for `days`.
+fn bucket(days: i64) -> usize {
+    match days {
+        ..=30 => 0,
+        31..=60 => 1,
+        61..=90 => 2,
+        _ => 3,
+    }
+}
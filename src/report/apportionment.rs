@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::io;
+
+use diesel::prelude::*;
+use serde::Serialize;
+
+use super::{text_table, yen, Period};
+use crate::error::Result;
+use crate::repository::{AccountRepository, ApportionmentRepository, CounterpartyRepository};
+
+/// One account's expenses under one 家事按分 ratio.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApportionmentRow {
+    pub account_id: i32,
+    pub code: String,
+    pub name: String,
+    pub counterparty_id: Option<i32>,
+    pub counterparty_name: Option<String>,
+    pub business_ratio: i32,
+    pub original: i64,
+    pub business: i64,
+    pub private: i64,
+}
+
+/// 家事按分 of the expenses in a period: the amounts as recorded and their
+/// business and private shares, including consumption tax. The private
+/// shares are what the year-end entry moves to 事業主貸.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApportionmentReport {
+    pub period: Period,
+    pub rows: Vec<ApportionmentRow>,
+    pub total_original: i64,
+    pub total_business: i64,
+    pub total_private: i64,
+}
+
+impl ApportionmentReport {
+    pub fn generate(
+        connection: &mut SqliteConnection,
+        period: Period,
+    ) -> Result<ApportionmentReport> {
+        let amounts = ApportionmentRepository::new(connection).amounts(period.from, period.to)?;
+        let accounts = AccountRepository::new(connection)
+            .list(true)?
+            .into_iter()
+            .map(|account| (account.id, account))
+            .collect::<HashMap<_, _>>();
+        let counterparties = CounterpartyRepository::new(connection)
+            .list()?
+            .into_iter()
+            .map(|counterparty| (counterparty.id, counterparty.name))
+            .collect::<HashMap<_, _>>();
+
+        // Amounts come per tax category; the report only splits by rule.
+        let mut rows: Vec<(i32, ApportionmentRow)> = Vec::new();
+        for amount in amounts {
+            if let Some((_, row)) = rows
+                .iter_mut()
+                .find(|(rule_id, _)| *rule_id == amount.rule_id)
+            {
+                row.original += amount.original;
+                row.business += amount.business;
+                row.private += amount.private;
+                continue;
+            }
+            let account = &accounts[&amount.account_id];
+            rows.push((
+                amount.rule_id,
+                ApportionmentRow {
+                    account_id: amount.account_id,
+                    code: account.code.clone(),
+                    name: account.name.clone(),
+                    counterparty_id: amount.counterparty_id,
+                    counterparty_name: amount
+                        .counterparty_id
+                        .and_then(|id| counterparties.get(&id).cloned()),
+                    business_ratio: amount.business_ratio,
+                    original: amount.original,
+                    business: amount.business,
+                    private: amount.private,
+                },
+            ));
+        }
+        let mut rows = rows.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+        rows.sort_by(|a, b| {
+            (&a.code, a.counterparty_id, a.business_ratio).cmp(&(
+                &b.code,
+                b.counterparty_id,
+                b.business_ratio,
+            ))
+        });
+        Ok(ApportionmentReport {
+            period,
+            total_original: rows.iter().map(|row| row.original).sum(),
+            total_business: rows.iter().map(|row| row.business).sum(),
+            total_private: rows.iter().map(|row| row.private).sum(),
+            rows,
+        })
+    }
+
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "code",
+            "name",
+            "counterparty",
+            "business_ratio",
+            "original",
+            "business",
+            "private",
+        ])?;
+        for row in &self.rows {
+            csv.write_record([
+                row.code.as_str(),
+                &row.name,
+                row.counterparty_name.as_deref().unwrap_or(""),
+                &row.business_ratio.to_string(),
+                &row.original.to_string(),
+                &row.business.to_string(),
+                &row.private.to_string(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                vec![
+                    row.code.clone(),
+                    row.name.clone(),
+                    row.counterparty_name.clone().unwrap_or_default(),
+                    format!("{}%", row.business_ratio),
+                    yen(row.original),
+                    yen(row.business),
+                    yen(row.private),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push(Vec::new());
+        rows.push(vec![
+            String::new(),
+            "合計".to_string(),
+            String::new(),
+            String::new(),
+            yen(self.total_original),
+            yen(self.total_business),
+            yen(self.total_private),
+        ]);
+        format!(
+            "家事按分 {} - {}\n\n{}",
+            self.period.from,
+            self.period.to,
+            text_table(
+                &[
+                    "コード",
+                    "勘定科目",
+                    "取引先",
+                    "事業割合",
+                    "計上額",
+                    "事業分",
+                    "家事分"
+                ],
+                &rows,
+                3
+            )
+        )
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use super::statement::{rolled_up_balances, Comparison, Statement, StatementLine};
+use super::Period;
+use crate::error::Result;
+use crate::models::account_codes::CAPITAL;
+use crate::models::{Account, AccountType};
+use crate::repository::{AccountRepository, LedgerRepository};
+
+/// 貸借対照表 laid out like page 4 of the blue-return 決算書. `prior` holds the
+/// balances at the start of the period (期首) and `current` those at its end
+/// (期末).
+///
+/// Profit from before the period that has not been closed into 元入金 yet is
+/// shown as part of 元入金, so the statement balances before year-end
+/// closing as well.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceSheet {
+    pub period: Period,
+    pub assets: Vec<StatementLine>,
+    pub asset_total: Comparison,
+    pub liabilities: Vec<StatementLine>,
+    pub liability_total: Comparison,
+    /// 事業主借, 元入金 and other equity accounts.
+    pub equity: Vec<StatementLine>,
+    /// 青色申告特別控除前の所得金額 for the period; zero at 期首.
+    pub income: Comparison,
+    pub liabilities_and_equity_total: Comparison,
+    /// Whether assets equal liabilities and equity in both columns.
+    pub balanced: bool,
+}
+
+impl BalanceSheet {
+    pub fn generate(connection: &mut SqliteConnection, period: Period) -> Result<BalanceSheet> {
+        let accounts = AccountRepository::new(connection).list(true)?;
+        let mut ledger = LedgerRepository::new(connection);
+        let opening = rolled_up_balances(&accounts, &ledger.balances_before(period.from)?);
+        let closing = rolled_up_balances(&accounts, &ledger.balances_as_of(period.to)?);
+        let unclosed_profit = profit(&accounts, &opening);
+
+        let lines = |account_type: AccountType| {
+            accounts
+                .iter()
+                .filter(|account| account.account_type == account_type)
+                .map(|account| {
+                    let mut amount = Comparison {
+                        current: closing.get(&account.id).copied().unwrap_or(0),
+                        prior: opening.get(&account.id).copied().unwrap_or(0),
+                    };
+                    if account.code == CAPITAL {
+                        amount.current += unclosed_profit;
+                        amount.prior += unclosed_profit;
+                    }
+                    StatementLine::account(account, amount)
+                })
+                .filter(|line| line.amount != Comparison::default())
+                .collect::<Vec<_>>()
+        };
+        let total = |lines: &[StatementLine]| lines.iter().map(|line| line.amount).sum();
+
+        let assets = lines(AccountType::Asset);
+        let liabilities = lines(AccountType::Liability);
+        let equity = lines(AccountType::Equity);
+        let income = Comparison {
+            current: profit(&accounts, &closing) - unclosed_profit,
+            prior: 0,
+        };
+        let asset_total: Comparison = total(&assets);
+        let liability_total: Comparison = total(&liabilities);
+        let liabilities_and_equity_total = liability_total + total(&equity) + income;
+
+        Ok(BalanceSheet {
+            period,
+            assets,
+            asset_total,
+            liabilities,
+            liability_total,
+            equity,
+            income,
+            liabilities_and_equity_total,
+            balanced: asset_total == liabilities_and_equity_total,
+        })
+    }
+}
+
+impl Statement for BalanceSheet {
+    fn title(&self) -> String {
+        format!("貸借対照表 {}", self.period.to)
+    }
+
+    fn columns(&self) -> (String, String) {
+        (
+            format!("期末 {}", self.period.to),
+            format!("期首 {}", self.period.from),
+        )
+    }
+
+    fn lines(&self) -> Vec<StatementLine> {
+        let mut lines = vec![StatementLine::heading("資産の部")];
+        lines.extend(self.assets.iter().cloned());
+        lines.push(StatementLine::total("資産合計", self.asset_total));
+        lines.push(StatementLine::heading("負債・資本の部"));
+        lines.extend(self.liabilities.iter().cloned());
+        lines.push(StatementLine::subtotal("負債合計", self.liability_total));
+        lines.extend(self.equity.iter().cloned());
+        lines.push(StatementLine::item(
+            "青色申告特別控除前の所得金額",
+            self.income,
+        ));
+        lines.push(StatementLine::total(
+            "負債・資本合計",
+            self.liabilities_and_equity_total,
+        ));
+        lines
+    }
+}
+
+/// Revenue less expenses in `balances`.
+fn profit(accounts: &[Account], balances: &HashMap<i32, i64>) -> i64 {
+    accounts
+        .iter()
+        .map(|account| {
+            let balance = balances.get(&account.id).copied().unwrap_or(0);
+            match account.account_type {
+                AccountType::Revenue => balance,
+                AccountType::Expense => -balance,
+                _ => 0,
+            }
+        })
+        .sum()
+}
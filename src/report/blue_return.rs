@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Months, NaiveDate};
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use super::depreciation::HEADERS as DEPRECIATION_HEADERS;
+use super::statement::{escape_html, rolled_up_balances, LineKind, Statement};
+use super::{text_table, yen, BalanceSheet, DepreciationSchedule, Period};
+use crate::error::Result;
+use crate::models::account_codes::INVENTORY;
+use crate::models::{Account, AccountType, ReturnLine};
+use crate::pdf;
+use crate::repository::{
+    AccountRepository, ApportionmentRepository, CounterpartyRepository, LedgerRepository,
+    ReturnLineRepository,
+};
+
+/// One line of 売上(収入)金額 or 経費 on the first page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReturnItem {
+    pub line: ReturnLine,
+    pub label: String,
+    /// Codes of the accounts reported on the line.
+    pub accounts: Vec<String>,
+    pub amount: i64,
+}
+
+/// 損益計算書, the first page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReturnIncomeStatement {
+    /// 売上(収入)金額, 家事消費等 and 雑収入 included.
+    pub sales: i64,
+    pub opening_inventory: i64,
+    pub purchases: i64,
+    pub closing_inventory: i64,
+    /// 差引原価
+    pub cost_of_sales: i64,
+    /// 差引金額
+    pub gross_profit: i64,
+    /// Every statutory expense line in the order of the form, then the
+    /// accounts without one, then 雑費.
+    pub expenses: Vec<ReturnItem>,
+    pub expense_total: i64,
+    /// 青色申告特別控除前の所得金額
+    pub income: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonthlyRow {
+    pub year: i32,
+    pub month: u32,
+    pub sales: i64,
+    pub purchases: i64,
+}
+
+/// 月別売上(収入)金額及び仕入金額, on the second page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MonthlySales {
+    pub months: Vec<MonthlyRow>,
+    pub household_consumption: i64,
+    pub misc_income: i64,
+    /// Equal to 売上(収入)金額 on the first page.
+    pub total_sales: i64,
+    pub total_purchases: i64,
+}
+
+/// One payee of 地代家賃の内訳, on the third page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RentRow {
+    /// `None` for rent recorded without a counterparty.
+    pub counterparty_id: Option<i32>,
+    pub name: String,
+    pub address: String,
+    /// 本年中の賃借料
+    pub rent: i64,
+    /// 左の賃借料のうち必要経費算入額, after 家事按分.
+    pub business: i64,
+}
+
+/// 所得税青色申告決算書 (一般用) for a fiscal year, built from the ledger.
+/// Accounts are put on the lines of the form through the return line
+/// mapping (see [`ReturnLineRepository`]). Depreciation and 家事按分 are
+/// taken as posted, so the year-end entries should be in before printing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlueReturn {
+    pub period: Period,
+    pub income_statement: ReturnIncomeStatement,
+    pub monthly: MonthlySales,
+    pub depreciation: DepreciationSchedule,
+    pub rent: Vec<RentRow>,
+    pub balance_sheet: BalanceSheet,
+}
+
+/// A printed section: a heading over a table laid out as for
+/// [`text_table`].
+struct Section {
+    heading: String,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    left: usize,
+}
+
+impl BlueReturn {
+    pub fn generate(connection: &mut SqliteConnection, period: Period) -> Result<BlueReturn> {
+        let accounts = AccountRepository::new(connection).list(true)?;
+        let lines = ReturnLineRepository::new(connection).resolve()?;
+        let income_statement = income_statement(connection, &accounts, &lines, period)?;
+        let monthly = monthly(connection, &accounts, &lines, period)?;
+        let depreciation = DepreciationSchedule::generate(connection, period)?;
+        let rent = rent(connection, &lines, period)?;
+        let balance_sheet = BalanceSheet::generate(connection, period)?;
+        Ok(BlueReturn {
+            period,
+            income_statement,
+            monthly,
+            depreciation,
+            rent,
+            balance_sheet,
+        })
+    }
+
+    pub fn title(&self) -> String {
+        format!(
+            "青色申告決算書(一般用) {} - {}",
+            self.period.from, self.period.to
+        )
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut table = format!("{}\n", self.title());
+        for (title, sections) in self.pages() {
+            table.push_str(&format!("\n■ {}\n", title));
+            for section in sections {
+                table.push_str(&format!(
+                    "\n{}\n\n{}",
+                    section.heading,
+                    text_table(&section.headers, &section.rows, section.left)
+                ));
+            }
+        }
+        table
+    }
+
+    /// A self-contained HTML document, one 決算書 page per printed A4 page.
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+  @page {{ size: A4; margin: 15mm; }}
+  body {{ font-family: "Hiragino Mincho ProN", "Yu Mincho", serif; font-size: 9pt; }}
+  section.page {{ break-after: page; }}
+  section.page:last-child {{ break-after: auto; }}
+  h1 {{ font-size: 14pt; text-align: center; }}
+  h2 {{ font-size: 11pt; margin: 1.2em 0 0.4em; }}
+  table {{ width: 100%; border-collapse: collapse; }}
+  th, td {{ border: 1px solid #333; padding: 2px 6px; }}
+  td.amount {{ text-align: right; font-variant-numeric: tabular-nums; }}
+  tr.separator td {{ border-top: 3px double #333; padding: 0; }}
+</style>
+</head>
+<body>
+"#,
+            title = escape_html(&self.title())
+        );
+        for (title, sections) in self.pages() {
+            html.push_str(&format!(
+                "<section class=\"page\">\n<h1>{}</h1>\n",
+                escape_html(&title)
+            ));
+            for section in sections {
+                html.push_str(&format!(
+                    "<h2>{}</h2>\n<table>\n<thead><tr>",
+                    escape_html(&section.heading)
+                ));
+                for header in &section.headers {
+                    html.push_str(&format!("<th>{}</th>", escape_html(header)));
+                }
+                html.push_str("</tr></thead>\n<tbody>\n");
+                for row in &section.rows {
+                    if row.is_empty() {
+                        html.push_str(&format!(
+                            "<tr class=\"separator\"><td colspan=\"{}\"></td></tr>\n",
+                            section.headers.len()
+                        ));
+                        continue;
+                    }
+                    html.push_str("<tr>");
+                    for i in 0..section.headers.len() {
+                        let cell = row.get(i).map(String::as_str).unwrap_or_default();
+                        match i < section.left {
+                            true => html.push_str(&format!("<td>{}</td>", escape_html(cell))),
+                            false => html.push_str(&format!(
+                                "<td class=\"amount\">{}</td>",
+                                escape_html(cell)
+                            )),
+                        }
+                    }
+                    html.push_str("</tr>\n");
+                }
+                html.push_str("</tbody>\n</table>\n");
+            }
+            html.push_str("</section>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// The same pages as a PDF file.
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let mut document = pdf::Document::new(&self.title());
+        for (title, sections) in self.pages() {
+            document.page_break();
+            document.title(&title);
+            for section in sections {
+                document.heading(&section.heading);
+                document.table(&section.headers, &section.rows, section.left);
+            }
+        }
+        document.finish()
+    }
+
+    /// The four pages of the form with their sections.
+    fn pages(&self) -> Vec<(String, Vec<Section>)> {
+        vec![
+            (
+                "損益計算書".to_string(),
+                vec![self.income_statement_section()],
+            ),
+            (
+                "月別売上(収入)金額及び仕入金額".to_string(),
+                vec![self.monthly_section()],
+            ),
+            (
+                "減価償却費の計算・地代家賃の内訳".to_string(),
+                vec![self.depreciation_section(), self.rent_section()],
+            ),
+            ("貸借対照表".to_string(), vec![self.balance_sheet_section()]),
+        ]
+    }
+
+    fn income_statement_section(&self) -> Section {
+        let statement = &self.income_statement;
+        let row = |label: &str, amount: i64| vec![label.to_string(), yen(amount)];
+        let mut rows = vec![
+            row("売上(収入)金額", statement.sales),
+            Vec::new(),
+            row("期首商品棚卸高", statement.opening_inventory),
+            row("仕入金額", statement.purchases),
+            row("小計", statement.opening_inventory + statement.purchases),
+            row("期末商品棚卸高", statement.closing_inventory),
+            row("差引原価", statement.cost_of_sales),
+            row("差引金額", statement.gross_profit),
+            Vec::new(),
+        ];
+        rows.extend(
+            statement
+                .expenses
+                .iter()
+                .map(|item| row(&item.label, item.amount)),
+        );
+        rows.push(row("計", statement.expense_total));
+        rows.push(Vec::new());
+        rows.push(row("青色申告特別控除前の所得金額", statement.income));
+        Section {
+            heading: format!("自 {} 至 {}", self.period.from, self.period.to),
+            headers: vec!["科目", "金額(円)"],
+            rows,
+            left: 1,
+        }
+    }
+
+    fn monthly_section(&self) -> Section {
+        let monthly = &self.monthly;
+        let mut rows = monthly
+            .months
+            .iter()
+            .map(|row| {
+                vec![
+                    format!("{}年{}月", row.year, row.month),
+                    yen(row.sales),
+                    yen(row.purchases),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push(vec![
+            "家事消費等".to_string(),
+            yen(monthly.household_consumption),
+        ]);
+        rows.push(vec!["雑収入".to_string(), yen(monthly.misc_income)]);
+        rows.push(Vec::new());
+        rows.push(vec![
+            "計".to_string(),
+            yen(monthly.total_sales),
+            yen(monthly.total_purchases),
+        ]);
+        Section {
+            heading: format!("自 {} 至 {}", self.period.from, self.period.to),
+            headers: vec!["月", "売上(収入)金額", "仕入金額"],
+            rows,
+            left: 1,
+        }
+    }
+
+    fn depreciation_section(&self) -> Section {
+        Section {
+            heading: "減価償却費の計算".to_string(),
+            headers: DEPRECIATION_HEADERS.to_vec(),
+            rows: self.depreciation.rows(),
+            left: 2,
+        }
+    }
+
+    fn rent_section(&self) -> Section {
+        let mut rows = self
+            .rent
+            .iter()
+            .map(|row| {
+                vec![
+                    row.address.clone(),
+                    row.name.clone(),
+                    yen(row.rent),
+                    yen(row.business),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push(Vec::new());
+        rows.push(vec![
+            String::new(),
+            "計".to_string(),
+            yen(self.rent.iter().map(|row| row.rent).sum()),
+            yen(self.rent.iter().map(|row| row.business).sum()),
+        ]);
+        Section {
+            heading: "地代家賃の内訳".to_string(),
+            headers: vec![
+                "支払先の住所",
+                "支払先の氏名",
+                "本年中の賃借料",
+                "必要経費算入額",
+            ],
+            rows,
+            left: 2,
+        }
+    }
+
+    fn balance_sheet_section(&self) -> Section {
+        let (closing, opening) = self.balance_sheet.columns();
+        let rows = self
+            .balance_sheet
+            .lines()
+            .into_iter()
+            .map(|line| match line.kind {
+                LineKind::Heading => vec![format!("【{}】", line.label)],
+                _ => vec![line.label, yen(line.amount.prior), yen(line.amount.current)],
+            })
+            .collect();
+        Section {
+            heading: format!("{} / {}", opening, closing),
+            headers: vec!["科目", "期首", "期末"],
+            rows,
+            left: 1,
+        }
+    }
+}
+
+fn income_statement(
+    connection: &mut SqliteConnection,
+    accounts: &[Account],
+    lines: &HashMap<i32, ReturnLine>,
+    period: Period,
+) -> Result<ReturnIncomeStatement> {
+    let mut ledger = LedgerRepository::new(connection);
+    let balances = rolled_up_balances(
+        accounts,
+        &ledger.totals_by_account(Some(period.from), Some(period.to))?,
+    );
+    let (opening_inventory, closing_inventory) =
+        match accounts.iter().find(|account| account.code == INVENTORY) {
+            Some(inventory) => (
+                ledger.opening_balance(inventory.id, period.from)?,
+                ledger.balance_as_of(inventory.id, period.to)?,
+            ),
+            None => (0, 0),
+        };
+
+    let mut sales = 0;
+    let mut cost_of_sales = 0;
+    // Every statutory line is printed, whether used or not.
+    let mut expenses = ReturnLine::ALL
+        .iter()
+        .filter(|line| {
+            line.account_type() == AccountType::Expense
+                && !matches!(line, ReturnLine::Purchases | ReturnLine::Other)
+        })
+        .map(|line| ReturnItem {
+            line: *line,
+            label: line.label().to_string(),
+            accounts: Vec::new(),
+            amount: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut others = Vec::new();
+    // Accounts come ordered by code.
+    for account in accounts {
+        let (Some(&balance), Some(&line)) = (balances.get(&account.id), lines.get(&account.id))
+        else {
+            continue;
+        };
+        match line {
+            ReturnLine::Sales | ReturnLine::HouseholdConsumption | ReturnLine::MiscIncome => {
+                sales += balance
+            }
+            ReturnLine::Purchases => cost_of_sales += balance,
+            ReturnLine::Other => others.push(ReturnItem {
+                line,
+                label: account.name.clone(),
+                accounts: vec![account.code.clone()],
+                amount: balance,
+            }),
+            _ => {
+                if let Some(item) = expenses.iter_mut().find(|item| item.line == line) {
+                    item.accounts.push(account.code.clone());
+                    item.amount += balance;
+                }
+            }
+        }
+    }
+    // The blank lines come just before 雑費.
+    let miscellaneous = expenses.pop();
+    expenses.extend(others.into_iter().filter(|item| item.amount != 0));
+    expenses.extend(miscellaneous);
+
+    let expense_total = expenses.iter().map(|item| item.amount).sum();
+    let gross_profit = sales - cost_of_sales;
+    Ok(ReturnIncomeStatement {
+        sales,
+        opening_inventory,
+        purchases: cost_of_sales + closing_inventory - opening_inventory,
+        closing_inventory,
+        cost_of_sales,
+        gross_profit,
+        expenses,
+        expense_total,
+        income: gross_profit - expense_total,
+    })
+}
+
+fn monthly(
+    connection: &mut SqliteConnection,
+    accounts: &[Account],
+    lines: &HashMap<i32, ReturnLine>,
+    period: Period,
+) -> Result<MonthlySales> {
+    let mut ledger = LedgerRepository::new(connection);
+    let mut monthly = MonthlySales {
+        months: Vec::new(),
+        household_consumption: 0,
+        misc_income: 0,
+        total_sales: 0,
+        total_purchases: 0,
+    };
+    let mut from = period.from;
+    while from <= period.to {
+        let first = from.with_day(1).unwrap_or(from);
+        let to = first
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(NaiveDate::MAX)
+            .min(period.to);
+        let balances =
+            rolled_up_balances(accounts, &ledger.totals_by_account(Some(from), Some(to))?);
+        let mut row = MonthlyRow {
+            year: from.year(),
+            month: from.month(),
+            sales: 0,
+            purchases: 0,
+        };
+        for (account_id, balance) in balances {
+            match lines.get(&account_id) {
+                Some(ReturnLine::Sales) => row.sales += balance,
+                Some(ReturnLine::HouseholdConsumption) => monthly.household_consumption += balance,
+                Some(ReturnLine::MiscIncome) => monthly.misc_income += balance,
+                Some(ReturnLine::Purchases) => row.purchases += balance,
+                _ => {}
+            }
+        }
+        monthly.months.push(row);
+        let Some(next) = to.succ_opt() else {
+            break;
+        };
+        from = next;
+    }
+    monthly.total_sales = monthly.months.iter().map(|row| row.sales).sum::<i64>()
+        + monthly.household_consumption
+        + monthly.misc_income;
+    monthly.total_purchases = monthly.months.iter().map(|row| row.purchases).sum();
+    Ok(monthly)
+}
+
+fn rent(
+    connection: &mut SqliteConnection,
+    lines: &HashMap<i32, ReturnLine>,
+    period: Period,
+) -> Result<Vec<RentRow>> {
+    let rent_accounts = lines
+        .iter()
+        .filter(|(_, line)| **line == ReturnLine::Rent)
+        .map(|(account_id, _)| *account_id)
+        .collect::<Vec<_>>();
+    let payees = ApportionmentRepository::new(connection).by_counterparty(
+        &rent_accounts,
+        period.from,
+        period.to,
+    )?;
+    let counterparties = CounterpartyRepository::new(connection)
+        .list()?
+        .into_iter()
+        .map(|counterparty| (counterparty.id, counterparty))
+        .collect::<HashMap<_, _>>();
+    Ok(payees
+        .into_iter()
+        .filter(|payee| payee.original != 0)
+        .map(|payee| {
+            let counterparty = payee.counterparty_id.and_then(|id| counterparties.get(&id));
+            RentRow {
+                counterparty_id: payee.counterparty_id,
+                name: counterparty.map_or("取引先未設定".to_string(), |c| c.name.clone()),
+                address: counterparty.map_or(String::new(), |c| c.address.clone()),
+                rent: payee.original,
+                business: payee.business,
+            }
+        })
+        .collect())
+}
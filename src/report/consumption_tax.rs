@@ -0,0 +1,301 @@
+use std::io;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::Serialize;
+
+use super::{text_table, yen, Period};
+use crate::error::Result;
+use crate::models::{
+    AccountType, Counterparty, EntryKind, JournalLine, Side, TaxCategory, TaxMethod,
+};
+use crate::repository::TaxSettingsRepository;
+use crate::schema::{accounts, counterparties, journal_entries, journal_lines};
+
+/// Tax categories that appear in the report, in the order of the return.
+const CATEGORIES: [TaxCategory; 4] = [
+    TaxCategory::Taxable10,
+    TaxCategory::Reduced8,
+    TaxCategory::Exempt,
+    TaxCategory::NonTaxable,
+];
+
+/// Sales and purchases of one tax category. Bases are before tax.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CategorySummary {
+    pub tax_category: TaxCategory,
+    pub rate: i64,
+    pub sales_base: i64,
+    pub sales_tax: i64,
+    pub purchase_base: i64,
+    pub purchase_tax: i64,
+    /// The part of `purchase_tax` that may be credited.
+    pub creditable_purchase_tax: i64,
+}
+
+/// 消費税集計表: taxable sales and purchases by rate with the figures of
+/// the return under the standard method (一般課税).
+///
+/// Lines on revenue accounts are sales, credits adding and debits (returns)
+/// subtracting; lines on any other account are purchases. Output tax is
+/// recomputed from the sales bases (割戻し計算) while input tax is the sum
+/// recorded on the lines (積上げ計算), all of it creditable as for a
+/// business whose taxable sales ratio is 95% or more.
+///
+/// Purchases from a counterparty that was not a qualified invoice issuer
+/// on the date of the entry are only credited in part under the
+/// transitional measures (80% until 2026-09-30, then 50% until 2029-09-30).
+/// Lines without a counterparty are taken to have a qualified invoice.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConsumptionTaxReport {
+    pub period: Period,
+    pub method: TaxMethod,
+    pub categories: Vec<CategorySummary>,
+    /// 仮受消費税 or tax included in sales, as recorded.
+    pub output_tax: i64,
+    /// 仮払消費税 or tax included in purchases, as recorded.
+    pub input_tax: i64,
+    /// Input tax after the transitional measures.
+    pub creditable_input_tax: i64,
+    /// 課税標準額: taxable sales before tax, each rate rounded down to
+    /// 1,000 yen.
+    pub taxable_base: i64,
+    /// National part of the output tax (7.8% and 6.24%).
+    pub national_output_tax: i64,
+    /// National part of the creditable input tax (78/100 of it).
+    pub national_input_tax: i64,
+    /// 差引税額, rounded down to 100 yen; negative for a refund.
+    pub national_tax_due: i64,
+    /// 地方消費税 (22/78 of the national tax), rounded down to 100 yen.
+    pub local_tax_due: i64,
+    pub total_due: i64,
+}
+
+impl ConsumptionTaxReport {
+    pub fn generate(
+        connection: &mut SqliteConnection,
+        period: Period,
+    ) -> Result<ConsumptionTaxReport> {
+        let settings = TaxSettingsRepository::new(connection).get()?;
+        let lines = journal_lines::table
+            .inner_join(journal_entries::table)
+            .inner_join(accounts::table)
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::kind.eq(EntryKind::Regular))
+            .filter(journal_entries::entry_date.ge(period.from))
+            .filter(journal_entries::entry_date.le(period.to))
+            .left_join(counterparties::table)
+            .filter(journal_lines::tax_category.ne(TaxCategory::OutOfScope))
+            .select((
+                JournalLine::as_select(),
+                accounts::account_type,
+                journal_entries::entry_date,
+                Option::<Counterparty>::as_select(),
+            ))
+            .load::<(JournalLine, AccountType, NaiveDate, Option<Counterparty>)>(connection)?;
+
+        let mut categories = CATEGORIES
+            .iter()
+            .map(|&tax_category| CategorySummary {
+                tax_category,
+                rate: tax_category.rate(),
+                sales_base: 0,
+                sales_tax: 0,
+                purchase_base: 0,
+                purchase_tax: 0,
+                creditable_purchase_tax: 0,
+            })
+            .collect::<Vec<_>>();
+        for (line, account_type, entry_date, counterparty) in lines {
+            let Some(summary) = categories
+                .iter_mut()
+                .find(|summary| summary.tax_category == line.tax_category)
+            else {
+                continue;
+            };
+            let (base, tax) = (line.base_amount(), line.tax_amount);
+            let creditable = match counterparty {
+                Some(counterparty) if !counterparty.qualified_on(entry_date) => {
+                    tax * transitional_credit_percent(entry_date) / 100
+                }
+                _ => tax,
+            };
+            match (account_type, line.side) {
+                (AccountType::Revenue, Side::Credit) => {
+                    summary.sales_base += base;
+                    summary.sales_tax += tax;
+                }
+                (AccountType::Revenue, Side::Debit) => {
+                    summary.sales_base -= base;
+                    summary.sales_tax -= tax;
+                }
+                (_, Side::Debit) => {
+                    summary.purchase_base += base;
+                    summary.purchase_tax += tax;
+                    summary.creditable_purchase_tax += creditable;
+                }
+                (_, Side::Credit) => {
+                    summary.purchase_base -= base;
+                    summary.purchase_tax -= tax;
+                    summary.creditable_purchase_tax -= creditable;
+                }
+            }
+        }
+
+        let output_tax = categories.iter().map(|c| c.sales_tax).sum();
+        let input_tax = categories.iter().map(|c| c.purchase_tax).sum();
+        let creditable_input_tax: i64 = categories.iter().map(|c| c.creditable_purchase_tax).sum();
+        let mut taxable_base = 0;
+        let mut national_output_tax = 0;
+        for summary in categories.iter().filter(|summary| summary.rate > 0) {
+            let base = round_down(summary.sales_base, 1_000);
+            taxable_base += base;
+            // National rates: 7.8% of 10% and 6.24% of 8%.
+            national_output_tax += match summary.tax_category {
+                TaxCategory::Taxable10 => base * 78 / 1_000,
+                TaxCategory::Reduced8 => base * 624 / 10_000,
+                _ => 0,
+            };
+        }
+        let national_input_tax = creditable_input_tax * 78 / 100;
+        let national_tax_due = round_down(national_output_tax - national_input_tax, 100);
+        let local_tax_due = round_down(national_tax_due * 22 / 78, 100);
+
+        Ok(ConsumptionTaxReport {
+            period,
+            method: settings.method,
+            categories,
+            output_tax,
+            input_tax,
+            creditable_input_tax,
+            taxable_base,
+            national_output_tax,
+            national_input_tax,
+            national_tax_due,
+            local_tax_due,
+            total_due: national_tax_due + local_tax_due,
+        })
+    }
+
+    /// One CSV record per tax category.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "tax_category",
+            "rate",
+            "sales_base",
+            "sales_tax",
+            "purchase_base",
+            "purchase_tax",
+            "creditable_purchase_tax",
+        ])?;
+        for summary in &self.categories {
+            csv.write_record([
+                summary.tax_category.as_str(),
+                &summary.rate.to_string(),
+                &summary.sales_base.to_string(),
+                &summary.sales_tax.to_string(),
+                &summary.purchase_base.to_string(),
+                &summary.purchase_tax.to_string(),
+                &summary.creditable_purchase_tax.to_string(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut rows = self
+            .categories
+            .iter()
+            .map(|summary| {
+                vec![
+                    category_label(summary.tax_category).to_string(),
+                    yen(summary.sales_base),
+                    yen(summary.sales_tax),
+                    yen(summary.purchase_base),
+                    yen(summary.purchase_tax),
+                    yen(summary.creditable_purchase_tax),
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push(Vec::new());
+        rows.push(vec![
+            "合計".to_string(),
+            String::new(),
+            yen(self.output_tax),
+            String::new(),
+            yen(self.input_tax),
+            yen(self.creditable_input_tax),
+        ]);
+
+        let summary = [
+            ("課税標準額", self.taxable_base),
+            ("消費税額", self.national_output_tax),
+            ("控除対象仕入税額", self.national_input_tax),
+            ("差引税額", self.national_tax_due),
+            ("地方消費税額", self.local_tax_due),
+            ("納付税額合計", self.total_due),
+        ]
+        .iter()
+        .map(|(label, amount)| vec![label.to_string(), yen(*amount)])
+        .collect::<Vec<_>>();
+
+        format!(
+            "消費税集計表 {} - {} ({})\n\n{}\n{}",
+            self.period.from,
+            self.period.to,
+            match self.method {
+                TaxMethod::Inclusive => "税込経理",
+                TaxMethod::Exclusive => "税抜経理",
+            },
+            text_table(
+                &[
+                    "区分",
+                    "売上(税抜)",
+                    "売上税額",
+                    "仕入(税抜)",
+                    "仕入税額",
+                    "控除対象",
+                ],
+                &rows,
+                1
+            ),
+            text_table(&["申告", "金額"], &summary, 1)
+        )
+    }
+}
+
+fn category_label(tax_category: TaxCategory) -> &'static str {
+    match tax_category {
+        TaxCategory::Taxable10 => "課税 10%",
+        TaxCategory::Reduced8 => "課税 8% (軽減)",
+        TaxCategory::Exempt => "免税",
+        TaxCategory::NonTaxable => "非課税",
+        TaxCategory::OutOfScope => "不課税",
+    }
+}
+
+/// Share in percent of the tax on a purchase without a qualified invoice
+/// that may still be credited on `date`.
+fn transitional_credit_percent(date: NaiveDate) -> i64 {
+    let on_or_before = |y, m, d| date <= NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    if on_or_before(2023, 9, 30) {
+        100
+    } else if on_or_before(2026, 9, 30) {
+        80
+    } else if on_or_before(2029, 9, 30) {
+        50
+    } else {
+        0
+    }
+}
+
+/// Rounds positive amounts down to a multiple of `unit`; refunds are left
+/// as they are.
+fn round_down(amount: i64, unit: i64) -> i64 {
+    match amount {
+        amount if amount > 0 => amount / unit * unit,
+        amount => amount,
+    }
+}
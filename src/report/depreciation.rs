@@ -0,0 +1,206 @@
+use std::io;
+
+use chrono::{Datelike, NaiveDate};
+use diesel::prelude::*;
+use serde::Serialize;
+
+use super::{text_table, yen, Period};
+use crate::depreciation::{depreciate, YearDepreciation};
+use crate::error::Result;
+use crate::models::DepreciationMethod;
+use crate::repository::{FixedAssetRepository, SmallAmountUsage};
+
+/// Column headings of the printed schedule.
+pub(super) const HEADERS: [&str; 12] = [
+    "資産",
+    "取得年月",
+    "取得価額(償却保証額)",
+    "償却の基礎",
+    "償却方法",
+    "耐用年数",
+    "償却率",
+    "償却期間",
+    "償却費",
+    "事業専用",
+    "必要経費算入額",
+    "未償却残高",
+];
+
+/// One asset on the depreciation schedule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepreciationRow {
+    pub asset_id: i32,
+    pub name: String,
+    pub quantity: String,
+    pub acquired_on: NaiveDate,
+    pub cost: i64,
+    pub method: DepreciationMethod,
+    pub useful_life: i32,
+    pub business_ratio: i32,
+    #[serde(flatten)]
+    pub year: YearDepreciation,
+}
+
+/// 減価償却費の計算: the depreciation of every asset held during the
+/// period, as on the third page of the blue-return statement. The period
+/// is taken to be a fiscal year. Small-amount assets beyond the annual cap
+/// are listed as expensed but reported in `warnings`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DepreciationSchedule {
+    pub period: Period,
+    pub rows: Vec<DepreciationRow>,
+    pub total_depreciation: i64,
+    /// Total of 本年分の必要経費算入額.
+    pub total_business_portion: i64,
+    /// Total 未償却残高 at the end of the period, disposed assets excluded.
+    pub total_closing_book_value: i64,
+    pub small_amount: SmallAmountUsage,
+    pub warnings: Vec<String>,
+}
+
+impl DepreciationSchedule {
+    pub fn generate(
+        connection: &mut SqliteConnection,
+        period: Period,
+    ) -> Result<DepreciationSchedule> {
+        let mut repository = FixedAssetRepository::new(connection);
+        let small_amount = repository.small_amount_usage(period.from, period.to)?;
+        let mut rows = Vec::new();
+        for asset in repository.list()? {
+            let Some(year) = depreciate(&asset, period.from, period.to)? else {
+                continue;
+            };
+            rows.push(DepreciationRow {
+                asset_id: asset.id,
+                name: asset.name,
+                quantity: asset.quantity,
+                acquired_on: asset.acquired_on,
+                cost: asset.cost,
+                method: asset.method,
+                useful_life: asset.useful_life,
+                business_ratio: asset.business_ratio,
+                year,
+            });
+        }
+        Ok(DepreciationSchedule {
+            period,
+            total_depreciation: rows.iter().map(|row| row.year.depreciation).sum(),
+            total_business_portion: rows.iter().map(|row| row.year.business_portion).sum(),
+            total_closing_book_value: rows
+                .iter()
+                .filter(|row| !row.year.disposed)
+                .map(|row| row.year.closing_book_value)
+                .sum(),
+            rows,
+            warnings: small_amount.warning().into_iter().collect(),
+            small_amount,
+        })
+    }
+
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "asset_id",
+            "name",
+            "quantity",
+            "acquired_on",
+            "cost",
+            "guaranteed_amount",
+            "basis",
+            "method",
+            "useful_life",
+            "rate",
+            "months",
+            "depreciation",
+            "business_ratio",
+            "business_portion",
+            "closing_book_value",
+            "disposed",
+        ])?;
+        for row in &self.rows {
+            csv.write_record([
+                &row.asset_id.to_string(),
+                &row.name,
+                &row.quantity,
+                &row.acquired_on.to_string(),
+                &row.cost.to_string(),
+                &row.year
+                    .guaranteed_amount
+                    .map_or(String::new(), |amount| amount.to_string()),
+                &row.year.basis.to_string(),
+                row.method.as_str(),
+                &row.useful_life.to_string(),
+                &rate(row.year.rate),
+                &row.year.months.to_string(),
+                &row.year.depreciation.to_string(),
+                &row.business_ratio.to_string(),
+                &row.year.business_portion.to_string(),
+                &row.year.closing_book_value.to_string(),
+                &row.year.disposed.to_string(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    /// Rows of the printed schedule, totals included, under [`HEADERS`].
+    pub(super) fn rows(&self) -> Vec<Vec<String>> {
+        let mut rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                vec![
+                    row.name.clone(),
+                    format!("{}年{}月", row.acquired_on.year(), row.acquired_on.month()),
+                    match row.year.guaranteed_amount {
+                        Some(guaranteed) => format!("{} ({})", yen(row.cost), yen(guaranteed)),
+                        None => yen(row.cost),
+                    },
+                    yen(row.year.basis),
+                    row.method.label().to_string(),
+                    format!("{}年", row.useful_life),
+                    rate(row.year.rate),
+                    format!("{}/12", row.year.months),
+                    yen(row.year.depreciation),
+                    format!("{}%", row.business_ratio),
+                    yen(row.year.business_portion),
+                    match row.year.disposed {
+                        true => "除却".to_string(),
+                        false => yen(row.year.closing_book_value),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push(Vec::new());
+        let mut total = vec![String::new(); 12];
+        total[0] = "計".to_string();
+        total[8] = yen(self.total_depreciation);
+        total[10] = yen(self.total_business_portion);
+        total[11] = yen(self.total_closing_book_value);
+        rows.push(total);
+        rows
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut warnings = self
+            .warnings
+            .iter()
+            .map(|warning| format!("warning: {}\n", warning))
+            .collect::<String>();
+        if !warnings.is_empty() {
+            warnings.insert(0, '\n');
+        }
+        format!(
+            "減価償却費の計算 {} - {}\n\n{}{}",
+            self.period.from,
+            self.period.to,
+            text_table(&HEADERS, &self.rows(), 2),
+            warnings
+        )
+    }
+}
+
+/// A rate in thousandths as printed, e.g. `0.250`.
+fn rate(thousandths: i64) -> String {
+    format!("{}.{:03}", thousandths / 1000, thousandths % 1000)
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use super::statement::{rolled_up_balances, Comparison, Statement, StatementLine};
+use super::Period;
+use crate::error::Result;
+use crate::models::account_codes::{INVENTORY, PURCHASES};
+use crate::models::{Account, AccountType};
+use crate::repository::{AccountRepository, LedgerRepository};
+
+/// 損益計算書 laid out like page 1 of the blue-return 決算書, with the same
+/// period one year earlier as the comparative column.
+///
+/// Cost of sales is the balance of 仕入高. Opening and closing inventory are
+/// the balances of 商品, and purchases are derived from the three, so the
+/// statement is right whether or not inventory has been transferred to and
+/// from 仕入高 with adjusting entries.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncomeStatement {
+    pub period: Period,
+    pub prior_period: Period,
+    /// 売上(収入)金額, including 雑収入.
+    pub sales: Comparison,
+    pub opening_inventory: Comparison,
+    pub purchases: Comparison,
+    pub closing_inventory: Comparison,
+    /// 差引原価
+    pub cost_of_sales: Comparison,
+    /// 差引金額 (sales less cost of sales)
+    pub gross_profit: Comparison,
+    /// One line per top-level expense account, sub-accounts included.
+    pub expenses: Vec<StatementLine>,
+    pub expense_total: Comparison,
+    /// 青色申告特別控除前の所得金額
+    pub income: Comparison,
+}
+
+/// Figures for a single period.
+struct Figures {
+    sales: i64,
+    opening_inventory: i64,
+    closing_inventory: i64,
+    cost_of_sales: i64,
+    expenses: HashMap<i32, i64>,
+}
+
+impl IncomeStatement {
+    pub fn generate(connection: &mut SqliteConnection, period: Period) -> Result<IncomeStatement> {
+        let accounts = AccountRepository::new(connection).list(true)?;
+        let prior_period = period.prior_year();
+        let current = figures(connection, &accounts, period)?;
+        let prior = figures(connection, &accounts, prior_period)?;
+        let compare = |f: fn(&Figures) -> i64| Comparison {
+            current: f(&current),
+            prior: f(&prior),
+        };
+
+        let sales = compare(|f| f.sales);
+        let opening_inventory = compare(|f| f.opening_inventory);
+        let closing_inventory = compare(|f| f.closing_inventory);
+        let cost_of_sales = compare(|f| f.cost_of_sales);
+        let purchases = cost_of_sales + closing_inventory - opening_inventory;
+        let gross_profit = sales - cost_of_sales;
+
+        // Accounts come ordered by code, which is the order of the form.
+        let expenses = accounts
+            .iter()
+            .map(|account| {
+                let amount = Comparison {
+                    current: current.expenses.get(&account.id).copied().unwrap_or(0),
+                    prior: prior.expenses.get(&account.id).copied().unwrap_or(0),
+                };
+                StatementLine::account(account, amount)
+            })
+            .filter(|line| line.amount != Comparison::default())
+            .collect::<Vec<StatementLine>>();
+        let expense_total = expenses.iter().map(|line| line.amount).sum();
+
+        Ok(IncomeStatement {
+            period,
+            prior_period,
+            sales,
+            opening_inventory,
+            purchases,
+            closing_inventory,
+            cost_of_sales,
+            gross_profit,
+            expenses,
+            expense_total,
+            income: gross_profit - expense_total,
+        })
+    }
+}
+
+impl Statement for IncomeStatement {
+    fn title(&self) -> String {
+        format!("損益計算書 {} - {}", self.period.from, self.period.to)
+    }
+
+    fn columns(&self) -> (String, String) {
+        (
+            format!("当期 {} - {}", self.period.from, self.period.to),
+            format!("前期 {} - {}", self.prior_period.from, self.prior_period.to),
+        )
+    }
+
+    fn lines(&self) -> Vec<StatementLine> {
+        let mut lines = vec![
+            StatementLine::item("売上(収入)金額", self.sales),
+            StatementLine::heading("売上原価"),
+            StatementLine::item("期首商品棚卸高", self.opening_inventory),
+            StatementLine::item("仕入金額", self.purchases),
+            StatementLine::subtotal("小計", self.opening_inventory + self.purchases),
+            StatementLine::item("期末商品棚卸高", self.closing_inventory),
+            StatementLine::subtotal("差引原価", self.cost_of_sales),
+            StatementLine::subtotal("差引金額", self.gross_profit),
+            StatementLine::heading("経費"),
+        ];
+        lines.extend(self.expenses.iter().cloned());
+        lines.push(StatementLine::subtotal("経費計", self.expense_total));
+        lines.push(StatementLine::total(
+            "青色申告特別控除前の所得金額",
+            self.income,
+        ));
+        lines
+    }
+}
+
+fn figures(
+    connection: &mut SqliteConnection,
+    accounts: &[Account],
+    period: Period,
+) -> Result<Figures> {
+    let mut ledger = LedgerRepository::new(connection);
+    let totals = ledger.totals_by_account(Some(period.from), Some(period.to))?;
+    let balances = rolled_up_balances(accounts, &totals);
+    let by_code = |code: &str| accounts.iter().find(|account| account.code == code);
+    let inventory = by_code(INVENTORY).map(|account| account.id);
+    let purchases = by_code(PURCHASES).map(|account| account.id);
+
+    let (opening_inventory, closing_inventory) = match inventory {
+        Some(id) => (
+            ledger.opening_balance(id, period.from)?,
+            ledger.balance_as_of(id, period.to)?,
+        ),
+        None => (0, 0),
+    };
+
+    let mut figures = Figures {
+        sales: 0,
+        opening_inventory,
+        closing_inventory,
+        cost_of_sales: 0,
+        expenses: HashMap::new(),
+    };
+    for account in accounts {
+        let Some(&balance) = balances.get(&account.id) else {
+            continue;
+        };
+        match account.account_type {
+            AccountType::Revenue => figures.sales += balance,
+            AccountType::Expense if Some(account.id) == purchases => {
+                figures.cost_of_sales += balance
+            }
+            AccountType::Expense => {
+                figures.expenses.insert(account.id, balance);
+            }
+            _ => {}
+        }
+    }
+    Ok(figures)
+}
@@ -0,0 +1,140 @@
+//! Financial reports built from posted journal lines.
+//!
+//! Each report is a plain struct that serializes to JSON for the HTTP API
+//! and can be written as CSV or as a text table for the CLI.
+
+pub mod aging;
+pub mod apportionment;
+pub mod balance_sheet;
+pub mod blue_return;
+pub mod consumption_tax;
+pub mod depreciation;
+pub mod income_statement;
+pub mod statement;
+pub mod trial_balance;
+pub mod withholding;
+
+pub use aging::{AgingReport, AgingRow};
+pub use apportionment::{ApportionmentReport, ApportionmentRow};
+pub use balance_sheet::BalanceSheet;
+pub use blue_return::{
+    BlueReturn, MonthlyRow, MonthlySales, RentRow, ReturnIncomeStatement, ReturnItem,
+};
+pub use consumption_tax::{CategorySummary, ConsumptionTaxReport};
+pub use depreciation::{DepreciationRow, DepreciationSchedule};
+pub use income_statement::IncomeStatement;
+pub use statement::{Comparison, LineKind, Statement, StatementLine};
+pub use trial_balance::{TrialBalance, TrialBalanceGroup, TrialBalanceRow};
+pub use withholding::{PaymentRecord, PaymentRecordSummary, WithholdingLedger, WithholdingRow};
+
+use chrono::{Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Inclusive date range covered by a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Period {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl Period {
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Result<Period> {
+        if from > to {
+            return Err(Error::Validation(format!(
+                "report period starts after it ends: {} > {}",
+                from, to
+            )));
+        }
+        Ok(Period { from, to })
+    }
+
+    /// The same dates one year earlier, used for comparative figures.
+    pub fn prior_year(&self) -> Period {
+        let year = Months::new(12);
+        Period {
+            from: self.from.checked_sub_months(year).unwrap_or(NaiveDate::MIN),
+            to: self.to.checked_sub_months(year).unwrap_or(NaiveDate::MIN),
+        }
+    }
+}
+
+/// Formats whole yen with thousands separators, e.g. `-1,234,567`.
+pub fn yen(amount: i64) -> String {
+    let digits = amount.unsigned_abs().to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if amount < 0 {
+        formatted.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Terminal width of `text`, counting full-width characters as two columns.
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Renders rows as an aligned text table. The first `left` columns are
+/// left-aligned and the rest, usually amounts, are right-aligned. An empty
+/// row is drawn as a separator.
+pub fn text_table(headers: &[&str], rows: &[Vec<String>], left: usize) -> String {
+    let mut widths = headers.iter().map(|h| display_width(h)).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    let mut table = String::new();
+    let mut push_row = |cells: &mut dyn Iterator<Item = &str>| {
+        let line = cells
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                let padding = " ".repeat(width - display_width(cell));
+                if i < left {
+                    format!("{}{}", cell, padding)
+                } else {
+                    format!("{}{}", padding, cell)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    };
+    let separator = widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>();
+
+    push_row(&mut headers.iter().copied());
+    push_row(&mut separator.iter().map(String::as_str));
+    for row in rows {
+        if row.is_empty() {
+            push_row(&mut separator.iter().map(String::as_str));
+        } else {
+            push_row(&mut row.iter().map(String::as_str));
+        }
+    }
+    table
+}
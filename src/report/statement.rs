@@ -0,0 +1,276 @@
+//! Building blocks shared by the financial statements: comparative amounts,
+//! rendered lines, and per-account balances rolled up the account tree.
+
+use std::collections::HashMap;
+use std::io;
+use std::iter::Sum;
+use std::ops::{Add, Sub};
+
+use serde::Serialize;
+
+use super::{text_table, yen};
+use crate::error::Result;
+use crate::models::Account;
+use crate::repository::Totals;
+
+/// An amount for the reported period next to its comparative figure.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Comparison {
+    pub current: i64,
+    pub prior: i64,
+}
+
+impl Add for Comparison {
+    type Output = Comparison;
+
+    fn add(self, other: Comparison) -> Comparison {
+        Comparison {
+            current: self.current + other.current,
+            prior: self.prior + other.prior,
+        }
+    }
+}
+
+impl Sub for Comparison {
+    type Output = Comparison;
+
+    fn sub(self, other: Comparison) -> Comparison {
+        Comparison {
+            current: self.current - other.current,
+            prior: self.prior - other.prior,
+        }
+    }
+}
+
+impl Sum for Comparison {
+    fn sum<I: Iterator<Item = Comparison>>(iter: I) -> Comparison {
+        iter.fold(Comparison::default(), Add::add)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    /// Section title without amounts.
+    Heading,
+    Item,
+    Subtotal,
+    Total,
+}
+
+impl LineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineKind::Heading => "heading",
+            LineKind::Item => "item",
+            LineKind::Subtotal => "subtotal",
+            LineKind::Total => "total",
+        }
+    }
+}
+
+/// One printed line of a statement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatementLine {
+    /// Account code for lines backed by an account.
+    pub code: Option<String>,
+    pub label: String,
+    pub amount: Comparison,
+    pub kind: LineKind,
+}
+
+impl StatementLine {
+    pub fn heading(label: &str) -> Self {
+        StatementLine::new(LineKind::Heading, label, Comparison::default())
+    }
+
+    pub fn item(label: &str, amount: Comparison) -> Self {
+        StatementLine::new(LineKind::Item, label, amount)
+    }
+
+    pub fn subtotal(label: &str, amount: Comparison) -> Self {
+        StatementLine::new(LineKind::Subtotal, label, amount)
+    }
+
+    pub fn total(label: &str, amount: Comparison) -> Self {
+        StatementLine::new(LineKind::Total, label, amount)
+    }
+
+    pub fn account(account: &Account, amount: Comparison) -> Self {
+        StatementLine {
+            code: Some(account.code.clone()),
+            ..StatementLine::item(&account.name, amount)
+        }
+    }
+
+    fn new(kind: LineKind, label: &str, amount: Comparison) -> Self {
+        StatementLine {
+            code: None,
+            label: label.to_string(),
+            amount,
+            kind,
+        }
+    }
+}
+
+/// A statement made of [`StatementLine`]s, which gives it CSV, text and
+/// HTML renderings on top of its JSON form.
+pub trait Statement: Serialize {
+    fn title(&self) -> String;
+
+    /// Headings of the current and comparative columns.
+    fn columns(&self) -> (String, String);
+
+    fn lines(&self) -> Vec<StatementLine>;
+
+    fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        write_csv(writer, &self.lines())
+    }
+
+    fn to_table(&self) -> String {
+        to_table(&self.title(), self.columns(), &self.lines())
+    }
+
+    fn to_html(&self) -> String {
+        to_html(&self.title(), self.columns(), &self.lines())
+    }
+}
+
+/// Writes `kind,code,label,current,prior` records, headings included so the
+/// file keeps the statement's structure.
+fn write_csv<W: io::Write>(writer: W, lines: &[StatementLine]) -> Result<()> {
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(["kind", "code", "label", "current", "prior"])?;
+    for line in lines {
+        let (current, prior) = match line.kind {
+            LineKind::Heading => (String::new(), String::new()),
+            _ => (
+                line.amount.current.to_string(),
+                line.amount.prior.to_string(),
+            ),
+        };
+        csv.write_record([
+            line.kind.as_str(),
+            line.code.as_deref().unwrap_or_default(),
+            &line.label,
+            &current,
+            &prior,
+        ])?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+fn to_table(title: &str, columns: (String, String), lines: &[StatementLine]) -> String {
+    let rows = lines
+        .iter()
+        .map(|line| match line.kind {
+            LineKind::Heading => vec![format!("【{}】", line.label)],
+            LineKind::Item => vec![
+                format!("  {}", line.label),
+                yen(line.amount.current),
+                yen(line.amount.prior),
+            ],
+            LineKind::Subtotal | LineKind::Total => vec![
+                line.label.clone(),
+                yen(line.amount.current),
+                yen(line.amount.prior),
+            ],
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "{}\n\n{}",
+        title,
+        text_table(&["", &columns.0, &columns.1], &rows, 1)
+    )
+}
+
+/// A self-contained HTML page laid out for printing on A4.
+fn to_html(title: &str, columns: (String, String), lines: &[StatementLine]) -> String {
+    let mut html = format!(
+        r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+  @page {{ size: A4; margin: 15mm; }}
+  body {{ font-family: "Hiragino Mincho ProN", "Yu Mincho", serif; font-size: 10.5pt; }}
+  h1 {{ font-size: 14pt; text-align: center; }}
+  table {{ width: 100%; border-collapse: collapse; }}
+  th, td {{ border: 1px solid #333; padding: 2px 6px; }}
+  td.amount {{ text-align: right; font-variant-numeric: tabular-nums; }}
+  tr.heading td {{ background: #eee; font-weight: bold; }}
+  tr.item td.label {{ padding-left: 1.5em; }}
+  tr.subtotal td, tr.total td {{ font-weight: bold; }}
+  tr.total td {{ border-top: 3px double #333; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+<thead><tr><th>科目</th><th>{current}</th><th>{prior}</th></tr></thead>
+<tbody>
+"#,
+        title = escape_html(title),
+        current = escape_html(&columns.0),
+        prior = escape_html(&columns.1),
+    );
+    for line in lines {
+        let amounts = match line.kind {
+            LineKind::Heading => String::from("<td></td><td></td>"),
+            _ => format!(
+                r#"<td class="amount">{}</td><td class="amount">{}</td>"#,
+                yen(line.amount.current),
+                yen(line.amount.prior)
+            ),
+        };
+        html.push_str(&format!(
+            "<tr class=\"{}\"><td class=\"label\">{}</td>{}</tr>\n",
+            line.kind.as_str(),
+            escape_html(&line.label),
+            amounts
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    html
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Normal-side balances from per-account `totals`, with sub-account
+/// balances added to their top-level account.
+pub fn rolled_up_balances(
+    accounts: &[Account],
+    totals: &HashMap<i32, Totals>,
+) -> HashMap<i32, i64> {
+    let by_id = accounts
+        .iter()
+        .map(|account| (account.id, account))
+        .collect::<HashMap<_, _>>();
+    let mut balances = HashMap::new();
+    for (account_id, totals) in totals {
+        let Some(mut account) = by_id.get(account_id).copied() else {
+            continue;
+        };
+        let balance = account.account_type.balance(totals.debit, totals.credit);
+        while let Some(parent) = account.parent_id.and_then(|id| by_id.get(&id)) {
+            account = parent;
+        }
+        *balances.entry(account.id).or_insert(0) += balance;
+    }
+    balances
+}
@@ -0,0 +1,184 @@
+use std::io;
+
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde::Serialize;
+
+use super::{text_table, yen, Period};
+use crate::error::Result;
+use crate::models::AccountType;
+use crate::repository::{AccountRepository, LedgerRepository};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrialBalanceRow {
+    pub account_id: i32,
+    pub code: String,
+    pub name: String,
+    pub opening_balance: i64,
+    pub debit: i64,
+    pub credit: i64,
+    pub closing_balance: i64,
+}
+
+/// Accounts of one type with their subtotals.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrialBalanceGroup {
+    pub account_type: AccountType,
+    pub rows: Vec<TrialBalanceRow>,
+    pub opening_balance: i64,
+    pub debit: i64,
+    pub credit: i64,
+    pub closing_balance: i64,
+}
+
+/// 合計残高試算表 for a date range. Balances are positive on each account's
+/// normal side; accounts without a balance or activity are left out.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrialBalance {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub groups: Vec<TrialBalanceGroup>,
+    pub debit_total: i64,
+    pub credit_total: i64,
+    /// Whether the period's debit and credit totals agree.
+    pub balanced: bool,
+}
+
+impl TrialBalance {
+    pub fn generate(connection: &mut SqliteConnection, period: Period) -> Result<TrialBalance> {
+        let Period { from, to } = period;
+        let accounts = AccountRepository::new(connection).list(true)?;
+        let mut ledger = LedgerRepository::new(connection);
+        let opening = ledger.balances_before(from)?;
+        let period = ledger.totals_by_account(Some(from), Some(to))?;
+
+        let mut groups = AccountType::ALL
+            .iter()
+            .map(|&account_type| TrialBalanceGroup {
+                account_type,
+                rows: Vec::new(),
+                opening_balance: 0,
+                debit: 0,
+                credit: 0,
+                closing_balance: 0,
+            })
+            .collect::<Vec<_>>();
+        for account in accounts {
+            let before = opening.get(&account.id).copied().unwrap_or_default();
+            let during = period.get(&account.id).copied().unwrap_or_default();
+            let opening_balance = account.account_type.balance(before.debit, before.credit);
+            if opening_balance == 0 && during.lines == 0 {
+                continue;
+            }
+            let group = groups
+                .iter_mut()
+                .find(|group| group.account_type == account.account_type)
+                .unwrap();
+            let closing_balance =
+                opening_balance + account.account_type.balance(during.debit, during.credit);
+            group.opening_balance += opening_balance;
+            group.debit += during.debit;
+            group.credit += during.credit;
+            group.closing_balance += closing_balance;
+            group.rows.push(TrialBalanceRow {
+                account_id: account.id,
+                code: account.code,
+                name: account.name,
+                opening_balance,
+                debit: during.debit,
+                credit: during.credit,
+                closing_balance,
+            });
+        }
+        groups.retain(|group| !group.rows.is_empty());
+
+        let debit_total = groups.iter().map(|group| group.debit).sum();
+        let credit_total = groups.iter().map(|group| group.credit).sum();
+        Ok(TrialBalance {
+            from,
+            to,
+            groups,
+            debit_total,
+            credit_total,
+            balanced: debit_total == credit_total,
+        })
+    }
+
+    /// One CSV record per account, without subtotals.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "account_type",
+            "code",
+            "name",
+            "opening_balance",
+            "debit",
+            "credit",
+            "closing_balance",
+        ])?;
+        for group in &self.groups {
+            for row in &group.rows {
+                csv.write_record([
+                    group.account_type.as_str(),
+                    &row.code,
+                    &row.name,
+                    &row.opening_balance.to_string(),
+                    &row.debit.to_string(),
+                    &row.credit.to_string(),
+                    &row.closing_balance.to_string(),
+                ])?;
+            }
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    /// Text table with a subtotal per account type.
+    pub fn to_table(&self) -> String {
+        let mut rows = Vec::new();
+        for group in &self.groups {
+            for row in &group.rows {
+                rows.push(vec![
+                    row.code.clone(),
+                    row.name.clone(),
+                    yen(row.opening_balance),
+                    yen(row.debit),
+                    yen(row.credit),
+                    yen(row.closing_balance),
+                ]);
+            }
+            rows.push(vec![
+                String::new(),
+                format!("{}計", group.account_type.label()),
+                yen(group.opening_balance),
+                yen(group.debit),
+                yen(group.credit),
+                yen(group.closing_balance),
+            ]);
+            rows.push(Vec::new());
+        }
+        rows.push(vec![
+            String::new(),
+            "合計".to_string(),
+            String::new(),
+            yen(self.debit_total),
+            yen(self.credit_total),
+            String::new(),
+        ]);
+
+        let mut table = format!("合計残高試算表 {} - {}\n\n", self.from, self.to);
+        table.push_str(&text_table(
+            &["コード", "勘定科目", "前期繰越", "借方", "貸方", "残高"],
+            &rows,
+            2,
+        ));
+        if !self.balanced {
+            table.push_str(&format!(
+                "\nwarning: debits {} do not equal credits {}\n",
+                yen(self.debit_total),
+                yen(self.credit_total)
+            ));
+        }
+        table
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::io;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::Serialize;
+
+use super::{text_table, yen, Period};
+use crate::error::Result;
+use crate::models::account_codes::WITHHOLDING;
+use crate::models::{AccountType, EntryKind, JournalLine, Side, Withholding};
+use crate::repository::{AccountRepository, CounterpartyRepository, LedgerRepository};
+use crate::schema::{accounts, counterparties, journal_entries, journal_lines};
+use crate::withholding::PAYMENT_RECORD_THRESHOLD;
+
+/// One line on 預り金.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WithholdingRow {
+    pub entry_id: i32,
+    pub entry_date: NaiveDate,
+    pub counterparty_id: Option<i32>,
+    pub counterparty_name: Option<String>,
+    pub description: String,
+    /// Remuneration the tax was withheld from, consumption tax included.
+    pub payment: i64,
+    pub withheld: i64,
+    /// Paid to the tax office (納付).
+    pub remitted: i64,
+    pub balance: i64,
+}
+
+/// 預り金 ledger: income tax withheld from payments to each payee and paid
+/// over to the tax office, with the balance still held.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WithholdingLedger {
+    pub period: Period,
+    pub opening_balance: i64,
+    pub rows: Vec<WithholdingRow>,
+    pub total_withheld: i64,
+    pub total_remitted: i64,
+    pub closing_balance: i64,
+}
+
+impl WithholdingLedger {
+    pub fn generate(
+        connection: &mut SqliteConnection,
+        period: Period,
+    ) -> Result<WithholdingLedger> {
+        let account = AccountRepository::new(connection).find_by_code(WITHHOLDING)?;
+        let ledger =
+            LedgerRepository::new(connection).ledger(account.id, period.from, period.to)?;
+        let entry_ids = ledger
+            .lines
+            .iter()
+            .map(|line| line.entry_id)
+            .collect::<Vec<_>>();
+        let lines = journal_lines::table
+            .inner_join(accounts::table)
+            .filter(journal_lines::entry_id.eq_any(&entry_ids))
+            .select((JournalLine::as_select(), accounts::account_type))
+            .load::<(JournalLine, AccountType)>(connection)?;
+        let names = counterparty_names(connection)?;
+
+        let rows = ledger
+            .lines
+            .iter()
+            .map(|ledger_line| {
+                let counterparty_id = lines
+                    .iter()
+                    .find(|(line, _)| {
+                        (line.entry_id, line.line_no) == (ledger_line.entry_id, ledger_line.line_no)
+                    })
+                    .and_then(|(line, _)| line.counterparty_id);
+                // Expenses of the same entry and payee, for withheld lines.
+                let payment = match (counterparty_id, ledger_line.credit) {
+                    (Some(id), credit) if credit > 0 => lines
+                        .iter()
+                        .filter(|(line, account_type)| {
+                            line.entry_id == ledger_line.entry_id
+                                && line.counterparty_id == Some(id)
+                                && *account_type == AccountType::Expense
+                        })
+                        .map(|(line, _)| signed(line, Side::Debit))
+                        .sum(),
+                    _ => 0,
+                };
+                WithholdingRow {
+                    entry_id: ledger_line.entry_id,
+                    entry_date: ledger_line.entry_date,
+                    counterparty_id,
+                    counterparty_name: counterparty_id.and_then(|id| names.get(&id).cloned()),
+                    description: match ledger_line.description.is_empty() {
+                        true => ledger_line.memo.clone(),
+                        false => ledger_line.description.clone(),
+                    },
+                    payment,
+                    withheld: ledger_line.credit,
+                    remitted: ledger_line.debit,
+                    balance: ledger_line.balance,
+                }
+            })
+            .collect();
+
+        Ok(WithholdingLedger {
+            period,
+            opening_balance: ledger.opening_balance,
+            rows,
+            total_withheld: ledger.credit_total,
+            total_remitted: ledger.debit_total,
+            closing_balance: ledger.closing_balance,
+        })
+    }
+
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "entry_date",
+            "entry_id",
+            "counterparty",
+            "description",
+            "payment",
+            "withheld",
+            "remitted",
+            "balance",
+        ])?;
+        for row in &self.rows {
+            csv.write_record([
+                row.entry_date.to_string().as_str(),
+                &row.entry_id.to_string(),
+                row.counterparty_name.as_deref().unwrap_or(""),
+                &row.description,
+                &row.payment.to_string(),
+                &row.withheld.to_string(),
+                &row.remitted.to_string(),
+                &row.balance.to_string(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![vec![
+            String::new(),
+            String::new(),
+            "前期繰越".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            yen(self.opening_balance),
+        ]];
+        rows.extend(self.rows.iter().map(|row| {
+            vec![
+                row.entry_date.to_string(),
+                row.counterparty_name.clone().unwrap_or_default(),
+                row.description.clone(),
+                yen(row.payment),
+                yen(row.withheld),
+                yen(row.remitted),
+                yen(row.balance),
+            ]
+        }));
+        rows.push(Vec::new());
+        rows.push(vec![
+            String::new(),
+            String::new(),
+            "合計".to_string(),
+            String::new(),
+            yen(self.total_withheld),
+            yen(self.total_remitted),
+            yen(self.closing_balance),
+        ]);
+        format!(
+            "預り金元帳(源泉所得税) {} - {}\n\n{}",
+            self.period.from,
+            self.period.to,
+            text_table(
+                &[
+                    "日付",
+                    "取引先",
+                    "摘要",
+                    "支払金額",
+                    "源泉徴収税額",
+                    "納付額",
+                    "残高"
+                ],
+                &rows,
+                3
+            )
+        )
+    }
+}
+
+/// Yearly totals for one payee, the figures of its 支払調書.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaymentRecord {
+    pub counterparty_id: i32,
+    pub name: String,
+    pub address: String,
+    /// Entries with remuneration for the payee.
+    pub payments: i64,
+    /// 支払金額, consumption tax included.
+    pub amount: i64,
+    /// 源泉徴収税額
+    pub withheld: i64,
+    /// Whether the amount is over the 50,000 yen above which a 支払調書 has
+    /// to be filed.
+    pub filing_required: bool,
+}
+
+/// 報酬、料金、契約金及び賞金の支払調書 summary: remuneration paid in the
+/// period to every counterparty with withholding on payments, and the tax
+/// withheld from it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaymentRecordSummary {
+    pub period: Period,
+    pub records: Vec<PaymentRecord>,
+    pub total_amount: i64,
+    pub total_withheld: i64,
+}
+
+impl PaymentRecordSummary {
+    pub fn generate(
+        connection: &mut SqliteConnection,
+        period: Period,
+    ) -> Result<PaymentRecordSummary> {
+        let tax_account = AccountRepository::new(connection)
+            .find_by_code(WITHHOLDING)?
+            .id;
+        let payees = counterparties::table
+            .filter(counterparties::withholding.eq(Withholding::Payments))
+            .select((
+                counterparties::id,
+                counterparties::name,
+                counterparties::address,
+            ))
+            .order_by((counterparties::name, counterparties::id))
+            .load::<(i32, String, String)>(connection)?;
+        let ids = payees.iter().map(|payee| payee.0).collect::<Vec<_>>();
+        let lines = journal_lines::table
+            .inner_join(journal_entries::table)
+            .inner_join(accounts::table)
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::kind.eq(EntryKind::Regular))
+            .filter(journal_entries::entry_date.between(period.from, period.to))
+            .filter(journal_lines::counterparty_id.eq_any(&ids))
+            .select((JournalLine::as_select(), accounts::account_type))
+            .load::<(JournalLine, AccountType)>(connection)?;
+
+        let mut records = Vec::new();
+        for (counterparty_id, name, address) in payees {
+            let ours = lines
+                .iter()
+                .filter(|(line, _)| line.counterparty_id == Some(counterparty_id))
+                .collect::<Vec<_>>();
+            let mut entries = ours
+                .iter()
+                .filter(|(_, account_type)| *account_type == AccountType::Expense)
+                .map(|(line, _)| line.entry_id)
+                .collect::<Vec<_>>();
+            entries.sort_unstable();
+            entries.dedup();
+            let amount = ours
+                .iter()
+                .filter(|(_, account_type)| *account_type == AccountType::Expense)
+                .map(|(line, _)| signed(line, Side::Debit))
+                .sum::<i64>();
+            let withheld = ours
+                .iter()
+                .filter(|(line, _)| line.account_id == tax_account)
+                .map(|(line, _)| signed(line, Side::Credit))
+                .sum::<i64>();
+            if amount == 0 && withheld == 0 {
+                continue;
+            }
+            records.push(PaymentRecord {
+                counterparty_id,
+                name,
+                address,
+                payments: entries.len() as i64,
+                amount,
+                withheld,
+                filing_required: amount > PAYMENT_RECORD_THRESHOLD,
+            });
+        }
+        Ok(PaymentRecordSummary {
+            period,
+            total_amount: records.iter().map(|record| record.amount).sum(),
+            total_withheld: records.iter().map(|record| record.withheld).sum(),
+            records,
+        })
+    }
+
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "name",
+            "address",
+            "payments",
+            "amount",
+            "withheld",
+            "filing_required",
+        ])?;
+        for record in &self.records {
+            csv.write_record([
+                record.name.as_str(),
+                &record.address,
+                &record.payments.to_string(),
+                &record.amount.to_string(),
+                &record.withheld.to_string(),
+                &record.filing_required.to_string(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut rows = self
+            .records
+            .iter()
+            .map(|record| {
+                vec![
+                    record.name.clone(),
+                    record.address.clone(),
+                    record.payments.to_string(),
+                    yen(record.amount),
+                    yen(record.withheld),
+                    match record.filing_required {
+                        true => "要".to_string(),
+                        false => String::new(),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        rows.push(Vec::new());
+        rows.push(vec![
+            "合計".to_string(),
+            String::new(),
+            String::new(),
+            yen(self.total_amount),
+            yen(self.total_withheld),
+            String::new(),
+        ]);
+        format!(
+            "支払調書合計 {} - {}\n\n{}",
+            self.period.from,
+            self.period.to,
+            text_table(
+                &[
+                    "支払を受ける者",
+                    "住所",
+                    "回数",
+                    "支払金額",
+                    "源泉徴収税額",
+                    "提出"
+                ],
+                &rows,
+                2
+            )
+        )
+    }
+}
+
+/// Gross amount of `line`, positive on `side`.
+fn signed(line: &JournalLine, side: Side) -> i64 {
+    match line.side == side {
+        true => line.gross_amount(),
+        false => -line.gross_amount(),
+    }
+}
+
+fn counterparty_names(connection: &mut SqliteConnection) -> Result<HashMap<i32, String>> {
+    Ok(CounterpartyRepository::new(connection)
+        .list()?
+        .into_iter()
+        .map(|counterparty| (counterparty.id, counterparty.name))
+        .collect())
+}
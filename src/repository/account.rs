@@ -0,0 +1,132 @@
+use chrono::Utc;
+use diesel::{insert_into, prelude::*};
+
+use crate::error::{Error, Result};
+use crate::models::{Account, AccountChanges, NewAccount};
+use crate::schema::{accounts, return_lines};
+
+pub struct AccountRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> AccountRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        AccountRepository { connection }
+    }
+
+    /// Lists accounts ordered by code.
+    pub fn list(&mut self, include_inactive: bool) -> Result<Vec<Account>> {
+        let mut query = accounts::table.select(Account::as_select()).into_boxed();
+        if !include_inactive {
+            query = query.filter(accounts::active.eq(true));
+        }
+        Ok(query.order_by(accounts::code).load(self.connection)?)
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<Account> {
+        accounts::table
+            .find(id)
+            .select(Account::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("account {}", id)))
+    }
+
+    pub fn find_by_code(&mut self, code: &str) -> Result<Account> {
+        accounts::table
+            .filter(accounts::code.eq(code))
+            .select(Account::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("account {}", code)))
+    }
+
+    pub fn create(&mut self, new_account: &NewAccount) -> Result<Account> {
+        if new_account.code.trim().is_empty() || new_account.name.trim().is_empty() {
+            return Err(Error::Validation(
+                "account code and name are required".to_string(),
+            ));
+        }
+        self.connection.transaction(|connection| {
+            let mut repository = AccountRepository::new(connection);
+            if repository.find_by_code(&new_account.code).is_ok() {
+                return Err(Error::Validation(format!(
+                    "account code {} is already used",
+                    new_account.code
+                )));
+            }
+            if let Some(parent_id) = new_account.parent_id {
+                let parent = repository.find(parent_id)?;
+                if parent.account_type != new_account.account_type {
+                    return Err(Error::Validation(format!(
+                        "parent account {} is not of type {}",
+                        parent.code, new_account.account_type
+                    )));
+                }
+            }
+            Ok(insert_into(accounts::table)
+                .values(new_account)
+                .returning(Account::as_returning())
+                .get_result(repository.connection)?)
+        })
+    }
+
+    pub fn update(&mut self, id: i32, changes: &AccountChanges) -> Result<Account> {
+        self.connection.transaction(|connection| {
+            let mut repository = AccountRepository::new(connection);
+            let account = repository.find(id)?;
+            if let Some(Some(parent_id)) = changes.parent_id {
+                repository.check_parent(&account, parent_id)?;
+            }
+            Ok(diesel::update(accounts::table.find(id))
+                .set((changes, accounts::updated_at.eq(Utc::now().naive_utc())))
+                .returning(Account::as_returning())
+                .get_result(repository.connection)?)
+        })
+    }
+
+    /// Hides the account from new entries while keeping its history.
+    pub fn deactivate(&mut self, id: i32) -> Result<Account> {
+        let changes = AccountChanges {
+            active: Some(false),
+            ..Default::default()
+        };
+        self.update(id, &changes)
+    }
+
+    /// Deletes an account and its return line mapping. The foreign keys
+    /// refuse accounts that sub-accounts, journal lines or other records
+    /// still refer to; those should be deactivated instead.
+    pub fn delete(&mut self, id: i32) -> Result<()> {
+        self.connection.transaction(|connection| {
+            diesel::delete(return_lines::table.find(id)).execute(connection)?;
+            match diesel::delete(accounts::table.find(id)).execute(connection)? {
+                0 => Err(Error::NotFound(format!("account {}", id))),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// Rejects parents of a different type and parents that would make the
+    /// hierarchy cyclic.
+    fn check_parent(&mut self, account: &Account, parent_id: i32) -> Result<()> {
+        let mut ancestor = Some(parent_id);
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == account.id {
+                return Err(Error::Validation(format!(
+                    "account {} cannot be its own ancestor",
+                    account.code
+                )));
+            }
+            let parent = self.find(ancestor_id)?;
+            if parent.account_type != account.account_type {
+                return Err(Error::Validation(format!(
+                    "parent account {} is not of type {}",
+                    parent.code, account.account_type
+                )));
+            }
+            ancestor = parent.parent_id;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::models::account_codes::OWNER_DRAWINGS;
+use crate::models::{
+    AccountType, ApportionmentRule, EntryKind, FiscalYear, FiscalYearState, JournalEntryWithLines,
+    JournalLine, NewApportionmentRule, NewJournalEntry, NewJournalLine, Side, TaxCategory,
+};
+use crate::repository::{
+    AccountRepository, CounterpartyRepository, FiscalYearRepository, JournalRepository,
+};
+use crate::schema::{apportionment_rules, fiscal_years, journal_entries, journal_lines};
+
+/// Expenses of one account in a period that fall under one rule, split
+/// into the business and private share. Amounts include consumption tax.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApportionedAmount {
+    pub account_id: i32,
+    pub rule_id: i32,
+    /// The counterparty the rule is limited to, if any.
+    pub counterparty_id: Option<i32>,
+    pub business_ratio: i32,
+    pub tax_category: TaxCategory,
+    pub original: i64,
+    pub business: i64,
+    pub private: i64,
+}
+
+/// Expenses paid to one counterparty in a period, split like
+/// [`ApportionedAmount`]; lines without a counterparty come under `None`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PayeeAmount {
+    pub counterparty_id: Option<i32>,
+    pub original: i64,
+    pub business: i64,
+    pub private: i64,
+}
+
+/// 家事按分 rules and the year-end entries moving the private share of
+/// expenses to 事業主貸.
+pub struct ApportionmentRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> ApportionmentRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        ApportionmentRepository { connection }
+    }
+
+    pub fn list_rules(&mut self) -> Result<Vec<ApportionmentRule>> {
+        Ok(apportionment_rules::table
+            .select(ApportionmentRule::as_select())
+            .order_by((
+                apportionment_rules::account_id,
+                apportionment_rules::counterparty_id,
+                apportionment_rules::effective_from,
+            ))
+            .load(self.connection)?)
+    }
+
+    pub fn create_rule(&mut self, new_rule: &NewApportionmentRule) -> Result<ApportionmentRule> {
+        if !(0..=100).contains(&new_rule.business_ratio) {
+            return Err(Error::Validation(format!(
+                "business ratio must be between 0 and 100: {}",
+                new_rule.business_ratio
+            )));
+        }
+        self.connection.transaction(|connection| {
+            let account = AccountRepository::new(connection).find(new_rule.account_id)?;
+            if account.account_type != AccountType::Expense {
+                return Err(Error::Validation(format!(
+                    "account {} {} is not an expense account",
+                    account.code, account.name
+                )));
+            }
+            if let Some(counterparty_id) = new_rule.counterparty_id {
+                CounterpartyRepository::new(connection).find(counterparty_id)?;
+            }
+            let existing = apportionment_rules::table
+                .filter(apportionment_rules::account_id.eq(new_rule.account_id))
+                .filter(apportionment_rules::counterparty_id.is(new_rule.counterparty_id))
+                .filter(apportionment_rules::effective_from.eq(new_rule.effective_from))
+                .count()
+                .get_result::<i64>(connection)?;
+            if existing > 0 {
+                return Err(Error::Validation(format!(
+                    "account {} already has a rule from {}",
+                    account.code, new_rule.effective_from
+                )));
+            }
+            Ok(insert_into(apportionment_rules::table)
+                .values(new_rule)
+                .returning(ApportionmentRule::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    pub fn delete_rule(&mut self, id: i32) -> Result<()> {
+        match diesel::delete(apportionment_rules::table.find(id)).execute(self.connection)? {
+            0 => Err(Error::NotFound(format!("apportionment rule {}", id))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Splits the expenses from `from` to `to` on accounts with rules. Each
+    /// line falls under the latest rule in effect on its date, a rule for
+    /// the line's counterparty taking precedence over one for the whole
+    /// account. Depreciation and earlier 家事按分 entries are left out, the
+    /// former being apportioned per asset.
+    pub fn amounts(&mut self, from: NaiveDate, to: NaiveDate) -> Result<Vec<ApportionedAmount>> {
+        let rules = self.list_rules()?;
+        let lines = self.lines(rules.iter().map(|rule| rule.account_id).collect(), from, to)?;
+
+        let mut amounts = BTreeMap::new();
+        for (line, entry_date) in lines {
+            let Some(rule) = rule_for(&rules, line.account_id, line.counterparty_id, entry_date)
+            else {
+                continue;
+            };
+            let amount = amounts
+                .entry((line.account_id, rule.id, line.tax_category.as_str()))
+                .or_insert(ApportionedAmount {
+                    account_id: line.account_id,
+                    rule_id: rule.id,
+                    counterparty_id: rule.counterparty_id,
+                    business_ratio: rule.business_ratio,
+                    tax_category: line.tax_category,
+                    original: 0,
+                    business: 0,
+                    private: 0,
+                });
+            amount.original += gross(&line);
+        }
+        Ok(amounts
+            .into_values()
+            .map(|amount| {
+                let business = amount.original * amount.business_ratio as i64 / 100;
+                ApportionedAmount {
+                    business,
+                    private: amount.original - business,
+                    ..amount
+                }
+            })
+            .collect())
+    }
+
+    /// Expenses on `account_ids` from `from` to `to` per counterparty, each
+    /// line split by the rule it falls under as in [`Self::amounts`] and in
+    /// full if there is none. Used for the breakdowns of the 決算書.
+    pub fn by_counterparty(
+        &mut self,
+        account_ids: &[i32],
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<PayeeAmount>> {
+        let rules = self.list_rules()?;
+        let mut groups = BTreeMap::new();
+        for (line, entry_date) in self.lines(account_ids.to_vec(), from, to)? {
+            let rule = rule_for(&rules, line.account_id, line.counterparty_id, entry_date);
+            let (_, original) = groups
+                .entry((line.counterparty_id, rule.map(|rule| rule.id)))
+                .or_insert((rule.map_or(100, |rule| rule.business_ratio), 0));
+            *original += gross(&line);
+        }
+
+        let mut payees = BTreeMap::new();
+        for ((counterparty_id, _), (ratio, original)) in groups {
+            let business = original * ratio as i64 / 100;
+            let payee = payees.entry(counterparty_id).or_insert(PayeeAmount {
+                counterparty_id,
+                original: 0,
+                business: 0,
+                private: 0,
+            });
+            payee.original += original;
+            payee.business += business;
+            payee.private += original - business;
+        }
+        Ok(payees.into_values().collect())
+    }
+
+    /// Posts the year's 家事按分 entry on its last day, crediting each
+    /// account with its private share, tax category included, against
+    /// 事業主貸. Fails if the year already has one that has not been
+    /// voided, or if there is nothing to apportion.
+    pub fn post_adjustments(&mut self, fiscal_year_id: i32) -> Result<JournalEntryWithLines> {
+        self.connection.transaction(|connection| {
+            let year = FiscalYearRepository::new(connection).find(fiscal_year_id)?;
+            if year.state == FiscalYearState::Closed {
+                return Err(Error::Validation(format!(
+                    "fiscal year {} - {} is closed",
+                    year.start_date, year.end_date
+                )));
+            }
+            let mut repository = ApportionmentRepository::new(connection);
+            if repository.has_adjustments(&year)? {
+                return Err(Error::Validation(format!(
+                    "家事按分 for {} - {} has already been posted",
+                    year.start_date, year.end_date
+                )));
+            }
+            repository.adjustment_entry(&year)?.ok_or_else(|| {
+                Error::Validation(format!(
+                    "nothing to apportion in {} - {}",
+                    year.start_date, year.end_date
+                ))
+            })
+        })
+    }
+
+    /// Posts the year's 家事按分 entry unless that has been done already;
+    /// used when the year is closed.
+    pub(crate) fn ensure_adjustments(&mut self, year: &FiscalYear) -> Result<()> {
+        if !self.has_adjustments(year)? {
+            self.adjustment_entry(year)?;
+        }
+        Ok(())
+    }
+
+    /// Lines of regular entries on `account_ids` with their dates. The
+    /// depreciation and 家事按分 entries are left out, the former being
+    /// apportioned per asset and the latter being the result.
+    fn lines(
+        &mut self,
+        account_ids: Vec<i32>,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(JournalLine, NaiveDate)>> {
+        let generated = fiscal_years::table
+            .select((
+                fiscal_years::depreciation_entry_id,
+                fiscal_years::apportionment_entry_id,
+            ))
+            .load::<(Option<i32>, Option<i32>)>(self.connection)?
+            .into_iter()
+            .flat_map(|(depreciation, apportionment)| [depreciation, apportionment])
+            .flatten()
+            .collect::<Vec<_>>();
+        Ok(journal_lines::table
+            .inner_join(journal_entries::table)
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::kind.eq(EntryKind::Regular))
+            .filter(journal_entries::entry_date.ge(from))
+            .filter(journal_entries::entry_date.le(to))
+            .filter(journal_entries::id.ne_all(generated))
+            .filter(journal_lines::account_id.eq_any(account_ids))
+            .select((JournalLine::as_select(), journal_entries::entry_date))
+            .load(self.connection)?)
+    }
+
+    fn has_adjustments(&mut self, year: &FiscalYear) -> Result<bool> {
+        match year.apportionment_entry_id {
+            Some(entry_id) => Ok(JournalRepository::new(self.connection)
+                .find(entry_id)?
+                .entry
+                .voided_at
+                .is_none()),
+            None => Ok(false),
+        }
+    }
+
+    fn adjustment_entry(&mut self, year: &FiscalYear) -> Result<Option<JournalEntryWithLines>> {
+        let mut credits = Vec::new();
+        for amount in self.amounts(year.start_date, year.end_date)? {
+            if amount.private <= 0 {
+                continue;
+            }
+            credits.push(NewJournalLine {
+                description: format!("家事按分 事業{}%", amount.business_ratio),
+                ..NewJournalLine::credit(amount.account_id, amount.private).tax(amount.tax_category)
+            });
+        }
+        if credits.is_empty() {
+            return Ok(None);
+        }
+
+        let drawings = AccountRepository::new(self.connection).find_by_code(OWNER_DRAWINGS)?;
+        let private = credits.iter().map(|line| line.amount).sum();
+        let mut lines =
+            vec![NewJournalLine::debit(drawings.id, private).tax(TaxCategory::OutOfScope)];
+        lines.extend(credits);
+        let entry = JournalRepository::new(self.connection).create(&NewJournalEntry {
+            entry_date: year.end_date,
+            memo: "家事按分".to_string(),
+            lines,
+        })?;
+        diesel::update(fiscal_years::table.find(year.id))
+            .set((
+                fiscal_years::apportionment_entry_id.eq(entry.entry.id),
+                fiscal_years::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(self.connection)?;
+        Ok(Some(entry))
+    }
+}
+
+/// The rule a line on `account_id` paid to `counterparty_id` falls under on
+/// `date`.
+fn rule_for(
+    rules: &[ApportionmentRule],
+    account_id: i32,
+    counterparty_id: Option<i32>,
+    date: NaiveDate,
+) -> Option<&ApportionmentRule> {
+    let latest = |counterparty_id: Option<i32>| {
+        rules
+            .iter()
+            .filter(|rule| {
+                rule.account_id == account_id
+                    && rule.counterparty_id == counterparty_id
+                    && rule.effective_from <= date
+            })
+            .max_by_key(|rule| rule.effective_from)
+    };
+    counterparty_id
+        .and_then(|counterparty_id| latest(Some(counterparty_id)))
+        .or_else(|| latest(None))
+}
+
+/// The line's amount with tax, negative on the credit side.
+fn gross(line: &JournalLine) -> i64 {
+    let gross = line.gross_amount();
+    match line.side {
+        Side::Debit => gross,
+        Side::Credit => -gross,
+    }
+}
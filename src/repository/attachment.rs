@@ -0,0 +1,293 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::{insert_into, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::attachment::FileStore;
+use crate::error::{Error, Result};
+use crate::models::{
+    Attachment, AttachmentAction, AttachmentChanges, AttachmentHistory, NewAttachment,
+};
+use crate::repository::{CounterpartyRepository, JournalRepository};
+use crate::schema::{attachment_history, attachments};
+
+/// Conditions for searching attachments. Dates and amounts are inclusive,
+/// and the conditions given must all hold.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AttachmentQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    pub counterparty_id: Option<i32>,
+    pub entry_id: Option<i32>,
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+/// An attachment record as it was after one change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttachmentRevision {
+    pub id: i32,
+    pub action: AttachmentAction,
+    pub reason: String,
+    pub recorded_at: NaiveDateTime,
+    pub attachment: Attachment,
+}
+
+/// Evidence files and their records. Every change to a record is written
+/// to its history in the same transaction; records are never removed and
+/// the content is never replaced.
+pub struct AttachmentRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> AttachmentRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        AttachmentRepository { connection }
+    }
+
+    /// Finds an attachment, deleted or not.
+    pub fn find(&mut self, id: i32) -> Result<Attachment> {
+        attachments::table
+            .find(id)
+            .select(Attachment::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("attachment {}", id)))
+    }
+
+    /// Attachments matching `query`, ordered by transaction date.
+    pub fn search(&mut self, query: &AttachmentQuery) -> Result<Vec<Attachment>> {
+        let mut select = attachments::table
+            .select(Attachment::as_select())
+            .order_by((attachments::transaction_date, attachments::id))
+            .into_boxed();
+        if let Some(from) = query.from {
+            select = select.filter(attachments::transaction_date.ge(from));
+        }
+        if let Some(to) = query.to {
+            select = select.filter(attachments::transaction_date.le(to));
+        }
+        if let Some(min) = query.min_amount {
+            select = select.filter(attachments::amount.ge(min));
+        }
+        if let Some(max) = query.max_amount {
+            select = select.filter(attachments::amount.le(max));
+        }
+        if let Some(counterparty_id) = query.counterparty_id {
+            select = select.filter(attachments::counterparty_id.eq(counterparty_id));
+        }
+        if let Some(entry_id) = query.entry_id {
+            select = select.filter(attachments::entry_id.eq(entry_id));
+        }
+        if !query.include_deleted {
+            select = select.filter(attachments::deleted_at.is_null());
+        }
+        Ok(select.load(self.connection)?)
+    }
+
+    /// Stores `content` and records it. Content already stored, for
+    /// another record or one whose insert failed, is reused as it is.
+    pub fn create(&mut self, new_attachment: &NewAttachment, content: &[u8]) -> Result<Attachment> {
+        validate(&new_attachment.file_name, new_attachment.amount)?;
+        let store = FileStore::for_connection(self.connection)?;
+        self.connection.transaction(|connection| {
+            check_references(
+                connection,
+                new_attachment.counterparty_id,
+                new_attachment.entry_id,
+            )?;
+            let sha256 = store.put(content)?;
+            let attachment = insert_into(attachments::table)
+                .values((
+                    new_attachment,
+                    attachments::sha256.eq(sha256),
+                    attachments::size.eq(content.len() as i64),
+                ))
+                .returning(Attachment::as_returning())
+                .get_result(connection)?;
+            record(connection, &attachment, AttachmentAction::Created, "")?;
+            Ok(attachment)
+        })
+    }
+
+    /// The record and its content, checked against the stored hash.
+    pub fn content(&mut self, id: i32) -> Result<(Attachment, Vec<u8>)> {
+        let attachment = self.find(id)?;
+        let content = FileStore::for_connection(self.connection)?.read(&attachment.sha256)?;
+        Ok((attachment, content))
+    }
+
+    /// Corrects the record of a live attachment, noting why in its history.
+    pub fn update(
+        &mut self,
+        id: i32,
+        changes: &AttachmentChanges,
+        reason: &str,
+    ) -> Result<Attachment> {
+        self.connection.transaction(|connection| {
+            let existing = AttachmentRepository::new(connection).find_live(id)?;
+            validate(
+                changes.file_name.as_ref().unwrap_or(&existing.file_name),
+                changes.amount.unwrap_or(existing.amount),
+            )?;
+            check_references(
+                connection,
+                changes.counterparty_id.flatten(),
+                changes.entry_id.flatten(),
+            )?;
+            let attachment = diesel::update(attachments::table.find(id))
+                .set((changes, attachments::updated_at.eq(Utc::now().naive_utc())))
+                .returning(Attachment::as_returning())
+                .get_result(connection)?;
+            record(connection, &attachment, AttachmentAction::Updated, reason)?;
+            Ok(attachment)
+        })
+    }
+
+    /// Marks an attachment deleted. The record, its history and the stored
+    /// content are all kept.
+    pub fn delete(&mut self, id: i32, reason: &str) -> Result<Attachment> {
+        self.connection.transaction(|connection| {
+            AttachmentRepository::new(connection).find_live(id)?;
+            let now = Utc::now().naive_utc();
+            let attachment = diesel::update(attachments::table.find(id))
+                .set((
+                    attachments::deleted_at.eq(now),
+                    attachments::updated_at.eq(now),
+                ))
+                .returning(Attachment::as_returning())
+                .get_result(connection)?;
+            record(connection, &attachment, AttachmentAction::Deleted, reason)?;
+            Ok(attachment)
+        })
+    }
+
+    /// Every version of the record, oldest first.
+    pub fn history(&mut self, id: i32) -> Result<Vec<AttachmentRevision>> {
+        self.find(id)?;
+        attachment_history::table
+            .filter(attachment_history::attachment_id.eq(id))
+            .select(AttachmentHistory::as_select())
+            .order_by(attachment_history::id)
+            .load(self.connection)?
+            .into_iter()
+            .map(|row| {
+                Ok(AttachmentRevision {
+                    attachment: snapshot(&row)?,
+                    id: row.id,
+                    action: row.action,
+                    reason: row.reason,
+                    recorded_at: row.recorded_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that every stored file still matches its hash and size, and
+    /// that every record is as its history last left it, which catches
+    /// changes made around this repository. Returns the number of
+    /// attachments checked.
+    pub fn verify(&mut self) -> Result<usize> {
+        let store = FileStore::for_connection(self.connection)?;
+        let all = self.search(&AttachmentQuery {
+            include_deleted: true,
+            ..Default::default()
+        })?;
+        let mut problems = Vec::new();
+        for attachment in &all {
+            match store.read(&attachment.sha256) {
+                Ok(content) if content.len() as i64 != attachment.size => problems.push(format!(
+                    "attachment {} is {} bytes, not {}",
+                    attachment.id,
+                    content.len(),
+                    attachment.size
+                )),
+                Ok(_) => {}
+                Err(Error::Integrity(message)) => problems.push(message),
+                Err(e) => return Err(e),
+            }
+            let latest = attachment_history::table
+                .filter(attachment_history::attachment_id.eq(attachment.id))
+                .select(AttachmentHistory::as_select())
+                .order_by(attachment_history::id.desc())
+                .first(self.connection)
+                .optional()?;
+            match latest {
+                Some(row) if snapshot(&row)? == *attachment => {}
+                Some(_) => problems.push(format!(
+                    "attachment {} was changed without a history record",
+                    attachment.id
+                )),
+                None => problems.push(format!("attachment {} has no history", attachment.id)),
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(all.len()),
+            false => Err(Error::Integrity(problems.join("; "))),
+        }
+    }
+
+    fn find_live(&mut self, id: i32) -> Result<Attachment> {
+        let attachment = self.find(id)?;
+        match attachment.deleted_at {
+            Some(_) => Err(Error::Validation(format!("attachment {} is deleted", id))),
+            None => Ok(attachment),
+        }
+    }
+}
+
+fn validate(file_name: &str, amount: i64) -> Result<()> {
+    if file_name.trim().is_empty() {
+        return Err(Error::Validation("file name is required".to_string()));
+    }
+    if amount < 0 {
+        return Err(Error::Validation(format!(
+            "amount must not be negative: {}",
+            amount
+        )));
+    }
+    Ok(())
+}
+
+fn check_references(
+    connection: &mut SqliteConnection,
+    counterparty_id: Option<i32>,
+    entry_id: Option<i32>,
+) -> Result<()> {
+    if let Some(id) = counterparty_id {
+        CounterpartyRepository::new(connection).find(id)?;
+    }
+    if let Some(id) = entry_id {
+        JournalRepository::new(connection).find(id)?;
+    }
+    Ok(())
+}
+
+fn record(
+    connection: &mut SqliteConnection,
+    attachment: &Attachment,
+    action: AttachmentAction,
+    reason: &str,
+) -> Result<()> {
+    let snapshot = serde_json::to_string(attachment)
+        .map_err(|e| Error::Validation(format!("cannot record attachment: {}", e)))?;
+    insert_into(attachment_history::table)
+        .values((
+            attachment_history::attachment_id.eq(attachment.id),
+            attachment_history::action.eq(action),
+            attachment_history::reason.eq(reason),
+            attachment_history::snapshot.eq(snapshot),
+        ))
+        .execute(connection)?;
+    Ok(())
+}
+
+fn snapshot(row: &AttachmentHistory) -> Result<Attachment> {
+    serde_json::from_str(&row.snapshot).map_err(|e| {
+        Error::Integrity(format!(
+            "history record {} of attachment {} is unreadable: {}",
+            row.id, row.attachment_id, e
+        ))
+    })
+}
@@ -0,0 +1,46 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::error::{Error, Result};
+use crate::models::{BusinessProfile, BusinessProfileChanges};
+use crate::repository::counterparty::is_valid_registration_number;
+use crate::schema::business_profile;
+
+/// Our name, address and registration number as printed on invoices, kept
+/// in a single row.
+pub struct BusinessProfileRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> BusinessProfileRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        BusinessProfileRepository { connection }
+    }
+
+    pub fn get(&mut self) -> Result<BusinessProfile> {
+        Ok(business_profile::table
+            .find(1)
+            .select(BusinessProfile::as_select())
+            .first(self.connection)?)
+    }
+
+    /// Applies to documents rendered from now on, including those of
+    /// invoices issued before.
+    pub fn update(&mut self, changes: &BusinessProfileChanges) -> Result<BusinessProfile> {
+        if let Some(Some(number)) = &changes.registration_number {
+            if !is_valid_registration_number(number) {
+                return Err(Error::Validation(format!(
+                    "invalid registration number: {}",
+                    number
+                )));
+            }
+        }
+        Ok(diesel::update(business_profile::table.find(1))
+            .set((
+                changes,
+                business_profile::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .returning(BusinessProfile::as_returning())
+            .get_result(self.connection)?)
+    }
+}
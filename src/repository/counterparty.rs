@@ -0,0 +1,163 @@
+use chrono::Utc;
+use diesel::{insert_into, prelude::*};
+
+use crate::error::{Error, Result};
+use crate::models::{Counterparty, CounterpartyChanges, NewCounterparty};
+use crate::schema::counterparties;
+
+pub struct CounterpartyRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> CounterpartyRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        CounterpartyRepository { connection }
+    }
+
+    /// Lists counterparties ordered by name.
+    pub fn list(&mut self) -> Result<Vec<Counterparty>> {
+        Ok(counterparties::table
+            .select(Counterparty::as_select())
+            .order_by((counterparties::name, counterparties::id))
+            .load(self.connection)?)
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<Counterparty> {
+        counterparties::table
+            .find(id)
+            .select(Counterparty::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("counterparty {}", id)))
+    }
+
+    pub fn find_by_registration_number(&mut self, number: &str) -> Result<Counterparty> {
+        counterparties::table
+            .filter(counterparties::registration_number.eq(number))
+            .select(Counterparty::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("counterparty {}", number)))
+    }
+
+    pub fn create(&mut self, new_counterparty: &NewCounterparty) -> Result<Counterparty> {
+        validate(new_counterparty)?;
+        self.connection.transaction(|connection| {
+            let mut repository = CounterpartyRepository::new(connection);
+            if let Some(number) = &new_counterparty.registration_number {
+                repository.check_unused(number, None)?;
+            }
+            Ok(insert_into(counterparties::table)
+                .values(new_counterparty)
+                .returning(Counterparty::as_returning())
+                .get_result(repository.connection)?)
+        })
+    }
+
+    /// Applies `changes`, validating the counterparty as it will be after
+    /// the update.
+    pub fn update(&mut self, id: i32, changes: &CounterpartyChanges) -> Result<Counterparty> {
+        self.connection.transaction(|connection| {
+            let mut repository = CounterpartyRepository::new(connection);
+            let existing = repository.find(id)?;
+            let updated = NewCounterparty {
+                name: changes.name.clone().unwrap_or(existing.name),
+                registration_number: changes
+                    .registration_number
+                    .clone()
+                    .unwrap_or(existing.registration_number),
+                address: changes.address.clone().unwrap_or(existing.address),
+                qualified: changes.qualified.unwrap_or(existing.qualified),
+                valid_from: changes.valid_from.unwrap_or(existing.valid_from),
+                valid_until: changes.valid_until.unwrap_or(existing.valid_until),
+                withholding: changes.withholding.unwrap_or(existing.withholding),
+            };
+            validate(&updated)?;
+            if let Some(number) = &updated.registration_number {
+                repository.check_unused(number, Some(id))?;
+            }
+            Ok(diesel::update(counterparties::table.find(id))
+                .set((
+                    changes,
+                    counterparties::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning(Counterparty::as_returning())
+                .get_result(repository.connection)?)
+        })
+    }
+
+    /// Deletes a counterparty. The foreign keys refuse one that journal
+    /// lines, invoices or other records still refer to.
+    pub fn delete(&mut self, id: i32) -> Result<()> {
+        match diesel::delete(counterparties::table.find(id)).execute(self.connection)? {
+            0 => Err(Error::NotFound(format!("counterparty {}", id))),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_unused(&mut self, number: &str, id: Option<i32>) -> Result<()> {
+        match self.find_by_registration_number(number) {
+            Ok(other) if Some(other.id) != id => Err(Error::Validation(format!(
+                "registration number {} is already used by {}",
+                number, other.name
+            ))),
+            Ok(_) | Err(Error::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn validate(counterparty: &NewCounterparty) -> Result<()> {
+    if counterparty.name.trim().is_empty() {
+        return Err(Error::Validation(
+            "counterparty name is required".to_string(),
+        ));
+    }
+    match &counterparty.registration_number {
+        Some(number) if !is_valid_registration_number(number) => {
+            return Err(Error::Validation(format!(
+                "invalid registration number: {}",
+                number
+            )));
+        }
+        None if counterparty.qualified => {
+            return Err(Error::Validation(
+                "a qualified counterparty needs a registration number".to_string(),
+            ));
+        }
+        _ => {}
+    }
+    if let (Some(from), Some(until)) = (counterparty.valid_from, counterparty.valid_until) {
+        if from > until {
+            return Err(Error::Validation(format!(
+                "registration starts after it ends: {} > {}",
+                from, until
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Checks the form and check digit of a registration number: `T` and a
+/// 13-digit number whose first digit is `9 - (Σ Pn × Qn mod 9)`, where Pn
+/// is the n-th of the other twelve digits counted from the right and Qn is
+/// 1 for odd n and 2 for even n.
+pub fn is_valid_registration_number(number: &str) -> bool {
+    let Some(digits) = number.strip_prefix('T') else {
+        return false;
+    };
+    if digits.len() != 13 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+    let digits = digits
+        .bytes()
+        .map(|b| (b - b'0') as u32)
+        .collect::<Vec<_>>();
+    let sum = digits[1..]
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| digit * if i % 2 == 0 { 1 } else { 2 })
+        .sum::<u32>();
+    digits[0] == 9 - sum % 9
+}
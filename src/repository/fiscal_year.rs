@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use chrono::{Months, NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+
+use crate::error::{Error, Result};
+use crate::models::account_codes::{CAPITAL, OWNER_CONTRIBUTIONS, OWNER_DRAWINGS};
+use crate::models::{
+    Account, AccountType, EntryKind, FiscalYear, FiscalYearState, NewFiscalYear, NewJournalEntry,
+    NewJournalLine, Side,
+};
+use crate::repository::{
+    AccountRepository, ApportionmentRepository, FixedAssetRepository, JournalRepository,
+    LedgerRepository,
+};
+use crate::schema::{fiscal_years, journal_entries};
+
+pub struct FiscalYearRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> FiscalYearRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        FiscalYearRepository { connection }
+    }
+
+    /// Lists fiscal years, oldest first.
+    pub fn list(&mut self) -> Result<Vec<FiscalYear>> {
+        Ok(fiscal_years::table
+            .select(FiscalYear::as_select())
+            .order_by(fiscal_years::start_date)
+            .load(self.connection)?)
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<FiscalYear> {
+        fiscal_years::table
+            .find(id)
+            .select(FiscalYear::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("fiscal year {}", id)))
+    }
+
+    /// The fiscal year containing `date`, if one has been set up.
+    pub fn find_by_date(&mut self, date: NaiveDate) -> Result<Option<FiscalYear>> {
+        Ok(fiscal_years::table
+            .filter(fiscal_years::start_date.le(date))
+            .filter(fiscal_years::end_date.ge(date))
+            .select(FiscalYear::as_select())
+            .first(self.connection)
+            .optional()?)
+    }
+
+    pub fn create(&mut self, new_year: &NewFiscalYear) -> Result<FiscalYear> {
+        if new_year.start_date > new_year.end_date {
+            return Err(Error::Validation(format!(
+                "fiscal year starts after it ends: {} > {}",
+                new_year.start_date, new_year.end_date
+            )));
+        }
+        self.connection.transaction(|connection| {
+            let overlapping = fiscal_years::table
+                .filter(fiscal_years::start_date.le(new_year.end_date))
+                .filter(fiscal_years::end_date.ge(new_year.start_date))
+                .select(FiscalYear::as_select())
+                .first(connection)
+                .optional()?;
+            if let Some(existing) = overlapping {
+                return Err(Error::Validation(format!(
+                    "fiscal year overlaps {} - {}",
+                    existing.start_date, existing.end_date
+                )));
+            }
+            Ok(insert_into(fiscal_years::table)
+                .values(new_year)
+                .returning(FiscalYear::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    /// Closes the year. Depreciation and the 家事按分 entry are posted
+    /// first unless they already have been. Revenue, expenses, 事業主貸 and 事業主借 are
+    /// transferred into 元入金 by a closing entry on the last day, and the
+    /// resulting asset, liability and capital balances are brought forward
+    /// by an opening entry on the first day of the next year, which is
+    /// created if needed. Entries dated in a closed year are rejected.
+    pub fn close(&mut self, id: i32) -> Result<FiscalYear> {
+        self.connection.transaction(|connection| {
+            let mut repository = FiscalYearRepository::new(connection);
+            let year = repository.find(id)?;
+            if year.state == FiscalYearState::Closed {
+                return Err(Error::Validation(format!(
+                    "fiscal year {} - {} is already closed",
+                    year.start_date, year.end_date
+                )));
+            }
+            let earlier_open = fiscal_years::table
+                .filter(fiscal_years::end_date.lt(year.start_date))
+                .filter(fiscal_years::state.eq(FiscalYearState::Open))
+                .select(FiscalYear::as_select())
+                .first(repository.connection)
+                .optional()?;
+            if let Some(earlier) = earlier_open {
+                return Err(Error::Validation(format!(
+                    "fiscal year {} - {} must be closed first",
+                    earlier.start_date, earlier.end_date
+                )));
+            }
+            let next = repository.next_year(&year)?;
+            FixedAssetRepository::new(repository.connection).ensure_depreciation(&year)?;
+            ApportionmentRepository::new(repository.connection).ensure_adjustments(&year)?;
+
+            let accounts = AccountRepository::new(repository.connection).list(true)?;
+            let mut balances = LedgerRepository::new(repository.connection)
+                .balances_as_of(year.end_date)?
+                .into_iter()
+                .map(|(account_id, totals)| (account_id, totals.debit - totals.credit))
+                .collect::<HashMap<_, _>>();
+
+            let capital = AccountRepository::new(repository.connection).find_by_code(CAPITAL)?;
+            let mut closing = Vec::new();
+            for account in &accounts {
+                if !closes_into_capital(account) {
+                    continue;
+                }
+                let Some(balance) = balances.remove(&account.id) else {
+                    continue;
+                };
+                if let Some(line) = line(account.id, -balance) {
+                    *balances.entry(capital.id).or_insert(0) += balance;
+                    closing.push(line);
+                }
+            }
+            let transferred = closing
+                .iter()
+                .map(|line| match line.side {
+                    Side::Debit => line.amount,
+                    Side::Credit => -line.amount,
+                })
+                .sum::<i64>();
+            closing.extend(line(capital.id, -transferred));
+            let closing_entry = match closing.len() {
+                0 => None,
+                _ => {
+                    let entry = NewJournalEntry {
+                        entry_date: year.end_date,
+                        memo: "決算振替".to_string(),
+                        lines: closing,
+                    };
+                    Some(
+                        JournalRepository::new(repository.connection)
+                            .post(&entry, EntryKind::Closing)?,
+                    )
+                }
+            };
+
+            let opening = accounts
+                .iter()
+                .filter_map(|account| line(account.id, *balances.get(&account.id)?))
+                .collect::<Vec<_>>();
+            let opening_entry = match opening.len() {
+                0 => None,
+                _ => {
+                    let entry = NewJournalEntry {
+                        entry_date: next.start_date,
+                        memo: "開始仕訳".to_string(),
+                        lines: opening,
+                    };
+                    Some(
+                        JournalRepository::new(repository.connection)
+                            .post(&entry, EntryKind::Opening)?,
+                    )
+                }
+            };
+
+            let now = Utc::now().naive_utc();
+            Ok(diesel::update(fiscal_years::table.find(id))
+                .set((
+                    fiscal_years::state.eq(FiscalYearState::Closed),
+                    fiscal_years::closing_entry_id.eq(closing_entry.map(|e| e.entry.id)),
+                    fiscal_years::opening_entry_id.eq(opening_entry.map(|e| e.entry.id)),
+                    fiscal_years::closed_at.eq(now),
+                    fiscal_years::updated_at.eq(now),
+                ))
+                .returning(FiscalYear::as_returning())
+                .get_result(repository.connection)?)
+        })
+    }
+
+    /// Reopens a closed year for corrections, voiding its closing and
+    /// opening entries. The following year must still be open.
+    pub fn reopen(&mut self, id: i32) -> Result<FiscalYear> {
+        self.connection.transaction(|connection| {
+            let mut repository = FiscalYearRepository::new(connection);
+            let year = repository.find(id)?;
+            if year.state == FiscalYearState::Open {
+                return Err(Error::Validation(format!(
+                    "fiscal year {} - {} is not closed",
+                    year.start_date, year.end_date
+                )));
+            }
+            let later_closed = fiscal_years::table
+                .filter(fiscal_years::start_date.gt(year.end_date))
+                .filter(fiscal_years::state.eq(FiscalYearState::Closed))
+                .count()
+                .get_result::<i64>(repository.connection)?;
+            if later_closed > 0 {
+                return Err(Error::Validation(format!(
+                    "a fiscal year after {} is closed; reopen it first",
+                    year.end_date
+                )));
+            }
+
+            let now = Utc::now().naive_utc();
+            let generated = [year.closing_entry_id, year.opening_entry_id];
+            diesel::update(journal_entries::table)
+                .filter(journal_entries::id.eq_any(generated.iter().flatten()))
+                .set((
+                    journal_entries::voided_at.eq(now),
+                    journal_entries::updated_at.eq(now),
+                ))
+                .execute(repository.connection)?;
+            Ok(diesel::update(fiscal_years::table.find(id))
+                .set((
+                    fiscal_years::state.eq(FiscalYearState::Open),
+                    fiscal_years::closing_entry_id.eq(None::<i32>),
+                    fiscal_years::opening_entry_id.eq(None::<i32>),
+                    fiscal_years::closed_at.eq(None::<chrono::NaiveDateTime>),
+                    fiscal_years::updated_at.eq(now),
+                ))
+                .returning(FiscalYear::as_returning())
+                .get_result(repository.connection)?)
+        })
+    }
+
+    /// The year starting the day after `year` ends, created with the same
+    /// length if it does not exist yet.
+    fn next_year(&mut self, year: &FiscalYear) -> Result<FiscalYear> {
+        let start_date = year
+            .end_date
+            .succ_opt()
+            .ok_or_else(|| Error::Validation("fiscal year ends too late".to_string()))?;
+        if let Some(next) = self.find_by_date(start_date)? {
+            return Ok(next);
+        }
+        let months = Months::new(12);
+        let end_date = start_date
+            .checked_add_months(months)
+            .and_then(|date| date.pred_opt())
+            .ok_or_else(|| Error::Validation("fiscal year ends too late".to_string()))?;
+        self.create(&NewFiscalYear {
+            start_date,
+            end_date,
+        })
+    }
+}
+
+/// Rejects dates inside a closed fiscal year.
+pub(crate) fn ensure_open(connection: &mut SqliteConnection, date: NaiveDate) -> Result<()> {
+    match FiscalYearRepository::new(connection).find_by_date(date)? {
+        Some(year) if year.state == FiscalYearState::Closed => Err(Error::PeriodClosed {
+            date,
+            start: year.start_date,
+            end: year.end_date,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Accounts whose year-end balance is moved into 元入金.
+fn closes_into_capital(account: &Account) -> bool {
+    matches!(
+        account.account_type,
+        AccountType::Revenue | AccountType::Expense
+    ) || account.code == OWNER_DRAWINGS
+        || account.code == OWNER_CONTRIBUTIONS
+}
+
+/// A line moving `net` (debit minus credit) onto the account.
+fn line(account_id: i32, net: i64) -> Option<NewJournalLine> {
+    match net {
+        0 => None,
+        net if net > 0 => Some(NewJournalLine::debit(account_id, net)),
+        net => Some(NewJournalLine::credit(account_id, -net)),
+    }
+}
@@ -0,0 +1,289 @@
+use chrono::{Datelike, NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+use serde::Serialize;
+
+use crate::depreciation::{check_cost, check_useful_life, depreciate, small_amount_cap};
+use crate::error::{Error, Result};
+use crate::models::account_codes::{DEPRECIATION, OWNER_DRAWINGS};
+use crate::models::{
+    AccountType, DepreciationMethod, EntryKind, FiscalYear, FiscalYearState, FixedAsset,
+    FixedAssetChanges, JournalEntryWithLines, NewFixedAsset, NewJournalEntry, NewJournalLine,
+};
+use crate::repository::{AccountRepository, FiscalYearRepository, JournalRepository};
+use crate::schema::{fiscal_years, fixed_assets};
+
+/// Small-amount assets acquired in a year against the annual cap of the
+/// small-amount rule.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SmallAmountUsage {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total: i64,
+    pub cap: i64,
+}
+
+impl SmallAmountUsage {
+    /// A warning if more than the cap has been expensed; the excess has to
+    /// be depreciated in the ordinary way instead.
+    pub fn warning(&self) -> Option<String> {
+        (self.total > self.cap).then(|| {
+            format!(
+                "small-amount assets acquired {} - {} total {} yen, {} over the cap of {}",
+                self.from,
+                self.to,
+                self.total,
+                self.total - self.cap,
+                self.cap
+            )
+        })
+    }
+}
+
+/// The fixed asset register and the year-end depreciation entries.
+pub struct FixedAssetRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> FixedAssetRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        FixedAssetRepository { connection }
+    }
+
+    /// Lists assets in order of acquisition.
+    pub fn list(&mut self) -> Result<Vec<FixedAsset>> {
+        Ok(fixed_assets::table
+            .select(FixedAsset::as_select())
+            .order_by((fixed_assets::acquired_on, fixed_assets::id))
+            .load(self.connection)?)
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<FixedAsset> {
+        fixed_assets::table
+            .find(id)
+            .select(FixedAsset::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("fixed asset {}", id)))
+    }
+
+    pub fn create(&mut self, new_asset: &NewFixedAsset) -> Result<FixedAsset> {
+        if new_asset.name.trim().is_empty() {
+            return Err(Error::Validation(
+                "fixed asset name is required".to_string(),
+            ));
+        }
+        if new_asset.cost <= 0 {
+            return Err(Error::Validation(format!(
+                "acquisition cost must be positive: {}",
+                new_asset.cost
+            )));
+        }
+        check_business_ratio(new_asset.business_ratio)?;
+        check_useful_life(new_asset.method, new_asset.useful_life)?;
+        check_cost(new_asset.method, new_asset.cost)?;
+        self.connection.transaction(|connection| {
+            let account = AccountRepository::new(connection).find(new_asset.account_id)?;
+            if account.account_type != AccountType::Asset {
+                return Err(Error::Validation(format!(
+                    "account {} {} is not an asset account",
+                    account.code, account.name
+                )));
+            }
+            Ok(insert_into(fixed_assets::table)
+                .values(new_asset)
+                .returning(FixedAsset::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    /// Applies `changes`, e.g. recording a disposal.
+    pub fn update(&mut self, id: i32, changes: &FixedAssetChanges) -> Result<FixedAsset> {
+        if changes
+            .name
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err(Error::Validation(
+                "fixed asset name is required".to_string(),
+            ));
+        }
+        if let Some(ratio) = changes.business_ratio {
+            check_business_ratio(ratio)?;
+        }
+        self.connection.transaction(|connection| {
+            let existing = FixedAssetRepository::new(connection).find(id)?;
+            if let Some(Some(disposed_on)) = changes.disposed_on {
+                if disposed_on < existing.acquired_on {
+                    return Err(Error::Validation(format!(
+                        "disposal precedes acquisition: {} < {}",
+                        disposed_on, existing.acquired_on
+                    )));
+                }
+            }
+            Ok(diesel::update(fixed_assets::table.find(id))
+                .set((changes, fixed_assets::updated_at.eq(Utc::now().naive_utc())))
+                .returning(FixedAsset::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    /// The small-amount assets acquired from `from` to `to`.
+    pub fn small_amount_usage(
+        &mut self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<SmallAmountUsage> {
+        let costs = fixed_assets::table
+            .filter(fixed_assets::method.eq(DepreciationMethod::SmallAmount))
+            .filter(fixed_assets::acquired_on.between(from, to))
+            .select(fixed_assets::cost)
+            .load::<i64>(self.connection)?;
+        Ok(SmallAmountUsage {
+            from,
+            to,
+            total: costs.iter().sum(),
+            cap: small_amount_cap(from, to),
+        })
+    }
+
+    /// The small-amount usage of the fiscal year containing `date`, or of
+    /// its calendar year if no fiscal year has been set up.
+    pub fn small_amount_usage_on(&mut self, date: NaiveDate) -> Result<SmallAmountUsage> {
+        let (from, to) = match FiscalYearRepository::new(self.connection).find_by_date(date)? {
+            Some(year) => (year.start_date, year.end_date),
+            None => (
+                NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date),
+                NaiveDate::from_ymd_opt(date.year(), 12, 31).unwrap_or(date),
+            ),
+        };
+        self.small_amount_usage(from, to)
+    }
+
+    /// Posts the depreciation of every asset for a fiscal year as one entry
+    /// on its last day. Fails if the year already has a depreciation entry
+    /// that has not been voided, or if there is nothing to depreciate.
+    pub fn post_depreciation(&mut self, fiscal_year_id: i32) -> Result<JournalEntryWithLines> {
+        self.connection.transaction(|connection| {
+            let year = FiscalYearRepository::new(connection).find(fiscal_year_id)?;
+            if year.state == FiscalYearState::Closed {
+                return Err(Error::Validation(format!(
+                    "fiscal year {} - {} is closed",
+                    year.start_date, year.end_date
+                )));
+            }
+            let mut repository = FixedAssetRepository::new(connection);
+            if repository.has_depreciation(&year)? {
+                return Err(Error::Validation(format!(
+                    "depreciation for {} - {} has already been posted",
+                    year.start_date, year.end_date
+                )));
+            }
+            repository.depreciation_entry(&year)?.ok_or_else(|| {
+                Error::Validation(format!(
+                    "nothing to depreciate in {} - {}",
+                    year.start_date, year.end_date
+                ))
+            })
+        })
+    }
+
+    /// Posts the year's depreciation unless that has been done already;
+    /// used when the year is closed.
+    pub(crate) fn ensure_depreciation(&mut self, year: &FiscalYear) -> Result<()> {
+        if !self.has_depreciation(year)? {
+            self.depreciation_entry(year)?;
+        }
+        Ok(())
+    }
+
+    fn has_depreciation(&mut self, year: &FiscalYear) -> Result<bool> {
+        match year.depreciation_entry_id {
+            Some(entry_id) => Ok(JournalRepository::new(self.connection)
+                .find(entry_id)?
+                .entry
+                .voided_at
+                .is_none()),
+            None => Ok(false),
+        }
+    }
+
+    /// Debits the business share of each asset's depreciation to 減価償却費
+    /// and the private share to 事業主貸, crediting the asset account. The
+    /// book value left on an asset disposed of during the year is moved to
+    /// 事業主貸, since gains and losses on business assets other than
+    /// inventory belong to another income category.
+    fn depreciation_entry(&mut self, year: &FiscalYear) -> Result<Option<JournalEntryWithLines>> {
+        let mut accounts = AccountRepository::new(self.connection);
+        let expense = accounts.find_by_code(DEPRECIATION)?.id;
+        let drawings = accounts.find_by_code(OWNER_DRAWINGS)?.id;
+
+        let mut lines = Vec::new();
+        for asset in self.list()? {
+            let Some(depreciation) = depreciate(&asset, year.start_date, year.end_date)? else {
+                continue;
+            };
+            let described = |line: NewJournalLine, description: &str| NewJournalLine {
+                description: description.to_string(),
+                ..line
+            };
+            let private = depreciation.depreciation - depreciation.business_portion;
+            for (account_id, amount) in [
+                (expense, depreciation.business_portion),
+                (drawings, private),
+            ] {
+                if amount > 0 {
+                    lines.push(described(
+                        NewJournalLine::debit(account_id, amount),
+                        &asset.name,
+                    ));
+                }
+            }
+            if depreciation.depreciation > 0 {
+                lines.push(described(
+                    NewJournalLine::credit(asset.account_id, depreciation.depreciation),
+                    &asset.name,
+                ));
+            }
+            if depreciation.disposed && depreciation.closing_book_value > 0 {
+                let description = format!("{} 除却", asset.name);
+                lines.push(described(
+                    NewJournalLine::debit(drawings, depreciation.closing_book_value),
+                    &description,
+                ));
+                lines.push(described(
+                    NewJournalLine::credit(asset.account_id, depreciation.closing_book_value),
+                    &description,
+                ));
+            }
+        }
+        if lines.is_empty() {
+            return Ok(None);
+        }
+
+        let entry = JournalRepository::new(self.connection).post(
+            &NewJournalEntry {
+                entry_date: year.end_date,
+                memo: "減価償却費".to_string(),
+                lines,
+            },
+            EntryKind::Regular,
+        )?;
+        diesel::update(fiscal_years::table.find(year.id))
+            .set((
+                fiscal_years::depreciation_entry_id.eq(entry.entry.id),
+                fiscal_years::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(self.connection)?;
+        Ok(Some(entry))
+    }
+}
+
+fn check_business_ratio(ratio: i32) -> Result<()> {
+    if !(0..=100).contains(&ratio) {
+        return Err(Error::Validation(format!(
+            "business-use ratio must be between 0 and 100: {}",
+            ratio
+        )));
+    }
+    Ok(())
+}
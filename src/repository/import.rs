@@ -0,0 +1,435 @@
+use diesel::{insert_into, prelude::*};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::import::{fingerprints, parse_statement};
+use crate::models::{
+    ImportBatch, ImportProfile, ImportRule, NewImportProfile, NewImportRule, NewJournalEntry,
+    NewJournalLine, SkippedDuplicate, StagedStatus, StagedTransaction,
+};
+use crate::repository::{AccountRepository, CounterpartyRepository, JournalRepository};
+use crate::schema::{
+    import_batches, import_profiles, import_rules, skipped_duplicates, staged_transactions,
+};
+
+/// Conditions for listing staged transactions.
+#[derive(Debug, Clone, Default)]
+pub struct StagedQuery {
+    pub batch_id: Option<i32>,
+    pub status: Option<StagedStatus>,
+}
+
+/// A statement file as imported. Rows seen in an earlier import are
+/// listed under `skipped` instead of being staged again.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub batch: ImportBatch,
+    pub transactions: Vec<StagedTransaction>,
+    pub skipped: Vec<SkippedDuplicate>,
+}
+
+/// What undoing an import batch removed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UndoneImport {
+    pub batch: ImportBatch,
+    pub removed_transactions: usize,
+    /// Entries posted from the batch, now void.
+    pub voided_entry_ids: Vec<i32>,
+}
+
+/// The entry suggested for a pending transaction by the first matching
+/// rule, if any.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Proposal {
+    pub transaction: StagedTransaction,
+    pub rule_id: Option<i32>,
+    pub entry: Option<NewJournalEntry>,
+}
+
+/// Statement imports: institution profiles, staged statement rows and the
+/// rules that turn them into journal entries.
+pub struct ImportRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> ImportRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        ImportRepository { connection }
+    }
+
+    pub fn list_profiles(&mut self) -> Result<Vec<ImportProfile>> {
+        Ok(import_profiles::table
+            .select(ImportProfile::as_select())
+            .order_by(import_profiles::name)
+            .load(self.connection)?)
+    }
+
+    pub fn find_profile(&mut self, id: i32) -> Result<ImportProfile> {
+        import_profiles::table
+            .find(id)
+            .select(ImportProfile::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("import profile {}", id)))
+    }
+
+    pub fn create_profile(&mut self, new_profile: &NewImportProfile) -> Result<ImportProfile> {
+        if new_profile.name.trim().is_empty() {
+            return Err(Error::Validation(
+                "import profile name is required".to_string(),
+            ));
+        }
+        let columns = [
+            Some(new_profile.skip_rows),
+            Some(new_profile.date_column),
+            Some(new_profile.description_column),
+            Some(new_profile.withdrawal_column),
+            new_profile.deposit_column,
+            new_profile.balance_column,
+        ];
+        if columns.iter().flatten().any(|&column| column < 0) {
+            return Err(Error::Validation(
+                "column numbers and skip_rows must not be negative".to_string(),
+            ));
+        }
+        self.connection.transaction(|connection| {
+            AccountRepository::new(connection).find(new_profile.account_id)?;
+            let existing = import_profiles::table
+                .filter(import_profiles::name.eq(&new_profile.name))
+                .count()
+                .get_result::<i64>(connection)?;
+            if existing > 0 {
+                return Err(Error::Validation(format!(
+                    "import profile {} already exists",
+                    new_profile.name
+                )));
+            }
+            Ok(insert_into(import_profiles::table)
+                .values(new_profile)
+                .returning(ImportProfile::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    /// Parses a statement with the profile's mapping and stages its rows as
+    /// one batch. Nothing is stored if any row is invalid. Rows already
+    /// staged by an earlier import, recognised by their fingerprint, are
+    /// recorded as skipped duplicates.
+    pub fn import(
+        &mut self,
+        profile_id: i32,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<ImportSummary> {
+        let profile = self.find_profile(profile_id)?;
+        let rows = parse_statement(&profile, bytes)?;
+        let fingerprints = fingerprints(profile.account_id, &rows);
+        self.connection.transaction(|connection| {
+            let batch = insert_into(import_batches::table)
+                .values((
+                    import_batches::profile_id.eq(profile.id),
+                    import_batches::file_name.eq(file_name),
+                ))
+                .returning(ImportBatch::as_returning())
+                .get_result(connection)?;
+            let mut transactions = Vec::with_capacity(rows.len());
+            let mut skipped = Vec::new();
+            for (row, fingerprint) in rows.iter().zip(fingerprints) {
+                let existing = staged_transactions::table
+                    .filter(staged_transactions::fingerprint.eq(&fingerprint))
+                    .select(staged_transactions::id)
+                    .first::<i32>(connection)
+                    .optional()?;
+                if let Some(duplicate_of) = existing {
+                    skipped.push(
+                        insert_into(skipped_duplicates::table)
+                            .values((
+                                skipped_duplicates::batch_id.eq(batch.id),
+                                skipped_duplicates::row_no.eq(row.row_no),
+                                skipped_duplicates::transaction_date.eq(row.date),
+                                skipped_duplicates::description.eq(&row.description),
+                                skipped_duplicates::withdrawal.eq(row.withdrawal),
+                                skipped_duplicates::deposit.eq(row.deposit),
+                                skipped_duplicates::duplicate_of.eq(duplicate_of),
+                            ))
+                            .returning(SkippedDuplicate::as_returning())
+                            .get_result(connection)?,
+                    );
+                    continue;
+                }
+                transactions.push(
+                    insert_into(staged_transactions::table)
+                        .values((
+                            staged_transactions::batch_id.eq(batch.id),
+                            staged_transactions::row_no.eq(row.row_no),
+                            staged_transactions::account_id.eq(profile.account_id),
+                            staged_transactions::transaction_date.eq(row.date),
+                            staged_transactions::description.eq(&row.description),
+                            staged_transactions::withdrawal.eq(row.withdrawal),
+                            staged_transactions::deposit.eq(row.deposit),
+                            staged_transactions::balance.eq(row.balance),
+                            staged_transactions::fingerprint.eq(&fingerprint),
+                        ))
+                        .returning(StagedTransaction::as_returning())
+                        .get_result(connection)?,
+                );
+            }
+            Ok(ImportSummary {
+                batch,
+                transactions,
+                skipped,
+            })
+        })
+    }
+
+    /// Lists import batches, latest first.
+    pub fn list_batches(&mut self) -> Result<Vec<ImportBatch>> {
+        Ok(import_batches::table
+            .select(ImportBatch::as_select())
+            .order_by(import_batches::id.desc())
+            .load(self.connection)?)
+    }
+
+    pub fn find_batch(&mut self, id: i32) -> Result<ImportBatch> {
+        import_batches::table
+            .find(id)
+            .select(ImportBatch::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("import batch {}", id)))
+    }
+
+    /// The rows of a batch that were skipped as duplicates, in file order.
+    pub fn skipped(&mut self, batch_id: i32) -> Result<Vec<SkippedDuplicate>> {
+        self.find_batch(batch_id)?;
+        Ok(skipped_duplicates::table
+            .filter(skipped_duplicates::batch_id.eq(batch_id))
+            .select(SkippedDuplicate::as_select())
+            .order_by(skipped_duplicates::row_no)
+            .load(self.connection)?)
+    }
+
+    /// Removes a batch as if it had never been imported: entries posted
+    /// from it are voided, which fails if one lies in a closed period, and
+    /// its staged and skipped rows are deleted so the statement can be
+    /// imported again. A batch whose rows a later import skipped as
+    /// duplicates must be undone after that later one.
+    pub fn undo_batch(&mut self, batch_id: i32) -> Result<UndoneImport> {
+        self.connection.transaction(|connection| {
+            let mut repository = ImportRepository::new(connection);
+            let batch = repository.find_batch(batch_id)?;
+            let transactions = repository.transactions(&StagedQuery {
+                batch_id: Some(batch_id),
+                status: None,
+            })?;
+            let ids = transactions.iter().map(|t| t.id).collect::<Vec<_>>();
+            let later = skipped_duplicates::table
+                .filter(skipped_duplicates::duplicate_of.eq_any(&ids))
+                .filter(skipped_duplicates::batch_id.ne(batch_id))
+                .select(skipped_duplicates::batch_id)
+                .first::<i32>(repository.connection)
+                .optional()?;
+            if let Some(later) = later {
+                return Err(Error::Validation(format!(
+                    "import batch {} skipped rows of batch {} as duplicates; undo it first",
+                    later, batch_id
+                )));
+            }
+
+            let mut voided_entry_ids = Vec::new();
+            for entry_id in transactions.iter().filter_map(|t| t.entry_id) {
+                let entry = JournalRepository::new(repository.connection).find(entry_id)?;
+                if entry.entry.voided_at.is_none() {
+                    JournalRepository::new(repository.connection).void(entry_id)?;
+                    voided_entry_ids.push(entry_id);
+                }
+            }
+            diesel::delete(
+                skipped_duplicates::table.filter(skipped_duplicates::batch_id.eq(batch_id)),
+            )
+            .execute(repository.connection)?;
+            let removed_transactions = diesel::delete(
+                staged_transactions::table.filter(staged_transactions::batch_id.eq(batch_id)),
+            )
+            .execute(repository.connection)?;
+            diesel::delete(import_batches::table.find(batch_id)).execute(repository.connection)?;
+            Ok(UndoneImport {
+                batch,
+                removed_transactions,
+                voided_entry_ids,
+            })
+        })
+    }
+
+    pub fn find_transaction(&mut self, id: i32) -> Result<StagedTransaction> {
+        staged_transactions::table
+            .find(id)
+            .select(StagedTransaction::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("staged transaction {}", id)))
+    }
+
+    /// Lists staged transactions in statement order.
+    pub fn transactions(&mut self, query: &StagedQuery) -> Result<Vec<StagedTransaction>> {
+        let mut transactions = staged_transactions::table
+            .select(StagedTransaction::as_select())
+            .into_boxed();
+        if let Some(batch_id) = query.batch_id {
+            transactions = transactions.filter(staged_transactions::batch_id.eq(batch_id));
+        }
+        if let Some(status) = query.status {
+            transactions = transactions.filter(staged_transactions::status.eq(status));
+        }
+        Ok(transactions
+            .order_by((staged_transactions::batch_id, staged_transactions::row_no))
+            .load(self.connection)?)
+    }
+
+    pub fn list_rules(&mut self) -> Result<Vec<ImportRule>> {
+        Ok(import_rules::table
+            .select(ImportRule::as_select())
+            .order_by((import_rules::priority, import_rules::id))
+            .load(self.connection)?)
+    }
+
+    pub fn create_rule(&mut self, new_rule: &NewImportRule) -> Result<ImportRule> {
+        if let (Some(min), Some(max)) = (new_rule.min_amount, new_rule.max_amount) {
+            if min > max {
+                return Err(Error::Validation(format!(
+                    "rule amount range is empty: {} > {}",
+                    min, max
+                )));
+            }
+        }
+        self.connection.transaction(|connection| {
+            AccountRepository::new(connection).find(new_rule.account_id)?;
+            if let Some(counterparty_id) = new_rule.counterparty_id {
+                CounterpartyRepository::new(connection).find(counterparty_id)?;
+            }
+            Ok(insert_into(import_rules::table)
+                .values(new_rule)
+                .returning(ImportRule::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    pub fn delete_rule(&mut self, id: i32) -> Result<()> {
+        match diesel::delete(import_rules::table.find(id)).execute(self.connection)? {
+            0 => Err(Error::NotFound(format!("import rule {}", id))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Proposes entries for the pending transactions, of one batch or all.
+    pub fn proposals(&mut self, batch_id: Option<i32>) -> Result<Vec<Proposal>> {
+        let rules = self.list_rules()?;
+        let pending = self.transactions(&StagedQuery {
+            batch_id,
+            status: Some(StagedStatus::Pending),
+        })?;
+        Ok(pending
+            .into_iter()
+            .map(|transaction| {
+                let rule = rules.iter().find(|rule| rule.matches(&transaction));
+                Proposal {
+                    rule_id: rule.map(|rule| rule.id),
+                    entry: rule.map(|rule| propose(&transaction, rule)),
+                    transaction,
+                }
+            })
+            .collect())
+    }
+
+    /// Posts a pending transaction, either as `entry` after review or as
+    /// proposed by the rules. The statement amount is posted as it stands:
+    /// no income tax is withheld from it automatically.
+    pub fn post(
+        &mut self,
+        transaction_id: i32,
+        entry: Option<&NewJournalEntry>,
+    ) -> Result<StagedTransaction> {
+        self.connection.transaction(|connection| {
+            let mut repository = ImportRepository::new(connection);
+            let transaction = repository.pending(transaction_id)?;
+            let entry = match entry {
+                Some(entry) => entry.clone(),
+                None => {
+                    let rules = repository.list_rules()?;
+                    let rule = rules
+                        .iter()
+                        .find(|rule| rule.matches(&transaction))
+                        .ok_or_else(|| {
+                            Error::Validation(format!(
+                                "no rule matches staged transaction {}; post it with an entry",
+                                transaction_id
+                            ))
+                        })?;
+                    propose(&transaction, rule)
+                }
+            };
+            let posted =
+                JournalRepository::new(repository.connection).create_from_statement(&entry)?;
+            Ok(
+                diesel::update(staged_transactions::table.find(transaction_id))
+                    .set((
+                        staged_transactions::status.eq(StagedStatus::Posted),
+                        staged_transactions::entry_id.eq(posted.entry.id),
+                    ))
+                    .returning(StagedTransaction::as_returning())
+                    .get_result(repository.connection)?,
+            )
+        })
+    }
+
+    /// Marks a pending transaction as not to be posted, e.g. a transfer
+    /// already recorded from the other account's statement.
+    pub fn ignore(&mut self, transaction_id: i32) -> Result<StagedTransaction> {
+        self.connection.transaction(|connection| {
+            let mut repository = ImportRepository::new(connection);
+            repository.pending(transaction_id)?;
+            Ok(
+                diesel::update(staged_transactions::table.find(transaction_id))
+                    .set(staged_transactions::status.eq(StagedStatus::Ignored))
+                    .returning(StagedTransaction::as_returning())
+                    .get_result(repository.connection)?,
+            )
+        })
+    }
+
+    fn pending(&mut self, transaction_id: i32) -> Result<StagedTransaction> {
+        let transaction = self.find_transaction(transaction_id)?;
+        if transaction.status != StagedStatus::Pending {
+            return Err(Error::Validation(format!(
+                "staged transaction {} is already {}",
+                transaction_id, transaction.status
+            )));
+        }
+        Ok(transaction)
+    }
+}
+
+/// The entry `rule` proposes: a withdrawal debits the rule's account and
+/// credits the statement's account, a deposit the other way round.
+fn propose(transaction: &StagedTransaction, rule: &ImportRule) -> NewJournalEntry {
+    let amount = transaction.amount();
+    let mut other = match transaction.withdrawal {
+        0 => NewJournalLine::credit(rule.account_id, amount),
+        _ => NewJournalLine::debit(rule.account_id, amount),
+    };
+    other.tax_category = rule.tax_category;
+    other.counterparty_id = rule.counterparty_id;
+    let statement = match transaction.withdrawal {
+        0 => NewJournalLine::debit(transaction.account_id, amount),
+        _ => NewJournalLine::credit(transaction.account_id, amount),
+    };
+    let (first, second) = match transaction.withdrawal {
+        0 => (statement, other),
+        _ => (other, statement),
+    };
+    NewJournalEntry {
+        entry_date: transaction.transaction_date,
+        memo: transaction.description.clone(),
+        lines: vec![first, second],
+    }
+}
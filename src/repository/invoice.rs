@@ -0,0 +1,394 @@
+use chrono::Utc;
+use diesel::{insert_into, prelude::*};
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::invoice::{totals, InvoiceDocument, InvoiceTotals};
+use crate::models::account_codes::{ACCOUNTS_RECEIVABLE, ORDINARY_DEPOSITS, SALES};
+use crate::models::{
+    Invoice, InvoiceLine, InvoicePayment, InvoiceStatus, InvoiceWithLines, NewInvoice,
+    NewJournalEntry, NewJournalLine, NewOpenItemMatch, Side,
+};
+use crate::repository::{
+    AccountRepository, BusinessProfileRepository, CounterpartyRepository, JournalRepository,
+    OpenItemRepository, TaxSettingsRepository,
+};
+use crate::schema::{invoice_lines, invoices, journal_lines};
+
+/// Conditions for listing invoices.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InvoiceQuery {
+    pub status: Option<InvoiceStatus>,
+    pub counterparty_id: Option<i32>,
+}
+
+/// Invoices to clients. Drafts can be edited freely; issuing numbers the
+/// invoice and posts the receivable, and payments are applied to it as open
+/// items.
+pub struct InvoiceRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> InvoiceRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        InvoiceRepository { connection }
+    }
+
+    /// Lists invoices ordered by issue date, then by id.
+    pub fn list(&mut self, query: &InvoiceQuery) -> Result<Vec<InvoiceWithLines>> {
+        let mut select = invoices::table.select(Invoice::as_select()).into_boxed();
+        if let Some(status) = query.status {
+            select = select.filter(invoices::status.eq(status));
+        }
+        if let Some(counterparty_id) = query.counterparty_id {
+            select = select.filter(invoices::counterparty_id.eq(counterparty_id));
+        }
+        let invoices = select
+            .order_by((invoices::issue_date, invoices::id))
+            .load(self.connection)?;
+        let lines = InvoiceLine::belonging_to(&invoices)
+            .select(InvoiceLine::as_select())
+            .order_by(invoice_lines::line_no)
+            .load(self.connection)?
+            .grouped_by(&invoices);
+        Ok(invoices
+            .into_iter()
+            .zip(lines)
+            .map(|(invoice, lines)| InvoiceWithLines { invoice, lines })
+            .collect())
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<InvoiceWithLines> {
+        let invoice = invoices::table
+            .find(id)
+            .select(Invoice::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("invoice {}", id)))?;
+        let lines = InvoiceLine::belonging_to(&invoice)
+            .select(InvoiceLine::as_select())
+            .order_by(invoice_lines::line_no)
+            .load(self.connection)?;
+        Ok(InvoiceWithLines { invoice, lines })
+    }
+
+    /// Records a draft.
+    pub fn create(&mut self, new_invoice: &NewInvoice) -> Result<InvoiceWithLines> {
+        validate(new_invoice)?;
+        self.connection.transaction(|connection| {
+            CounterpartyRepository::new(connection).find(new_invoice.counterparty_id)?;
+            let invoice = insert_into(invoices::table)
+                .values((
+                    invoices::counterparty_id.eq(new_invoice.counterparty_id),
+                    invoices::issue_date.eq(new_invoice.issue_date),
+                    invoices::due_date.eq(new_invoice.due_date),
+                    invoices::notes.eq(&new_invoice.notes),
+                ))
+                .returning(Invoice::as_returning())
+                .get_result(connection)?;
+            insert_lines(connection, invoice.id, new_invoice)?;
+            InvoiceRepository::new(connection).find(invoice.id)
+        })
+    }
+
+    /// Replaces a draft with `new_invoice`.
+    pub fn update(&mut self, id: i32, new_invoice: &NewInvoice) -> Result<InvoiceWithLines> {
+        validate(new_invoice)?;
+        self.connection.transaction(|connection| {
+            let mut repository = InvoiceRepository::new(connection);
+            repository.find_in(id, InvoiceStatus::Draft)?;
+            CounterpartyRepository::new(repository.connection).find(new_invoice.counterparty_id)?;
+            diesel::update(invoices::table.find(id))
+                .set((
+                    invoices::counterparty_id.eq(new_invoice.counterparty_id),
+                    invoices::issue_date.eq(new_invoice.issue_date),
+                    invoices::due_date.eq(new_invoice.due_date),
+                    invoices::notes.eq(&new_invoice.notes),
+                    invoices::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(repository.connection)?;
+            diesel::delete(invoice_lines::table.filter(invoice_lines::invoice_id.eq(id)))
+                .execute(repository.connection)?;
+            insert_lines(repository.connection, id, new_invoice)?;
+            repository.find(id)
+        })
+    }
+
+    /// Gives a draft the next number and posts the receivable on its issue
+    /// date: 売掛金 against 売上高, split by tax rate.
+    pub fn issue(&mut self, id: i32) -> Result<InvoiceWithLines> {
+        self.connection.transaction(|connection| {
+            let mut repository = InvoiceRepository::new(connection);
+            let existing = repository.find_in(id, InvoiceStatus::Draft)?;
+            let totals = repository.totals(&existing)?;
+            if totals.total == 0 {
+                return Err(Error::Validation(format!(
+                    "invoice {} has nothing to bill",
+                    id
+                )));
+            }
+            let client = CounterpartyRepository::new(repository.connection)
+                .find(existing.invoice.counterparty_id)?;
+            let prefix = BusinessProfileRepository::new(repository.connection)
+                .get()?
+                .invoice_prefix;
+            let sequence = invoices::table
+                .select(diesel::dsl::max(invoices::sequence))
+                .first::<Option<i32>>(repository.connection)?
+                .unwrap_or(0)
+                + 1;
+            let number = format!("{}{:06}", prefix, sequence);
+
+            let mut accounts = AccountRepository::new(repository.connection);
+            let (receivable, sales) = (
+                accounts.find_by_code(ACCOUNTS_RECEIVABLE)?.id,
+                accounts.find_by_code(SALES)?.id,
+            );
+            let mut lines =
+                vec![NewJournalLine::debit(receivable, totals.total).counterparty(client.id)];
+            lines.extend(
+                totals
+                    .rates
+                    .iter()
+                    .filter(|rate| rate.base > 0)
+                    .map(|rate| {
+                        NewJournalLine::credit(sales, rate.base)
+                            .tax(rate.tax_category)
+                            .excluding_tax()
+                            .counterparty(client.id)
+                    }),
+            );
+            let entry = JournalRepository::new(repository.connection).create(&NewJournalEntry {
+                entry_date: existing.invoice.issue_date,
+                memo: format!("請求書 {} {}", number, client.name),
+                lines,
+            })?;
+
+            diesel::update(invoices::table.find(id))
+                .set((
+                    invoices::sequence.eq(sequence),
+                    invoices::number.eq(&number),
+                    invoices::status.eq(InvoiceStatus::Issued),
+                    invoices::entry_id.eq(entry.entry.id),
+                    invoices::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(repository.connection)?;
+            repository.find(id)
+        })
+    }
+
+    /// Records a payment of an issued invoice into its account on its
+    /// date, and applies it to the receivable. Payments may be partial; the
+    /// invoice is paid once nothing is left.
+    pub fn pay(&mut self, id: i32, payment: &InvoicePayment) -> Result<InvoiceWithLines> {
+        self.connection.transaction(|connection| {
+            let mut repository = InvoiceRepository::new(connection);
+            let existing = repository.find_in(id, InvoiceStatus::Issued)?;
+            if payment.paid_on < existing.invoice.issue_date {
+                return Err(Error::Validation(format!(
+                    "invoice {} was issued on {}, after {}",
+                    id, existing.invoice.issue_date, payment.paid_on
+                )));
+            }
+            let item_line_id = repository.receivable_line(&existing)?;
+            let left = OpenItemRepository::new(repository.connection)
+                .find(item_line_id)?
+                .remaining;
+            let amount = payment.amount.unwrap_or(left);
+            if amount <= 0 || amount > left {
+                return Err(Error::Validation(format!(
+                    "invoice {} has {} left to pay, not {}",
+                    id, left, amount
+                )));
+            }
+            let mut accounts = AccountRepository::new(repository.connection);
+            let receivable = accounts.find_by_code(ACCOUNTS_RECEIVABLE)?.id;
+            let account_id = match payment.account_id {
+                Some(id) => id,
+                None => accounts.find_by_code(ORDINARY_DEPOSITS)?.id,
+            };
+            let number = existing.invoice.number.clone().unwrap_or_default();
+            let entry = JournalRepository::new(repository.connection).create(&NewJournalEntry {
+                entry_date: payment.paid_on,
+                memo: format!("入金 請求書 {}", number),
+                lines: vec![
+                    NewJournalLine::debit(account_id, amount),
+                    NewJournalLine::credit(receivable, amount)
+                        .counterparty(existing.invoice.counterparty_id),
+                ],
+            })?;
+            let payment_line_id = entry
+                .lines
+                .iter()
+                .find(|line| line.account_id == receivable)
+                .map(|line| line.id)
+                .ok_or_else(|| {
+                    Error::Integrity(format!("entry {} has no 売掛金", entry.entry.id))
+                })?;
+            OpenItemRepository::new(repository.connection).match_payment(&NewOpenItemMatch {
+                item_line_id,
+                payment_line_id,
+                amount: Some(amount),
+            })?;
+            repository.find(id)
+        })
+    }
+
+    /// Cancels a draft or an issued invoice with no payments applied,
+    /// voiding its receivable. The number of an issued invoice stays taken.
+    pub fn void(&mut self, id: i32) -> Result<InvoiceWithLines> {
+        self.connection.transaction(|connection| {
+            let mut repository = InvoiceRepository::new(connection);
+            let existing = repository.find(id)?;
+            match existing.invoice.status {
+                InvoiceStatus::Draft | InvoiceStatus::Issued => {}
+                status => {
+                    return Err(Error::Validation(format!(
+                        "invoice {} is {} and cannot be voided",
+                        id, status
+                    )))
+                }
+            }
+            if let Some(entry_id) = existing.invoice.entry_id {
+                let line_id = repository.receivable_line(&existing)?;
+                if OpenItemRepository::new(repository.connection)
+                    .find(line_id)?
+                    .matched
+                    > 0
+                {
+                    return Err(Error::Validation(format!(
+                        "invoice {} has payments applied to it",
+                        id
+                    )));
+                }
+                JournalRepository::new(repository.connection).void(entry_id)?;
+            }
+            diesel::update(invoices::table.find(id))
+                .set((
+                    invoices::status.eq(InvoiceStatus::Void),
+                    invoices::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(repository.connection)?;
+            repository.find(id)
+        })
+    }
+
+    /// The invoice as printed, with our details and the client's.
+    pub fn document(&mut self, id: i32) -> Result<InvoiceDocument> {
+        let InvoiceWithLines { invoice, lines } = self.find(id)?;
+        let issuer = BusinessProfileRepository::new(self.connection).get()?;
+        let client = CounterpartyRepository::new(self.connection).find(invoice.counterparty_id)?;
+        let rounding = TaxSettingsRepository::new(self.connection).get()?.rounding;
+        Ok(InvoiceDocument {
+            totals: totals(&lines, rounding, client.withholding),
+            issuer,
+            client,
+            invoice,
+            lines,
+        })
+    }
+
+    fn totals(&mut self, invoice: &InvoiceWithLines) -> Result<InvoiceTotals> {
+        let rounding = TaxSettingsRepository::new(self.connection).get()?.rounding;
+        let withholding = CounterpartyRepository::new(self.connection)
+            .find(invoice.invoice.counterparty_id)?
+            .withholding;
+        Ok(totals(&invoice.lines, rounding, withholding))
+    }
+
+    /// The 売掛金 line posted on issue.
+    fn receivable_line(&mut self, invoice: &InvoiceWithLines) -> Result<i32> {
+        let receivable = AccountRepository::new(self.connection)
+            .find_by_code(ACCOUNTS_RECEIVABLE)?
+            .id;
+        invoice
+            .invoice
+            .entry_id
+            .map(|entry_id| {
+                journal_lines::table
+                    .filter(journal_lines::entry_id.eq(entry_id))
+                    .filter(journal_lines::account_id.eq(receivable))
+                    .filter(journal_lines::side.eq(Side::Debit))
+                    .select(journal_lines::id)
+                    .first::<i32>(self.connection)
+                    .optional()
+            })
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| {
+                Error::Integrity(format!("invoice {} has no receivable", invoice.invoice.id))
+            })
+    }
+
+    fn find_in(&mut self, id: i32, status: InvoiceStatus) -> Result<InvoiceWithLines> {
+        let invoice = self.find(id)?;
+        match invoice.invoice.status == status {
+            true => Ok(invoice),
+            false => Err(Error::Validation(format!(
+                "invoice {} is {}, not {}",
+                id, invoice.invoice.status, status
+            ))),
+        }
+    }
+}
+
+fn validate(new_invoice: &NewInvoice) -> Result<()> {
+    if new_invoice.lines.is_empty() {
+        return Err(Error::Validation(
+            "an invoice needs at least one line".to_string(),
+        ));
+    }
+    if new_invoice.due_date < new_invoice.issue_date {
+        return Err(Error::Validation(format!(
+            "invoice is due before it is issued: {} < {}",
+            new_invoice.due_date, new_invoice.issue_date
+        )));
+    }
+    let mut total = 0i64;
+    for line in &new_invoice.lines {
+        if line.description.trim().is_empty() {
+            return Err(Error::Validation(
+                "invoice line description is required".to_string(),
+            ));
+        }
+        if line.quantity <= 0 || line.unit_price < 0 {
+            return Err(Error::Validation(format!(
+                "invalid quantity or unit price: {} x {}",
+                line.quantity, line.unit_price
+            )));
+        }
+        // Tax of up to 10% is added on top, so leave room for it.
+        total = (line.quantity as i64)
+            .checked_mul(line.unit_price)
+            .and_then(|amount| total.checked_add(amount))
+            .filter(|total| *total <= i64::MAX / 2)
+            .ok_or_else(|| Error::Validation("invoice amount overflow".to_string()))?;
+    }
+    Ok(())
+}
+
+fn insert_lines(
+    connection: &mut SqliteConnection,
+    invoice_id: i32,
+    new_invoice: &NewInvoice,
+) -> Result<()> {
+    let rows = new_invoice
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            (
+                invoice_lines::invoice_id.eq(invoice_id),
+                invoice_lines::line_no.eq(i as i32 + 1),
+                invoice_lines::description.eq(&line.description),
+                invoice_lines::quantity.eq(line.quantity),
+                invoice_lines::unit_price.eq(line.unit_price),
+                invoice_lines::tax_category.eq(line.tax_category),
+            )
+        })
+        .collect::<Vec<_>>();
+    insert_into(invoice_lines::table)
+        .values(&rows)
+        .execute(connection)?;
+    Ok(())
+}
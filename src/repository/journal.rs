@@ -0,0 +1,537 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+
+use crate::error::{Error, Result};
+use crate::models::account_codes::{INPUT_TAX, OUTPUT_TAX};
+use crate::models::{
+    Account, AccountType, EntryKind, JournalEntry, JournalEntryWithLines, JournalLine,
+    NewJournalEntry, NewJournalLine, Side, TaxCategory, TaxMethod, TaxSettings, Withholding,
+};
+use crate::repository::fiscal_year::ensure_open;
+use crate::repository::{AccountRepository, TaxSettingsRepository};
+use crate::schema::{accounts, counterparties, journal_entries, journal_lines};
+use crate::withholding::withholding_tax;
+
+/// Conditions for listing journal entries. Dates are inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct JournalQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Only entries with at least one line against this account.
+    pub account_id: Option<i32>,
+    pub include_voided: bool,
+}
+
+pub struct JournalRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> JournalRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        JournalRepository { connection }
+    }
+
+    /// Records a balanced entry. Nothing is written unless every line is
+    /// valid, the debit and credit totals are equal and the date is not in a
+    /// closed fiscal year.
+    ///
+    /// Lines without a tax category take the account's. Under 税抜経理 the
+    /// tax on each taxable line is moved onto a 仮受消費税 line (revenue
+    /// accounts) or a 仮払消費税 line (everything else) right after it; under
+    /// 税込経理 it is only recorded on the line. Totals are checked after
+    /// tax has been worked out, so lines entered before tax balance against
+    /// their gross counterpart.
+    ///
+    /// Remuneration exchanged with a counterparty that has a withholding
+    /// rule has income tax withheld: the largest asset or liability line on
+    /// the other side (the payment, payable or receivable) is reduced by the
+    /// tax, which goes onto a 預り金 line for payments or a 事業主貸 line for
+    /// receipts. Entries that already have such a line for the counterparty
+    /// are taken as withheld by hand.
+    pub fn create(&mut self, new_entry: &NewJournalEntry) -> Result<JournalEntryWithLines> {
+        self.create_entry(new_entry, true)
+    }
+
+    /// Creates an entry for a transaction taken from a bank or card
+    /// statement. The statement line records what actually moved, so no
+    /// income tax is withheld from it; tax withheld by the payer has to be
+    /// entered on its own line.
+    pub(crate) fn create_from_statement(
+        &mut self,
+        new_entry: &NewJournalEntry,
+    ) -> Result<JournalEntryWithLines> {
+        self.create_entry(new_entry, false)
+    }
+
+    fn create_entry(
+        &mut self,
+        new_entry: &NewJournalEntry,
+        withhold: bool,
+    ) -> Result<JournalEntryWithLines> {
+        self.connection.transaction(|connection| {
+            check_shape(&new_entry.lines)?;
+            let accounts = check_accounts(connection, &new_entry.lines)?;
+            let rules = check_counterparties(connection, &new_entry.lines)?;
+            let settings = TaxSettingsRepository::new(connection).get()?;
+            let mut lines = apply_tax(connection, &new_entry.lines, &accounts, &settings)?;
+            if withhold {
+                apply_withholding(connection, &mut lines, &accounts, &rules)?;
+            }
+            JournalRepository::new(connection).insert(new_entry, EntryKind::Regular, &lines)
+        })
+    }
+
+    /// Checks that `lines` would make a valid entry as [`create`](Self::create)
+    /// works them out, without recording anything. The date is not looked
+    /// at, so a closed fiscal year is only found when the entry is created.
+    pub fn check(&mut self, lines: &[NewJournalLine]) -> Result<()> {
+        check_shape(lines)?;
+        let accounts = check_accounts(self.connection, lines)?;
+        let rules = check_counterparties(self.connection, lines)?;
+        let settings = TaxSettingsRepository::new(self.connection).get()?;
+        let mut taxed = apply_tax(self.connection, lines, &accounts, &settings)?;
+        apply_withholding(self.connection, &mut taxed, &accounts, &rules)?;
+        check_balance(taxed.iter().map(|line| (line.side, line.amount)))
+    }
+
+    /// Records an entry generated by the application itself, such as a
+    /// closing entry. Inactive accounts are accepted because balances left
+    /// on them still have to be carried forward, and no tax is split off.
+    pub(crate) fn post(
+        &mut self,
+        new_entry: &NewJournalEntry,
+        kind: EntryKind,
+    ) -> Result<JournalEntryWithLines> {
+        check_shape(&new_entry.lines)?;
+        let lines = new_entry
+            .lines
+            .iter()
+            .map(TaxedLine::from)
+            .collect::<Vec<_>>();
+        self.insert(new_entry, kind, &lines)
+    }
+
+    fn insert(
+        &mut self,
+        new_entry: &NewJournalEntry,
+        kind: EntryKind,
+        lines: &[TaxedLine],
+    ) -> Result<JournalEntryWithLines> {
+        self.connection.transaction(|connection| {
+            check_balance(lines.iter().map(|line| (line.side, line.amount)))?;
+            ensure_open(connection, new_entry.entry_date)?;
+
+            let entry = insert_into(journal_entries::table)
+                .values((
+                    journal_entries::entry_date.eq(new_entry.entry_date),
+                    journal_entries::memo.eq(&new_entry.memo),
+                    journal_entries::kind.eq(kind),
+                ))
+                .returning(JournalEntry::as_returning())
+                .get_result(connection)?;
+
+            let mut inserted = Vec::with_capacity(lines.len());
+            for (line_no, line) in (1..).zip(lines) {
+                inserted.push(
+                    insert_into(journal_lines::table)
+                        .values((
+                            journal_lines::entry_id.eq(entry.id),
+                            journal_lines::line_no.eq(line_no),
+                            journal_lines::account_id.eq(line.account_id),
+                            journal_lines::side.eq(line.side),
+                            journal_lines::amount.eq(line.amount),
+                            journal_lines::description.eq(&line.description),
+                            journal_lines::tax_category.eq(line.tax_category),
+                            journal_lines::tax_amount.eq(line.tax_amount),
+                            journal_lines::tax_included.eq(line.tax_included),
+                            journal_lines::counterparty_id.eq(line.counterparty_id),
+                        ))
+                        .returning(JournalLine::as_returning())
+                        .get_result(connection)?,
+                );
+            }
+            Ok(JournalEntryWithLines {
+                entry,
+                lines: inserted,
+            })
+        })
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<JournalEntryWithLines> {
+        let entry = journal_entries::table
+            .find(id)
+            .select(JournalEntry::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("journal entry {}", id)))?;
+        let lines = JournalLine::belonging_to(&entry)
+            .select(JournalLine::as_select())
+            .order_by(journal_lines::line_no)
+            .load(self.connection)?;
+        Ok(JournalEntryWithLines { entry, lines })
+    }
+
+    /// Lists entries ordered by date, then by id.
+    pub fn list(&mut self, query: &JournalQuery) -> Result<Vec<JournalEntryWithLines>> {
+        let mut entries = journal_entries::table
+            .select(JournalEntry::as_select())
+            .into_boxed();
+        if let Some(from) = query.from {
+            entries = entries.filter(journal_entries::entry_date.ge(from));
+        }
+        if let Some(to) = query.to {
+            entries = entries.filter(journal_entries::entry_date.le(to));
+        }
+        if let Some(account_id) = query.account_id {
+            entries = entries.filter(
+                journal_entries::id.eq_any(
+                    journal_lines::table
+                        .filter(journal_lines::account_id.eq(account_id))
+                        .select(journal_lines::entry_id),
+                ),
+            );
+        }
+        if !query.include_voided {
+            entries = entries.filter(journal_entries::voided_at.is_null());
+        }
+        let entries = entries
+            .order_by((journal_entries::entry_date, journal_entries::id))
+            .load(self.connection)?;
+
+        let lines = JournalLine::belonging_to(&entries)
+            .select(JournalLine::as_select())
+            .order_by(journal_lines::line_no)
+            .load(self.connection)?
+            .grouped_by(&entries);
+        Ok(entries
+            .into_iter()
+            .zip(lines)
+            .map(|(entry, lines)| JournalEntryWithLines { entry, lines })
+            .collect())
+    }
+
+    /// Marks an entry as void. Voided entries stay in the database for the
+    /// audit trail but no longer count towards balances.
+    pub fn void(&mut self, id: i32) -> Result<JournalEntryWithLines> {
+        self.connection.transaction(|connection| {
+            let mut repository = JournalRepository::new(connection);
+            let existing = repository.find(id)?;
+            if existing.entry.voided_at.is_some() {
+                return Err(Error::Validation(format!(
+                    "journal entry {} is already void",
+                    id
+                )));
+            }
+            if existing.entry.kind != EntryKind::Regular {
+                return Err(Error::Validation(format!(
+                    "journal entry {} is a {} entry; reopen its fiscal year instead",
+                    id, existing.entry.kind
+                )));
+            }
+            ensure_open(repository.connection, existing.entry.entry_date)?;
+            let now = Utc::now().naive_utc();
+            diesel::update(journal_entries::table.find(id))
+                .set((
+                    journal_entries::voided_at.eq(now),
+                    journal_entries::updated_at.eq(now),
+                ))
+                .execute(repository.connection)?;
+            repository.find(id)
+        })
+    }
+}
+
+/// A line as it will be stored, with its tax worked out. Converting a
+/// [`NewJournalLine`] directly gives an untaxed line.
+struct TaxedLine {
+    account_id: i32,
+    side: Side,
+    amount: i64,
+    description: String,
+    tax_category: TaxCategory,
+    tax_amount: i64,
+    tax_included: bool,
+    counterparty_id: Option<i32>,
+}
+
+impl TaxedLine {
+    fn gross_amount(&self) -> i64 {
+        match self.tax_included {
+            true => self.amount,
+            false => self.amount + self.tax_amount,
+        }
+    }
+}
+
+impl From<&NewJournalLine> for TaxedLine {
+    fn from(line: &NewJournalLine) -> Self {
+        TaxedLine {
+            account_id: line.account_id,
+            side: line.side,
+            amount: line.amount,
+            description: line.description.clone(),
+            tax_category: line.tax_category.unwrap_or(TaxCategory::OutOfScope),
+            tax_amount: 0,
+            tax_included: false,
+            counterparty_id: line.counterparty_id,
+        }
+    }
+}
+
+/// Totals the lines by side. Fails on overflow rather than wrapping.
+pub fn totals(lines: &[NewJournalLine]) -> Result<(i64, i64)> {
+    sum_sides(lines.iter().map(|line| (line.side, line.amount)))
+}
+
+fn sum_sides(lines: impl Iterator<Item = (Side, i64)>) -> Result<(i64, i64)> {
+    let (mut debit, mut credit) = (0i64, 0i64);
+    for (side, amount) in lines {
+        let total = match side {
+            Side::Debit => &mut debit,
+            Side::Credit => &mut credit,
+        };
+        *total = total.checked_add(amount).ok_or_else(overflow)?;
+    }
+    Ok((debit, credit))
+}
+
+fn overflow() -> Error {
+    Error::Validation("journal entry amount overflow".to_string())
+}
+
+fn check_shape(lines: &[NewJournalLine]) -> Result<()> {
+    if lines.len() < 2 {
+        return Err(Error::Validation(
+            "a journal entry needs at least two lines".to_string(),
+        ));
+    }
+    if let Some(line) = lines.iter().find(|line| line.amount <= 0) {
+        return Err(Error::Validation(format!(
+            "line amounts must be positive, got {}",
+            line.amount
+        )));
+    }
+    Ok(())
+}
+
+fn check_balance(lines: impl Iterator<Item = (Side, i64)>) -> Result<()> {
+    let (debit, credit) = sum_sides(lines)?;
+    if debit == 0 || credit == 0 || debit != credit {
+        return Err(Error::Unbalanced { debit, credit });
+    }
+    Ok(())
+}
+
+/// Loads the accounts of the lines, all of which must exist and be active.
+fn check_accounts(
+    connection: &mut SqliteConnection,
+    lines: &[NewJournalLine],
+) -> Result<HashMap<i32, Account>> {
+    let ids = lines.iter().map(|line| line.account_id).collect::<Vec<_>>();
+    let found = accounts::table
+        .filter(accounts::id.eq_any(&ids))
+        .select(Account::as_select())
+        .load(connection)?
+        .into_iter()
+        .map(|account| (account.id, account))
+        .collect::<HashMap<_, _>>();
+    for id in ids {
+        match found.get(&id) {
+            None => return Err(Error::NotFound(format!("account {}", id))),
+            Some(account) if !account.active => {
+                return Err(Error::Validation(format!("account {} is inactive", id)));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(found)
+}
+
+/// Checks that the counterparties of the lines exist and returns their
+/// withholding rules.
+fn check_counterparties(
+    connection: &mut SqliteConnection,
+    lines: &[NewJournalLine],
+) -> Result<HashMap<i32, Withholding>> {
+    let ids = lines
+        .iter()
+        .filter_map(|line| line.counterparty_id)
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let found = counterparties::table
+        .filter(counterparties::id.eq_any(&ids))
+        .select((counterparties::id, counterparties::withholding))
+        .load::<(i32, Withholding)>(connection)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    match ids.into_iter().find(|id| !found.contains_key(id)) {
+        Some(id) => Err(Error::NotFound(format!("counterparty {}", id))),
+        None => Ok(found),
+    }
+}
+
+fn apply_tax(
+    connection: &mut SqliteConnection,
+    lines: &[NewJournalLine],
+    accounts: &HashMap<i32, Account>,
+    settings: &TaxSettings,
+) -> Result<Vec<TaxedLine>> {
+    let mut tax_accounts = None;
+    let mut taxed = Vec::with_capacity(lines.len());
+    for line in lines {
+        let account = &accounts[&line.account_id];
+        let tax_category = line.tax_category.unwrap_or(account.tax_category);
+        let rate = tax_category.rate() as i128;
+        let mut taxed_line = TaxedLine {
+            tax_category,
+            ..TaxedLine::from(line)
+        };
+        if rate == 0 {
+            taxed.push(taxed_line);
+            continue;
+        }
+
+        let amount = line.amount as i128;
+        let (gross, tax) = match line.tax_included {
+            true => (
+                line.amount,
+                settings.rounding.divide(amount * rate, 100 + rate),
+            ),
+            false => {
+                let tax = settings.rounding.divide(amount * rate, 100);
+                (line.amount.checked_add(tax).ok_or_else(overflow)?, tax)
+            }
+        };
+        taxed_line.tax_amount = tax;
+        match settings.method {
+            TaxMethod::Inclusive => {
+                taxed_line.amount = gross;
+                taxed_line.tax_included = true;
+                taxed.push(taxed_line);
+            }
+            TaxMethod::Exclusive => {
+                taxed_line.amount = gross - tax;
+                taxed_line.tax_included = false;
+                let description = taxed_line.description.clone();
+                taxed.push(taxed_line);
+                if tax == 0 {
+                    continue;
+                }
+                let (input, output) = match tax_accounts {
+                    Some(ids) => ids,
+                    None => {
+                        let mut repository = AccountRepository::new(connection);
+                        let ids = (
+                            repository.find_by_code(INPUT_TAX)?.id,
+                            repository.find_by_code(OUTPUT_TAX)?.id,
+                        );
+                        *tax_accounts.insert(ids)
+                    }
+                };
+                taxed.push(TaxedLine {
+                    account_id: match account.account_type {
+                        AccountType::Revenue => output,
+                        _ => input,
+                    },
+                    side: line.side,
+                    amount: tax,
+                    description,
+                    tax_category: TaxCategory::OutOfScope,
+                    tax_amount: 0,
+                    tax_included: false,
+                    counterparty_id: line.counterparty_id,
+                });
+            }
+        }
+    }
+    Ok(taxed)
+}
+
+fn apply_withholding(
+    connection: &mut SqliteConnection,
+    lines: &mut Vec<TaxedLine>,
+    accounts: &HashMap<i32, Account>,
+    rules: &HashMap<i32, Withholding>,
+) -> Result<()> {
+    let mut counterparty_ids = rules.keys().copied().collect::<Vec<_>>();
+    counterparty_ids.sort_unstable();
+    for counterparty_id in counterparty_ids {
+        let rule = rules[&counterparty_id];
+        let (Some((account_type, side)), Some(code)) = (rule.remuneration(), rule.account_code())
+        else {
+            continue;
+        };
+        let tax_account = AccountRepository::new(connection).find_by_code(code)?.id;
+        let ours = |line: &&TaxedLine| line.counterparty_id == Some(counterparty_id);
+        if lines
+            .iter()
+            .filter(ours)
+            .any(|line| line.account_id == tax_account)
+        {
+            continue;
+        }
+        let remuneration = lines
+            .iter()
+            .filter(ours)
+            .filter(|line| {
+                accounts
+                    .get(&line.account_id)
+                    .is_some_and(|account| account.account_type == account_type)
+            })
+            .map(|line| {
+                let gross = line.gross_amount();
+                if line.side == side {
+                    gross
+                } else {
+                    -gross
+                }
+            })
+            .sum::<i64>();
+        let tax = withholding_tax(remuneration);
+        if tax == 0 {
+            continue;
+        }
+
+        // The payment or balance settling the remuneration, preferring one
+        // tagged with the counterparty.
+        let settlement = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.side != side && line.account_id != tax_account)
+            .filter(|(_, line)| {
+                accounts.get(&line.account_id).is_some_and(|account| {
+                    matches!(
+                        account.account_type,
+                        AccountType::Asset | AccountType::Liability
+                    )
+                })
+            })
+            .max_by_key(|(_, line)| (line.counterparty_id == Some(counterparty_id), line.amount))
+            .map(|(i, _)| i);
+        let Some(settlement) = settlement.filter(|&i| lines[i].amount > tax) else {
+            return Err(Error::Validation(format!(
+                "no payment or balance line to withhold {} yen of income tax from",
+                tax
+            )));
+        };
+        lines[settlement].amount -= tax;
+        lines.insert(
+            settlement + 1,
+            TaxedLine {
+                account_id: tax_account,
+                side: lines[settlement].side,
+                amount: tax,
+                description: "源泉所得税".to_string(),
+                tax_category: TaxCategory::OutOfScope,
+                tax_amount: 0,
+                tax_included: false,
+                counterparty_id: Some(counterparty_id),
+            },
+        );
+    }
+    Ok(())
+}
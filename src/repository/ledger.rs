@@ -0,0 +1,312 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::NaiveDate;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::models::{Account, EntryKind, Side};
+use crate::repository::AccountRepository;
+use crate::schema::{accounts, journal_entries, journal_lines};
+
+sql_function! {
+    /// `SUM` over whole-yen amounts. Diesel's generic `sum` widens `BigInt`
+    /// to `Numeric`, but yen totals comfortably fit in an `i64`.
+    #[aggregate]
+    #[sql_name = "SUM"]
+    fn sum_yen(expr: BigInt) -> Nullable<BigInt>;
+}
+
+/// Counterpart label used when an entry has more than one other account.
+pub const MULTIPLE_COUNTERPARTS: &str = "諸口";
+
+/// Debit and credit totals of posted lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    pub debit: i64,
+    pub credit: i64,
+    pub lines: i64,
+}
+
+impl Totals {
+    fn add(&mut self, side: Side, amount: i64, lines: i64) {
+        match side {
+            Side::Debit => self.debit += amount,
+            Side::Credit => self.credit += amount,
+        }
+        self.lines += lines;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LedgerLine {
+    pub entry_id: i32,
+    pub line_no: i32,
+    pub entry_date: NaiveDate,
+    pub memo: String,
+    pub description: String,
+    /// The other account of the entry, or 諸口 when there are several.
+    pub counterpart: String,
+    pub debit: i64,
+    pub credit: i64,
+    /// Running balance after this line, positive on the normal side.
+    pub balance: i64,
+}
+
+/// General ledger (総勘定元帳) of one account over a date range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Ledger {
+    pub account: Account,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Balance carried forward from everything before `from`.
+    pub opening_balance: i64,
+    pub lines: Vec<LedgerLine>,
+    pub debit_total: i64,
+    pub credit_total: i64,
+    pub closing_balance: i64,
+}
+
+/// Balance queries over posted journal lines. Voided entries are ignored
+/// everywhere, and so are closing entries: a fiscal year's balances restart
+/// from its opening entry instead.
+pub struct LedgerRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> LedgerRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        LedgerRepository { connection }
+    }
+
+    /// Totals of regular entries for one account between `from` and `to`
+    /// (both inclusive, either open-ended).
+    pub fn totals(
+        &mut self,
+        account_id: i32,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Totals> {
+        let totals = self.sum_lines(Some(account_id), EntryKind::Regular, from, to)?;
+        Ok(totals.get(&account_id).copied().unwrap_or_default())
+    }
+
+    /// Totals of regular entries for every account with lines in the range.
+    pub fn totals_by_account(
+        &mut self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<HashMap<i32, Totals>> {
+        self.sum_lines(None, EntryKind::Regular, from, to)
+    }
+
+    /// Totals brought into `date` for every account: the opening entries of
+    /// the current fiscal year, or all history if there are none, plus
+    /// regular entries up to the day before.
+    pub fn balances_before(&mut self, date: NaiveDate) -> Result<HashMap<i32, Totals>> {
+        self.carried(None, date, date.pred_opt())
+    }
+
+    /// Totals at the end of `date` for every account. Closing entries never
+    /// count, so balances at a year end are the figures before closing.
+    pub fn balances_as_of(&mut self, date: NaiveDate) -> Result<HashMap<i32, Totals>> {
+        self.carried(None, date, Some(date))
+    }
+
+    /// Balance of the account at the end of `date`, positive on the
+    /// account's normal side.
+    pub fn balance_as_of(&mut self, account_id: i32, date: NaiveDate) -> Result<i64> {
+        let account = AccountRepository::new(self.connection).find(account_id)?;
+        let totals = self.carried(Some(account_id), date, Some(date))?;
+        let totals = totals.get(&account_id).copied().unwrap_or_default();
+        Ok(account.account_type.balance(totals.debit, totals.credit))
+    }
+
+    /// Balance carried into `date`. On the first day of a fiscal year this
+    /// is the balance brought forward by its opening entry.
+    pub fn opening_balance(&mut self, account_id: i32, date: NaiveDate) -> Result<i64> {
+        let account = AccountRepository::new(self.connection).find(account_id)?;
+        let totals = self.carried(Some(account_id), date, date.pred_opt())?;
+        let totals = totals.get(&account_id).copied().unwrap_or_default();
+        Ok(account.account_type.balance(totals.debit, totals.credit))
+    }
+
+    /// Lines of one account between `from` and `to` with running balances.
+    pub fn ledger(&mut self, account_id: i32, from: NaiveDate, to: NaiveDate) -> Result<Ledger> {
+        let account = AccountRepository::new(self.connection).find(account_id)?;
+        let opening_balance = self.opening_balance(account_id, from)?;
+
+        let rows = journal_lines::table
+            .inner_join(journal_entries::table)
+            .filter(journal_lines::account_id.eq(account_id))
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::kind.eq(EntryKind::Regular))
+            .filter(journal_entries::entry_date.between(from, to))
+            .order_by((
+                journal_entries::entry_date,
+                journal_entries::id,
+                journal_lines::line_no,
+            ))
+            .select((
+                journal_lines::entry_id,
+                journal_lines::line_no,
+                journal_entries::entry_date,
+                journal_entries::memo,
+                journal_lines::description,
+                journal_lines::side,
+                journal_lines::amount,
+            ))
+            .load::<(i32, i32, NaiveDate, String, String, Side, i64)>(self.connection)?;
+
+        let entry_ids = rows.iter().map(|row| row.0).collect::<BTreeSet<_>>();
+        let counterparts = self.counterparts(account_id, &entry_ids)?;
+
+        let mut balance = opening_balance;
+        let (mut debit_total, mut credit_total) = (0, 0);
+        let lines = rows
+            .into_iter()
+            .map(
+                |(entry_id, line_no, entry_date, memo, description, side, amount)| {
+                    let (debit, credit) = match side {
+                        Side::Debit => (amount, 0),
+                        Side::Credit => (0, amount),
+                    };
+                    debit_total += debit;
+                    credit_total += credit;
+                    balance += account.account_type.balance(debit, credit);
+                    LedgerLine {
+                        entry_id,
+                        line_no,
+                        entry_date,
+                        memo,
+                        description,
+                        counterpart: counterparts.get(&entry_id).cloned().unwrap_or_default(),
+                        debit,
+                        credit,
+                        balance,
+                    }
+                },
+            )
+            .collect();
+
+        Ok(Ledger {
+            account,
+            from,
+            to,
+            opening_balance,
+            lines,
+            debit_total,
+            credit_total,
+            closing_balance: balance,
+        })
+    }
+
+    /// Opening entries on the latest opening date on or before `date`, plus
+    /// regular entries from that date through `through`.
+    fn carried(
+        &mut self,
+        account_id: Option<i32>,
+        date: NaiveDate,
+        through: Option<NaiveDate>,
+    ) -> Result<HashMap<i32, Totals>> {
+        let opened = journal_entries::table
+            .filter(journal_entries::kind.eq(EntryKind::Opening))
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::entry_date.le(date))
+            .select(diesel::dsl::max(journal_entries::entry_date))
+            .first::<Option<NaiveDate>>(self.connection)?;
+
+        let mut totals = match opened {
+            Some(opened) => {
+                self.sum_lines(account_id, EntryKind::Opening, Some(opened), Some(opened))?
+            }
+            None => HashMap::new(),
+        };
+        if let Some(through) = through {
+            for (id, regular) in
+                self.sum_lines(account_id, EntryKind::Regular, opened, Some(through))?
+            {
+                let entry = totals.entry(id).or_default();
+                entry.debit += regular.debit;
+                entry.credit += regular.credit;
+                entry.lines += regular.lines;
+            }
+        }
+        Ok(totals)
+    }
+
+    fn sum_lines(
+        &mut self,
+        account_id: Option<i32>,
+        kind: EntryKind,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<HashMap<i32, Totals>> {
+        let mut query = journal_lines::table
+            .inner_join(journal_entries::table)
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::kind.eq(kind))
+            .group_by((journal_lines::account_id, journal_lines::side))
+            .select((
+                journal_lines::account_id,
+                journal_lines::side,
+                sum_yen(journal_lines::amount),
+                count_star(),
+            ))
+            .into_boxed();
+        if let Some(account_id) = account_id {
+            query = query.filter(journal_lines::account_id.eq(account_id));
+        }
+        if let Some(from) = from {
+            query = query.filter(journal_entries::entry_date.ge(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(journal_entries::entry_date.le(to));
+        }
+
+        let mut totals = HashMap::<i32, Totals>::new();
+        for (account_id, side, amount, lines) in
+            query.load::<(i32, Side, Option<i64>, i64)>(self.connection)?
+        {
+            totals
+                .entry(account_id)
+                .or_default()
+                .add(side, amount.unwrap_or(0), lines);
+        }
+        Ok(totals)
+    }
+
+    /// Name of the other account of each entry, or 諸口 if there are several.
+    fn counterparts(
+        &mut self,
+        account_id: i32,
+        entry_ids: &BTreeSet<i32>,
+    ) -> Result<HashMap<i32, String>> {
+        let mut names = HashMap::<i32, BTreeSet<String>>::new();
+        let ids = entry_ids.iter().copied().collect::<Vec<_>>();
+        // Stay under SQLite's bound-parameter limit for long ledgers.
+        for chunk in ids.chunks(500) {
+            let rows = journal_lines::table
+                .inner_join(accounts::table)
+                .filter(journal_lines::entry_id.eq_any(chunk))
+                .filter(journal_lines::account_id.ne(account_id))
+                .select((journal_lines::entry_id, accounts::name))
+                .load::<(i32, String)>(self.connection)?;
+            for (entry_id, name) in rows {
+                names.entry(entry_id).or_default().insert(name);
+            }
+        }
+        Ok(names
+            .into_iter()
+            .map(|(entry_id, names)| {
+                let name = match names.len() {
+                    1 => names.into_iter().next().unwrap(),
+                    _ => MULTIPLE_COUNTERPARTS.to_string(),
+                };
+                (entry_id, name)
+            })
+            .collect())
+    }
+}
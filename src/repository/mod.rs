@@ -0,0 +1,36 @@
+//! Data access for the application tables. Each repository borrows a
+//! connection and returns the crate [`Error`](crate::Error) type.
+
+pub mod account;
+pub mod apportionment;
+pub mod attachment;
+pub mod business_profile;
+pub mod counterparty;
+pub mod fiscal_year;
+pub mod fixed_asset;
+pub mod import;
+pub mod invoice;
+pub mod journal;
+pub mod ledger;
+pub mod open_item;
+pub mod post;
+pub mod recurring_entry;
+pub mod return_line;
+pub mod tax_settings;
+
+pub use account::AccountRepository;
+pub use apportionment::{ApportionedAmount, ApportionmentRepository, PayeeAmount};
+pub use attachment::{AttachmentQuery, AttachmentRepository, AttachmentRevision};
+pub use business_profile::BusinessProfileRepository;
+pub use counterparty::CounterpartyRepository;
+pub use fiscal_year::FiscalYearRepository;
+pub use fixed_asset::{FixedAssetRepository, SmallAmountUsage};
+pub use import::{ImportRepository, ImportSummary, Proposal, StagedQuery, UndoneImport};
+pub use invoice::{InvoiceQuery, InvoiceRepository};
+pub use journal::{JournalQuery, JournalRepository};
+pub use ledger::{Ledger, LedgerLine, LedgerRepository, Totals};
+pub use open_item::{CounterpartyOpenItems, OpenItem, OpenItemQuery, OpenItemRepository};
+pub use post::{BulkItem, BulkStatus, PostFilter, PostRepository};
+pub use recurring_entry::{Occurrence, RecurringEntryRepository};
+pub use return_line::ReturnLineRepository;
+pub use tax_settings::TaxSettingsRepository;
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::models::{
+    EntryKind, InvoiceStatus, JournalLine, NewOpenItemMatch, OpenItemKind, OpenItemMatch,
+};
+use crate::repository::CounterpartyRepository;
+use crate::schema::{accounts, invoices, journal_entries, journal_lines, open_item_matches};
+
+/// Conditions for listing open items.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OpenItemQuery {
+    #[serde(default)]
+    pub kind: OpenItemKind,
+    pub counterparty_id: Option<i32>,
+    /// Only lines dated up to this day, and payments applied by then.
+    pub as_of: Option<NaiveDate>,
+}
+
+/// A line on a receivable or payable account: an item to be paid, or a
+/// payment to apply to items.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenItem {
+    pub line_id: i32,
+    pub entry_id: i32,
+    pub entry_date: NaiveDate,
+    pub account_id: i32,
+    pub counterparty_id: i32,
+    pub description: String,
+    /// For receivables posted by an invoice.
+    pub invoice_number: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub amount: i64,
+    /// Paid against the item, or applied to items for a payment.
+    pub matched: i64,
+    pub remaining: i64,
+}
+
+/// What is open with one counterparty.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CounterpartyOpenItems {
+    pub counterparty_id: i32,
+    pub name: String,
+    /// Items not paid in full, oldest first.
+    pub items: Vec<OpenItem>,
+    /// Payments not applied in full, oldest first.
+    pub payments: Vec<OpenItem>,
+    pub outstanding: i64,
+    pub unapplied: i64,
+    /// Outstanding less unapplied, the counterparty's balance on the
+    /// accounts.
+    pub balance: i64,
+}
+
+/// Receivables and payables per counterparty, and the payments applied to
+/// them.
+///
+/// Items are lines of regular entries with a counterparty on 売掛金 (debits)
+/// or on 買掛金 and 未払金 (credits); payments are lines on the other side
+/// of the same account with the same counterparty. Lines of voided entries
+/// and matches involving them are left out.
+pub struct OpenItemRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> OpenItemRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        OpenItemRepository { connection }
+    }
+
+    /// Open items and unapplied payments per counterparty, ordered by name.
+    /// Counterparties with nothing open are left out.
+    pub fn outstanding(&mut self, query: &OpenItemQuery) -> Result<Vec<CounterpartyOpenItems>> {
+        let (items, payments) = self.lines(query)?;
+        let mut ids = items
+            .iter()
+            .chain(&payments)
+            .filter(|line| line.remaining > 0)
+            .map(|line| line.counterparty_id)
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        let mut counterparties = CounterpartyRepository::new(self.connection)
+            .list()?
+            .into_iter()
+            .filter(|counterparty| ids.binary_search(&counterparty.id).is_ok())
+            .map(|counterparty| CounterpartyOpenItems {
+                counterparty_id: counterparty.id,
+                name: counterparty.name,
+                items: Vec::new(),
+                payments: Vec::new(),
+                outstanding: 0,
+                unapplied: 0,
+                balance: 0,
+            })
+            .collect::<Vec<_>>();
+        for open in &mut counterparties {
+            let ours = |line: &&OpenItem| {
+                line.counterparty_id == open.counterparty_id && line.remaining > 0
+            };
+            open.items = items.iter().filter(ours).cloned().collect();
+            open.payments = payments.iter().filter(ours).cloned().collect();
+            open.outstanding = open.items.iter().map(|item| item.remaining).sum();
+            open.unapplied = open.payments.iter().map(|item| item.remaining).sum();
+            open.balance = open.outstanding - open.unapplied;
+        }
+        Ok(counterparties)
+    }
+
+    /// An item or payment with what is left of it.
+    pub fn find(&mut self, line_id: i32) -> Result<OpenItem> {
+        let (kind, line) = self.find_line(line_id)?.ok_or_else(|| not_open(line_id))?;
+        let (items, payments) = self.lines(&OpenItemQuery {
+            kind,
+            counterparty_id: line.counterparty_id,
+            as_of: None,
+        })?;
+        items
+            .into_iter()
+            .chain(payments)
+            .find(|open| open.line_id == line_id)
+            .ok_or_else(|| not_open(line_id))
+    }
+
+    /// Applies `amount` of a payment to an item, by default as much as both
+    /// have left. A fully paid invoice is marked paid.
+    pub fn match_payment(&mut self, new_match: &NewOpenItemMatch) -> Result<OpenItemMatch> {
+        self.connection.transaction(|connection| {
+            let mut repository = OpenItemRepository::new(connection);
+            let item = repository.find_line(new_match.item_line_id)?;
+            let payment = repository.find_line(new_match.payment_line_id)?;
+            let (kind, item) = item.ok_or_else(|| not_open(new_match.item_line_id))?;
+            let (_, payment) = payment.ok_or_else(|| not_open(new_match.payment_line_id))?;
+            if item.side != kind.item_side() {
+                return Err(Error::Validation(format!(
+                    "line {} is a payment, not an item",
+                    item.id
+                )));
+            }
+            if payment.side == kind.item_side() {
+                return Err(Error::Validation(format!(
+                    "line {} is an item, not a payment",
+                    payment.id
+                )));
+            }
+            if (item.account_id, item.counterparty_id)
+                != (payment.account_id, payment.counterparty_id)
+            {
+                return Err(Error::Validation(format!(
+                    "lines {} and {} are not on the same account and counterparty",
+                    item.id, payment.id
+                )));
+            }
+
+            let (items, payments) = repository.lines(&OpenItemQuery {
+                kind,
+                counterparty_id: item.counterparty_id,
+                as_of: None,
+            })?;
+            let left = |lines: &[OpenItem], id: i32| {
+                lines
+                    .iter()
+                    .find(|line| line.line_id == id)
+                    .map_or(0, |line| line.remaining)
+            };
+            let (item_left, payment_left) = (left(&items, item.id), left(&payments, payment.id));
+            let amount = new_match.amount.unwrap_or(item_left.min(payment_left));
+            if amount <= 0 {
+                return Err(Error::Validation(match new_match.amount {
+                    Some(amount) => format!("amount must be positive: {}", amount),
+                    None => format!(
+                        "nothing left to match on lines {} and {}",
+                        item.id, payment.id
+                    ),
+                }));
+            }
+            if amount > item_left || amount > payment_left {
+                return Err(Error::Validation(format!(
+                    "{} is more than is left: {} on item {}, {} on payment {}",
+                    amount, item_left, item.id, payment_left, payment.id
+                )));
+            }
+
+            let existing = open_item_matches::table
+                .filter(open_item_matches::item_line_id.eq(item.id))
+                .filter(open_item_matches::payment_line_id.eq(payment.id))
+                .select(OpenItemMatch::as_select())
+                .first(repository.connection)
+                .optional()?;
+            let matched = match existing {
+                Some(existing) => diesel::update(open_item_matches::table.find(existing.id))
+                    .set(open_item_matches::amount.eq(existing.amount + amount))
+                    .returning(OpenItemMatch::as_returning())
+                    .get_result(repository.connection)?,
+                None => insert_into(open_item_matches::table)
+                    .values((
+                        open_item_matches::item_line_id.eq(item.id),
+                        open_item_matches::payment_line_id.eq(payment.id),
+                        open_item_matches::amount.eq(amount),
+                    ))
+                    .returning(OpenItemMatch::as_returning())
+                    .get_result(repository.connection)?,
+            };
+            repository.settle_invoice(item.entry_id, item.id)?;
+            Ok(matched)
+        })
+    }
+
+    /// Removes a match. An invoice it had settled is open again.
+    pub fn unmatch(&mut self, id: i32) -> Result<()> {
+        self.connection.transaction(|connection| {
+            let existing = open_item_matches::table
+                .find(id)
+                .select(OpenItemMatch::as_select())
+                .first(connection)
+                .optional()?
+                .ok_or_else(|| Error::NotFound(format!("open item match {}", id)))?;
+            diesel::delete(open_item_matches::table.find(id)).execute(connection)?;
+            let entry_id = journal_lines::table
+                .find(existing.item_line_id)
+                .select(journal_lines::entry_id)
+                .first(connection)?;
+            OpenItemRepository::new(connection).settle_invoice(entry_id, existing.item_line_id)
+        })
+    }
+
+    /// Applies unapplied payments to the oldest open items of the same
+    /// account and counterparty, first in first out.
+    pub fn auto_match(&mut self, query: &OpenItemQuery) -> Result<Vec<OpenItemMatch>> {
+        self.connection.transaction(|connection| {
+            let mut repository = OpenItemRepository::new(connection);
+            let (mut items, payments) = repository.lines(query)?;
+            let mut pairs = Vec::new();
+            for payment in payments {
+                let mut left = payment.remaining;
+                for item in items.iter_mut().filter(|item| {
+                    (item.account_id, item.counterparty_id)
+                        == (payment.account_id, payment.counterparty_id)
+                }) {
+                    if left == 0 {
+                        break;
+                    }
+                    let amount = left.min(item.remaining);
+                    if amount > 0 {
+                        pairs.push((item.line_id, payment.line_id, amount));
+                        item.remaining -= amount;
+                        left -= amount;
+                    }
+                }
+            }
+            pairs
+                .into_iter()
+                .map(|(item_line_id, payment_line_id, amount)| {
+                    repository.match_payment(&NewOpenItemMatch {
+                        item_line_id,
+                        payment_line_id,
+                        amount: Some(amount),
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Matches on a line, as item or as payment.
+    pub fn matches(&mut self, line_id: i32) -> Result<Vec<OpenItemMatch>> {
+        Ok(open_item_matches::table
+            .filter(
+                open_item_matches::item_line_id
+                    .eq(line_id)
+                    .or(open_item_matches::payment_line_id.eq(line_id)),
+            )
+            .select(OpenItemMatch::as_select())
+            .order_by(open_item_matches::id)
+            .load(self.connection)?)
+    }
+
+    /// Items and payments of `query`, settled ones included, each ordered
+    /// by date.
+    fn lines(&mut self, query: &OpenItemQuery) -> Result<(Vec<OpenItem>, Vec<OpenItem>)> {
+        let account_ids = accounts::table
+            .filter(accounts::code.eq_any(query.kind.account_codes()))
+            .select(accounts::id)
+            .load::<i32>(self.connection)?;
+        let mut select = journal_lines::table
+            .inner_join(journal_entries::table)
+            .filter(journal_lines::account_id.eq_any(&account_ids))
+            .filter(journal_lines::counterparty_id.is_not_null())
+            .filter(journal_entries::voided_at.is_null())
+            .filter(journal_entries::kind.eq(EntryKind::Regular))
+            .select((
+                JournalLine::as_select(),
+                journal_entries::entry_date,
+                journal_entries::memo,
+            ))
+            .into_boxed();
+        if let Some(counterparty_id) = query.counterparty_id {
+            select = select.filter(journal_lines::counterparty_id.eq(counterparty_id));
+        }
+        if let Some(as_of) = query.as_of {
+            select = select.filter(journal_entries::entry_date.le(as_of));
+        }
+        let rows = select
+            .order_by((journal_entries::entry_date, journal_lines::id))
+            .load::<(JournalLine, NaiveDate, String)>(self.connection)?;
+
+        let ids = rows.iter().map(|(line, _, _)| line.id).collect::<Vec<_>>();
+        let mut matched = HashMap::<i32, i64>::new();
+        for found in open_item_matches::table
+            .filter(open_item_matches::item_line_id.eq_any(&ids))
+            .filter(open_item_matches::payment_line_id.eq_any(&ids))
+            .select(OpenItemMatch::as_select())
+            .load(self.connection)?
+        {
+            *matched.entry(found.item_line_id).or_default() += found.amount;
+            *matched.entry(found.payment_line_id).or_default() += found.amount;
+        }
+        let entry_ids = rows
+            .iter()
+            .map(|(line, _, _)| line.entry_id)
+            .collect::<Vec<_>>();
+        let invoices = invoices::table
+            .filter(invoices::entry_id.eq_any(&entry_ids))
+            .select((invoices::entry_id, invoices::number, invoices::due_date))
+            .load::<(Option<i32>, Option<String>, NaiveDate)>(self.connection)?
+            .into_iter()
+            .filter_map(|(entry_id, number, due_date)| Some((entry_id?, (number, due_date))))
+            .collect::<HashMap<_, _>>();
+
+        let (mut items, mut payments) = (Vec::new(), Vec::new());
+        for (line, entry_date, memo) in rows {
+            let amount = line.gross_amount();
+            let matched = matched.get(&line.id).copied().unwrap_or(0);
+            let invoice = invoices.get(&line.entry_id);
+            let open = OpenItem {
+                line_id: line.id,
+                entry_id: line.entry_id,
+                entry_date,
+                account_id: line.account_id,
+                counterparty_id: line.counterparty_id.unwrap_or_default(),
+                description: match line.description.is_empty() {
+                    true => memo,
+                    false => line.description,
+                },
+                invoice_number: invoice.and_then(|(number, _)| number.clone()),
+                due_date: invoice.map(|(_, due_date)| *due_date),
+                amount,
+                matched,
+                remaining: amount - matched,
+            };
+            match line.side == query.kind.item_side() {
+                true => items.push(open),
+                false => payments.push(open),
+            }
+        }
+        Ok((items, payments))
+    }
+
+    /// The kind of a line that can take part in a match, or `None` for
+    /// lines that cannot.
+    fn find_line(&mut self, id: i32) -> Result<Option<(OpenItemKind, JournalLine)>> {
+        let (line, code, voided_at, kind) = journal_lines::table
+            .inner_join(accounts::table)
+            .inner_join(journal_entries::table)
+            .filter(journal_lines::id.eq(id))
+            .select((
+                JournalLine::as_select(),
+                accounts::code,
+                journal_entries::voided_at,
+                journal_entries::kind,
+            ))
+            .first::<(
+                JournalLine,
+                String,
+                Option<chrono::NaiveDateTime>,
+                EntryKind,
+            )>(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("journal line {}", id)))?;
+        if voided_at.is_some() || kind != EntryKind::Regular || line.counterparty_id.is_none() {
+            return Ok(None);
+        }
+        Ok(OpenItemKind::ALL
+            .iter()
+            .find(|kind| kind.account_codes().contains(&code.as_str()))
+            .map(|&kind| (kind, line)))
+    }
+
+    /// Marks the invoice that posted `entry_id` paid once its receivable
+    /// line is settled in full, by the latest payment applied to it, and
+    /// issued again when it no longer is.
+    fn settle_invoice(&mut self, entry_id: i32, line_id: i32) -> Result<()> {
+        let Some((invoice_id, status)) = invoices::table
+            .filter(invoices::entry_id.eq(entry_id))
+            .select((invoices::id, invoices::status))
+            .first::<(i32, InvoiceStatus)>(self.connection)
+            .optional()?
+        else {
+            return Ok(());
+        };
+        let amount = journal_lines::table
+            .find(line_id)
+            .select(JournalLine::as_select())
+            .first(self.connection)?
+            .gross_amount();
+        let payments = open_item_matches::table
+            .inner_join(
+                journal_lines::table.on(journal_lines::id.eq(open_item_matches::payment_line_id)),
+            )
+            .inner_join(journal_entries::table.on(journal_entries::id.eq(journal_lines::entry_id)))
+            .filter(open_item_matches::item_line_id.eq(line_id))
+            .filter(journal_entries::voided_at.is_null())
+            .select((
+                open_item_matches::amount,
+                journal_entries::id,
+                journal_entries::entry_date,
+            ))
+            .order_by((journal_entries::entry_date, journal_entries::id))
+            .load::<(i64, i32, NaiveDate)>(self.connection)?;
+        let paid = payments.iter().map(|(amount, _, _)| amount).sum::<i64>();
+        let changes = match (status, paid >= amount, payments.last()) {
+            (InvoiceStatus::Issued, true, Some(&(_, payment_entry_id, paid_on))) => {
+                (InvoiceStatus::Paid, Some(payment_entry_id), Some(paid_on))
+            }
+            (InvoiceStatus::Paid, false, _) => (InvoiceStatus::Issued, None, None),
+            _ => return Ok(()),
+        };
+        diesel::update(invoices::table.find(invoice_id))
+            .set((
+                invoices::status.eq(changes.0),
+                invoices::payment_entry_id.eq(changes.1),
+                invoices::paid_on.eq(changes.2),
+                invoices::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(self.connection)?;
+        Ok(())
+    }
+}
+
+fn not_open(line_id: i32) -> Error {
+    Error::Validation(format!(
+        "line {} is not on a receivable or payable account with a counterparty",
+        line_id
+    ))
+}
@@ -0,0 +1,299 @@
+use chrono::Utc;
+use diesel::{insert_into, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::models::{NewPost, Post, PostChanges};
+use crate::schema::{post_tags, posts};
+
+/// Upper bound on the number of posts a single bulk operation may touch.
+pub const BULK_LIMIT: usize = 1000;
+
+/// Ids bound per `IN (...)` list, kept well under SQLite's parameter limit.
+const BATCH_SIZE: usize = 200;
+
+/// Posts per multi-row `INSERT`; each binds the five columns of [`NewPost`].
+const INSERT_BATCH_SIZE: usize = BATCH_SIZE / 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkStatus {
+    Created,
+    Updated,
+    Deleted,
+    NotFound,
+}
+
+/// Outcome for one post of a bulk operation, in request order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BulkItem {
+    pub id: i32,
+    pub status: BulkStatus,
+}
+
+/// Selects posts for bulk deletion. At least one condition is required.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PostFilter {
+    pub ids: Option<Vec<i32>>,
+    pub category_id: Option<i32>,
+    pub author: Option<String>,
+    pub published: Option<bool>,
+}
+
+impl PostFilter {
+    fn is_empty(&self) -> bool {
+        self.ids.is_none()
+            && self.category_id.is_none()
+            && self.author.is_none()
+            && self.published.is_none()
+    }
+}
+
+fn check_limit(count: usize) -> Result<()> {
+    if count > BULK_LIMIT {
+        return Err(Error::Validation(format!(
+            "bulk operation affects {} posts; the limit is {}",
+            count, BULK_LIMIT
+        )));
+    }
+    Ok(())
+}
+
+pub struct PostRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> PostRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        PostRepository { connection }
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<Post> {
+        posts::table
+            .filter(posts::id.eq(id))
+            .select(Post::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("post {}", id)))
+    }
+
+    pub fn list(&mut self, published: Option<bool>, limit: i64, offset: i64) -> Result<Vec<Post>> {
+        let mut query = posts::table.select(Post::as_select()).into_boxed();
+        if let Some(published) = published {
+            query = query.filter(posts::published.eq(published));
+        }
+        Ok(query
+            .order_by(posts::id)
+            .limit(limit)
+            .offset(offset)
+            .load(self.connection)?)
+    }
+
+    pub fn create(&mut self, new_post: &NewPost) -> Result<Post> {
+        Ok(insert_into(posts::table)
+            .values(new_post)
+            .returning(Post::as_returning())
+            .get_result(self.connection)?)
+    }
+
+    /// Applies `changes` only if the stored row is still at
+    /// `expected_version`; otherwise returns [`Error::Conflict`] carrying the
+    /// current version so the caller can reload, merge and retry.
+    pub fn update(
+        &mut self,
+        id: i32,
+        expected_version: i32,
+        changes: &PostChanges,
+    ) -> Result<Post> {
+        self.connection.transaction(|connection| {
+            let updated = diesel::update(
+                posts::table
+                    .filter(posts::id.eq(id))
+                    .filter(posts::version.eq(expected_version)),
+            )
+            .set((
+                changes,
+                posts::version.eq(posts::version + 1),
+                posts::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .returning(Post::as_returning())
+            .get_result(connection)
+            .optional()?;
+
+            match updated {
+                Some(post) => Ok(post),
+                None => Err(PostRepository::new(connection).conflict(id, expected_version)),
+            }
+        })
+    }
+
+    /// Deletes the post, and its tags, if it is still at `expected_version`.
+    pub fn delete(&mut self, id: i32, expected_version: i32) -> Result<()> {
+        self.connection.transaction(|connection| {
+            diesel::delete(post_tags::table.filter(post_tags::post_id.eq(id)))
+                .execute(connection)?;
+            let deleted = diesel::delete(
+                posts::table
+                    .filter(posts::id.eq(id))
+                    .filter(posts::version.eq(expected_version)),
+            )
+            .execute(connection)?;
+
+            match deleted {
+                0 => Err(PostRepository::new(connection).conflict(id, expected_version)),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    /// Inserts all posts in one transaction, a batch of rows per statement.
+    /// Diesel cannot add `RETURNING` to a multi-row insert on SQLite, so the
+    /// new ids are read back afterwards: rowids are handed out above the
+    /// highest one in use, in the order rows are inserted.
+    pub fn bulk_create(&mut self, new_posts: &[NewPost]) -> Result<Vec<BulkItem>> {
+        check_limit(new_posts.len())?;
+        self.connection.transaction(|connection| {
+            let before = posts::table
+                .select(diesel::dsl::max(posts::id))
+                .first::<Option<i32>>(connection)?
+                .unwrap_or(0);
+            for chunk in new_posts.chunks(INSERT_BATCH_SIZE) {
+                insert_into(posts::table)
+                    .values(chunk)
+                    .execute(connection)?;
+            }
+            let ids = posts::table
+                .select(posts::id.assume_not_null())
+                .filter(posts::id.gt(before))
+                .order_by(posts::id)
+                .load::<i32>(connection)?;
+            Ok(ids
+                .into_iter()
+                .map(|id| BulkItem {
+                    id,
+                    status: BulkStatus::Created,
+                })
+                .collect())
+        })
+    }
+
+    pub fn bulk_set_published(&mut self, ids: &[i32], published: bool) -> Result<Vec<BulkItem>> {
+        let changes = PostChanges {
+            published: Some(published),
+            ..Default::default()
+        };
+        self.bulk_update(ids, &changes)
+    }
+
+    pub fn bulk_recategorize(
+        &mut self,
+        ids: &[i32],
+        category_id: Option<i32>,
+    ) -> Result<Vec<BulkItem>> {
+        let changes = PostChanges {
+            category_id: Some(category_id),
+            ..Default::default()
+        };
+        self.bulk_update(ids, &changes)
+    }
+
+    fn bulk_update(&mut self, ids: &[i32], changes: &PostChanges) -> Result<Vec<BulkItem>> {
+        check_limit(ids.len())?;
+        let now = Utc::now().naive_utc();
+        self.connection.transaction(|connection| {
+            let mut updated = Vec::new();
+            for chunk in ids.chunks(BATCH_SIZE) {
+                updated.extend(
+                    diesel::update(posts::table.filter(posts::id.eq_any(chunk)))
+                        .set((
+                            changes,
+                            posts::version.eq(posts::version + 1),
+                            posts::updated_at.eq(now),
+                        ))
+                        .returning(posts::id.assume_not_null())
+                        .get_results::<i32>(connection)?,
+                );
+            }
+            Ok(items_for(ids, &updated, BulkStatus::Updated))
+        })
+    }
+
+    /// Deletes every post matching `filter`. Fails without deleting anything
+    /// if more than [`BULK_LIMIT`] posts match.
+    pub fn bulk_delete(&mut self, filter: &PostFilter) -> Result<Vec<BulkItem>> {
+        if filter.is_empty() {
+            return Err(Error::Validation(
+                "bulk delete requires at least one filter".to_string(),
+            ));
+        }
+        if let Some(ids) = &filter.ids {
+            check_limit(ids.len())?;
+        }
+        self.connection.transaction(|connection| {
+            let mut query = posts::table
+                .select(posts::id.assume_not_null())
+                .into_boxed();
+            if let Some(ids) = &filter.ids {
+                query = query.filter(posts::id.eq_any(ids));
+            }
+            if let Some(category_id) = filter.category_id {
+                query = query.filter(posts::category_id.eq(category_id));
+            }
+            if let Some(author) = &filter.author {
+                query = query.filter(posts::author.eq(author));
+            }
+            if let Some(published) = filter.published {
+                query = query.filter(posts::published.eq(published));
+            }
+            let matched = query
+                .order_by(posts::id)
+                .limit(BULK_LIMIT as i64 + 1)
+                .load::<i32>(connection)?;
+            check_limit(matched.len())?;
+
+            for chunk in matched.chunks(BATCH_SIZE) {
+                diesel::delete(post_tags::table.filter(post_tags::post_id.eq_any(chunk)))
+                    .execute(connection)?;
+                diesel::delete(posts::table.filter(posts::id.eq_any(chunk))).execute(connection)?;
+            }
+            Ok(match &filter.ids {
+                Some(ids) => items_for(ids, &matched, BulkStatus::Deleted),
+                None => matched
+                    .into_iter()
+                    .map(|id| BulkItem {
+                        id,
+                        status: BulkStatus::Deleted,
+                    })
+                    .collect(),
+            })
+        })
+    }
+
+    fn conflict(&mut self, id: i32, expected: i32) -> Error {
+        match self.find(id) {
+            Ok(current) => Error::Conflict {
+                resource: "post",
+                id,
+                expected,
+                current: current.version,
+            },
+            Err(e) => e,
+        }
+    }
+}
+
+/// Reports `status` for each requested id that was affected and
+/// [`BulkStatus::NotFound`] for the rest, preserving request order.
+fn items_for(requested: &[i32], affected: &[i32], status: BulkStatus) -> Vec<BulkItem> {
+    requested
+        .iter()
+        .map(|&id| BulkItem {
+            id,
+            status: if affected.contains(&id) {
+                status
+            } else {
+                BulkStatus::NotFound
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,438 @@
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, Utc};
+use diesel::{insert_into, prelude::*};
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::models::{
+    NewJournalEntry, NewJournalLine, NewRecurringEntry, OccurrenceChanges, OccurrenceStatus,
+    RecurringEntry, RecurringEntryLine, RecurringEntryWithLines, RecurringOccurrence,
+};
+use crate::recurring::{check_schedule, due_dates, is_due};
+use crate::repository::JournalRepository;
+use crate::schema::{recurring_entries, recurring_entry_lines, recurring_occurrences};
+
+/// One occurrence of a recurring entry, as it will be or was posted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Occurrence {
+    pub recurring_entry_id: i32,
+    pub due_date: NaiveDate,
+    pub status: OccurrenceStatus,
+    /// The date the entry is posted on.
+    pub entry_date: NaiveDate,
+    /// The lines of the template, or those changed for this occurrence.
+    pub lines: Vec<NewJournalLine>,
+    /// Whether the date or the lines were changed for this occurrence.
+    pub modified: bool,
+    pub entry_id: Option<i32>,
+}
+
+/// Templates of entries that repeat, and their occurrences.
+///
+/// Occurrences fall due on the template's schedule and are posted by
+/// [`materialize`](Self::materialize). Each is posted once however often
+/// that runs, and one can be skipped or changed before it is posted.
+/// Changing a template only affects occurrences not yet posted.
+pub struct RecurringEntryRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> RecurringEntryRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        RecurringEntryRepository { connection }
+    }
+
+    /// Lists templates ordered by name.
+    pub fn list(&mut self) -> Result<Vec<RecurringEntryWithLines>> {
+        let entries = recurring_entries::table
+            .select(RecurringEntry::as_select())
+            .order_by((recurring_entries::name, recurring_entries::id))
+            .load(self.connection)?;
+        let lines = RecurringEntryLine::belonging_to(&entries)
+            .select(RecurringEntryLine::as_select())
+            .order_by(recurring_entry_lines::line_no)
+            .load(self.connection)?
+            .grouped_by(&entries);
+        Ok(entries
+            .into_iter()
+            .zip(lines)
+            .map(|(recurring_entry, lines)| RecurringEntryWithLines {
+                recurring_entry,
+                lines,
+            })
+            .collect())
+    }
+
+    pub fn find(&mut self, id: i32) -> Result<RecurringEntryWithLines> {
+        let recurring_entry = recurring_entries::table
+            .find(id)
+            .select(RecurringEntry::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("recurring entry {}", id)))?;
+        let lines = RecurringEntryLine::belonging_to(&recurring_entry)
+            .select(RecurringEntryLine::as_select())
+            .order_by(recurring_entry_lines::line_no)
+            .load(self.connection)?;
+        Ok(RecurringEntryWithLines {
+            recurring_entry,
+            lines,
+        })
+    }
+
+    /// Records a template. Its lines must make a valid journal entry.
+    pub fn create(&mut self, new_entry: &NewRecurringEntry) -> Result<RecurringEntryWithLines> {
+        self.connection.transaction(|connection| {
+            validate(connection, new_entry)?;
+            let entry = insert_into(recurring_entries::table)
+                .values((
+                    recurring_entries::name.eq(&new_entry.name),
+                    recurring_entries::memo.eq(&new_entry.memo),
+                    recurring_entries::schedule.eq(new_entry.schedule),
+                    recurring_entries::day.eq(new_entry.day),
+                    recurring_entries::month.eq(new_entry.month),
+                    recurring_entries::start_date.eq(new_entry.start_date),
+                    recurring_entries::end_date.eq(new_entry.end_date),
+                ))
+                .returning(RecurringEntry::as_returning())
+                .get_result(connection)?;
+            insert_lines(connection, entry.id, &new_entry.lines)?;
+            RecurringEntryRepository::new(connection).find(entry.id)
+        })
+    }
+
+    /// Replaces a template. Occurrences already posted stay as they are.
+    pub fn update(
+        &mut self,
+        id: i32,
+        new_entry: &NewRecurringEntry,
+    ) -> Result<RecurringEntryWithLines> {
+        self.connection.transaction(|connection| {
+            let mut repository = RecurringEntryRepository::new(connection);
+            repository.find(id)?;
+            validate(repository.connection, new_entry)?;
+            diesel::update(recurring_entries::table.find(id))
+                .set((
+                    recurring_entries::name.eq(&new_entry.name),
+                    recurring_entries::memo.eq(&new_entry.memo),
+                    recurring_entries::schedule.eq(new_entry.schedule),
+                    recurring_entries::day.eq(new_entry.day),
+                    recurring_entries::month.eq(new_entry.month),
+                    recurring_entries::start_date.eq(new_entry.start_date),
+                    recurring_entries::end_date.eq(new_entry.end_date),
+                    recurring_entries::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(repository.connection)?;
+            diesel::delete(
+                recurring_entry_lines::table
+                    .filter(recurring_entry_lines::recurring_entry_id.eq(id)),
+            )
+            .execute(repository.connection)?;
+            insert_lines(repository.connection, id, &new_entry.lines)?;
+            repository.find(id)
+        })
+    }
+
+    /// Deletes a template that has never been posted. One that has is
+    /// ended by giving it an end date instead.
+    pub fn delete(&mut self, id: i32) -> Result<()> {
+        self.connection.transaction(|connection| {
+            RecurringEntryRepository::new(connection).find(id)?;
+            let posted: i64 = recurring_occurrences::table
+                .filter(recurring_occurrences::recurring_entry_id.eq(id))
+                .filter(recurring_occurrences::status.eq(OccurrenceStatus::Posted))
+                .count()
+                .get_result(connection)?;
+            if posted > 0 {
+                return Err(Error::Validation(format!(
+                    "recurring entry {} has been posted; give it an end date instead",
+                    id
+                )));
+            }
+            diesel::delete(
+                recurring_occurrences::table
+                    .filter(recurring_occurrences::recurring_entry_id.eq(id)),
+            )
+            .execute(connection)?;
+            diesel::delete(
+                recurring_entry_lines::table
+                    .filter(recurring_entry_lines::recurring_entry_id.eq(id)),
+            )
+            .execute(connection)?;
+            diesel::delete(recurring_entries::table.find(id)).execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Occurrences falling due from `from` (by default the start date) to
+    /// `to`, with those posted on dates no longer on the schedule.
+    pub fn occurrences(
+        &mut self,
+        id: i32,
+        from: Option<NaiveDate>,
+        to: NaiveDate,
+    ) -> Result<Vec<Occurrence>> {
+        let template = self.find(id)?;
+        let from = from.unwrap_or(template.recurring_entry.start_date);
+        let rows = recurring_occurrences::table
+            .filter(recurring_occurrences::recurring_entry_id.eq(id))
+            .filter(recurring_occurrences::due_date.between(from, to))
+            .select(RecurringOccurrence::as_select())
+            .load(self.connection)?;
+        let mut occurrences = BTreeMap::new();
+        for date in due_dates(&template.recurring_entry, from, to) {
+            occurrences.insert(date, occurrence(&template, date, None)?);
+        }
+        for row in rows {
+            if row.status == OccurrenceStatus::Posted || occurrences.contains_key(&row.due_date) {
+                occurrences.insert(
+                    row.due_date,
+                    occurrence(&template, row.due_date, Some(&row))?,
+                );
+            }
+        }
+        Ok(occurrences.into_values().collect())
+    }
+
+    /// Leaves out the occurrence due on `due_date`.
+    pub fn skip(&mut self, id: i32, due_date: NaiveDate) -> Result<Occurrence> {
+        self.save(id, due_date, OccurrenceStatus::Skipped, None, None)
+    }
+
+    /// Changes the occurrence due on `due_date` before it is posted,
+    /// replacing any earlier changes. A skipped occurrence is due again.
+    pub fn modify(
+        &mut self,
+        id: i32,
+        due_date: NaiveDate,
+        changes: &OccurrenceChanges,
+    ) -> Result<Occurrence> {
+        if let Some(lines) = &changes.lines {
+            JournalRepository::new(self.connection).check(lines)?;
+        }
+        let lines = changes
+            .lines
+            .as_ref()
+            .map(|lines| {
+                serde_json::to_string(lines)
+                    .map_err(|e| Error::Validation(format!("cannot record lines: {}", e)))
+            })
+            .transpose()?;
+        self.save(
+            id,
+            due_date,
+            OccurrenceStatus::Due,
+            changes.entry_date,
+            lines,
+        )
+    }
+
+    /// Undoes skipping or changing the occurrence due on `due_date`.
+    pub fn restore(&mut self, id: i32, due_date: NaiveDate) -> Result<Occurrence> {
+        self.save(id, due_date, OccurrenceStatus::Due, None, None)
+    }
+
+    /// Posts every occurrence of every template due up to `to` that is not
+    /// posted or skipped yet, and returns them. Running it again posts
+    /// nothing twice. Nothing is posted if any of them fails, for instance
+    /// because it falls in a closed fiscal year.
+    pub fn materialize(&mut self, to: NaiveDate) -> Result<Vec<Occurrence>> {
+        self.connection.transaction(|connection| {
+            let mut repository = RecurringEntryRepository::new(connection);
+            let mut posted = Vec::new();
+            for template in repository.list()? {
+                let id = template.recurring_entry.id;
+                for due in repository.occurrences(id, None, to)? {
+                    if due.status != OccurrenceStatus::Due {
+                        continue;
+                    }
+                    let memo = if template.recurring_entry.memo.is_empty() {
+                        template.recurring_entry.name.clone()
+                    } else {
+                        template.recurring_entry.memo.clone()
+                    };
+                    let entry =
+                        JournalRepository::new(repository.connection).create(&NewJournalEntry {
+                            entry_date: due.entry_date,
+                            memo,
+                            lines: due.lines.clone(),
+                        })?;
+                    upsert(
+                        repository.connection,
+                        id,
+                        due.due_date,
+                        (OccurrenceStatus::Posted, Some(entry.entry.id)),
+                        None,
+                    )?;
+                    posted.push(Occurrence {
+                        status: OccurrenceStatus::Posted,
+                        entry_id: Some(entry.entry.id),
+                        ..due
+                    });
+                }
+            }
+            Ok(posted)
+        })
+    }
+
+    /// Records the state of an occurrence that is not posted yet.
+    fn save(
+        &mut self,
+        id: i32,
+        due_date: NaiveDate,
+        status: OccurrenceStatus,
+        entry_date: Option<NaiveDate>,
+        lines: Option<String>,
+    ) -> Result<Occurrence> {
+        self.connection.transaction(|connection| {
+            let mut repository = RecurringEntryRepository::new(connection);
+            let template = repository.find(id)?;
+            if !is_due(&template.recurring_entry, due_date) {
+                return Err(Error::Validation(format!(
+                    "recurring entry {} is not due on {}",
+                    id, due_date
+                )));
+            }
+            let existing = recurring_occurrences::table
+                .filter(recurring_occurrences::recurring_entry_id.eq(id))
+                .filter(recurring_occurrences::due_date.eq(due_date))
+                .select(recurring_occurrences::status)
+                .first::<OccurrenceStatus>(repository.connection)
+                .optional()?;
+            if existing == Some(OccurrenceStatus::Posted) {
+                return Err(Error::Validation(format!(
+                    "recurring entry {} is already posted for {}",
+                    id, due_date
+                )));
+            }
+            let row = upsert(
+                repository.connection,
+                id,
+                due_date,
+                (status, None),
+                Some((entry_date, lines)),
+            )?;
+            occurrence(&template, due_date, Some(&row))
+        })
+    }
+}
+
+/// Inserts or updates the occurrence row of `due_date`. Changes are kept
+/// when `changes` is `None`.
+fn upsert(
+    connection: &mut SqliteConnection,
+    id: i32,
+    due_date: NaiveDate,
+    (status, entry_id): (OccurrenceStatus, Option<i32>),
+    changes: Option<(Option<NaiveDate>, Option<String>)>,
+) -> Result<RecurringOccurrence> {
+    let now = Utc::now().naive_utc();
+    let existing = recurring_occurrences::table
+        .filter(recurring_occurrences::recurring_entry_id.eq(id))
+        .filter(recurring_occurrences::due_date.eq(due_date))
+        .select(RecurringOccurrence::as_select())
+        .first(connection)
+        .optional()?;
+    let (entry_date, lines) = match (changes, &existing) {
+        (Some(changes), _) => changes,
+        (None, Some(existing)) => (existing.entry_date, existing.lines.clone()),
+        (None, None) => (None, None),
+    };
+    Ok(match existing {
+        Some(existing) => diesel::update(recurring_occurrences::table.find(existing.id))
+            .set((
+                recurring_occurrences::status.eq(status),
+                recurring_occurrences::entry_date.eq(entry_date),
+                recurring_occurrences::lines.eq(lines),
+                recurring_occurrences::entry_id.eq(entry_id),
+                recurring_occurrences::updated_at.eq(now),
+            ))
+            .returning(RecurringOccurrence::as_returning())
+            .get_result(connection)?,
+        None => insert_into(recurring_occurrences::table)
+            .values((
+                recurring_occurrences::recurring_entry_id.eq(id),
+                recurring_occurrences::due_date.eq(due_date),
+                recurring_occurrences::status.eq(status),
+                recurring_occurrences::entry_date.eq(entry_date),
+                recurring_occurrences::lines.eq(lines),
+                recurring_occurrences::entry_id.eq(entry_id),
+            ))
+            .returning(RecurringOccurrence::as_returning())
+            .get_result(connection)?,
+    })
+}
+
+/// The occurrence of `template` due on `due_date`, with what is recorded
+/// about it.
+fn occurrence(
+    template: &RecurringEntryWithLines,
+    due_date: NaiveDate,
+    row: Option<&RecurringOccurrence>,
+) -> Result<Occurrence> {
+    let lines = match row.and_then(|row| row.lines.as_ref()) {
+        Some(lines) => serde_json::from_str(lines).map_err(|e| {
+            Error::Integrity(format!(
+                "changed lines of recurring entry {} on {} are unreadable: {}",
+                template.recurring_entry.id, due_date, e
+            ))
+        })?,
+        None => template.lines.iter().map(NewJournalLine::from).collect(),
+    };
+    Ok(Occurrence {
+        recurring_entry_id: template.recurring_entry.id,
+        due_date,
+        status: row.map_or(OccurrenceStatus::Due, |row| row.status),
+        entry_date: row.and_then(|row| row.entry_date).unwrap_or(due_date),
+        lines,
+        modified: row.is_some_and(|row| row.entry_date.is_some() || row.lines.is_some()),
+        entry_id: row.and_then(|row| row.entry_id),
+    })
+}
+
+fn validate(connection: &mut SqliteConnection, new_entry: &NewRecurringEntry) -> Result<()> {
+    if new_entry.name.trim().is_empty() {
+        return Err(Error::Validation(
+            "recurring entry name is required".to_string(),
+        ));
+    }
+    check_schedule(new_entry.schedule, new_entry.day, new_entry.month)?;
+    if let Some(end_date) = new_entry.end_date {
+        if end_date < new_entry.start_date {
+            return Err(Error::Validation(format!(
+                "recurring entry ends before it starts: {} < {}",
+                end_date, new_entry.start_date
+            )));
+        }
+    }
+    JournalRepository::new(connection).check(&new_entry.lines)
+}
+
+fn insert_lines(
+    connection: &mut SqliteConnection,
+    recurring_entry_id: i32,
+    lines: &[NewJournalLine],
+) -> Result<()> {
+    let rows = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            (
+                recurring_entry_lines::recurring_entry_id.eq(recurring_entry_id),
+                recurring_entry_lines::line_no.eq(i as i32 + 1),
+                recurring_entry_lines::account_id.eq(line.account_id),
+                recurring_entry_lines::side.eq(line.side),
+                recurring_entry_lines::amount.eq(line.amount),
+                recurring_entry_lines::description.eq(&line.description),
+                recurring_entry_lines::tax_category.eq(line.tax_category),
+                recurring_entry_lines::tax_included.eq(line.tax_included),
+                recurring_entry_lines::counterparty_id.eq(line.counterparty_id),
+            )
+        })
+        .collect::<Vec<_>>();
+    insert_into(recurring_entry_lines::table)
+        .values(&rows)
+        .execute(connection)?;
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use diesel::{insert_into, prelude::*};
+
+use crate::error::{Error, Result};
+use crate::models::{AccountType, ReturnLine, ReturnLineMapping};
+use crate::repository::AccountRepository;
+use crate::schema::return_lines;
+
+/// Which line of the blue-return 決算書 each revenue and expense account is
+/// reported on.
+pub struct ReturnLineRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> ReturnLineRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        ReturnLineRepository { connection }
+    }
+
+    pub fn list(&mut self) -> Result<Vec<ReturnLineMapping>> {
+        Ok(return_lines::table
+            .select(ReturnLineMapping::as_select())
+            .order_by(return_lines::account_id)
+            .load(self.connection)?)
+    }
+
+    /// Reports `account_id` on `line` from now on. Only top-level accounts
+    /// are mapped; sub-accounts are reported with their parent.
+    pub fn set(&mut self, account_id: i32, line: ReturnLine) -> Result<ReturnLineMapping> {
+        self.connection.transaction(|connection| {
+            let account = AccountRepository::new(connection).find(account_id)?;
+            if account.parent_id.is_some() {
+                return Err(Error::Validation(format!(
+                    "account {} {} is a sub-account and follows its parent",
+                    account.code, account.name
+                )));
+            }
+            if account.account_type != line.account_type() {
+                return Err(Error::Validation(format!(
+                    "account {} {} cannot be reported on {}",
+                    account.code, account.name, line
+                )));
+            }
+            let now = Utc::now().naive_utc();
+            Ok(insert_into(return_lines::table)
+                .values((
+                    return_lines::account_id.eq(account_id),
+                    return_lines::line.eq(line),
+                    return_lines::updated_at.eq(now),
+                ))
+                .on_conflict(return_lines::account_id)
+                .do_update()
+                .set((
+                    return_lines::line.eq(line),
+                    return_lines::updated_at.eq(now),
+                ))
+                .returning(ReturnLineMapping::as_returning())
+                .get_result(connection)?)
+        })
+    }
+
+    /// Puts the account back on its default line.
+    pub fn clear(&mut self, account_id: i32) -> Result<()> {
+        match diesel::delete(return_lines::table.find(account_id)).execute(self.connection)? {
+            0 => Err(Error::NotFound(format!(
+                "return line of account {}",
+                account_id
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The line of every revenue and expense account. Sub-accounts take
+    /// their top-level account's; unmapped revenue goes to 売上(収入)金額 and
+    /// unmapped expenses to a line of their own.
+    pub fn resolve(&mut self) -> Result<HashMap<i32, ReturnLine>> {
+        let accounts = AccountRepository::new(self.connection).list(true)?;
+        let mapped = self
+            .list()?
+            .into_iter()
+            .map(|mapping| (mapping.account_id, mapping.line))
+            .collect::<HashMap<_, _>>();
+        let by_id = accounts
+            .iter()
+            .map(|account| (account.id, account))
+            .collect::<HashMap<_, _>>();
+
+        let mut lines = HashMap::new();
+        for account in &accounts {
+            let mut top = account;
+            while let Some(parent) = top.parent_id.and_then(|id| by_id.get(&id)) {
+                top = parent;
+            }
+            let line = match (mapped.get(&top.id), top.account_type) {
+                (Some(line), _) => *line,
+                (None, AccountType::Revenue) => ReturnLine::Sales,
+                (None, AccountType::Expense) => ReturnLine::Other,
+                _ => continue,
+            };
+            lines.insert(account.id, line);
+        }
+        Ok(lines)
+    }
+}
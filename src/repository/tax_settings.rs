@@ -0,0 +1,34 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::error::Result;
+use crate::models::{TaxSettings, TaxSettingsChanges};
+use crate::schema::tax_settings;
+
+/// The bookkeeping method and rounding for consumption tax, kept in a
+/// single row.
+pub struct TaxSettingsRepository<'a> {
+    connection: &'a mut SqliteConnection,
+}
+
+impl<'a> TaxSettingsRepository<'a> {
+    pub fn new(connection: &'a mut SqliteConnection) -> Self {
+        TaxSettingsRepository { connection }
+    }
+
+    pub fn get(&mut self) -> Result<TaxSettings> {
+        Ok(tax_settings::table
+            .find(1)
+            .select(TaxSettings::as_select())
+            .first(self.connection)?)
+    }
+
+    /// Applies to entries recorded from now on; posted lines keep the
+    /// treatment they were recorded with.
+    pub fn update(&mut self, changes: &TaxSettingsChanges) -> Result<TaxSettings> {
+        Ok(diesel::update(tax_settings::table.find(1))
+            .set((changes, tax_settings::updated_at.eq(Utc::now().naive_utc())))
+            .returning(TaxSettings::as_returning())
+            .get_result(self.connection)?)
+    }
+}
@@ -1,5 +1,72 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    accounts (id) {
+        id -> Integer,
+        code -> Text,
+        name -> Text,
+        kana -> Text,
+        account_type -> Text,
+        tax_category -> Text,
+        active -> Bool,
+        parent_id -> Nullable<Integer>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    apportionment_rules (id) {
+        id -> Integer,
+        account_id -> Integer,
+        counterparty_id -> Nullable<Integer>,
+        business_ratio -> Integer,
+        effective_from -> Date,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    attachment_history (id) {
+        id -> Integer,
+        attachment_id -> Integer,
+        action -> Text,
+        reason -> Text,
+        snapshot -> Text,
+        recorded_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    attachments (id) {
+        id -> Integer,
+        sha256 -> Text,
+        file_name -> Text,
+        content_type -> Text,
+        size -> BigInt,
+        transaction_date -> Date,
+        amount -> BigInt,
+        counterparty_id -> Nullable<Integer>,
+        entry_id -> Nullable<Integer>,
+        description -> Text,
+        deleted_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    business_profile (id) {
+        id -> Integer,
+        name -> Text,
+        address -> Text,
+        registration_number -> Nullable<Text>,
+        bank_details -> Text,
+        invoice_prefix -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     category (id) {
         id -> Nullable<Integer>,
@@ -8,6 +75,169 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    counterparties (id) {
+        id -> Integer,
+        name -> Text,
+        registration_number -> Nullable<Text>,
+        address -> Text,
+        qualified -> Bool,
+        valid_from -> Nullable<Date>,
+        valid_until -> Nullable<Date>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        withholding -> Text,
+    }
+}
+
+diesel::table! {
+    fiscal_years (id) {
+        id -> Integer,
+        start_date -> Date,
+        end_date -> Date,
+        state -> Text,
+        closing_entry_id -> Nullable<Integer>,
+        opening_entry_id -> Nullable<Integer>,
+        closed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        depreciation_entry_id -> Nullable<Integer>,
+        apportionment_entry_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    fixed_assets (id) {
+        id -> Integer,
+        name -> Text,
+        account_id -> Integer,
+        quantity -> Text,
+        acquired_on -> Date,
+        cost -> BigInt,
+        useful_life -> Integer,
+        method -> Text,
+        business_ratio -> Integer,
+        disposed_on -> Nullable<Date>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    import_batches (id) {
+        id -> Integer,
+        profile_id -> Integer,
+        file_name -> Text,
+        imported_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    import_profiles (id) {
+        id -> Integer,
+        name -> Text,
+        account_id -> Integer,
+        encoding -> Text,
+        skip_rows -> Integer,
+        date_column -> Integer,
+        date_format -> Text,
+        description_column -> Integer,
+        withdrawal_column -> Integer,
+        deposit_column -> Nullable<Integer>,
+        balance_column -> Nullable<Integer>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    import_rules (id) {
+        id -> Integer,
+        priority -> Integer,
+        description_contains -> Nullable<Text>,
+        min_amount -> Nullable<BigInt>,
+        max_amount -> Nullable<BigInt>,
+        account_id -> Integer,
+        tax_category -> Nullable<Text>,
+        counterparty_id -> Nullable<Integer>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    invoice_lines (id) {
+        id -> Integer,
+        invoice_id -> Integer,
+        line_no -> Integer,
+        description -> Text,
+        quantity -> Integer,
+        unit_price -> BigInt,
+        tax_category -> Text,
+    }
+}
+
+diesel::table! {
+    invoices (id) {
+        id -> Integer,
+        sequence -> Nullable<Integer>,
+        number -> Nullable<Text>,
+        counterparty_id -> Integer,
+        issue_date -> Date,
+        due_date -> Date,
+        status -> Text,
+        notes -> Text,
+        entry_id -> Nullable<Integer>,
+        payment_entry_id -> Nullable<Integer>,
+        paid_on -> Nullable<Date>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    journal_entries (id) {
+        id -> Integer,
+        entry_date -> Date,
+        memo -> Text,
+        voided_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        kind -> Text,
+    }
+}
+
+diesel::table! {
+    journal_lines (id) {
+        id -> Integer,
+        entry_id -> Integer,
+        line_no -> Integer,
+        account_id -> Integer,
+        side -> Text,
+        amount -> BigInt,
+        description -> Text,
+        tax_category -> Text,
+        tax_amount -> BigInt,
+        tax_included -> Bool,
+        counterparty_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    open_item_matches (id) {
+        id -> Integer,
+        item_line_id -> Integer,
+        payment_line_id -> Integer,
+        amount -> BigInt,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    post_tags (post_id, tag_id) {
+        post_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
 diesel::table! {
     posts (id) {
         id -> Nullable<Integer>,
@@ -19,7 +249,161 @@ diesel::table! {
         good_count -> Integer,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        version -> Integer,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(category, posts,);
+diesel::table! {
+    recurring_entries (id) {
+        id -> Integer,
+        name -> Text,
+        memo -> Text,
+        schedule -> Text,
+        day -> Nullable<Integer>,
+        month -> Nullable<Integer>,
+        start_date -> Date,
+        end_date -> Nullable<Date>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    recurring_entry_lines (id) {
+        id -> Integer,
+        recurring_entry_id -> Integer,
+        line_no -> Integer,
+        account_id -> Integer,
+        side -> Text,
+        amount -> BigInt,
+        description -> Text,
+        tax_category -> Nullable<Text>,
+        tax_included -> Bool,
+        counterparty_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    recurring_occurrences (id) {
+        id -> Integer,
+        recurring_entry_id -> Integer,
+        due_date -> Date,
+        status -> Text,
+        entry_date -> Nullable<Date>,
+        lines -> Nullable<Text>,
+        entry_id -> Nullable<Integer>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    return_lines (account_id) {
+        account_id -> Integer,
+        line -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    skipped_duplicates (id) {
+        id -> Integer,
+        batch_id -> Integer,
+        row_no -> Integer,
+        transaction_date -> Date,
+        description -> Text,
+        withdrawal -> BigInt,
+        deposit -> BigInt,
+        duplicate_of -> Integer,
+    }
+}
+
+diesel::table! {
+    staged_transactions (id) {
+        id -> Integer,
+        batch_id -> Integer,
+        row_no -> Integer,
+        account_id -> Integer,
+        transaction_date -> Date,
+        description -> Text,
+        withdrawal -> BigInt,
+        deposit -> BigInt,
+        balance -> Nullable<BigInt>,
+        status -> Text,
+        entry_id -> Nullable<Integer>,
+        fingerprint -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    tax_settings (id) {
+        id -> Integer,
+        method -> Text,
+        rounding -> Text,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Nullable<Integer>,
+        name -> Text,
+    }
+}
+
+diesel::joinable!(apportionment_rules -> accounts (account_id));
+diesel::joinable!(apportionment_rules -> counterparties (counterparty_id));
+diesel::joinable!(attachment_history -> attachments (attachment_id));
+diesel::joinable!(attachments -> counterparties (counterparty_id));
+diesel::joinable!(attachments -> journal_entries (entry_id));
+diesel::joinable!(fixed_assets -> accounts (account_id));
+diesel::joinable!(import_batches -> import_profiles (profile_id));
+diesel::joinable!(import_profiles -> accounts (account_id));
+diesel::joinable!(import_rules -> accounts (account_id));
+diesel::joinable!(import_rules -> counterparties (counterparty_id));
+diesel::joinable!(invoice_lines -> invoices (invoice_id));
+diesel::joinable!(invoices -> counterparties (counterparty_id));
+diesel::joinable!(journal_lines -> accounts (account_id));
+diesel::joinable!(journal_lines -> counterparties (counterparty_id));
+diesel::joinable!(journal_lines -> journal_entries (entry_id));
+diesel::joinable!(recurring_entry_lines -> accounts (account_id));
+diesel::joinable!(recurring_entry_lines -> counterparties (counterparty_id));
+diesel::joinable!(recurring_entry_lines -> recurring_entries (recurring_entry_id));
+diesel::joinable!(recurring_occurrences -> journal_entries (entry_id));
+diesel::joinable!(recurring_occurrences -> recurring_entries (recurring_entry_id));
+diesel::joinable!(return_lines -> accounts (account_id));
+diesel::joinable!(skipped_duplicates -> import_batches (batch_id));
+diesel::joinable!(skipped_duplicates -> staged_transactions (duplicate_of));
+diesel::joinable!(staged_transactions -> accounts (account_id));
+diesel::joinable!(staged_transactions -> import_batches (batch_id));
+diesel::joinable!(staged_transactions -> journal_entries (entry_id));
+
+diesel::allow_tables_to_appear_in_same_query!(
+    accounts,
+    apportionment_rules,
+    attachment_history,
+    attachments,
+    business_profile,
+    category,
+    counterparties,
+    fiscal_years,
+    fixed_assets,
+    import_batches,
+    import_profiles,
+    import_rules,
+    invoice_lines,
+    invoices,
+    journal_entries,
+    journal_lines,
+    open_item_matches,
+    post_tags,
+    posts,
+    recurring_entries,
+    recurring_entry_lines,
+    recurring_occurrences,
+    return_lines,
+    skipped_duplicates,
+    staged_transactions,
+    tags,
+    tax_settings,
+);
@@ -0,0 +1,380 @@
+//! Helpers for tests that need a database.
+//!
+//! Every [`TestDb`] is a fresh, fully migrated database that no other test
+//! can see, so tests using it are safe to run in parallel.
+
+use std::path::{Path, PathBuf};
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::Router;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::{insert_into, prelude::*};
+use diesel_migrations::MigrationHarness;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use tempfile::TempDir;
+use tower::ServiceExt;
+
+use crate::http::Pool;
+use crate::models::{
+    Category, Counterparty, JournalEntryWithLines, NewCounterparty, NewJournalEntry,
+    NewJournalLine, Post, TaxCategory, Withholding,
+};
+use crate::report::Period;
+use crate::repository::{CounterpartyRepository, JournalRepository};
+use crate::MIGRATIONS;
+
+pub struct TestDb {
+    connection: SqliteConnection,
+    // Kept alive so the database file is removed when the test ends.
+    dir: Option<TempDir>,
+}
+
+impl TestDb {
+    /// Opens a private in-memory database.
+    pub fn in_memory() -> Self {
+        TestDb {
+            connection: migrated(":memory:"),
+            dir: None,
+        }
+    }
+
+    /// Creates a database file in a temporary directory. Use this when the
+    /// code under test opens its own connections (backups, CLI commands).
+    pub fn temp_file() -> Self {
+        let dir = TempDir::new().expect("failed to create temp dir");
+        let path = dir.path().join("database.db");
+        TestDb {
+            connection: migrated(path.to_str().unwrap()),
+            dir: Some(dir),
+        }
+    }
+
+    /// Path of the database file, or `None` for in-memory databases.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.path().join("database.db"))
+    }
+
+    /// Temporary directory holding the database file.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_ref().map(|dir| dir.path())
+    }
+
+    /// Connection pool over the database file, for exercising the HTTP API.
+    pub fn pool(&self) -> Pool {
+        let path = self.path().expect("pool() needs a temp_file() database");
+        crate::http::pool(path.to_str().unwrap()).expect("failed to build pool")
+    }
+
+    pub fn conn(&mut self) -> &mut SqliteConnection {
+        &mut self.connection
+    }
+
+    pub fn into_connection(self) -> SqliteConnection {
+        self.connection
+    }
+}
+
+fn migrated(database_url: &str) -> SqliteConnection {
+    let mut connection = SqliteConnection::establish(database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
+    connection
+        .run_pending_migrations(MIGRATIONS)
+        .expect("failed to run migrations");
+    crate::enable_foreign_keys(&mut connection).expect("failed to enable foreign keys");
+    connection
+}
+
+/// Returns a fresh in-memory database connection.
+pub fn connection() -> SqliteConnection {
+    TestDb::in_memory().into_connection()
+}
+
+/// Runs `f` inside a transaction that is always rolled back, leaving the
+/// database exactly as it was.
+pub fn rollback<T>(
+    connection: &mut SqliteConnection,
+    f: impl FnOnce(&mut SqliteConnection) -> T,
+) -> T {
+    connection.test_transaction(|connection| Ok::<_, diesel::result::Error>(f(connection)))
+}
+
+/// Id of the seeded account with the given code, e.g. `"101"` for 現金.
+pub fn account_id(connection: &mut SqliteConnection, code: &str) -> i32 {
+    use crate::schema::accounts;
+    accounts::table
+        .filter(accounts::code.eq(code))
+        .select(accounts::id)
+        .first(connection)
+        .unwrap_or_else(|_| panic!("no account with code {}", code))
+}
+
+/// The date `y`-`m`-`d`, which must exist.
+pub fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap_or_else(|| panic!("no date {}-{}-{}", y, m, d))
+}
+
+/// The calendar year `y` as a report period.
+pub fn year(y: i32) -> Period {
+    Period::new(date(y, 1, 1), date(y, 12, 31)).unwrap()
+}
+
+/// A request to the HTTP API, with `body` sent as JSON when given.
+pub fn request(method: &str, uri: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder().method(method).uri(uri);
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .unwrap()
+}
+
+/// A response collected by [`send`].
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// The body as JSON, or `Value::Null` when it is not JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or(Value::Null)
+    }
+
+    /// The body as text.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// The value of header `name`, if present and text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+/// Sends `request` through `app` and collects the response.
+pub async fn send(app: &Router, request: Request<Body>) -> TestResponse {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let (parts, body) = response.into_parts();
+    TestResponse {
+        status: parts.status,
+        headers: parts.headers,
+        body: body.collect().await.unwrap().to_bytes().to_vec(),
+    }
+}
+
+#[derive(Default)]
+pub struct PostBuilder {
+    post: Post,
+}
+
+impl PostBuilder {
+    pub fn new(title: &str) -> Self {
+        PostBuilder {
+            post: Post {
+                title: title.to_string(),
+                body: format!("{} body", title),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.post.body = body.to_string();
+        self
+    }
+
+    pub fn category_id(mut self, category_id: i32) -> Self {
+        self.post.category_id = Some(category_id);
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.post.author = Some(author.to_string());
+        self
+    }
+
+    pub fn published(mut self, published: bool) -> Self {
+        self.post.published = published;
+        self
+    }
+
+    pub fn good_count(mut self, good_count: i32) -> Self {
+        self.post.good_count = good_count;
+        self
+    }
+
+    pub fn created_at(mut self, created_at: NaiveDateTime) -> Self {
+        self.post.created_at = created_at;
+        self.post.updated_at = created_at;
+        self
+    }
+
+    pub fn build(self) -> Post {
+        self.post
+    }
+
+    pub fn insert(self, connection: &mut SqliteConnection) -> QueryResult<Post> {
+        use crate::schema::posts;
+        insert_into(posts::table)
+            .values(&self.post)
+            .returning(Post::as_returning())
+            .get_result(connection)
+    }
+}
+
+#[derive(Default)]
+pub struct CategoryBuilder {
+    category: Category,
+}
+
+impl CategoryBuilder {
+    pub fn new(name: &str) -> Self {
+        CategoryBuilder {
+            category: Category {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.category.description = Some(description.to_string());
+        self
+    }
+
+    pub fn build(self) -> Category {
+        self.category
+    }
+
+    pub fn insert(self, connection: &mut SqliteConnection) -> QueryResult<Category> {
+        use crate::schema::category;
+        insert_into(category::table)
+            .values(&self.category)
+            .returning(Category::as_returning())
+            .get_result(connection)
+    }
+}
+
+/// A journal entry whose lines name accounts by code, resolved on insert.
+pub struct EntryBuilder {
+    entry: NewJournalEntry,
+    codes: Vec<Option<String>>,
+}
+
+impl EntryBuilder {
+    pub fn new(entry_date: NaiveDate) -> Self {
+        EntryBuilder {
+            entry: NewJournalEntry {
+                entry_date,
+                memo: String::new(),
+                lines: Vec::new(),
+            },
+            codes: Vec::new(),
+        }
+    }
+
+    pub fn memo(mut self, memo: &str) -> Self {
+        self.entry.memo = memo.to_string();
+        self
+    }
+
+    /// Debits the account with code `code`.
+    pub fn debit(self, code: &str, amount: i64) -> Self {
+        self.coded(code, NewJournalLine::debit(0, amount))
+    }
+
+    /// Credits the account with code `code`.
+    pub fn credit(self, code: &str, amount: i64) -> Self {
+        self.coded(code, NewJournalLine::credit(0, amount))
+    }
+
+    /// Adds a line as given, for accounts already known by id.
+    pub fn line(mut self, line: NewJournalLine) -> Self {
+        self.entry.lines.push(line);
+        self.codes.push(None);
+        self
+    }
+
+    pub fn lines(self, lines: impl IntoIterator<Item = NewJournalLine>) -> Self {
+        lines.into_iter().fold(self, EntryBuilder::line)
+    }
+
+    /// Sets the tax category of the line added last.
+    pub fn tax(mut self, tax_category: TaxCategory) -> Self {
+        let line = self.entry.lines.last_mut().expect("no line to tag");
+        line.tax_category = Some(tax_category);
+        self
+    }
+
+    /// Names the counterparty on the line added last.
+    pub fn counterparty(mut self, counterparty_id: i32) -> Self {
+        let line = self.entry.lines.last_mut().expect("no line to tag");
+        line.counterparty_id = Some(counterparty_id);
+        self
+    }
+
+    pub fn build(mut self, connection: &mut SqliteConnection) -> NewJournalEntry {
+        for (line, code) in self.entry.lines.iter_mut().zip(&self.codes) {
+            if let Some(code) = code {
+                line.account_id = account_id(connection, code);
+            }
+        }
+        self.entry
+    }
+
+    pub fn insert(self, connection: &mut SqliteConnection) -> crate::Result<JournalEntryWithLines> {
+        let entry = self.build(connection);
+        JournalRepository::new(connection).create(&entry)
+    }
+
+    fn coded(mut self, code: &str, line: NewJournalLine) -> Self {
+        self.entry.lines.push(line);
+        self.codes.push(Some(code.to_string()));
+        self
+    }
+}
+
+pub struct CounterpartyBuilder {
+    counterparty: NewCounterparty,
+}
+
+impl CounterpartyBuilder {
+    pub fn new(name: &str) -> Self {
+        CounterpartyBuilder {
+            counterparty: NewCounterparty {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn address(mut self, address: &str) -> Self {
+        self.counterparty.address = address.to_string();
+        self
+    }
+
+    /// Sets the 登録番号 and marks the counterparty as qualified.
+    pub fn registration_number(mut self, number: &str) -> Self {
+        self.counterparty.registration_number = Some(number.to_string());
+        self.counterparty.qualified = true;
+        self
+    }
+
+    pub fn withholding(mut self, withholding: Withholding) -> Self {
+        self.counterparty.withholding = withholding;
+        self
+    }
+
+    pub fn build(self) -> NewCounterparty {
+        self.counterparty
+    }
+
+    pub fn insert(self, connection: &mut SqliteConnection) -> crate::Result<Counterparty> {
+        CounterpartyRepository::new(connection).create(&self.counterparty)
+    }
+}
@@ -0,0 +1,21 @@
+//! 源泉徴収 of income tax on 報酬・料金 such as 原稿料 and fees paid to
+//! freelancers (所得税法第204条). The rate is 10.21%, 復興特別所得税
+//! included, on up to 1,000,000 yen of a single payment and 20.42% on the
+//! part above. Tax is worked out on the payment with consumption tax.
+
+/// The part of a payment above this is taxed at the higher rate.
+pub const HIGHER_RATE_THRESHOLD: i64 = 1_000_000;
+/// Yearly payments to one payee above which a 支払調書 has to be filed for
+/// 原稿料, デザイン料 and most other 報酬・料金.
+pub const PAYMENT_RECORD_THRESHOLD: i64 = 50_000;
+
+/// Income tax to withhold from a payment of `amount` yen, rounded down to
+/// the yen.
+pub fn withholding_tax(amount: i64) -> i64 {
+    if amount <= 0 {
+        return 0;
+    }
+    let amount = amount as i128;
+    let lower = amount.min(HIGHER_RATE_THRESHOLD as i128);
+    ((lower * 1021 + (amount - lower) * 2042) / 10_000) as i64
+}
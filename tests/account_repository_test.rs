@@ -0,0 +1,110 @@
+use new_tax_account_backend::models::{AccountChanges, AccountType, NewAccount, TaxCategory};
+use new_tax_account_backend::repository::AccountRepository;
+use new_tax_account_backend::test_util;
+use new_tax_account_backend::Error;
+
+fn new_account(code: &str, name: &str, account_type: AccountType) -> NewAccount {
+    NewAccount {
+        code: code.to_string(),
+        name: name.to_string(),
+        kana: String::new(),
+        account_type,
+        tax_category: TaxCategory::OutOfScope,
+        parent_id: None,
+    }
+}
+
+#[test]
+fn test_default_chart_of_accounts() {
+    let mut connection = test_util::connection();
+    let mut repository = AccountRepository::new(&mut connection);
+
+    let accounts = repository.list(false).unwrap();
+    assert!(accounts.len() > 50);
+    assert!(accounts.windows(2).all(|w| w[0].code < w[1].code));
+
+    let cash = repository.find_by_code("101").unwrap();
+    assert_eq!(cash.name, "現金");
+    assert_eq!(cash.account_type, AccountType::Asset);
+
+    let sales = repository.find_by_code("401").unwrap();
+    assert_eq!(sales.name, "売上高");
+    assert_eq!(sales.account_type, AccountType::Revenue);
+    assert_eq!(sales.tax_category, TaxCategory::Taxable10);
+
+    let capital = repository.find_by_code("301").unwrap();
+    assert_eq!(capital.name, "元入金");
+    assert_eq!(capital.account_type, AccountType::Equity);
+}
+
+#[test]
+fn test_create_sub_account() {
+    let mut connection = test_util::connection();
+    let mut repository = AccountRepository::new(&mut connection);
+    let bank = repository.find_by_code("111").unwrap();
+
+    let mut new = new_account("111-1", "普通預金 A銀行", AccountType::Asset);
+    new.parent_id = Some(bank.id);
+    let account = repository.create(&new).unwrap();
+    assert_eq!(account.parent_id, Some(bank.id));
+    assert!(account.active);
+
+    // Duplicate codes and parents of another type are rejected.
+    assert!(matches!(repository.create(&new), Err(Error::Validation(_))));
+    let mut wrong_type = new_account("599-1", "雑費 A", AccountType::Expense);
+    wrong_type.parent_id = Some(bank.id);
+    assert!(matches!(
+        repository.create(&wrong_type),
+        Err(Error::Validation(_))
+    ));
+
+    // The parent cannot be deleted while it has sub-accounts.
+    assert!(repository
+        .delete(bank.id)
+        .unwrap_err()
+        .is_foreign_key_violation());
+    repository.delete(account.id).unwrap();
+    repository.delete(bank.id).unwrap();
+}
+
+#[test]
+fn test_update_and_deactivate() {
+    let mut connection = test_util::connection();
+    let mut repository = AccountRepository::new(&mut connection);
+    let parent = repository
+        .create(&new_account("900", "親", AccountType::Expense))
+        .unwrap();
+    let child = repository
+        .create(&new_account("901", "子", AccountType::Expense))
+        .unwrap();
+
+    let changes = AccountChanges {
+        kana: Some("コ".to_string()),
+        tax_category: Some(TaxCategory::Reduced8),
+        parent_id: Some(Some(parent.id)),
+        ..Default::default()
+    };
+    let updated = repository.update(child.id, &changes).unwrap();
+    assert_eq!(updated.kana, "コ");
+    assert_eq!(updated.tax_category, TaxCategory::Reduced8);
+    assert_eq!(updated.parent_id, Some(parent.id));
+
+    // Making the parent a child of its own child would create a cycle.
+    let cycle = AccountChanges {
+        parent_id: Some(Some(child.id)),
+        ..Default::default()
+    };
+    assert!(matches!(
+        repository.update(parent.id, &cycle),
+        Err(Error::Validation(_))
+    ));
+
+    repository.deactivate(child.id).unwrap();
+    let active = repository.list(false).unwrap();
+    assert!(active.iter().all(|a| a.id != child.id));
+    assert!(repository
+        .list(true)
+        .unwrap()
+        .iter()
+        .any(|a| a.id == child.id));
+}
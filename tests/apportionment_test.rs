@@ -0,0 +1,285 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde_json::{json, Value};
+
+use new_tax_account_backend::models::{NewApportionmentRule, NewFiscalYear, Side, TaxCategory};
+use new_tax_account_backend::report::ApportionmentReport;
+use new_tax_account_backend::repository::{
+    AccountRepository, ApportionmentRepository, FiscalYearRepository, JournalRepository,
+};
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, year, CounterpartyBuilder, EntryBuilder, TestDb,
+};
+use new_tax_account_backend::{http, Error};
+
+fn pay(connection: &mut SqliteConnection, on: NaiveDate, code: &str, amount: i64) -> i32 {
+    pay_to(connection, on, code, amount, None)
+}
+
+fn pay_to(
+    connection: &mut SqliteConnection,
+    on: NaiveDate,
+    code: &str,
+    amount: i64,
+    counterparty_id: Option<i32>,
+) -> i32 {
+    let mut entry = EntryBuilder::new(on).debit(code, amount);
+    if let Some(counterparty_id) = counterparty_id {
+        entry = entry.counterparty(counterparty_id);
+    }
+    entry
+        .credit("111", amount)
+        .insert(connection)
+        .unwrap()
+        .entry
+        .id
+}
+
+fn rule(
+    connection: &mut SqliteConnection,
+    code: &str,
+    counterparty_id: Option<i32>,
+    business_ratio: i32,
+    effective_from: NaiveDate,
+) -> NewApportionmentRule {
+    NewApportionmentRule {
+        account_id: account_id(connection, code),
+        counterparty_id,
+        business_ratio,
+        effective_from,
+    }
+}
+
+#[test]
+fn test_rules_are_validated() {
+    let mut connection = test_util::connection();
+    let rent = rule(&mut connection, "526", None, 40, date(2024, 1, 1));
+    let sales = rule(&mut connection, "401", None, 40, date(2024, 1, 1));
+    let mut repository = ApportionmentRepository::new(&mut connection);
+
+    repository.create_rule(&rent).unwrap();
+    for invalid in [
+        NewApportionmentRule {
+            business_ratio: 101,
+            ..rent.clone()
+        },
+        // Only expenses are apportioned.
+        sales,
+        // One rule per account, counterparty and date.
+        rent.clone(),
+    ] {
+        assert!(matches!(
+            repository.create_rule(&invalid),
+            Err(Error::Validation(_))
+        ));
+    }
+    assert!(matches!(
+        repository.create_rule(&NewApportionmentRule {
+            counterparty_id: Some(999),
+            ..rent
+        }),
+        Err(Error::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_apportioned_accounts_cannot_be_deleted() {
+    let mut connection = test_util::connection();
+    let rent = rule(&mut connection, "526", None, 40, date(2024, 1, 1));
+    let created = ApportionmentRepository::new(&mut connection)
+        .create_rule(&rent)
+        .unwrap();
+
+    assert!(AccountRepository::new(&mut connection)
+        .delete(rent.account_id)
+        .unwrap_err()
+        .is_foreign_key_violation());
+    ApportionmentRepository::new(&mut connection)
+        .delete_rule(created.id)
+        .unwrap();
+    AccountRepository::new(&mut connection)
+        .delete(rent.account_id)
+        .unwrap();
+}
+
+#[test]
+fn test_amounts_follow_effective_dates_and_counterparties() {
+    let mut connection = test_util::connection();
+    let carrier = CounterpartyBuilder::new("携帯電話会社")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let rules = [
+        rule(&mut connection, "526", None, 40, date(2024, 1, 1)),
+        rule(&mut connection, "526", None, 50, date(2024, 7, 1)),
+        rule(&mut connection, "515", None, 100, date(2024, 1, 1)),
+        rule(&mut connection, "515", Some(carrier), 60, date(2024, 1, 1)),
+    ];
+    for new_rule in &rules {
+        ApportionmentRepository::new(&mut connection)
+            .create_rule(new_rule)
+            .unwrap();
+    }
+    pay(&mut connection, date(2024, 6, 30), "526", 110_000);
+    pay(&mut connection, date(2024, 7, 1), "526", 110_000);
+    pay(&mut connection, date(2024, 7, 10), "515", 5_500);
+    pay_to(
+        &mut connection,
+        date(2024, 7, 10),
+        "515",
+        3_301,
+        Some(carrier),
+    );
+    // Accounts without rules are left alone.
+    pay(&mut connection, date(2024, 7, 10), "520", 9_999);
+
+    let report = ApportionmentReport::generate(&mut connection, year(2024)).unwrap();
+    let rows = report
+        .rows
+        .iter()
+        .map(|row| {
+            (
+                row.code.as_str(),
+                row.counterparty_name.as_deref(),
+                row.business_ratio,
+                row.original,
+                row.business,
+                row.private,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            ("515", None, 100, 5_500, 5_500, 0),
+            ("515", Some("携帯電話会社"), 60, 3_301, 1_980, 1_321),
+            ("526", None, 40, 110_000, 44_000, 66_000),
+            ("526", None, 50, 110_000, 55_000, 55_000),
+        ]
+    );
+    assert_eq!(
+        (
+            report.total_original,
+            report.total_business,
+            report.total_private
+        ),
+        (228_801, 106_480, 122_321)
+    );
+    assert!(report.to_table().contains("家事按分"));
+}
+
+#[test]
+fn test_year_end_entry_moves_private_share_to_drawings() {
+    let mut connection = test_util::connection();
+    let year = FiscalYearRepository::new(&mut connection)
+        .create(&NewFiscalYear {
+            start_date: date(2024, 1, 1),
+            end_date: date(2024, 12, 31),
+        })
+        .unwrap();
+    let new_rule = rule(&mut connection, "526", None, 40, date(2024, 1, 1));
+    let mut repository = ApportionmentRepository::new(&mut connection);
+    repository.create_rule(&new_rule).unwrap();
+    // Nothing to apportion yet.
+    assert!(matches!(
+        repository.post_adjustments(year.id),
+        Err(Error::Validation(_))
+    ));
+
+    pay(&mut connection, date(2024, 3, 31), "526", 110_000);
+    pay(&mut connection, date(2024, 4, 30), "526", 110_000);
+    let (rent, drawings) = (
+        account_id(&mut connection, "526"),
+        account_id(&mut connection, "191"),
+    );
+    let entry = ApportionmentRepository::new(&mut connection)
+        .post_adjustments(year.id)
+        .unwrap();
+    assert_eq!(entry.entry.entry_date, date(2024, 12, 31));
+    let lines = entry
+        .lines
+        .iter()
+        .map(|line| (line.account_id, line.side, line.amount, line.tax_category))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            (drawings, Side::Debit, 132_000, TaxCategory::OutOfScope),
+            (rent, Side::Credit, 132_000, TaxCategory::Taxable10),
+        ]
+    );
+    assert!(matches!(
+        ApportionmentRepository::new(&mut connection).post_adjustments(year.id),
+        Err(Error::Validation(_))
+    ));
+
+    // The adjustment itself is not apportioned again.
+    let amounts = ApportionmentRepository::new(&mut connection)
+        .amounts(year.start_date, year.end_date)
+        .unwrap();
+    assert_eq!(amounts.len(), 1);
+    assert_eq!(amounts[0].original, 220_000);
+
+    // Closing keeps the entry already posted.
+    let closed = FiscalYearRepository::new(&mut connection)
+        .close(year.id)
+        .unwrap();
+    assert_eq!(closed.apportionment_entry_id, Some(entry.entry.id));
+}
+
+#[test]
+fn test_closing_posts_adjustments() {
+    let mut connection = test_util::connection();
+    let year = FiscalYearRepository::new(&mut connection)
+        .create(&NewFiscalYear {
+            start_date: date(2024, 1, 1),
+            end_date: date(2024, 12, 31),
+        })
+        .unwrap();
+    let new_rule = rule(&mut connection, "513", None, 30, date(2024, 1, 1));
+    ApportionmentRepository::new(&mut connection)
+        .create_rule(&new_rule)
+        .unwrap();
+    pay(&mut connection, date(2024, 8, 31), "513", 20_000);
+
+    let closed = FiscalYearRepository::new(&mut connection)
+        .close(year.id)
+        .unwrap();
+    let entry = JournalRepository::new(&mut connection)
+        .find(closed.apportionment_entry_id.unwrap())
+        .unwrap();
+    assert_eq!(entry.entry.memo, "家事按分");
+    assert_eq!(entry.lines[0].amount, 14_000);
+}
+
+#[tokio::test]
+async fn test_http_apportionment() {
+    let mut db = TestDb::temp_file();
+    let rent = account_id(db.conn(), "526");
+    pay(db.conn(), date(2024, 5, 31), "526", 100_000);
+    let app = http::router(db.pool());
+
+    let new_rule = json!({
+        "account_id": rent,
+        "business_ratio": 25,
+        "effective_from": "2024-01-01"
+    });
+    let response = send(
+        &app,
+        request("POST", "/apportionment/rules", Some(new_rule)),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let created = response.json();
+    assert_eq!(created["counterparty_id"], Value::Null);
+
+    let uri = "/reports/apportionment?from=2024-01-01&to=2024-12-31";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["total_private"], 75_000);
+
+    let uri = format!("/apportionment/rules/{}", created["id"]);
+    let response = send(&app, request("DELETE", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+}
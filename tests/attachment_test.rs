@@ -0,0 +1,382 @@
+use std::fs;
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use chrono::NaiveDate;
+use diesel::{sql_query, RunQueryDsl, SqliteConnection};
+use serde_json::{json, Value};
+
+use new_tax_account_backend::attachment::{self, FileStore};
+use new_tax_account_backend::models::{
+    Attachment, AttachmentAction, AttachmentChanges, NewAttachment,
+};
+use new_tax_account_backend::repository::{
+    AttachmentQuery, AttachmentRepository, CounterpartyRepository,
+};
+use new_tax_account_backend::test_util::{
+    self, date, request, send, CounterpartyBuilder, EntryBuilder, TestDb,
+};
+use new_tax_account_backend::{http, Error};
+
+/// Supplies bought in cash.
+fn entry(connection: &mut SqliteConnection, entry_date: NaiveDate, amount: i64) -> i32 {
+    EntryBuilder::new(entry_date)
+        .debit("520", amount)
+        .credit("101", amount)
+        .insert(connection)
+        .unwrap()
+        .entry
+        .id
+}
+
+fn receipt(
+    file_name: &str,
+    transaction_date: NaiveDate,
+    amount: i64,
+    counterparty_id: Option<i32>,
+) -> NewAttachment {
+    NewAttachment {
+        file_name: file_name.to_string(),
+        content_type: attachment::content_type(file_name).to_string(),
+        transaction_date,
+        amount,
+        counterparty_id,
+        ..Default::default()
+    }
+}
+
+fn ids(attachments: &[Attachment]) -> Vec<i32> {
+    attachments.iter().map(|attachment| attachment.id).collect()
+}
+
+#[test]
+fn test_store_and_search() {
+    let mut db = TestDb::temp_file();
+    let shop = CounterpartyBuilder::new("文具店")
+        .insert(db.conn())
+        .unwrap()
+        .id;
+    let landlord = CounterpartyBuilder::new("大家")
+        .insert(db.conn())
+        .unwrap()
+        .id;
+    let entry_id = entry(db.conn(), date(2024, 1, 10), 3_300);
+
+    let mut repository = AttachmentRepository::new(db.conn());
+    let pens = repository
+        .create(
+            &NewAttachment {
+                entry_id: Some(entry_id),
+                ..receipt("pens.pdf", date(2024, 1, 10), 3_300, Some(shop))
+            },
+            b"receipt for pens",
+        )
+        .unwrap();
+    let paper = repository
+        .create(
+            &receipt("paper.png", date(2024, 2, 3), 1_100, Some(shop)),
+            b"receipt for paper",
+        )
+        .unwrap();
+    let rent = repository
+        .create(
+            &receipt("rent.pdf", date(2024, 2, 27), 80_000, Some(landlord)),
+            b"rent invoice",
+        )
+        .unwrap();
+
+    // Files are named by their hash beside the database.
+    assert_eq!(pens.sha256, attachment::sha256(b"receipt for pens"));
+    assert_eq!(pens.size, 16);
+    assert_eq!(pens.content_type, "application/pdf");
+    let store = FileStore::new(db.dir().unwrap().join(attachment::DIRECTORY));
+    assert_eq!(
+        fs::read(store.path(&pens.sha256)).unwrap(),
+        b"receipt for pens"
+    );
+    assert_eq!(
+        AttachmentRepository::new(db.conn())
+            .content(paper.id)
+            .unwrap()
+            .1,
+        b"receipt for paper"
+    );
+
+    let mut search =
+        |query: AttachmentQuery| ids(&AttachmentRepository::new(db.conn()).search(&query).unwrap());
+    assert_eq!(
+        search(AttachmentQuery::default()),
+        vec![pens.id, paper.id, rent.id]
+    );
+    assert_eq!(
+        search(AttachmentQuery {
+            from: Some(date(2024, 2, 1)),
+            to: Some(date(2024, 2, 29)),
+            ..Default::default()
+        }),
+        vec![paper.id, rent.id]
+    );
+    assert_eq!(
+        search(AttachmentQuery {
+            min_amount: Some(1_100),
+            max_amount: Some(3_300),
+            ..Default::default()
+        }),
+        vec![pens.id, paper.id]
+    );
+    assert_eq!(
+        search(AttachmentQuery {
+            counterparty_id: Some(shop),
+            from: Some(date(2024, 2, 1)),
+            ..Default::default()
+        }),
+        vec![paper.id]
+    );
+    assert_eq!(
+        search(AttachmentQuery {
+            entry_id: Some(entry_id),
+            ..Default::default()
+        }),
+        vec![pens.id]
+    );
+
+    // The same content is stored once.
+    let copy = AttachmentRepository::new(db.conn())
+        .create(
+            &receipt("pens (copy).pdf", date(2024, 1, 10), 3_300, Some(shop)),
+            b"receipt for pens",
+        )
+        .unwrap();
+    assert_eq!(copy.sha256, pens.sha256);
+    assert_eq!(AttachmentRepository::new(db.conn()).verify().unwrap(), 4);
+}
+
+#[test]
+fn test_counterparties_with_attachments_cannot_be_deleted() {
+    let mut db = TestDb::temp_file();
+    let shop = CounterpartyBuilder::new("文具店")
+        .insert(db.conn())
+        .unwrap()
+        .id;
+    AttachmentRepository::new(db.conn())
+        .create(
+            &receipt("pens.pdf", date(2024, 1, 10), 3_300, Some(shop)),
+            b"receipt for pens",
+        )
+        .unwrap();
+
+    assert!(CounterpartyRepository::new(db.conn())
+        .delete(shop)
+        .unwrap_err()
+        .is_foreign_key_violation());
+}
+
+#[test]
+fn test_validation() {
+    let mut db = TestDb::temp_file();
+    let mut repository = AttachmentRepository::new(db.conn());
+    assert!(matches!(
+        repository.create(&receipt(" ", date(2024, 1, 1), 100, None), b"x"),
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        repository.create(&receipt("a.pdf", date(2024, 1, 1), -1, None), b"x"),
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        repository.create(&receipt("a.pdf", date(2024, 1, 1), 100, Some(999)), b"x"),
+        Err(Error::NotFound(_))
+    ));
+    assert!(matches!(
+        repository.create(
+            &NewAttachment {
+                entry_id: Some(999),
+                ..receipt("a.pdf", date(2024, 1, 1), 100, None)
+            },
+            b"x"
+        ),
+        Err(Error::NotFound(_))
+    ));
+
+    // Files are kept beside the database, so one is needed.
+    let mut connection = test_util::connection();
+    assert!(matches!(
+        AttachmentRepository::new(&mut connection)
+            .create(&receipt("a.pdf", date(2024, 1, 1), 100, None), b"x"),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[test]
+fn test_history_and_deletion() {
+    let mut db = TestDb::temp_file();
+    let entry_id = entry(db.conn(), date(2024, 3, 1), 5_500);
+    let mut repository = AttachmentRepository::new(db.conn());
+    let created = repository
+        .create(
+            &receipt("toner.pdf", date(2024, 3, 1), 5_000, None),
+            b"toner",
+        )
+        .unwrap();
+
+    let corrected = repository
+        .update(
+            created.id,
+            &AttachmentChanges {
+                amount: Some(5_500),
+                entry_id: Some(Some(entry_id)),
+                ..Default::default()
+            },
+            "金額の読み取り誤り",
+        )
+        .unwrap();
+    assert_eq!(
+        (corrected.amount, corrected.entry_id),
+        (5_500, Some(entry_id))
+    );
+    let deleted = repository.delete(created.id, "重複登録").unwrap();
+    assert!(deleted.deleted_at.is_some());
+
+    // Deleted attachments are left out of searches unless asked for and
+    // can no longer be changed.
+    assert!(repository
+        .search(&AttachmentQuery::default())
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        ids(&repository
+            .search(&AttachmentQuery {
+                include_deleted: true,
+                ..Default::default()
+            })
+            .unwrap()),
+        vec![created.id]
+    );
+    assert!(matches!(
+        repository.update(created.id, &AttachmentChanges::default(), ""),
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        repository.delete(created.id, ""),
+        Err(Error::Validation(_))
+    ));
+
+    let history = repository.history(created.id).unwrap();
+    let versions = history
+        .iter()
+        .map(|revision| {
+            (
+                revision.action,
+                revision.reason.as_str(),
+                revision.attachment.amount,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        versions,
+        vec![
+            (AttachmentAction::Created, "", 5_000),
+            (AttachmentAction::Updated, "金額の読み取り誤り", 5_500),
+            (AttachmentAction::Deleted, "重複登録", 5_500),
+        ]
+    );
+    assert_eq!(history[0].attachment, created);
+    assert_eq!(history[2].attachment, deleted);
+    assert!(matches!(repository.history(999), Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_evidence_cannot_be_altered() {
+    let mut db = TestDb::temp_file();
+    let created = AttachmentRepository::new(db.conn())
+        .create(&receipt("fuel.pdf", date(2024, 4, 1), 4_000, None), b"fuel")
+        .unwrap();
+
+    // The database refuses to rewrite history, content or records.
+    for statement in [
+        "UPDATE attachment_history SET reason = 'x'",
+        "DELETE FROM attachment_history",
+        "DELETE FROM attachments",
+        "UPDATE attachments SET sha256 = lower(hex(randomblob(32)))",
+    ] {
+        assert!(
+            sql_query(statement).execute(db.conn()).is_err(),
+            "{}",
+            statement
+        );
+    }
+
+    // A record changed around the repository no longer matches its history.
+    sql_query("UPDATE attachments SET amount = 400")
+        .execute(db.conn())
+        .unwrap();
+    assert!(matches!(
+        AttachmentRepository::new(db.conn()).verify(),
+        Err(Error::Integrity(message)) if message.contains("without a history record")
+    ));
+
+    // Neither does a file changed on disk.
+    let path = FileStore::new(db.dir().unwrap().join(attachment::DIRECTORY)).path(&created.sha256);
+    let mut permissions = fs::metadata(&path).unwrap().permissions();
+    assert!(permissions.readonly());
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(&path, permissions).unwrap();
+    fs::write(&path, b"petrol").unwrap();
+    assert!(matches!(
+        AttachmentRepository::new(db.conn()).content(created.id),
+        Err(Error::Integrity(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_http_attachments() {
+    let mut db = TestDb::temp_file();
+    let shop = CounterpartyBuilder::new("文具店")
+        .insert(db.conn())
+        .unwrap()
+        .id;
+    let app = http::router(db.pool());
+
+    let upload = Request::post(format!(
+        "/attachments?file_name=pens.pdf&transaction_date=2024-01-10&amount=3300&counterparty_id={}",
+        shop
+    ))
+    .header(header::CONTENT_TYPE, "application/pdf")
+    .body(Body::from("%PDF-1.4 pens"))
+    .unwrap();
+    let response = send(&app, upload).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let created = response.json();
+    assert_eq!(created["content_type"], "application/pdf");
+    assert_eq!(created["sha256"], attachment::sha256(b"%PDF-1.4 pens"));
+    let id = created["id"].as_i64().unwrap();
+
+    let uri = format!(
+        "/attachments?from=2024-01-01&to=2024-01-31&min_amount=3000&counterparty_id={}",
+        shop
+    );
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json().as_array().unwrap().len(), 1);
+
+    let uri = format!("/attachments/{}/content", id);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("content-type"), Some("application/pdf"));
+    assert_eq!(response.body, b"%PDF-1.4 pens");
+
+    let uri = format!("/attachments/{}?reason=typo", id);
+    let changes = json!({ "amount": 3_000, "counterparty_id": null });
+    let response = send(&app, request("PATCH", &uri, Some(changes))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let updated = response.json();
+    assert_eq!(updated["amount"], 3_000);
+    assert_eq!(updated["counterparty_id"], Value::Null);
+
+    let uri = format!("/attachments/{}/history", id);
+    let history = send(&app, request("GET", &uri, None)).await.json();
+    assert_eq!(history[1]["action"], "updated");
+    assert_eq!(history[1]["reason"], "typo");
+    assert_eq!(history[0]["attachment"]["amount"], 3_300);
+}
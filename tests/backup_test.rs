@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::Path;
+
+use diesel::prelude::*;
+
+use new_tax_account_backend::test_util::{PostBuilder, TestDb};
+use new_tax_account_backend::*;
+
+fn create_database(titles: &[&str]) -> TestDb {
+    let mut db = TestDb::temp_file();
+    for t in titles {
+        PostBuilder::new(t).insert(db.conn()).unwrap();
+    }
+    db
+}
+
+fn titles(path: &Path) -> Vec<String> {
+    use self::schema::posts::dsl as posts;
+    let mut connection = SqliteConnection::establish(path.to_str().unwrap()).unwrap();
+    posts::posts
+        .select(posts::title)
+        .order_by(posts::id)
+        .load(&mut connection)
+        .unwrap()
+}
+
+#[test]
+fn test_backup_while_connected() {
+    // The connection held by `db` stays open while the backup is taken.
+    let db = create_database(&["title1", "title2"]);
+    let database = db.path().unwrap();
+    let dest = db.dir().unwrap().join("copy.db");
+
+    backup::backup(&database, &dest).unwrap();
+
+    assert!(backup::verify(&dest).is_ok());
+    assert_eq!(titles(&dest), vec!["title1", "title2"]);
+}
+
+#[test]
+fn test_restore() {
+    let mut db = create_database(&["title1"]);
+    let database = db.path().unwrap();
+    let dest = db.dir().unwrap().join("copy.db");
+
+    backup::backup(&database, &dest).unwrap();
+    {
+        use self::schema::posts::dsl as posts;
+        diesel::delete(posts::posts).execute(db.conn()).unwrap();
+    }
+    assert!(titles(&database).is_empty());
+
+    backup::restore(&dest, &database).unwrap();
+    assert_eq!(titles(&database), vec!["title1"]);
+}
+
+#[test]
+fn test_restore_rejects_corrupt_backup() {
+    let db = create_database(&["title1"]);
+    let database = db.path().unwrap();
+    let corrupt = db.dir().unwrap().join("corrupt.db");
+
+    fs::write(&corrupt, b"this is not a sqlite database").unwrap();
+
+    assert!(backup::verify(&corrupt).is_err());
+    assert!(backup::restore(&corrupt, &database).is_err());
+    assert_eq!(titles(&database), vec!["title1"]);
+}
+
+#[test]
+fn test_backup_rotation() {
+    let db = create_database(&["title1"]);
+    let database = db.path().unwrap();
+    let backups = db.dir().unwrap().join("backups");
+
+    let mut written = Vec::new();
+    for _ in 0..5 {
+        written.push(backup::backup_rotated(&database, &backups, 3).unwrap());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+
+    let remaining = backup::list_backups(&database, &backups).unwrap();
+    assert_eq!(remaining, written[2..].to_vec());
+}
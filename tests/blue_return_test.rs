@@ -0,0 +1,218 @@
+use axum::http::StatusCode;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::models::{
+    AccountType, NewAccount, NewApportionmentRule, ReturnLine, TaxCategory,
+};
+use new_tax_account_backend::report::BlueReturn;
+use new_tax_account_backend::repository::{
+    AccountRepository, ApportionmentRepository, ReturnLineRepository,
+};
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, year, CounterpartyBuilder, EntryBuilder, TestDb,
+};
+use new_tax_account_backend::{http, Error};
+
+/// A year of sales, purchases and rent for a home office used 40% for
+/// business.
+fn sample_year(connection: &mut SqliteConnection) {
+    let landlord = CounterpartyBuilder::new("大家 太郎")
+        .address("東京都千代田区1-1")
+        .insert(connection)
+        .unwrap()
+        .id;
+    let rule = NewApportionmentRule {
+        account_id: account_id(connection, "526"),
+        counterparty_id: Some(landlord),
+        business_ratio: 40,
+        effective_from: date(2024, 1, 1),
+    };
+    ApportionmentRepository::new(connection)
+        .create_rule(&rule)
+        .unwrap();
+
+    for (entry_date, debit, credit, amount) in [
+        (date(2024, 1, 31), "111", "401", 500_000),
+        (date(2024, 3, 31), "111", "401", 300_000),
+        (date(2024, 3, 31), "111", "411", 10_000),
+        (date(2024, 2, 10), "501", "111", 200_000),
+        (date(2024, 5, 1), "520", "111", 30_000),
+        (date(2024, 6, 1), "528", "111", 5_000),
+    ] {
+        EntryBuilder::new(entry_date)
+            .debit(debit, amount)
+            .credit(credit, amount)
+            .insert(connection)
+            .unwrap();
+    }
+    for month in 1..=12 {
+        EntryBuilder::new(date(2024, month, 25))
+            .debit("526", 100_000)
+            .counterparty(landlord)
+            .credit("111", 100_000)
+            .insert(connection)
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_line_mapping() {
+    let mut connection = test_util::connection();
+    let (fees, sales, supplies) = (
+        account_id(&mut connection, "528"),
+        account_id(&mut connection, "401"),
+        account_id(&mut connection, "520"),
+    );
+    let toner = AccountRepository::new(&mut connection)
+        .create(&NewAccount {
+            code: "520-1".to_string(),
+            name: "トナー".to_string(),
+            kana: String::new(),
+            account_type: AccountType::Expense,
+            tax_category: TaxCategory::Taxable10,
+            parent_id: Some(supplies),
+        })
+        .unwrap();
+    let mut repository = ReturnLineRepository::new(&mut connection);
+
+    let lines = repository.resolve().unwrap();
+    assert_eq!(lines[&sales], ReturnLine::Sales);
+    assert_eq!(lines[&fees], ReturnLine::Other);
+    assert_eq!(lines[&toner.id], ReturnLine::Supplies);
+
+    repository.set(fees, ReturnLine::Communication).unwrap();
+    repository.set(fees, ReturnLine::Miscellaneous).unwrap();
+    assert_eq!(
+        repository.resolve().unwrap()[&fees],
+        ReturnLine::Miscellaneous
+    );
+    for (account_id, line) in [
+        (sales, ReturnLine::Rent),
+        (fees, ReturnLine::MiscIncome),
+        (toner.id, ReturnLine::Repairs),
+    ] {
+        assert!(matches!(
+            repository.set(account_id, line),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    repository.clear(fees).unwrap();
+    assert_eq!(repository.resolve().unwrap()[&fees], ReturnLine::Other);
+    assert!(matches!(repository.clear(fees), Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_blue_return_pages() {
+    let mut connection = test_util::connection();
+    sample_year(&mut connection);
+    let report = BlueReturn::generate(&mut connection, year(2024)).unwrap();
+
+    let statement = &report.income_statement;
+    assert_eq!(statement.sales, 810_000);
+    assert_eq!(statement.purchases, 200_000);
+    assert_eq!(statement.gross_profit, 610_000);
+    let expense = |label: &str| {
+        statement
+            .expenses
+            .iter()
+            .find(|item| item.label == label)
+            .map(|item| item.amount)
+    };
+    // Statutory lines are printed even when empty; other accounts only
+    // when used, just before 雑費.
+    assert_eq!(expense("租税公課"), Some(0));
+    assert_eq!(expense("消耗品費"), Some(30_000));
+    assert_eq!(expense("地代家賃"), Some(1_200_000));
+    assert_eq!(expense("支払手数料"), Some(5_000));
+    assert_eq!(expense("会議費"), None);
+    let labels = statement
+        .expenses
+        .iter()
+        .map(|item| item.label.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(labels[labels.len() - 2..], ["支払手数料", "雑費"]);
+    assert_eq!(statement.expense_total, 1_235_000);
+    assert_eq!(statement.income, -625_000);
+    assert_eq!(report.balance_sheet.income.current, statement.income);
+
+    let monthly = &report.monthly;
+    assert_eq!(monthly.months.len(), 12);
+    assert_eq!(
+        (monthly.months[0].sales, monthly.months[2].sales),
+        (500_000, 300_000)
+    );
+    assert_eq!(monthly.months[1].purchases, 200_000);
+    assert_eq!(monthly.misc_income, 10_000);
+    assert_eq!(monthly.total_sales, statement.sales);
+    assert_eq!(monthly.total_purchases, 200_000);
+
+    // Rent is broken down per landlord with the business share.
+    assert_eq!(report.rent.len(), 1);
+    assert_eq!(report.rent[0].name, "大家 太郎");
+    assert_eq!(
+        (report.rent[0].rent, report.rent[0].business),
+        (1_200_000, 480_000)
+    );
+
+    let table = report.to_table();
+    for title in ["損益計算書", "月別売上", "地代家賃の内訳", "貸借対照表"] {
+        assert!(table.contains(title), "{}", title);
+    }
+    assert_eq!(
+        report.to_html().matches("<section class=\"page\">").count(),
+        4
+    );
+}
+
+#[test]
+fn test_pdf_output() {
+    let mut connection = test_util::connection();
+    sample_year(&mut connection);
+    let pdf = BlueReturn::generate(&mut connection, year(2024))
+        .unwrap()
+        .to_pdf();
+    let text = String::from_utf8_lossy(&pdf);
+
+    assert!(pdf.starts_with(b"%PDF-1.4\n"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert!(text.contains("/Type /Pages"));
+    assert!(text.contains("/Count 4"));
+    // The cross-reference table is where the trailer says it is.
+    let offset = text
+        .lines()
+        .skip_while(|line| *line != "startxref")
+        .nth(1)
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+    assert!(pdf[offset..].starts_with(b"xref\n"));
+}
+
+#[tokio::test]
+async fn test_http_blue_return() {
+    let mut db = TestDb::temp_file();
+    sample_year(db.conn());
+    let fees = account_id(db.conn(), "528");
+    let app = http::router(db.pool());
+
+    let uri = format!("/return-lines/{}", fees);
+    let line = json!({ "line": "miscellaneous" });
+    let response = send(&app, request("PUT", &uri, Some(line))).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let uri = "/reports/blue-return?from=2024-01-01&to=2024-12-31";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let report = response.json();
+    let expenses = report["income_statement"]["expenses"].as_array().unwrap();
+    assert_eq!(expenses.last().unwrap()["label"], "雑費");
+    assert_eq!(expenses.last().unwrap()["amount"], 5_000);
+
+    let uri = "/reports/blue-return?from=2024-01-01&to=2024-12-31&format=pdf";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("content-type"), Some("application/pdf"));
+    assert!(response.body.starts_with(b"%PDF-"));
+}
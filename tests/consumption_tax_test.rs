@@ -0,0 +1,200 @@
+use axum::http::StatusCode;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::http;
+use new_tax_account_backend::models::{
+    JournalEntryWithLines, NewJournalLine, Rounding, TaxCategory, TaxMethod, TaxSettingsChanges,
+};
+use new_tax_account_backend::report::ConsumptionTaxReport;
+use new_tax_account_backend::repository::{JournalRepository, TaxSettingsRepository};
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, year, EntryBuilder, TestDb,
+};
+
+/// A sale of `amount` for cash, tax included.
+fn cash_sale(amount: i64) -> EntryBuilder {
+    EntryBuilder::new(date(2024, 5, 1))
+        .debit("101", amount)
+        .credit("401", amount)
+}
+
+fn set_method(connection: &mut SqliteConnection, method: TaxMethod, rounding: Rounding) {
+    TaxSettingsRepository::new(connection)
+        .update(&TaxSettingsChanges {
+            method: Some(method),
+            rounding: Some(rounding),
+        })
+        .unwrap();
+}
+
+/// (account id, amount, tax amount) of each line.
+fn amounts(entry: &JournalEntryWithLines) -> Vec<(i32, i64, i64)> {
+    entry
+        .lines
+        .iter()
+        .map(|line| (line.account_id, line.amount, line.tax_amount))
+        .collect()
+}
+
+#[test]
+fn test_inclusive_method_records_tax_on_the_line() {
+    let mut connection = test_util::connection();
+    let (cash, sales) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "401"),
+    );
+
+    let entry = cash_sale(11_000).insert(&mut connection).unwrap();
+
+    assert_eq!(
+        amounts(&entry),
+        vec![(cash, 11_000, 0), (sales, 11_000, 1_000)]
+    );
+    assert_eq!(entry.lines[0].tax_category, TaxCategory::OutOfScope);
+    assert_eq!(entry.lines[1].tax_category, TaxCategory::Taxable10);
+    assert!(entry.lines[1].tax_included);
+    assert_eq!(entry.lines[1].base_amount(), 10_000);
+}
+
+#[test]
+fn test_exclusive_method_splits_tax() {
+    let mut connection = test_util::connection();
+    let (cash, sales, supplies, input_tax, output_tax) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "401"),
+        account_id(&mut connection, "520"),
+        account_id(&mut connection, "144"),
+        account_id(&mut connection, "215"),
+    );
+    set_method(&mut connection, TaxMethod::Exclusive, Rounding::Floor);
+
+    let sale = cash_sale(11_000).insert(&mut connection).unwrap();
+    assert_eq!(
+        amounts(&sale),
+        vec![
+            (cash, 11_000, 0),
+            (sales, 10_000, 1_000),
+            (output_tax, 1_000, 0)
+        ]
+    );
+    assert!(!sale.lines[1].tax_included);
+    assert_eq!(sale.lines[2].tax_category, TaxCategory::OutOfScope);
+
+    // Entered before tax: the cash line carries the gross amount.
+    let purchase = EntryBuilder::new(date(2024, 5, 1))
+        .line(
+            NewJournalLine::debit(supplies, 1_080)
+                .tax(TaxCategory::Reduced8)
+                .excluding_tax(),
+        )
+        .credit("101", 1_166)
+        .insert(&mut connection)
+        .unwrap();
+    assert_eq!(
+        amounts(&purchase),
+        vec![(supplies, 1_080, 86), (input_tax, 86, 0), (cash, 1_166, 0)]
+    );
+}
+
+#[test]
+fn test_rounding() {
+    assert_eq!(Rounding::Floor.divide(10_050, 110), 91);
+    assert_eq!(Rounding::Round.divide(10_050, 110), 91);
+    assert_eq!(Rounding::Round.divide(10_100, 110), 92);
+    assert_eq!(Rounding::Ceil.divide(10_050, 110), 92);
+    assert_eq!(Rounding::Ceil.divide(11_000, 110), 100);
+
+    // Returns round like the sales they reverse.
+    assert_eq!(Rounding::Round.divide(55, 110), 1);
+    assert_eq!(Rounding::Round.divide(-55, 110), -1);
+    assert_eq!(Rounding::Round.divide(-10_050, 110), -91);
+    assert_eq!(Rounding::Round.divide(-10_100, 110), -92);
+
+    let mut connection = test_util::connection();
+    set_method(&mut connection, TaxMethod::Inclusive, Rounding::Ceil);
+    let entry = cash_sale(1_005).insert(&mut connection).unwrap();
+    assert_eq!(entry.lines[1].tax_amount, 92);
+}
+
+fn sample_entries(connection: &mut SqliteConnection) {
+    cash_sale(110_000).insert(connection).unwrap();
+    cash_sale(10_800)
+        .tax(TaxCategory::Reduced8)
+        .insert(connection)
+        .unwrap();
+    cash_sale(50_000)
+        .tax(TaxCategory::Exempt)
+        .insert(connection)
+        .unwrap();
+    EntryBuilder::new(date(2024, 5, 1))
+        .debit("526", 33_000)
+        .credit("101", 33_000)
+        .insert(connection)
+        .unwrap();
+    let voided = cash_sale(99_000).insert(connection).unwrap();
+    JournalRepository::new(connection)
+        .void(voided.entry.id)
+        .unwrap();
+}
+
+#[test]
+fn test_report_by_rate() {
+    let mut connection = test_util::connection();
+    sample_entries(&mut connection);
+
+    let report = ConsumptionTaxReport::generate(&mut connection, year(2024)).unwrap();
+
+    let summary = |category: TaxCategory| {
+        report
+            .categories
+            .iter()
+            .find(|summary| summary.tax_category == category)
+            .map(|s| (s.sales_base, s.sales_tax, s.purchase_base, s.purchase_tax))
+            .unwrap()
+    };
+    assert_eq!(report.method, TaxMethod::Inclusive);
+    assert_eq!(
+        summary(TaxCategory::Taxable10),
+        (100_000, 10_000, 30_000, 3_000)
+    );
+    assert_eq!(summary(TaxCategory::Reduced8), (10_000, 800, 0, 0));
+    assert_eq!(summary(TaxCategory::Exempt), (50_000, 0, 0, 0));
+    assert_eq!(report.output_tax, 10_800);
+    assert_eq!(report.input_tax, 3_000);
+    assert_eq!(report.taxable_base, 110_000);
+    assert_eq!(report.national_output_tax, 7_800 + 624);
+    assert_eq!(report.national_input_tax, 2_340);
+    assert_eq!(report.national_tax_due, 6_000);
+    assert_eq!(report.local_tax_due, 1_600);
+    assert_eq!(report.total_due, 7_600);
+
+    // The same figures come out when tax is split into its own accounts.
+    let mut connection = test_util::connection();
+    set_method(&mut connection, TaxMethod::Exclusive, Rounding::Floor);
+    sample_entries(&mut connection);
+    let split = ConsumptionTaxReport::generate(&mut connection, year(2024)).unwrap();
+    assert_eq!(split.categories, report.categories);
+    assert_eq!(split.total_due, 7_600);
+}
+
+#[tokio::test]
+async fn test_http_settings_and_report() {
+    let mut db = TestDb::temp_file();
+    sample_entries(db.conn());
+    let app = http::router(db.pool());
+
+    let changes = json!({ "rounding": "round" });
+    let response = send(&app, request("PATCH", "/tax-settings", Some(changes))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let settings = response.json();
+    assert_eq!(settings["method"], "inclusive");
+    assert_eq!(settings["rounding"], "round");
+
+    let uri = "/reports/consumption-tax?from=2024-01-01&to=2024-12-31";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let report = response.json();
+    assert_eq!(report["categories"][0]["tax_category"], "taxable_10");
+    assert_eq!(report["total_due"], 7_600);
+}
@@ -0,0 +1,292 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::models::{
+    CounterpartyChanges, NewApportionmentRule, NewCounterparty, NewImportRule, NewJournalEntry,
+    NewJournalLine, NewRecurringEntry, Schedule, TaxCategory,
+};
+use new_tax_account_backend::report::{ConsumptionTaxReport, Period};
+use new_tax_account_backend::repository::counterparty::is_valid_registration_number;
+use new_tax_account_backend::repository::{
+    ApportionmentRepository, CounterpartyRepository, ImportRepository, JournalRepository,
+    RecurringEntryRepository,
+};
+use new_tax_account_backend::test_util::{self, account_id, date, request, send, TestDb};
+use new_tax_account_backend::{http, Error};
+
+fn supplier(name: &str, registration_number: Option<&str>) -> NewCounterparty {
+    NewCounterparty {
+        name: name.to_string(),
+        registration_number: registration_number.map(str::to_string),
+        qualified: registration_number.is_some(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_registration_number_check_digit() {
+    assert!(is_valid_registration_number("T7000012050002"));
+    assert!(is_valid_registration_number("T7123456789012"));
+    assert!(is_valid_registration_number("T9111111111111"));
+
+    assert!(!is_valid_registration_number("T8123456789012"));
+    assert!(!is_valid_registration_number("7123456789012"));
+    assert!(!is_valid_registration_number("T712345678901"));
+    assert!(!is_valid_registration_number("T712345678901A"));
+}
+
+#[test]
+fn test_create_and_update_validation() {
+    let mut connection = test_util::connection();
+    let mut repository = CounterpartyRepository::new(&mut connection);
+
+    let created = repository
+        .create(&supplier("株式会社サンプル", Some("T7123456789012")))
+        .unwrap();
+    assert!(created.qualified);
+    assert!(created.qualified_on(date(2024, 1, 1)));
+
+    for invalid in [
+        supplier("", None),
+        supplier("番号誤り", Some("T8123456789012")),
+        supplier("重複", Some("T7123456789012")),
+        NewCounterparty {
+            qualified: true,
+            ..supplier("番号なし", None)
+        },
+        NewCounterparty {
+            valid_from: Some(date(2024, 2, 1)),
+            valid_until: Some(date(2024, 1, 31)),
+            ..supplier("期間誤り", Some("T9111111111111"))
+        },
+    ] {
+        assert!(matches!(
+            repository.create(&invalid),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    let updated = repository
+        .update(
+            created.id,
+            &CounterpartyChanges {
+                valid_until: Some(Some(date(2024, 3, 31))),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert!(updated.qualified_on(date(2024, 3, 31)));
+    assert!(!updated.qualified_on(date(2024, 4, 1)));
+
+    let cleared = CounterpartyChanges {
+        registration_number: Some(None),
+        ..Default::default()
+    };
+    assert!(matches!(
+        repository.update(created.id, &cleared),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[test]
+fn test_journal_lines_refer_to_counterparties() {
+    let mut connection = test_util::connection();
+    let (cash, supplies) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "520"),
+    );
+    let counterparty = CounterpartyRepository::new(&mut connection)
+        .create(&supplier("文具店", None))
+        .unwrap();
+    let entry = |counterparty_id: i32| NewJournalEntry {
+        entry_date: date(2024, 5, 1),
+        memo: String::new(),
+        lines: vec![
+            NewJournalLine::debit(supplies, 1_100).counterparty(counterparty_id),
+            NewJournalLine::credit(cash, 1_100),
+        ],
+    };
+
+    let created = JournalRepository::new(&mut connection)
+        .create(&entry(counterparty.id))
+        .unwrap();
+    assert_eq!(created.lines[0].counterparty_id, Some(counterparty.id));
+    assert_eq!(created.lines[1].counterparty_id, None);
+
+    assert!(matches!(
+        JournalRepository::new(&mut connection).create(&entry(counterparty.id + 1)),
+        Err(Error::NotFound(_))
+    ));
+    assert!(CounterpartyRepository::new(&mut connection)
+        .delete(counterparty.id)
+        .unwrap_err()
+        .is_foreign_key_violation());
+}
+
+#[test]
+fn test_counterparties_used_by_rules_and_templates_cannot_be_deleted() {
+    let mut connection = test_util::connection();
+    let (bank, rent) = (
+        account_id(&mut connection, "111"),
+        account_id(&mut connection, "526"),
+    );
+    let mut counterparties = CounterpartyRepository::new(&mut connection);
+    let ids = ["不動産会社", "大家", "管理会社"]
+        .map(|name| counterparties.create(&supplier(name, None)).unwrap().id);
+
+    ImportRepository::new(&mut connection)
+        .create_rule(&NewImportRule {
+            priority: 100,
+            description_contains: Some("家賃".to_string()),
+            min_amount: None,
+            max_amount: None,
+            account_id: rent,
+            tax_category: None,
+            counterparty_id: Some(ids[0]),
+        })
+        .unwrap();
+    ApportionmentRepository::new(&mut connection)
+        .create_rule(&NewApportionmentRule {
+            account_id: rent,
+            counterparty_id: Some(ids[1]),
+            business_ratio: 40,
+            effective_from: date(2024, 1, 1),
+        })
+        .unwrap();
+    RecurringEntryRepository::new(&mut connection)
+        .create(&NewRecurringEntry {
+            name: "管理費".to_string(),
+            memo: String::new(),
+            schedule: Schedule::Monthly,
+            day: Some(25),
+            month: None,
+            start_date: date(2024, 1, 1),
+            end_date: None,
+            lines: vec![
+                NewJournalLine::debit(rent, 5_000).counterparty(ids[2]),
+                NewJournalLine::credit(bank, 5_000),
+            ],
+        })
+        .unwrap();
+
+    let mut counterparties = CounterpartyRepository::new(&mut connection);
+    for id in ids {
+        assert!(counterparties
+            .delete(id)
+            .unwrap_err()
+            .is_foreign_key_violation());
+    }
+}
+
+fn purchase(
+    connection: &mut SqliteConnection,
+    entry_date: NaiveDate,
+    amount: i64,
+    counterparty_id: Option<i32>,
+) {
+    let (cash, supplies) = (account_id(connection, "101"), account_id(connection, "520"));
+    let mut line = NewJournalLine::debit(supplies, amount);
+    line.counterparty_id = counterparty_id;
+    JournalRepository::new(connection)
+        .create(&NewJournalEntry {
+            entry_date,
+            memo: String::new(),
+            lines: vec![line, NewJournalLine::credit(cash, amount)],
+        })
+        .unwrap();
+}
+
+fn report(
+    connection: &mut SqliteConnection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> ConsumptionTaxReport {
+    ConsumptionTaxReport::generate(connection, Period::new(from, to).unwrap()).unwrap()
+}
+
+#[test]
+fn test_transitional_input_tax_credit() {
+    let mut connection = test_util::connection();
+    let mut repository = CounterpartyRepository::new(&mut connection);
+    let qualified = repository
+        .create(&supplier("適格", Some("T7123456789012")))
+        .unwrap();
+    let unregistered = repository.create(&supplier("免税事業者", None)).unwrap();
+    let expired = repository
+        .create(&NewCounterparty {
+            valid_until: Some(date(2024, 3, 31)),
+            ..supplier("登録取消", Some("T9111111111111"))
+        })
+        .unwrap();
+
+    let may = date(2024, 5, 1);
+    purchase(&mut connection, may, 11_000, Some(qualified.id));
+    purchase(&mut connection, may, 11_000, Some(unregistered.id));
+    purchase(&mut connection, may, 11_000, Some(expired.id));
+    purchase(&mut connection, may, 2_200, None);
+    purchase(
+        &mut connection,
+        date(2027, 1, 15),
+        11_000,
+        Some(unregistered.id),
+    );
+
+    let year = report(&mut connection, date(2024, 1, 1), date(2024, 12, 31));
+    let taxable = &year.categories[0];
+    assert_eq!(taxable.tax_category, TaxCategory::Taxable10);
+    assert_eq!(taxable.purchase_tax, 3_200);
+    assert_eq!(taxable.creditable_purchase_tax, 1_000 + 800 + 800 + 200);
+    assert_eq!(year.input_tax, 3_200);
+    assert_eq!(year.creditable_input_tax, 2_800);
+    assert_eq!(year.national_input_tax, 2_184);
+
+    let later = report(&mut connection, date(2027, 1, 1), date(2027, 12, 31));
+    assert_eq!(later.input_tax, 1_000);
+    assert_eq!(later.creditable_input_tax, 500);
+}
+
+#[tokio::test]
+async fn test_http_counterparties() {
+    let mut db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_counterparty = json!({
+        "name": "株式会社サンプル",
+        "registration_number": "T7123456789012",
+        "qualified": true
+    });
+    let response = send(
+        &app,
+        request("POST", "/counterparties", Some(new_counterparty)),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let created = response.json();
+    assert_eq!(created["registration_number"], "T7123456789012");
+
+    let mistyped = json!({ "name": "番号誤り", "registration_number": "T8123456789012" });
+    let response = send(&app, request("POST", "/counterparties", Some(mistyped))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let uri = format!("/counterparties/{}", created["id"]);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    // Still named on a journal line, so the foreign key refuses the delete.
+    let id = created["id"].as_i64().unwrap() as i32;
+    let (supplies, cash) = (account_id(db.conn(), "520"), account_id(db.conn(), "101"));
+    JournalRepository::new(db.conn())
+        .create(&NewJournalEntry {
+            entry_date: date(2024, 4, 1),
+            memo: String::new(),
+            lines: vec![
+                NewJournalLine::debit(supplies, 1_100).counterparty(id),
+                NewJournalLine::credit(cash, 1_100),
+            ],
+        })
+        .unwrap();
+    let response = send(&app, request("DELETE", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+}
@@ -1,23 +1,25 @@
+// Baseline tests, kept as written; they predate the lints of the current
+// toolchain.
+#![allow(
+    ambiguous_glob_imports,
+    clippy::get_first,
+    clippy::len_zero,
+    clippy::let_and_return
+)]
+
 use std::vec;
 
 use diesel::dsl::{count_star, sum};
 use diesel::sqlite::Sqlite;
-use dotenvy;
 
 use diesel::{debug_query, insert_into, prelude::*};
-use diesel_migrations::EmbeddedMigrations;
-use diesel_migrations::{embed_migrations, MigrationHarness};
 
 use self::models::*;
+use new_tax_account_backend::test_util::{self, PostBuilder};
 use new_tax_account_backend::*;
 
-pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
-
 fn get_connection() -> SqliteConnection {
-    dotenvy::from_filename(".env.test").expect("failed to read .env file");
-    let mut connection = establish_connection();
-    connection.run_pending_migrations(MIGRATIONS).unwrap();
-    connection
+    test_util::connection()
 }
 
 fn insert_post_simple(
@@ -45,20 +47,18 @@ fn insert_post_full(
     author: Option<&str>,
     published: bool,
     good_count: i32,
-) -> QueryResult<usize> {
-    use self::schema::posts::dsl as posts;
-    let new_record = Post {
-        title: String::from(title),
-        body: String::from(body),
-        category_id,
-        author: author.map(String::from),
-        published,
-        good_count,
-        ..Default::default()
-    };
-    insert_into(posts::posts)
-        .values(&new_record)
-        .execute(connection)
+) -> QueryResult<Post> {
+    let mut builder = PostBuilder::new(title)
+        .body(body)
+        .published(published)
+        .good_count(good_count);
+    if let Some(category_id) = category_id {
+        builder = builder.category_id(category_id);
+    }
+    if let Some(author) = author {
+        builder = builder.author(author);
+    }
+    builder.insert(connection)
 }
 
 #[test]
@@ -0,0 +1,164 @@
+use axum::http::StatusCode;
+use diesel::SqliteConnection;
+
+use new_tax_account_backend::http;
+use new_tax_account_backend::models::{AccountType, NewAccount, TaxCategory};
+use new_tax_account_backend::report::{
+    BalanceSheet, Comparison, IncomeStatement, LineKind, Statement,
+};
+use new_tax_account_backend::repository::AccountRepository;
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, year, EntryBuilder, TestDb,
+};
+
+/// Two years of trading with inventory carried over through 仕入高 and a
+/// sub-account of 消耗品費.
+fn two_years(connection: &mut SqliteConnection) {
+    let supplies = account_id(connection, "520");
+    AccountRepository::new(connection)
+        .create(&NewAccount {
+            code: "5201".to_string(),
+            name: "事務用品費".to_string(),
+            kana: String::new(),
+            account_type: AccountType::Expense,
+            tax_category: TaxCategory::Taxable10,
+            parent_id: Some(supplies),
+        })
+        .unwrap();
+
+    for (entry_date, debit, credit, amount) in [
+        (date(2023, 1, 5), "111", "301", 1_000_000),
+        (date(2023, 3, 1), "501", "202", 200_000),
+        (date(2023, 6, 1), "111", "401", 500_000),
+        (date(2023, 9, 1), "526", "111", 120_000),
+        (date(2023, 12, 31), "131", "501", 50_000),
+        (date(2024, 1, 1), "501", "131", 50_000),
+        (date(2024, 2, 1), "501", "111", 300_000),
+        (date(2024, 5, 1), "122", "401", 900_000),
+        (date(2024, 6, 1), "101", "411", 10_000),
+        (date(2024, 7, 1), "526", "111", 240_000),
+        (date(2024, 8, 1), "5201", "111", 30_000),
+        (date(2024, 12, 31), "131", "501", 80_000),
+    ] {
+        EntryBuilder::new(entry_date)
+            .debit(debit, amount)
+            .credit(credit, amount)
+            .insert(connection)
+            .unwrap();
+    }
+}
+
+fn compare(current: i64, prior: i64) -> Comparison {
+    Comparison { current, prior }
+}
+
+#[test]
+fn test_income_statement_with_prior_year() {
+    let mut connection = test_util::connection();
+    two_years(&mut connection);
+
+    let statement = IncomeStatement::generate(&mut connection, year(2024)).unwrap();
+    assert_eq!(statement.prior_period, year(2023));
+    assert_eq!(statement.sales, compare(910_000, 500_000));
+    assert_eq!(statement.opening_inventory, compare(50_000, 0));
+    assert_eq!(statement.purchases, compare(300_000, 200_000));
+    assert_eq!(statement.closing_inventory, compare(80_000, 50_000));
+    assert_eq!(statement.cost_of_sales, compare(270_000, 150_000));
+    assert_eq!(statement.gross_profit, compare(640_000, 350_000));
+
+    let expenses = statement
+        .expenses
+        .iter()
+        .map(|line| (line.label.as_str(), line.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        expenses,
+        [
+            ("消耗品費", compare(30_000, 0)),
+            ("地代家賃", compare(240_000, 120_000)),
+        ]
+    );
+    assert_eq!(statement.expense_total, compare(270_000, 120_000));
+    assert_eq!(statement.income, compare(370_000, 230_000));
+
+    let lines = statement.lines();
+    assert_eq!(lines.last().unwrap().kind, LineKind::Total);
+    assert_eq!(lines.last().unwrap().amount, statement.income);
+}
+
+#[test]
+fn test_balance_sheet_balances_before_closing() {
+    let mut connection = test_util::connection();
+    two_years(&mut connection);
+
+    let sheet = BalanceSheet::generate(&mut connection, year(2024)).unwrap();
+    assert!(sheet.balanced);
+    assert_eq!(sheet.asset_total, compare(1_800_000, 1_430_000));
+    assert_eq!(sheet.liability_total, compare(200_000, 200_000));
+    assert_eq!(sheet.income, compare(370_000, 0));
+
+    let capital = sheet
+        .equity
+        .iter()
+        .find(|line| line.code.as_deref() == Some("301"))
+        .unwrap();
+    // Last year's profit has not been closed into 元入金 yet.
+    assert_eq!(capital.amount, compare(1_230_000, 1_230_000));
+
+    let bank = sheet
+        .assets
+        .iter()
+        .find(|line| line.code.as_deref() == Some("111"))
+        .unwrap();
+    assert_eq!(bank.amount, compare(810_000, 1_380_000));
+}
+
+#[test]
+fn test_statement_renderings() {
+    let mut connection = test_util::connection();
+    two_years(&mut connection);
+    let statement = IncomeStatement::generate(&mut connection, year(2024)).unwrap();
+
+    let mut csv = Vec::new();
+    statement.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("kind,code,label,current,prior\n"));
+    assert!(csv.contains("item,526,地代家賃,240000,120000\n"));
+    assert!(csv.contains("heading,,経費,,\n"));
+
+    let html = statement.to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>損益計算書 2024-01-01 - 2024-12-31</title>"));
+    assert!(html.contains(r#"<td class="amount">370,000</td>"#));
+
+    assert!(statement
+        .to_table()
+        .contains("青色申告特別控除前の所得金額"));
+}
+
+#[tokio::test]
+async fn test_statements_over_http() {
+    let mut db = TestDb::temp_file();
+    two_years(db.conn());
+    let app = http::router(db.pool());
+
+    let uri = "/reports/income-statement?from=2024-01-01&to=2024-12-31";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["income"]["current"], 370_000);
+
+    let uri = "/reports/balance-sheet?from=2024-01-01&to=2024-12-31&format=csv";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/csv; charset=utf-8")
+    );
+
+    let uri = "/reports/balance-sheet?from=2024-01-01&to=2024-12-31&format=html";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert!(response
+        .header("content-type")
+        .unwrap()
+        .starts_with("text/html"));
+}
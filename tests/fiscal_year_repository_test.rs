@@ -0,0 +1,208 @@
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+
+use new_tax_account_backend::models::{
+    EntryKind, FiscalYearState, NewFiscalYear, NewJournalEntry, NewJournalLine, Side,
+};
+use new_tax_account_backend::report::{IncomeStatement, Period, TrialBalance};
+use new_tax_account_backend::repository::{
+    FiscalYearRepository, JournalRepository, LedgerRepository,
+};
+use new_tax_account_backend::test_util::{self, account_id, date};
+use new_tax_account_backend::Error;
+
+fn entry(entry_date: NaiveDate, debit: i32, credit: i32, amount: i64) -> NewJournalEntry {
+    NewJournalEntry {
+        entry_date,
+        memo: String::new(),
+        lines: vec![
+            NewJournalLine::debit(debit, amount),
+            NewJournalLine::credit(credit, amount),
+        ],
+    }
+}
+
+fn year(connection: &mut SqliteConnection, y: i32) -> i32 {
+    FiscalYearRepository::new(connection)
+        .create(&NewFiscalYear {
+            start_date: date(y, 1, 1),
+            end_date: date(y, 12, 31),
+        })
+        .unwrap()
+        .id
+}
+
+/// A year of trading including money moving between the owner and the
+/// business. Returns the id of the rent entry.
+fn trade_2024(connection: &mut SqliteConnection) -> i32 {
+    let [bank, drawings, contributions, capital, sales, phone, rent] =
+        ["111", "191", "291", "301", "401", "515", "526"].map(|code| account_id(connection, code));
+    let mut journal = JournalRepository::new(connection);
+    journal
+        .create(&entry(date(2024, 1, 1), bank, capital, 1_000_000))
+        .unwrap();
+    journal
+        .create(&entry(date(2024, 3, 1), bank, sales, 500_000))
+        .unwrap();
+    let rent = journal
+        .create(&entry(date(2024, 4, 1), rent, bank, 100_000))
+        .unwrap();
+    journal
+        .create(&entry(date(2024, 5, 1), drawings, bank, 50_000))
+        .unwrap();
+    journal
+        .create(&entry(date(2024, 6, 1), phone, contributions, 20_000))
+        .unwrap();
+    rent.entry.id
+}
+
+#[test]
+fn test_create_rejects_overlapping_years() {
+    let mut connection = test_util::connection();
+    year(&mut connection, 2024);
+    let mut years = FiscalYearRepository::new(&mut connection);
+
+    let overlapping = years.create(&NewFiscalYear {
+        start_date: date(2024, 7, 1),
+        end_date: date(2025, 6, 30),
+    });
+    assert!(matches!(overlapping, Err(Error::Validation(_))));
+    let inverted = years.create(&NewFiscalYear {
+        start_date: date(2025, 12, 31),
+        end_date: date(2025, 1, 1),
+    });
+    assert!(matches!(inverted, Err(Error::Validation(_))));
+    assert_eq!(
+        years
+            .find_by_date(date(2024, 8, 15))
+            .unwrap()
+            .unwrap()
+            .start_date,
+        date(2024, 1, 1)
+    );
+    assert!(years.find_by_date(date(2025, 1, 1)).unwrap().is_none());
+}
+
+#[test]
+fn test_close_transfers_profit_and_carries_balances() {
+    let mut connection = test_util::connection();
+    let id = year(&mut connection, 2024);
+    trade_2024(&mut connection);
+    let [bank, capital, sales] =
+        ["111", "301", "401"].map(|code| account_id(&mut connection, code));
+
+    let closed = FiscalYearRepository::new(&mut connection)
+        .close(id)
+        .unwrap();
+    assert_eq!(closed.state, FiscalYearState::Closed);
+
+    let mut journal = JournalRepository::new(&mut connection);
+    let closing = journal.find(closed.closing_entry_id.unwrap()).unwrap();
+    assert_eq!(closing.entry.kind, EntryKind::Closing);
+    assert_eq!(closing.entry.entry_date, date(2024, 12, 31));
+    let to_capital = closing
+        .lines
+        .iter()
+        .find(|line| line.account_id == capital)
+        .unwrap();
+    // 500,000 - 120,000 profit + 20,000 事業主借 - 50,000 事業主貸
+    assert_eq!(
+        (to_capital.side, to_capital.amount),
+        (Side::Credit, 350_000)
+    );
+
+    let opening = journal.find(closed.opening_entry_id.unwrap()).unwrap();
+    assert_eq!(opening.entry.kind, EntryKind::Opening);
+    assert_eq!(opening.entry.entry_date, date(2025, 1, 1));
+    assert_eq!(opening.lines.len(), 2);
+
+    let mut years = FiscalYearRepository::new(&mut connection);
+    let next = years.find_by_date(date(2025, 1, 1)).unwrap().unwrap();
+    assert_eq!(next.end_date, date(2025, 12, 31));
+    assert_eq!(next.state, FiscalYearState::Open);
+
+    let mut ledger = LedgerRepository::new(&mut connection);
+    assert_eq!(
+        ledger.balance_as_of(sales, date(2024, 12, 31)).unwrap(),
+        500_000
+    );
+    assert_eq!(ledger.balance_as_of(sales, date(2025, 1, 1)).unwrap(), 0);
+    assert_eq!(
+        ledger.opening_balance(capital, date(2025, 1, 1)).unwrap(),
+        1_350_000
+    );
+    assert_eq!(
+        ledger.balance_as_of(bank, date(2025, 3, 1)).unwrap(),
+        1_350_000
+    );
+
+    // Reports for the closed year still show the figures before closing.
+    let statement = IncomeStatement::generate(&mut connection, test_util::year(2024)).unwrap();
+    assert_eq!(statement.income.current, 380_000);
+
+    let trial = TrialBalance::generate(
+        &mut connection,
+        Period::new(date(2025, 1, 1), date(2025, 12, 31)).unwrap(),
+    )
+    .unwrap();
+    assert!(trial.balanced);
+    let opening_rows = trial
+        .groups
+        .iter()
+        .flat_map(|group| &group.rows)
+        .map(|row| (row.code.as_str(), row.opening_balance))
+        .collect::<Vec<_>>();
+    assert_eq!(opening_rows, [("111", 1_350_000), ("301", 1_350_000)]);
+}
+
+#[test]
+fn test_closed_period_is_locked_until_reopened() {
+    let mut connection = test_util::connection();
+    let id = year(&mut connection, 2024);
+    let rent_entry = trade_2024(&mut connection);
+    let [bank, sales] = ["111", "401"].map(|code| account_id(&mut connection, code));
+    let closed = FiscalYearRepository::new(&mut connection)
+        .close(id)
+        .unwrap();
+
+    let mut journal = JournalRepository::new(&mut connection);
+    let late = journal.create(&entry(date(2024, 12, 31), bank, sales, 1_000));
+    assert!(matches!(late, Err(Error::PeriodClosed { .. })));
+    assert!(matches!(
+        journal.void(rent_entry),
+        Err(Error::PeriodClosed { .. })
+    ));
+    assert!(matches!(
+        journal.void(closed.opening_entry_id.unwrap()),
+        Err(Error::Validation(_))
+    ));
+    journal
+        .create(&entry(date(2025, 1, 1), bank, sales, 1_000))
+        .unwrap();
+
+    let mut years = FiscalYearRepository::new(&mut connection);
+    let reopened = years.reopen(id).unwrap();
+    assert_eq!(reopened.state, FiscalYearState::Open);
+    assert_eq!(reopened.opening_entry_id, None);
+
+    let mut journal = JournalRepository::new(&mut connection);
+    let opening = journal.find(closed.opening_entry_id.unwrap()).unwrap();
+    assert!(opening.entry.voided_at.is_some());
+    journal
+        .create(&entry(date(2024, 12, 31), bank, sales, 1_000))
+        .unwrap();
+
+    // Without the opening entry balances run on from the previous year.
+    let mut ledger = LedgerRepository::new(&mut connection);
+    assert_eq!(
+        ledger.balance_as_of(sales, date(2025, 1, 1)).unwrap(),
+        502_000
+    );
+
+    let mut years = FiscalYearRepository::new(&mut connection);
+    let next = years.find_by_date(date(2025, 1, 1)).unwrap().unwrap();
+    assert!(matches!(years.close(next.id), Err(Error::Validation(_))));
+    years.close(id).unwrap();
+    years.close(next.id).unwrap();
+    assert!(matches!(years.reopen(id), Err(Error::Validation(_))));
+}
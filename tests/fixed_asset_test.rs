@@ -0,0 +1,393 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::depreciation::depreciate;
+use new_tax_account_backend::models::{
+    DepreciationMethod, FixedAsset, FixedAssetChanges, NewFiscalYear, NewFixedAsset, Side,
+};
+use new_tax_account_backend::report::{DepreciationSchedule, Period};
+use new_tax_account_backend::repository::{
+    AccountRepository, FiscalYearRepository, FixedAssetRepository, JournalRepository,
+};
+use new_tax_account_backend::test_util::{self, account_id, date, request, send, year, TestDb};
+use new_tax_account_backend::{http, Error};
+
+fn computer(connection: &mut SqliteConnection) -> NewFixedAsset {
+    NewFixedAsset {
+        name: "パソコン".to_string(),
+        account_id: account_id(connection, "155"),
+        quantity: "1台".to_string(),
+        acquired_on: date(2024, 7, 10),
+        cost: 300_000,
+        useful_life: 4,
+        method: DepreciationMethod::StraightLine,
+        business_ratio: 100,
+    }
+}
+
+fn car(connection: &mut SqliteConnection) -> NewFixedAsset {
+    NewFixedAsset {
+        name: "普通自動車".to_string(),
+        account_id: account_id(connection, "154"),
+        quantity: "1台".to_string(),
+        acquired_on: date(2024, 1, 5),
+        cost: 2_000_000,
+        useful_life: 6,
+        method: DepreciationMethod::DecliningBalance,
+        business_ratio: 60,
+    }
+}
+
+fn year_of(asset: &FixedAsset, y: i32) -> Option<(i64, i64, u32)> {
+    depreciate(asset, date(y, 1, 1), date(y, 12, 31))
+        .unwrap()
+        .map(|year| (year.depreciation, year.closing_book_value, year.months))
+}
+
+#[test]
+fn test_straight_line_prorated_by_month() {
+    let mut connection = test_util::connection();
+    let new_asset = computer(&mut connection);
+    let asset = FixedAssetRepository::new(&mut connection)
+        .create(&new_asset)
+        .unwrap();
+
+    assert_eq!(year_of(&asset, 2023), None);
+    assert_eq!(year_of(&asset, 2024), Some((37_500, 262_500, 6)));
+    assert_eq!(year_of(&asset, 2025), Some((75_000, 187_500, 12)));
+    // The last year leaves a memorandum value of 1 yen.
+    assert_eq!(year_of(&asset, 2028), Some((37_499, 1, 12)));
+    assert_eq!(year_of(&asset, 2029), Some((0, 1, 12)));
+}
+
+#[test]
+fn test_declining_balance_switches_to_revised_rate() {
+    let mut connection = test_util::connection();
+    let new_asset = car(&mut connection);
+    let asset = FixedAssetRepository::new(&mut connection)
+        .create(&new_asset)
+        .unwrap();
+
+    let first = depreciate(&asset, date(2024, 1, 1), date(2024, 12, 31))
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.rate, 333);
+    assert_eq!(first.depreciation, 666_000);
+    assert_eq!(first.business_portion, 399_600);
+    assert_eq!(first.guaranteed_amount, Some(198_220));
+    assert!(!first.revised);
+
+    assert_eq!(year_of(&asset, 2026), Some((296_297, 593_481, 12)));
+    let fourth = depreciate(&asset, date(2027, 1, 1), date(2027, 12, 31))
+        .unwrap()
+        .unwrap();
+    assert!(fourth.revised);
+    assert_eq!((fourth.basis, fourth.rate), (593_481, 334));
+    assert_eq!(fourth.depreciation, 198_223);
+    assert_eq!(year_of(&asset, 2029), Some((197_034, 1, 12)));
+}
+
+#[test]
+fn test_create_validation() {
+    let mut connection = test_util::connection();
+    let (computer, car, rent) = (
+        computer(&mut connection),
+        car(&mut connection),
+        account_id(&mut connection, "526"),
+    );
+    let mut repository = FixedAssetRepository::new(&mut connection);
+    for invalid in [
+        NewFixedAsset {
+            useful_life: 25,
+            ..car.clone()
+        },
+        NewFixedAsset {
+            useful_life: 1,
+            ..computer.clone()
+        },
+        NewFixedAsset {
+            business_ratio: 120,
+            ..computer.clone()
+        },
+        NewFixedAsset {
+            cost: 0,
+            ..computer.clone()
+        },
+        NewFixedAsset {
+            account_id: rent,
+            ..computer.clone()
+        },
+    ] {
+        assert!(matches!(
+            repository.create(&invalid),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    let asset = repository.create(&computer).unwrap();
+    let before = FixedAssetChanges {
+        disposed_on: Some(Some(date(2024, 7, 1))),
+        ..Default::default()
+    };
+    assert!(matches!(
+        repository.update(asset.id, &before),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[test]
+fn test_accounts_used_by_assets_cannot_be_deleted() {
+    let mut connection = test_util::connection();
+    let computer = computer(&mut connection);
+    FixedAssetRepository::new(&mut connection)
+        .create(&computer)
+        .unwrap();
+
+    assert!(AccountRepository::new(&mut connection)
+        .delete(computer.account_id)
+        .unwrap_err()
+        .is_foreign_key_violation());
+}
+
+#[test]
+fn test_year_end_depreciation_entries() {
+    let mut connection = test_util::connection();
+    let [equipment, vehicles, drawings, expense] =
+        ["155", "154", "191", "521"].map(|code| account_id(&mut connection, code));
+    let (computer, car) = (computer(&mut connection), car(&mut connection));
+    let mut repository = FixedAssetRepository::new(&mut connection);
+    let computer = repository.create(&computer).unwrap();
+    repository.create(&car).unwrap();
+    let mut years = FiscalYearRepository::new(&mut connection);
+    let [y2024, y2025] = [2024, 2025].map(|y| {
+        years
+            .create(&NewFiscalYear {
+                start_date: date(y, 1, 1),
+                end_date: date(y, 12, 31),
+            })
+            .unwrap()
+            .id
+    });
+
+    let mut repository = FixedAssetRepository::new(&mut connection);
+    let entry = repository.post_depreciation(y2024).unwrap();
+    assert_eq!(entry.entry.entry_date, date(2024, 12, 31));
+    let lines = entry
+        .lines
+        .iter()
+        .map(|line| (line.account_id, line.side, line.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            (expense, Side::Debit, 399_600),
+            (drawings, Side::Debit, 266_400),
+            (vehicles, Side::Credit, 666_000),
+            (expense, Side::Debit, 37_500),
+            (equipment, Side::Credit, 37_500),
+        ]
+    );
+    assert!(matches!(
+        repository.post_depreciation(y2024),
+        Err(Error::Validation(_))
+    ));
+
+    // Closing does not post the depreciation a second time.
+    let closed = FiscalYearRepository::new(&mut connection)
+        .close(y2024)
+        .unwrap();
+    assert_eq!(closed.depreciation_entry_id, Some(entry.entry.id));
+
+    // The computer is sold in March 2025: three months of depreciation,
+    // then the remaining book value leaves the books through 事業主貸.
+    FixedAssetRepository::new(&mut connection)
+        .update(
+            computer.id,
+            &FixedAssetChanges {
+                disposed_on: Some(Some(date(2025, 3, 20))),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let closed = FiscalYearRepository::new(&mut connection)
+        .close(y2025)
+        .unwrap();
+    let entry = JournalRepository::new(&mut connection)
+        .find(closed.depreciation_entry_id.unwrap())
+        .unwrap();
+    let computer_lines = entry
+        .lines
+        .iter()
+        .filter(|line| line.description.starts_with("パソコン"))
+        .map(|line| (line.account_id, line.side, line.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        computer_lines,
+        vec![
+            (expense, Side::Debit, 18_750),
+            (equipment, Side::Credit, 18_750),
+            (drawings, Side::Debit, 243_750),
+            (equipment, Side::Credit, 243_750),
+        ]
+    );
+
+    let schedule = DepreciationSchedule::generate(
+        &mut connection,
+        Period::new(date(2025, 1, 1), date(2025, 12, 31)).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(schedule.rows.len(), 2);
+    assert_eq!(schedule.total_depreciation, 444_222 + 18_750);
+    assert_eq!(schedule.total_closing_book_value, 889_778);
+    assert!(schedule.to_table().contains("除却"));
+}
+
+#[tokio::test]
+async fn test_http_fixed_assets() {
+    let mut db = TestDb::temp_file();
+    let equipment = account_id(db.conn(), "155");
+    let app = http::router(db.pool());
+
+    let asset = json!({
+        "name": "パソコン",
+        "account_id": equipment,
+        "acquired_on": "2024-07-10",
+        "cost": 300000,
+        "useful_life": 4,
+        "method": "straight_line"
+    });
+    let response = send(&app, request("POST", "/fixed-assets", Some(asset))).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.json()["business_ratio"], 100);
+
+    let uri = "/reports/depreciation?from=2024-01-01&to=2024-12-31";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let schedule = response.json();
+    assert_eq!(schedule["rows"][0]["depreciation"], 37_500);
+    assert_eq!(schedule["rows"][0]["months"], 6);
+}
+
+fn small_asset(
+    connection: &mut SqliteConnection,
+    name: &str,
+    acquired_on: NaiveDate,
+    cost: i64,
+    method: DepreciationMethod,
+) -> NewFixedAsset {
+    NewFixedAsset {
+        name: name.to_string(),
+        account_id: account_id(
+            connection,
+            if method == DepreciationMethod::LumpSum {
+                "156"
+            } else {
+                "155"
+            },
+        ),
+        quantity: String::new(),
+        acquired_on,
+        cost,
+        useful_life: 4,
+        method,
+        business_ratio: 100,
+    }
+}
+
+#[test]
+fn test_small_amount_and_lump_sum_assets() {
+    let mut connection = test_util::connection();
+    let tablet = small_asset(
+        &mut connection,
+        "タブレット",
+        date(2024, 6, 1),
+        280_000,
+        DepreciationMethod::SmallAmount,
+    );
+    let desk = small_asset(
+        &mut connection,
+        "机",
+        date(2024, 11, 1),
+        100_001,
+        DepreciationMethod::LumpSum,
+    );
+    let mut repository = FixedAssetRepository::new(&mut connection);
+    for invalid in [
+        NewFixedAsset {
+            cost: 300_000,
+            ..tablet.clone()
+        },
+        NewFixedAsset {
+            cost: 200_000,
+            ..desk.clone()
+        },
+    ] {
+        assert!(matches!(
+            repository.create(&invalid),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    let tablet = repository.create(&tablet).unwrap();
+    assert_eq!(year_of(&tablet, 2024), Some((280_000, 0, 7)));
+    assert_eq!(year_of(&tablet, 2025), None);
+
+    // Written off in equal thirds whatever the months in use, and after a
+    // disposal too.
+    let desk = repository.create(&desk).unwrap();
+    let desk = repository
+        .update(
+            desk.id,
+            &FixedAssetChanges {
+                disposed_on: Some(Some(date(2025, 2, 1))),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    assert_eq!(year_of(&desk, 2024), Some((33_334, 66_667, 12)));
+    assert_eq!(year_of(&desk, 2025), Some((33_334, 33_333, 12)));
+    assert_eq!(year_of(&desk, 2026), Some((33_333, 0, 12)));
+    assert_eq!(year_of(&desk, 2027), None);
+}
+
+#[test]
+fn test_small_amount_annual_cap() {
+    let mut connection = test_util::connection();
+    let mut assets = (1..=11)
+        .map(|month| {
+            small_asset(
+                &mut connection,
+                &format!("備品{}", month),
+                date(2024, month, 1),
+                290_000,
+                DepreciationMethod::SmallAmount,
+            )
+        })
+        .collect::<Vec<_>>();
+    let last = assets.pop().unwrap();
+    let mut repository = FixedAssetRepository::new(&mut connection);
+    for asset in &assets {
+        repository.create(asset).unwrap();
+    }
+    let usage = repository.small_amount_usage_on(date(2024, 5, 1)).unwrap();
+    assert_eq!((usage.total, usage.cap), (2_900_000, 3_000_000));
+    assert_eq!(usage.warning(), None);
+
+    // Over the cap: still recorded, but with a warning.
+    repository.create(&last).unwrap();
+    let usage = repository.small_amount_usage_on(date(2024, 5, 1)).unwrap();
+    assert_eq!(usage.total, 3_190_000);
+    assert!(usage.warning().is_some());
+    // A short first year has a proportionally lower cap.
+    let short = repository
+        .small_amount_usage(date(2024, 7, 1), date(2024, 12, 31))
+        .unwrap();
+    assert_eq!((short.total, short.cap), (1_450_000, 1_500_000));
+
+    let schedule = DepreciationSchedule::generate(&mut connection, year(2024)).unwrap();
+    assert_eq!(schedule.total_depreciation, 3_190_000);
+    assert_eq!(schedule.warnings.len(), 1);
+    assert!(schedule.to_table().contains("warning:"));
+}
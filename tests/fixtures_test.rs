@@ -0,0 +1,137 @@
+use diesel::prelude::*;
+
+use self::models::*;
+use new_tax_account_backend::fixtures::{self, Fixtures, SeedConfig};
+use new_tax_account_backend::test_util::{self, TestDb};
+use new_tax_account_backend::*;
+
+fn get_connection() -> SqliteConnection {
+    test_util::connection()
+}
+
+fn load_posts(connection: &mut SqliteConnection) -> Vec<(String, Option<String>, i32)> {
+    use self::schema::posts::dsl as posts;
+    posts::posts
+        .order_by(posts::id)
+        .select((posts::title, posts::author, posts::good_count))
+        .load(connection)
+        .unwrap()
+}
+
+#[test]
+fn test_seed_counts() {
+    use self::schema::{category, post_tags, posts, tags};
+
+    let mut connection = get_connection();
+    let config = SeedConfig {
+        posts: 1200,
+        ..Default::default()
+    };
+    let summary = fixtures::seed(&mut connection, &config).unwrap();
+
+    assert_eq!(summary.categories, config.categories);
+    assert_eq!(summary.tags, config.tags);
+    assert_eq!(summary.posts, 1200);
+
+    let count = |n: QueryResult<i64>| n.unwrap() as usize;
+    assert_eq!(
+        count(category::table.count().get_result(&mut connection)),
+        8
+    );
+    assert_eq!(count(tags::table.count().get_result(&mut connection)), 15);
+    assert_eq!(
+        count(posts::table.count().get_result(&mut connection)),
+        1200
+    );
+    assert_eq!(
+        count(post_tags::table.count().get_result(&mut connection)),
+        summary.post_tags
+    );
+}
+
+#[test]
+fn test_seed_is_deterministic() {
+    let (mut first, mut second) = (get_connection(), get_connection());
+    fixtures::seed(&mut first, &SeedConfig::default()).unwrap();
+    fixtures::seed(&mut second, &SeedConfig::default()).unwrap();
+    assert_eq!(load_posts(&mut first), load_posts(&mut second));
+
+    let mut other = get_connection();
+    let config = SeedConfig {
+        seed: 7,
+        ..Default::default()
+    };
+    fixtures::seed(&mut other, &config).unwrap();
+    assert_ne!(load_posts(&mut first), load_posts(&mut other));
+}
+
+#[test]
+fn test_seed_twice_appends() {
+    use self::schema::tags;
+
+    let mut connection = get_connection();
+    fixtures::seed(&mut connection, &SeedConfig::default()).unwrap();
+    let summary = fixtures::seed(&mut connection, &SeedConfig::default()).unwrap();
+
+    assert_eq!(summary.tags, 15);
+    let names = tags::table
+        .select(tags::name)
+        .load::<String>(&mut connection)
+        .unwrap();
+    assert_eq!(names.len(), 30);
+}
+
+#[test]
+fn test_fixture_post() {
+    let mut fixtures = Fixtures::new(1);
+    let authors = vec![fixtures.author()];
+    let post: Post = fixtures.post(&[10], &authors);
+
+    assert!(post.id.is_none());
+    assert!(!post.title.is_empty());
+    assert!(post.category_id.is_none_or(|id| id == 10));
+    assert!(post.updated_at >= post.created_at);
+}
+
+#[test]
+fn test_seed_rollback() {
+    use self::schema::posts;
+
+    let mut db = TestDb::in_memory();
+    let seeded = test_util::rollback(db.conn(), |connection| {
+        fixtures::seed(connection, &SeedConfig::default()).unwrap()
+    });
+    assert_eq!(seeded.posts, 200);
+
+    let remaining: i64 = posts::table.count().get_result(db.conn()).unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[test]
+fn test_deleting_posts_removes_their_tags() {
+    use self::schema::{post_tags, posts};
+
+    let mut db = TestDb::temp_file();
+    fixtures::seed(db.conn(), &SeedConfig::default()).unwrap();
+    let tagged = post_tags::table
+        .select(post_tags::post_id)
+        .first::<i32>(db.conn())
+        .unwrap();
+    diesel::delete(posts::table.filter(posts::id.eq(tagged)))
+        .execute(db.conn())
+        .unwrap();
+    let left: i64 = post_tags::table
+        .filter(post_tags::post_id.eq(tagged))
+        .count()
+        .get_result(db.conn())
+        .unwrap();
+    assert_eq!(left, 0);
+
+    // Pooled connections of the HTTP API enforce foreign keys as well.
+    let pool = db.pool();
+    diesel::delete(posts::table)
+        .execute(&mut pool.get().unwrap())
+        .unwrap();
+    let left: i64 = post_tags::table.count().get_result(db.conn()).unwrap();
+    assert_eq!(left, 0);
+}
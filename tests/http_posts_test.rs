@@ -0,0 +1,140 @@
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use serde_json::json;
+
+use new_tax_account_backend::http;
+use new_tax_account_backend::test_util::{request, send, TestDb};
+
+fn if_match(mut request: Request<Body>, tag: &str) -> Request<Body> {
+    request
+        .headers_mut()
+        .insert(header::IF_MATCH, tag.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn test_update_with_if_match() {
+    let db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_post = json!({ "title": "title1", "body": "body1" });
+    let response = send(&app, request("POST", "/posts", Some(new_post))).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.header("etag"), Some("\"0\""));
+    let uri = format!("/posts/{}", response.json()["id"]);
+
+    let changes = json!({ "body": "new body", "author": null });
+    let response = send(
+        &app,
+        if_match(request("PATCH", &uri, Some(changes)), "\"0\""),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("etag"), Some("\"1\""));
+    assert_eq!(response.json()["body"], "new body");
+
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("etag"), Some("\"1\""));
+}
+
+#[tokio::test]
+async fn test_stale_if_match_conflicts() {
+    let db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_post = json!({ "title": "title1", "body": "body1" });
+    let response = send(&app, request("POST", "/posts", Some(new_post))).await;
+    let uri = format!("/posts/{}", response.json()["id"]);
+
+    let first = request("PATCH", &uri, Some(json!({ "body": "first" })));
+    let second = request("PATCH", &uri, Some(json!({ "body": "second" })));
+    send(&app, if_match(first, "\"0\"")).await;
+    let response = send(&app, if_match(second, "\"0\"")).await;
+
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.header("etag"), Some("\"1\""));
+    let error = response.json();
+    assert_eq!(error["current"]["body"], "first");
+    assert_eq!(error["current"]["version"], 1);
+}
+
+#[tokio::test]
+async fn test_if_match_lists_and_weak_tags() {
+    let db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_post = json!({ "title": "title1", "body": "body1" });
+    let response = send(&app, request("POST", "/posts", Some(new_post))).await;
+    let uri = format!("/posts/{}", response.json()["id"]);
+
+    let changes = || Some(json!({ "body": "new body" }));
+    let response = send(&app, if_match(request("PATCH", &uri, changes()), "W/\"0\"")).await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+
+    let response = send(
+        &app,
+        if_match(request("PATCH", &uri, changes()), "\"5\", \"0\""),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("etag"), Some("\"1\""));
+
+    let response = send(
+        &app,
+        if_match(request("PATCH", &uri, changes()), "\"5\", \"0\""),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+
+    let response = send(&app, if_match(request("PATCH", &uri, changes()), "1")).await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_update_requires_if_match() {
+    let db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_post = json!({ "title": "title1", "body": "body1" });
+    let response = send(&app, request("POST", "/posts", Some(new_post))).await;
+    let uri = format!("/posts/{}", response.json()["id"]);
+
+    let response = send(&app, request("PATCH", &uri, Some(json!({})))).await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_REQUIRED);
+
+    let response = send(&app, if_match(request("DELETE", &uri, None), "*")).await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_bulk_endpoints() {
+    let db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let posts = json!([
+        { "title": "title1", "body": "body1" },
+        { "title": "title2", "body": "body2", "author": "bob" },
+    ]);
+    let response = send(&app, request("POST", "/posts/bulk", Some(posts))).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let items = response.json();
+    assert_eq!(items[0]["status"], "created");
+    let ids = json!([items[0]["id"], items[1]["id"]]);
+
+    let publish = json!({ "ids": ids, "published": true });
+    let response = send(&app, request("POST", "/posts/bulk/publish", Some(publish))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()[1]["status"], "updated");
+
+    let filter = json!({ "author": "bob" });
+    let response = send(&app, request("POST", "/posts/bulk/delete", Some(filter))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json().as_array().unwrap().len(), 1);
+
+    let response = send(&app, request("POST", "/posts/bulk/delete", Some(json!({})))).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
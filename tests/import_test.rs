@@ -0,0 +1,496 @@
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use diesel::SqliteConnection;
+use serde_json::{json, Value};
+
+use new_tax_account_backend::models::{
+    Encoding, ImportProfile, NewCounterparty, NewImportProfile, NewImportRule, NewJournalEntry,
+    NewJournalLine, Side, StagedStatus, TaxCategory, Withholding,
+};
+use new_tax_account_backend::repository::{
+    AccountRepository, CounterpartyRepository, ImportRepository, JournalRepository, StagedQuery,
+};
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, CounterpartyBuilder, TestDb,
+};
+use new_tax_account_backend::{http, Error};
+
+const BANK_STATEMENT: &str = "\
+日付,摘要,お引出し,お預入れ,残高
+2024/04/01,前月繰越,,,\"1,000,000\"
+2024/04/05,ＡＭＡＺＯＮ　マーケットプレイス,\"3,300\",,\"996,700\"
+2024/04/10,振込　カ）サンプル,,\"110,000\",\"1,106,700\"
+
+2024/04/25,家賃　４月分,\"88,000\",,\"1,018,700\"
+2024/04/26,ATM,\"20,000\",,\"998,700\"
+";
+
+fn bank_profile(connection: &mut SqliteConnection) -> ImportProfile {
+    let bank = account_id(connection, "111");
+    ImportRepository::new(connection)
+        .create_profile(&NewImportProfile {
+            name: "みずほ銀行".to_string(),
+            account_id: bank,
+            encoding: Encoding::ShiftJis,
+            skip_rows: 1,
+            date_column: 0,
+            date_format: "%Y/%m/%d".to_string(),
+            description_column: 1,
+            withdrawal_column: 2,
+            deposit_column: Some(3),
+            balance_column: Some(4),
+        })
+        .unwrap()
+}
+
+fn shift_jis(text: &str) -> Vec<u8> {
+    let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(text);
+    assert!(!had_errors);
+    bytes.into_owned()
+}
+
+#[test]
+fn test_import_shift_jis_statement() {
+    let mut connection = test_util::connection();
+    let profile = bank_profile(&mut connection);
+    let mut imports = ImportRepository::new(&mut connection);
+
+    let summary = imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap();
+    assert_eq!(summary.batch.file_name, "202404.csv");
+    let rows = summary
+        .transactions
+        .iter()
+        .map(|t| {
+            (
+                t.row_no,
+                t.transaction_date,
+                t.withdrawal,
+                t.deposit,
+                t.balance,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (3, date(2024, 4, 5), 3_300, 0, Some(996_700)),
+            (4, date(2024, 4, 10), 0, 110_000, Some(1_106_700)),
+            (5, date(2024, 4, 25), 88_000, 0, Some(1_018_700)),
+            (6, date(2024, 4, 26), 20_000, 0, Some(998_700)),
+        ]
+    );
+    assert_eq!(
+        summary.transactions[0].description,
+        "ＡＭＡＺＯＮ　マーケットプレイス"
+    );
+    assert!(summary
+        .transactions
+        .iter()
+        .all(|t| t.status == StagedStatus::Pending && t.account_id == profile.account_id));
+}
+
+#[test]
+fn test_card_statement_and_invalid_rows() {
+    let mut connection = test_util::connection();
+    let card = account_id(&mut connection, "211");
+    let mut imports = ImportRepository::new(&mut connection);
+    let profile = imports
+        .create_profile(&NewImportProfile {
+            name: "カード".to_string(),
+            account_id: card,
+            encoding: Encoding::Utf8,
+            skip_rows: 0,
+            date_column: 0,
+            date_format: "%Y-%m-%d".to_string(),
+            description_column: 2,
+            withdrawal_column: 1,
+            deposit_column: None,
+            balance_column: None,
+        })
+        .unwrap();
+
+    let summary = imports
+        .import(
+            profile.id,
+            "card.csv",
+            "\u{feff}2024-05-02,\"1,980\",書店\n2024-05-09,-500,書店 返品\n".as_bytes(),
+        )
+        .unwrap();
+    let amounts = summary
+        .transactions
+        .iter()
+        .map(|t| (t.withdrawal, t.deposit))
+        .collect::<Vec<_>>();
+    assert_eq!(amounts, vec![(1_980, 0), (0, 500)]);
+
+    for invalid in ["2024-05-02,abc,書店\n", "2024/05/02,100,書店\n"] {
+        assert!(matches!(
+            imports.import(profile.id, "bad.csv", invalid.as_bytes()),
+            Err(Error::Validation(_))
+        ));
+    }
+    assert!(matches!(
+        imports.import(profile.id, "sjis.csv", &shift_jis("2024-05-02,100,書店\n")),
+        Err(Error::Validation(_))
+    ));
+    assert_eq!(
+        imports.transactions(&StagedQuery::default()).unwrap().len(),
+        2
+    );
+}
+
+#[test]
+fn test_rules_propose_and_post_entries() {
+    let mut connection = test_util::connection();
+    let (cash, bank, sales, supplies, rent) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "111"),
+        account_id(&mut connection, "401"),
+        account_id(&mut connection, "520"),
+        account_id(&mut connection, "526"),
+    );
+    let customer = CounterpartyRepository::new(&mut connection)
+        .create(&NewCounterparty {
+            name: "株式会社サンプル".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    let profile = bank_profile(&mut connection);
+    let mut imports = ImportRepository::new(&mut connection);
+    let batch = imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap()
+        .batch;
+
+    let rule = |priority, contains: &str, min, max, account_id| NewImportRule {
+        priority,
+        description_contains: Some(contains.to_string()),
+        min_amount: min,
+        max_amount: max,
+        account_id,
+        tax_category: None,
+        counterparty_id: None,
+    };
+    let amazon = imports
+        .create_rule(&NewImportRule {
+            tax_category: Some(TaxCategory::Reduced8),
+            ..rule(100, "amazon", None, Some(10_000), supplies)
+        })
+        .unwrap();
+    // Tried after the one above although created later.
+    imports
+        .create_rule(&rule(200, "amazon", None, None, rent))
+        .unwrap();
+    let rent_rule = imports
+        .create_rule(&rule(100, "家賃", Some(80_000), Some(100_000), rent))
+        .unwrap();
+    imports
+        .create_rule(&NewImportRule {
+            counterparty_id: Some(customer.id),
+            ..rule(100, "サンプル", None, None, sales)
+        })
+        .unwrap();
+    assert!(matches!(
+        imports.create_rule(&rule(1, "x", Some(2), Some(1), rent)),
+        Err(Error::Validation(_))
+    ));
+
+    let proposals = imports.proposals(Some(batch.id)).unwrap();
+    let matched = proposals.iter().map(|p| p.rule_id).collect::<Vec<_>>();
+    assert_eq!(matched[0], Some(amazon.id));
+    assert_eq!(matched[2], Some(rent_rule.id));
+    assert_eq!(matched[3], None);
+
+    let purchase = proposals[0].entry.as_ref().unwrap();
+    assert_eq!(purchase.entry_date, date(2024, 4, 5));
+    assert_eq!(
+        purchase.lines,
+        vec![
+            NewJournalLine::debit(supplies, 3_300).tax(TaxCategory::Reduced8),
+            NewJournalLine::credit(bank, 3_300),
+        ]
+    );
+    let deposit = proposals[1].entry.as_ref().unwrap();
+    assert_eq!(
+        deposit.lines,
+        vec![
+            NewJournalLine::debit(bank, 110_000),
+            NewJournalLine::credit(sales, 110_000).counterparty(customer.id),
+        ]
+    );
+
+    let posted = imports.post(proposals[1].transaction.id, None).unwrap();
+    assert_eq!(posted.status, StagedStatus::Posted);
+    assert!(matches!(
+        imports.post(proposals[1].transaction.id, None),
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        imports.post(proposals[3].transaction.id, None),
+        Err(Error::Validation(_))
+    ));
+
+    // A reviewed entry replaces the proposal.
+    let reviewed = NewJournalEntry {
+        entry_date: date(2024, 4, 26),
+        memo: "引出し".to_string(),
+        lines: vec![
+            NewJournalLine::debit(cash, 20_000),
+            NewJournalLine::credit(bank, 20_000),
+        ],
+    };
+    let cash_withdrawal = imports
+        .post(proposals[3].transaction.id, Some(&reviewed))
+        .unwrap();
+    imports.ignore(proposals[2].transaction.id).unwrap();
+
+    assert_eq!(imports.proposals(Some(batch.id)).unwrap().len(), 1);
+    let entry = JournalRepository::new(&mut connection)
+        .find(posted.entry_id.unwrap())
+        .unwrap();
+    assert_eq!(entry.entry.memo, "振込　カ）サンプル");
+    assert_eq!(entry.lines[1].side, Side::Credit);
+    assert_eq!(entry.lines[1].counterparty_id, Some(customer.id));
+    let entry = JournalRepository::new(&mut connection)
+        .find(cash_withdrawal.entry_id.unwrap())
+        .unwrap();
+    assert_eq!(entry.entry.memo, "引出し");
+}
+
+#[test]
+fn test_posted_statement_rows_are_not_withheld_from() {
+    let mut connection = test_util::connection();
+    let (bank, rent) = (
+        account_id(&mut connection, "111"),
+        account_id(&mut connection, "526"),
+    );
+    let landlord = CounterpartyBuilder::new("大家 太郎")
+        .withholding(Withholding::Payments)
+        .insert(&mut connection)
+        .unwrap();
+    let profile = bank_profile(&mut connection);
+    let mut imports = ImportRepository::new(&mut connection);
+    imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap();
+    imports
+        .create_rule(&NewImportRule {
+            priority: 100,
+            description_contains: Some("家賃".to_string()),
+            min_amount: None,
+            max_amount: None,
+            account_id: rent,
+            tax_category: None,
+            counterparty_id: Some(landlord.id),
+        })
+        .unwrap();
+
+    // The bank paid out 88,000 yen, so the bank line stays at that.
+    let proposal = imports
+        .proposals(None)
+        .unwrap()
+        .into_iter()
+        .find(|proposal| proposal.rule_id.is_some())
+        .unwrap();
+    let posted = imports.post(proposal.transaction.id, None).unwrap();
+    let entry = JournalRepository::new(&mut connection)
+        .find(posted.entry_id.unwrap())
+        .unwrap();
+    let lines = entry
+        .lines
+        .iter()
+        .map(|line| (line.account_id, line.side, line.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![(rent, Side::Debit, 88_000), (bank, Side::Credit, 88_000)]
+    );
+}
+
+#[test]
+fn test_overlapping_statements_skip_duplicates() {
+    let mut connection = test_util::connection();
+    let profile = bank_profile(&mut connection);
+    let mut imports = ImportRepository::new(&mut connection);
+    let first = imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap();
+    assert!(first.skipped.is_empty());
+
+    // Overlaps the first file from 4/25, with the description written in
+    // halfwidth and a second, identical ATM withdrawal.
+    let overlapping = "\
+日付,摘要,お引出し,お預入れ,残高
+2024/04/25,家賃 4月分,\"88,000\",,\"1,018,700\"
+2024/04/26,ATM,\"20,000\",,\"998,700\"
+2024/04/26,ATM,\"20,000\",,\"978,700\"
+2024/05/01,atm,\"5,000\",,\"973,700\"
+";
+    let second = imports
+        .import(profile.id, "202405.csv", &shift_jis(overlapping))
+        .unwrap();
+    let staged = second
+        .transactions
+        .iter()
+        .map(|t| (t.row_no, t.transaction_date, t.withdrawal))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        staged,
+        vec![(4, date(2024, 4, 26), 20_000), (5, date(2024, 5, 1), 5_000)]
+    );
+    let skipped = imports.skipped(second.batch.id).unwrap();
+    assert_eq!(skipped, second.skipped);
+    let skipped = skipped
+        .iter()
+        .map(|s| (s.row_no, s.duplicate_of))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        skipped,
+        vec![(2, first.transactions[2].id), (3, first.transactions[3].id)]
+    );
+
+    // Importing the same file again stages nothing.
+    let again = imports
+        .import(profile.id, "202405.csv", &shift_jis(overlapping))
+        .unwrap();
+    assert!(again.transactions.is_empty());
+    assert_eq!(again.skipped.len(), 4);
+    assert!(matches!(imports.skipped(999), Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_undo_import_batch() {
+    let mut connection = test_util::connection();
+    let supplies = account_id(&mut connection, "520");
+    let profile = bank_profile(&mut connection);
+    let mut imports = ImportRepository::new(&mut connection);
+    imports
+        .create_rule(&NewImportRule {
+            priority: 100,
+            description_contains: Some("amazon".to_string()),
+            min_amount: None,
+            max_amount: None,
+            account_id: supplies,
+            tax_category: None,
+            counterparty_id: None,
+        })
+        .unwrap();
+    let first = imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap();
+    let posted = imports.post(first.transactions[0].id, None).unwrap();
+    let second = imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap();
+    assert_eq!(second.skipped.len(), 4);
+
+    assert!(matches!(
+        imports.undo_batch(first.batch.id),
+        Err(Error::Validation(_))
+    ));
+    let undone = imports.undo_batch(second.batch.id).unwrap();
+    assert_eq!(undone.removed_transactions, 0);
+    let undone = imports.undo_batch(first.batch.id).unwrap();
+    assert_eq!(undone.removed_transactions, 4);
+    assert_eq!(undone.voided_entry_ids, vec![posted.entry_id.unwrap()]);
+    assert!(imports.list_batches().unwrap().is_empty());
+    assert!(matches!(
+        imports.undo_batch(first.batch.id),
+        Err(Error::NotFound(_))
+    ));
+
+    let reimported = imports
+        .import(profile.id, "202404.csv", &shift_jis(BANK_STATEMENT))
+        .unwrap();
+    assert_eq!(reimported.transactions.len(), 4);
+    let entry = JournalRepository::new(&mut connection)
+        .find(posted.entry_id.unwrap())
+        .unwrap();
+    assert!(entry.entry.voided_at.is_some());
+}
+
+#[test]
+fn test_accounts_used_by_imports_cannot_be_deleted() {
+    let mut connection = test_util::connection();
+    let supplies = account_id(&mut connection, "520");
+    let profile = bank_profile(&mut connection);
+    let rule = ImportRepository::new(&mut connection)
+        .create_rule(&NewImportRule {
+            priority: 100,
+            description_contains: Some("amazon".to_string()),
+            min_amount: None,
+            max_amount: None,
+            account_id: supplies,
+            tax_category: None,
+            counterparty_id: None,
+        })
+        .unwrap();
+
+    let mut accounts = AccountRepository::new(&mut connection);
+    for id in [profile.account_id, supplies] {
+        assert!(accounts.delete(id).unwrap_err().is_foreign_key_violation());
+    }
+    ImportRepository::new(&mut connection)
+        .delete_rule(rule.id)
+        .unwrap();
+    AccountRepository::new(&mut connection)
+        .delete(supplies)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_http_import_and_review() {
+    let mut db = TestDb::temp_file();
+    let bank = account_id(db.conn(), "111");
+    let app = http::router(db.pool());
+
+    let profile = json!({
+        "name": "みずほ銀行",
+        "account_id": bank,
+        "encoding": "shift_jis",
+        "skip_rows": 1,
+        "date_column": 0,
+        "date_format": "%Y/%m/%d",
+        "description_column": 1,
+        "withdrawal_column": 2,
+        "deposit_column": 3,
+        "balance_column": 4
+    });
+    let response = send(&app, request("POST", "/imports/profiles", Some(profile))).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let profile = response.json();
+
+    let statement = Request::post(format!(
+        "/imports/profiles/{}/statements?file_name=202404.csv",
+        profile["id"]
+    ))
+    .body(Body::from(shift_jis(BANK_STATEMENT)))
+    .unwrap();
+    let response = send(&app, statement).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let summary = response.json();
+    assert_eq!(summary["transactions"].as_array().unwrap().len(), 4);
+
+    let uri = format!("/imports/proposals?batch_id={}", summary["batch"]["id"]);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let proposals = response.json();
+    assert_eq!(proposals[0]["entry"], Value::Null);
+    let transaction = &proposals[0]["transaction"]["id"];
+
+    let uri = format!("/imports/transactions/{}/post", transaction);
+    let response = send(&app, request("POST", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let uri = format!("/imports/transactions/{}/ignore", transaction);
+    let response = send(&app, request("POST", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["status"], "ignored");
+
+    let uri = format!("/imports/batches/{}", summary["batch"]["id"]);
+    let response = send(&app, request("DELETE", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["removed_transactions"], 4);
+}
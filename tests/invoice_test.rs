@@ -0,0 +1,461 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::invoice::totals;
+use new_tax_account_backend::models::{
+    BusinessProfileChanges, InvoiceLine, InvoicePayment, InvoiceStatus, NewInvoice, NewInvoiceLine,
+    Rounding, Side, TaxCategory, TaxMethod, TaxSettingsChanges, Withholding,
+};
+use new_tax_account_backend::repository::{
+    BusinessProfileRepository, CounterpartyRepository, InvoiceQuery, InvoiceRepository,
+    JournalRepository, LedgerRepository, TaxSettingsRepository,
+};
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, CounterpartyBuilder, TestDb,
+};
+use new_tax_account_backend::{http, Error};
+
+const REGISTRATION_NUMBER: &str = "T7000012050002";
+
+fn line(description: &str, quantity: i32, unit_price: i64, tax: TaxCategory) -> NewInvoiceLine {
+    NewInvoiceLine {
+        description: description.to_string(),
+        quantity,
+        unit_price,
+        tax_category: tax,
+    }
+}
+
+/// Catering with standard-rated service and reduced-rate food.
+fn catering(counterparty_id: i32) -> NewInvoice {
+    NewInvoice {
+        counterparty_id,
+        issue_date: date(2024, 5, 31),
+        due_date: date(2024, 6, 30),
+        notes: String::new(),
+        lines: vec![
+            line("会場設営", 3, 1_000, TaxCategory::Taxable10),
+            line("配膳", 1, 1_999, TaxCategory::Taxable10),
+            line("弁当", 2, 555, TaxCategory::Reduced8),
+        ],
+    }
+}
+
+fn paid_on(date: NaiveDate) -> InvoicePayment {
+    InvoicePayment {
+        paid_on: date,
+        account_id: None,
+        amount: None,
+    }
+}
+
+fn balance(connection: &mut SqliteConnection, code: &str) -> i64 {
+    let account_id = account_id(connection, code);
+    LedgerRepository::new(connection)
+        .ledger(account_id, date(2024, 1, 1), date(2024, 12, 31))
+        .unwrap()
+        .closing_balance
+}
+
+#[test]
+fn test_totals_per_rate() {
+    let lines = catering(1)
+        .lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| InvoiceLine {
+            id: i as i32 + 1,
+            invoice_id: 1,
+            line_no: i as i32 + 1,
+            description: line.description,
+            quantity: line.quantity,
+            unit_price: line.unit_price,
+            tax_category: line.tax_category,
+        })
+        .collect::<Vec<_>>();
+
+    // Tax is rounded once per rate: 499.9 and 88.8, not per line.
+    let floor = totals(&lines, Rounding::Floor, Withholding::None);
+    let rates = floor
+        .rates
+        .iter()
+        .map(|rate| (rate.tax_category, rate.base, rate.tax))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rates,
+        vec![
+            (TaxCategory::Taxable10, 4_999, 499),
+            (TaxCategory::Reduced8, 1_110, 88),
+        ]
+    );
+    assert_eq!(
+        (floor.subtotal, floor.tax, floor.total, floor.amount_due),
+        (6_109, 587, 6_696, 6_696)
+    );
+    let round = totals(&lines, Rounding::Round, Withholding::None);
+    assert_eq!(round.tax, 500 + 89);
+
+    // A client withholding income tax pays the total less 10.21%.
+    let withheld = totals(&lines[..1], Rounding::Floor, Withholding::Receipts);
+    assert_eq!(
+        (withheld.total, withheld.withholding, withheld.amount_due),
+        (3_300, 336, 2_964)
+    );
+}
+
+#[test]
+fn test_issue_posts_receivable() {
+    let mut connection = test_util::connection();
+    let caterer = CounterpartyBuilder::new("株式会社ケータリング")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let (receivable, sales) = (
+        account_id(&mut connection, "122"),
+        account_id(&mut connection, "401"),
+    );
+    let mut repository = InvoiceRepository::new(&mut connection);
+    let draft = repository.create(&catering(caterer)).unwrap();
+    assert_eq!(draft.invoice.status, InvoiceStatus::Draft);
+    assert_eq!(draft.invoice.number, None);
+    assert_eq!(draft.lines.len(), 3);
+
+    let issued = repository.issue(draft.invoice.id).unwrap();
+    assert_eq!(issued.invoice.status, InvoiceStatus::Issued);
+    assert_eq!(issued.invoice.number.as_deref(), Some("INV-000001"));
+    // Issued invoices can no longer be edited or issued again.
+    assert!(matches!(
+        repository.update(draft.invoice.id, &catering(caterer)),
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(
+        repository.issue(draft.invoice.id),
+        Err(Error::Validation(_))
+    ));
+
+    let entry = JournalRepository::new(&mut connection)
+        .find(issued.invoice.entry_id.unwrap())
+        .unwrap();
+    let lines = entry
+        .lines
+        .iter()
+        .map(|line| {
+            (
+                line.account_id,
+                line.side,
+                line.amount,
+                line.tax_category,
+                line.tax_amount,
+                line.counterparty_id,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            (
+                receivable,
+                Side::Debit,
+                6_696,
+                TaxCategory::OutOfScope,
+                0,
+                Some(caterer)
+            ),
+            (
+                sales,
+                Side::Credit,
+                5_498,
+                TaxCategory::Taxable10,
+                499,
+                Some(caterer)
+            ),
+            (
+                sales,
+                Side::Credit,
+                1_198,
+                TaxCategory::Reduced8,
+                88,
+                Some(caterer)
+            ),
+        ]
+    );
+
+    // Under 税抜経理 the tax goes to 仮受消費税, as on the invoice.
+    TaxSettingsRepository::new(&mut connection)
+        .update(&TaxSettingsChanges {
+            method: Some(TaxMethod::Exclusive),
+            ..Default::default()
+        })
+        .unwrap();
+    let mut repository = InvoiceRepository::new(&mut connection);
+    let second = repository.create(&catering(caterer)).unwrap();
+    let second = repository.issue(second.invoice.id).unwrap();
+    assert_eq!(second.invoice.number.as_deref(), Some("INV-000002"));
+    assert_eq!(balance(&mut connection, "215"), 587);
+    assert_eq!(balance(&mut connection, "122"), 2 * 6_696);
+
+    let issued = InvoiceRepository::new(&mut connection)
+        .list(&InvoiceQuery {
+            status: Some(InvoiceStatus::Issued),
+            counterparty_id: Some(caterer),
+        })
+        .unwrap();
+    assert_eq!(issued.len(), 2);
+}
+
+#[test]
+fn test_validation() {
+    let mut connection = test_util::connection();
+    let caterer = CounterpartyBuilder::new("株式会社ケータリング")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let mut repository = InvoiceRepository::new(&mut connection);
+    for invoice in [
+        NewInvoice {
+            lines: Vec::new(),
+            ..catering(caterer)
+        },
+        NewInvoice {
+            due_date: date(2024, 5, 1),
+            ..catering(caterer)
+        },
+        NewInvoice {
+            lines: vec![line(" ", 1, 100, TaxCategory::Taxable10)],
+            ..catering(caterer)
+        },
+        NewInvoice {
+            lines: vec![line("配膳", 0, 100, TaxCategory::Taxable10)],
+            ..catering(caterer)
+        },
+        NewInvoice {
+            lines: vec![line("配膳", i32::MAX, i64::MAX, TaxCategory::Taxable10)],
+            ..catering(caterer)
+        },
+    ] {
+        assert!(matches!(
+            repository.create(&invoice),
+            Err(Error::Validation(_))
+        ));
+    }
+    assert!(matches!(
+        repository.create(&catering(999)),
+        Err(Error::NotFound(_))
+    ));
+
+    let free = repository
+        .create(&NewInvoice {
+            lines: vec![line("試食", 1, 0, TaxCategory::Reduced8)],
+            ..catering(caterer)
+        })
+        .unwrap();
+    assert!(matches!(
+        repository.issue(free.invoice.id),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[test]
+fn test_invoiced_counterparties_cannot_be_deleted() {
+    let mut connection = test_util::connection();
+    let caterer = CounterpartyBuilder::new("株式会社ケータリング")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    // Even a draft, which has no journal lines yet.
+    InvoiceRepository::new(&mut connection)
+        .create(&catering(caterer))
+        .unwrap();
+
+    assert!(CounterpartyRepository::new(&mut connection)
+        .delete(caterer)
+        .unwrap_err()
+        .is_foreign_key_violation());
+}
+
+#[test]
+fn test_payment_and_void() {
+    let mut connection = test_util::connection();
+    let caterer = CounterpartyBuilder::new("株式会社ケータリング")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let mut repository = InvoiceRepository::new(&mut connection);
+    let paid = repository.create(&catering(caterer)).unwrap().invoice.id;
+    let cancelled = repository.create(&catering(caterer)).unwrap().invoice.id;
+    repository.issue(paid).unwrap();
+    repository.issue(cancelled).unwrap();
+
+    assert!(matches!(
+        repository.pay(paid, &paid_on(date(2024, 5, 30))),
+        Err(Error::Validation(_))
+    ));
+    let invoice = repository.pay(paid, &paid_on(date(2024, 6, 28))).unwrap();
+    assert_eq!(invoice.invoice.status, InvoiceStatus::Paid);
+    assert_eq!(invoice.invoice.paid_on, Some(date(2024, 6, 28)));
+    assert!(matches!(
+        repository.pay(paid, &paid_on(date(2024, 6, 28))),
+        Err(Error::Validation(_))
+    ));
+    assert!(matches!(repository.void(paid), Err(Error::Validation(_))));
+
+    let void = repository.void(cancelled).unwrap();
+    assert_eq!(void.invoice.status, InvoiceStatus::Void);
+    // The number stays taken.
+    assert_eq!(void.invoice.number.as_deref(), Some("INV-000002"));
+    let entry = JournalRepository::new(&mut connection)
+        .find(void.invoice.entry_id.unwrap())
+        .unwrap();
+    assert!(entry.entry.voided_at.is_some());
+
+    assert_eq!(balance(&mut connection, "122"), 0);
+    assert_eq!(balance(&mut connection, "111"), 6_696);
+    assert_eq!(balance(&mut connection, "401"), 6_696);
+
+    let mut repository = InvoiceRepository::new(&mut connection);
+    let next = repository.create(&catering(caterer)).unwrap().invoice.id;
+    assert_eq!(
+        repository.issue(next).unwrap().invoice.number.as_deref(),
+        Some("INV-000003")
+    );
+    // Drafts can be voided too, and get no number.
+    let draft = repository.create(&catering(caterer)).unwrap().invoice.id;
+    assert_eq!(repository.void(draft).unwrap().invoice.number, None);
+}
+
+#[test]
+fn test_client_withholding() {
+    let mut connection = test_util::connection();
+    let publisher = CounterpartyBuilder::new("株式会社出版")
+        .withholding(Withholding::Receipts)
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let mut repository = InvoiceRepository::new(&mut connection);
+    let id = repository
+        .create(&NewInvoice {
+            lines: vec![line("原稿料", 1, 100_000, TaxCategory::Taxable10)],
+            ..catering(publisher)
+        })
+        .unwrap()
+        .invoice
+        .id;
+    repository.issue(id).unwrap();
+    assert_eq!(balance(&mut connection, "122"), 98_769);
+    assert_eq!(balance(&mut connection, "191"), 11_231);
+
+    InvoiceRepository::new(&mut connection)
+        .pay(id, &paid_on(date(2024, 6, 30)))
+        .unwrap();
+    assert_eq!(balance(&mut connection, "122"), 0);
+    assert_eq!(balance(&mut connection, "111"), 98_769);
+}
+
+#[test]
+fn test_document() {
+    let mut connection = test_util::connection();
+    let caterer = CounterpartyBuilder::new("株式会社ケータリング")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let id = InvoiceRepository::new(&mut connection)
+        .create(&catering(caterer))
+        .unwrap()
+        .invoice
+        .id;
+
+    let draft = InvoiceRepository::new(&mut connection)
+        .document(id)
+        .unwrap();
+    assert_eq!(draft.title(), "請求書");
+    assert!(draft.to_html().contains("(下書き)"));
+
+    BusinessProfileRepository::new(&mut connection)
+        .update(&BusinessProfileChanges {
+            name: Some("山田デザイン事務所".to_string()),
+            registration_number: Some(Some(REGISTRATION_NUMBER.to_string())),
+            bank_details: Some("○○銀行 本店 普通 1234567".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    InvoiceRepository::new(&mut connection).issue(id).unwrap();
+    let document = InvoiceRepository::new(&mut connection)
+        .document(id)
+        .unwrap();
+    assert_eq!(document.title(), "適格請求書");
+    assert_eq!(document.totals.total, 6_696);
+    let html = document.to_html();
+    for expected in [
+        "<h1>適格請求書</h1>",
+        "株式会社ケータリング 御中",
+        "登録番号: T7000012050002",
+        "INV-000001",
+        "2024-05-31",
+        "<td>弁当</td>",
+        "8%※",
+        "<td>10%対象</td><td class=\"amount\">4,999</td><td class=\"amount\">499</td>",
+        "<td>8%※対象</td><td class=\"amount\">1,110</td><td class=\"amount\">88</td>",
+        "6,696",
+        "※は軽減税率対象",
+        "お振込先: ○○銀行 本店 普通 1234567",
+    ] {
+        assert!(html.contains(expected), "{}", expected);
+    }
+    assert!(document.to_pdf().starts_with(b"%PDF-"));
+
+    assert!(matches!(
+        BusinessProfileRepository::new(&mut connection).update(&BusinessProfileChanges {
+            registration_number: Some(Some("T1234567890123".to_string())),
+            ..Default::default()
+        }),
+        Err(Error::Validation(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_http_invoices() {
+    let mut db = TestDb::temp_file();
+    let caterer = CounterpartyBuilder::new("株式会社ケータリング")
+        .insert(db.conn())
+        .unwrap()
+        .id;
+    let app = http::router(db.pool());
+
+    let profile =
+        json!({ "name": "山田デザイン事務所", "registration_number": REGISTRATION_NUMBER });
+    let response = send(&app, request("PATCH", "/business-profile", Some(profile))).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let new_invoice = serde_json::to_value(catering(caterer)).unwrap();
+    let response = send(&app, request("POST", "/invoices", Some(new_invoice))).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let id = response.json()["id"].as_i64().unwrap();
+
+    let uri = format!("/invoices/{}/issue", id);
+    let response = send(&app, request("POST", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let issued = response.json();
+    assert_eq!(issued["status"], "issued");
+    assert_eq!(issued["number"], "INV-000001");
+
+    let uri = format!("/invoices/{}/document?format=html", id);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.text().contains("適格請求書"));
+    let uri = format!("/invoices/{}/document?format=pdf", id);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.starts_with(b"%PDF-"));
+
+    let uri = format!("/invoices/{}/pay", id);
+    let payment = json!({ "paid_on": "2024-06-28" });
+    let response = send(&app, request("POST", &uri, Some(payment))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["status"], "paid");
+
+    let uri = format!("/invoices/{}/void", id);
+    let response = send(&app, request("POST", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
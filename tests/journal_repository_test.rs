@@ -0,0 +1,184 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+
+use new_tax_account_backend::models::{NewJournalEntry, NewJournalLine, Side};
+use new_tax_account_backend::repository::{AccountRepository, JournalQuery, JournalRepository};
+use new_tax_account_backend::test_util::{self, account_id, date};
+use new_tax_account_backend::{schema, Error};
+
+fn entry(entry_date: NaiveDate, memo: &str, lines: Vec<NewJournalLine>) -> NewJournalEntry {
+    NewJournalEntry {
+        entry_date,
+        memo: memo.to_string(),
+        lines,
+    }
+}
+
+fn count_entries(connection: &mut SqliteConnection) -> i64 {
+    schema::journal_entries::table
+        .count()
+        .get_result(connection)
+        .unwrap()
+}
+
+#[test]
+fn test_create_balanced_entry() {
+    let mut connection = test_util::connection();
+    let (cash, sales) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "401"),
+    );
+    let mut journal = JournalRepository::new(&mut connection);
+
+    let created = journal
+        .create(&entry(
+            date(2024, 4, 1),
+            "売上",
+            vec![
+                NewJournalLine::debit(cash, 11_000),
+                NewJournalLine::credit(sales, 11_000),
+            ],
+        ))
+        .unwrap();
+
+    assert_eq!(created.entry.memo, "売上");
+    assert_eq!(created.lines.len(), 2);
+    assert_eq!(created.lines[0].line_no, 1);
+    assert_eq!(created.lines[0].side, Side::Debit);
+    assert_eq!(created.lines[1].amount, 11_000);
+    assert_eq!(journal.find(created.entry.id).unwrap(), created);
+}
+
+#[test]
+fn test_reject_invalid_entries() {
+    let mut connection = test_util::connection();
+    let (cash, bank, sales) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "111"),
+        account_id(&mut connection, "401"),
+    );
+    let mut journal = JournalRepository::new(&mut connection);
+
+    let unbalanced = entry(
+        date(2024, 4, 1),
+        "",
+        vec![
+            NewJournalLine::debit(cash, 1_000),
+            NewJournalLine::credit(sales, 999),
+        ],
+    );
+    assert!(matches!(
+        journal.create(&unbalanced),
+        Err(Error::Unbalanced {
+            debit: 1_000,
+            credit: 999
+        })
+    ));
+
+    let single_line = entry(date(2024, 4, 1), "", vec![NewJournalLine::debit(cash, 1)]);
+    assert!(matches!(
+        journal.create(&single_line),
+        Err(Error::Validation(_))
+    ));
+
+    let negative = entry(
+        date(2024, 4, 1),
+        "",
+        vec![
+            NewJournalLine::debit(cash, -5),
+            NewJournalLine::credit(bank, -5),
+        ],
+    );
+    assert!(matches!(
+        journal.create(&negative),
+        Err(Error::Validation(_))
+    ));
+
+    let unknown_account = entry(
+        date(2024, 4, 1),
+        "",
+        vec![
+            NewJournalLine::debit(cash, 100),
+            NewJournalLine::credit(9999, 100),
+        ],
+    );
+    assert!(matches!(
+        journal.create(&unknown_account),
+        Err(Error::NotFound(_))
+    ));
+
+    assert_eq!(count_entries(&mut connection), 0);
+}
+
+#[test]
+fn test_list_and_void() {
+    let mut connection = test_util::connection();
+    let (cash, bank, rent) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "111"),
+        account_id(&mut connection, "526"),
+    );
+    let mut journal = JournalRepository::new(&mut connection);
+
+    let withdraw = journal
+        .create(&entry(
+            date(2024, 4, 10),
+            "引出",
+            vec![
+                NewJournalLine::debit(cash, 50_000),
+                NewJournalLine::credit(bank, 50_000),
+            ],
+        ))
+        .unwrap();
+    let pay_rent = journal
+        .create(&entry(
+            date(2024, 4, 25),
+            "家賃",
+            vec![
+                NewJournalLine::debit(rent, 80_000),
+                NewJournalLine::credit(bank, 80_000),
+            ],
+        ))
+        .unwrap();
+
+    let april = journal
+        .list(&JournalQuery {
+            from: Some(date(2024, 4, 1)),
+            to: Some(date(2024, 4, 30)),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(april.len(), 2);
+    assert_eq!(april[0].entry.memo, "引出");
+    assert_eq!(april[1].lines.len(), 2);
+
+    let rent_only = journal
+        .list(&JournalQuery {
+            account_id: Some(rent),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(rent_only.len(), 1);
+    assert_eq!(rent_only[0].entry.id, pay_rent.entry.id);
+
+    let voided = journal.void(withdraw.entry.id).unwrap();
+    assert!(voided.entry.voided_at.is_some());
+    assert!(matches!(
+        journal.void(withdraw.entry.id),
+        Err(Error::Validation(_))
+    ));
+    assert_eq!(journal.list(&JournalQuery::default()).unwrap().len(), 1);
+    let all = journal
+        .list(&JournalQuery {
+            include_voided: true,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(all.len(), 2);
+
+    // Accounts with journal lines cannot be deleted.
+    assert!(AccountRepository::new(&mut connection)
+        .delete(rent)
+        .unwrap_err()
+        .is_foreign_key_violation());
+}
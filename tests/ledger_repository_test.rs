@@ -0,0 +1,188 @@
+use chrono::NaiveDate;
+
+use new_tax_account_backend::models::{NewJournalEntry, NewJournalLine};
+use new_tax_account_backend::repository::{JournalRepository, LedgerRepository};
+use new_tax_account_backend::test_util::{self, account_id, date};
+
+fn entry(entry_date: NaiveDate, memo: &str, lines: Vec<NewJournalLine>) -> NewJournalEntry {
+    NewJournalEntry {
+        entry_date,
+        memo: memo.to_string(),
+        lines,
+    }
+}
+
+#[test]
+fn test_ledger_running_balance_and_opening_balance() {
+    let mut connection = test_util::connection();
+    let (cash, sales, rent, supplies) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "401"),
+        account_id(&mut connection, "526"),
+        account_id(&mut connection, "520"),
+    );
+    let mut journal = JournalRepository::new(&mut connection);
+    journal
+        .create(&entry(
+            date(2023, 12, 20),
+            "前年売上",
+            vec![
+                NewJournalLine::debit(cash, 50_000),
+                NewJournalLine::credit(sales, 50_000),
+            ],
+        ))
+        .unwrap();
+    journal
+        .create(&entry(
+            date(2024, 1, 5),
+            "売上",
+            vec![
+                NewJournalLine::debit(cash, 30_000),
+                NewJournalLine::credit(sales, 30_000),
+            ],
+        ))
+        .unwrap();
+    let paid = journal
+        .create(&entry(
+            date(2024, 1, 25),
+            "家賃と消耗品",
+            vec![
+                NewJournalLine::debit(rent, 40_000),
+                NewJournalLine::debit(supplies, 5_000),
+                NewJournalLine::credit(cash, 45_000),
+            ],
+        ))
+        .unwrap();
+    journal
+        .create(&entry(
+            date(2024, 2, 1),
+            "翌月売上",
+            vec![
+                NewJournalLine::debit(cash, 1_000),
+                NewJournalLine::credit(sales, 1_000),
+            ],
+        ))
+        .unwrap();
+
+    let mut ledger = LedgerRepository::new(&mut connection);
+    let january = ledger
+        .ledger(cash, date(2024, 1, 1), date(2024, 1, 31))
+        .unwrap();
+    assert_eq!(january.opening_balance, 50_000);
+    assert_eq!(january.lines.len(), 2);
+    assert_eq!(january.lines[0].counterpart, "売上高");
+    assert_eq!(january.lines[0].balance, 80_000);
+    assert_eq!(january.lines[1].entry_id, paid.entry.id);
+    assert_eq!(january.lines[1].counterpart, "諸口");
+    assert_eq!(january.lines[1].credit, 45_000);
+    assert_eq!(january.lines[1].balance, 35_000);
+    assert_eq!(
+        (january.debit_total, january.credit_total),
+        (30_000, 45_000)
+    );
+    assert_eq!(january.closing_balance, 35_000);
+
+    // Revenue balances are positive on the credit side.
+    let sales_ledger = ledger
+        .ledger(sales, date(2024, 1, 1), date(2024, 12, 31))
+        .unwrap();
+    assert_eq!(sales_ledger.opening_balance, 50_000);
+    assert_eq!(sales_ledger.closing_balance, 81_000);
+
+    assert_eq!(ledger.balance_as_of(cash, date(2023, 12, 19)).unwrap(), 0);
+    assert_eq!(
+        ledger.balance_as_of(cash, date(2024, 1, 31)).unwrap(),
+        35_000
+    );
+    assert_eq!(
+        ledger.opening_balance(cash, date(2024, 2, 1)).unwrap(),
+        35_000
+    );
+    assert_eq!(
+        ledger.balance_as_of(cash, date(2024, 2, 1)).unwrap(),
+        36_000
+    );
+}
+
+#[test]
+fn test_voided_entries_do_not_count() {
+    let mut connection = test_util::connection();
+    let (cash, sales) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "401"),
+    );
+    let mut journal = JournalRepository::new(&mut connection);
+    let kept = journal
+        .create(&entry(
+            date(2024, 3, 1),
+            "売上",
+            vec![
+                NewJournalLine::debit(cash, 10_000),
+                NewJournalLine::credit(sales, 10_000),
+            ],
+        ))
+        .unwrap();
+    let voided = journal
+        .create(&entry(
+            date(2024, 3, 2),
+            "誤入力",
+            vec![
+                NewJournalLine::debit(cash, 99_000),
+                NewJournalLine::credit(sales, 99_000),
+            ],
+        ))
+        .unwrap();
+    journal.void(voided.entry.id).unwrap();
+
+    let mut ledger = LedgerRepository::new(&mut connection);
+    let march = ledger
+        .ledger(cash, date(2024, 3, 1), date(2024, 3, 31))
+        .unwrap();
+    assert_eq!(march.lines.len(), 1);
+    assert_eq!(march.lines[0].entry_id, kept.entry.id);
+    assert_eq!(march.closing_balance, 10_000);
+
+    let totals = ledger.totals_by_account(None, None).unwrap();
+    assert_eq!(totals[&cash].debit, 10_000);
+    assert_eq!(totals[&cash].lines, 1);
+    assert_eq!(totals[&sales].credit, 10_000);
+}
+
+#[test]
+fn test_balances_over_many_lines() {
+    let mut connection = test_util::connection();
+    let (cash, sales) = (
+        account_id(&mut connection, "101"),
+        account_id(&mut connection, "401"),
+    );
+    let start = date(2024, 1, 1);
+    let mut journal = JournalRepository::new(&mut connection);
+    for day in 0..2_000 {
+        journal
+            .create(&entry(
+                start + chrono::Days::new(day % 366),
+                "売上",
+                vec![
+                    NewJournalLine::debit(cash, 100 + day as i64),
+                    NewJournalLine::credit(sales, 100 + day as i64),
+                ],
+            ))
+            .unwrap();
+    }
+
+    let mut ledger = LedgerRepository::new(&mut connection);
+    let expected = (0..2_000).map(|day| 100 + day).sum::<i64>();
+    let year = ledger
+        .ledger(cash, date(2024, 1, 1), date(2024, 12, 31))
+        .unwrap();
+    assert_eq!(year.lines.len(), 2_000);
+    assert_eq!(year.closing_balance, expected);
+    assert!(year
+        .lines
+        .windows(2)
+        .all(|pair| pair[0].entry_date <= pair[1].entry_date));
+
+    let first_half = ledger.balance_as_of(cash, date(2024, 6, 30)).unwrap();
+    let second_half = ledger.totals(cash, Some(date(2024, 7, 1)), None).unwrap();
+    assert_eq!(first_half + second_half.debit, expected);
+}
@@ -0,0 +1,399 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::models::{
+    InvoicePayment, InvoiceStatus, NewInvoice, NewInvoiceLine, NewOpenItemMatch, OpenItemKind,
+    TaxCategory,
+};
+use new_tax_account_backend::report::AgingReport;
+use new_tax_account_backend::repository::{
+    InvoiceRepository, JournalRepository, LedgerRepository, OpenItemQuery, OpenItemRepository,
+};
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, CounterpartyBuilder, EntryBuilder, TestDb,
+};
+use new_tax_account_backend::{http, Error};
+
+/// Posts `amount` from account `debit` to `credit`, both lines naming the
+/// counterparty, and returns the ids of the debit and credit lines.
+fn post(
+    connection: &mut SqliteConnection,
+    on: NaiveDate,
+    (debit, credit): (&str, &str),
+    amount: i64,
+    counterparty_id: i32,
+) -> (i32, i32) {
+    let entry = EntryBuilder::new(on)
+        .debit(debit, amount)
+        .counterparty(counterparty_id)
+        .credit(credit, amount)
+        .counterparty(counterparty_id)
+        .insert(connection)
+        .unwrap();
+    (entry.lines[0].id, entry.lines[1].id)
+}
+
+fn receivables(as_of: Option<NaiveDate>) -> OpenItemQuery {
+    OpenItemQuery {
+        kind: OpenItemKind::Receivable,
+        counterparty_id: None,
+        as_of,
+    }
+}
+
+fn pay(on: NaiveDate, amount: Option<i64>) -> InvoicePayment {
+    InvoicePayment {
+        paid_on: on,
+        account_id: None,
+        amount,
+    }
+}
+
+#[test]
+fn test_partial_invoice_payments() {
+    let mut connection = test_util::connection();
+    let client = CounterpartyBuilder::new("株式会社クライアント")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let mut invoices = InvoiceRepository::new(&mut connection);
+    let id = invoices
+        .create(&NewInvoice {
+            counterparty_id: client,
+            issue_date: date(2024, 5, 31),
+            due_date: date(2024, 6, 30),
+            notes: String::new(),
+            lines: vec![NewInvoiceLine {
+                description: "デザイン".to_string(),
+                quantity: 1,
+                unit_price: 100_000,
+                tax_category: TaxCategory::Taxable10,
+            }],
+        })
+        .unwrap()
+        .invoice
+        .id;
+    invoices.issue(id).unwrap();
+
+    let first = invoices
+        .pay(id, &pay(date(2024, 6, 10), Some(30_000)))
+        .unwrap();
+    assert_eq!(first.invoice.status, InvoiceStatus::Issued);
+    assert!(matches!(invoices.void(id), Err(Error::Validation(_))));
+    assert!(matches!(
+        invoices.pay(id, &pay(date(2024, 6, 20), Some(80_001))),
+        Err(Error::Validation(_))
+    ));
+    let paid = invoices.pay(id, &pay(date(2024, 6, 28), None)).unwrap();
+    assert_eq!(paid.invoice.status, InvoiceStatus::Paid);
+    assert_eq!(paid.invoice.paid_on, Some(date(2024, 6, 28)));
+    assert!(OpenItemRepository::new(&mut connection)
+        .outstanding(&receivables(None))
+        .unwrap()
+        .is_empty());
+
+    // As of between the payments, 80,000 was still open.
+    let open = OpenItemRepository::new(&mut connection)
+        .outstanding(&receivables(Some(date(2024, 6, 15))))
+        .unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!(
+        open[0].items[0].invoice_number.as_deref(),
+        Some("INV-000001")
+    );
+    assert_eq!(open[0].items[0].due_date, Some(date(2024, 6, 30)));
+    assert_eq!(
+        (open[0].items[0].amount, open[0].items[0].remaining),
+        (110_000, 80_000)
+    );
+
+    // Taking the last payment off opens the invoice again.
+    let mut open_items = OpenItemRepository::new(&mut connection);
+    let item_line = open[0].items[0].line_id;
+    let last = open_items.matches(item_line).unwrap().pop().unwrap();
+    assert_eq!(last.amount, 80_000);
+    open_items.unmatch(last.id).unwrap();
+    let open = open_items.outstanding(&receivables(None)).unwrap();
+    assert_eq!(
+        (open[0].outstanding, open[0].unapplied, open[0].balance),
+        (80_000, 80_000, 0)
+    );
+    let invoice = InvoiceRepository::new(&mut connection).find(id).unwrap();
+    assert_eq!(invoice.invoice.status, InvoiceStatus::Issued);
+    assert_eq!(invoice.invoice.paid_on, None);
+
+    let rematched = OpenItemRepository::new(&mut connection)
+        .auto_match(&receivables(None))
+        .unwrap();
+    assert_eq!(rematched.len(), 1);
+    let invoice = InvoiceRepository::new(&mut connection).find(id).unwrap();
+    assert_eq!(invoice.invoice.status, InvoiceStatus::Paid);
+    assert!(matches!(
+        OpenItemRepository::new(&mut connection).unmatch(999),
+        Err(Error::NotFound(_))
+    ));
+}
+
+#[test]
+fn test_payables_and_matching() {
+    let mut connection = test_util::connection();
+    let supplier = CounterpartyBuilder::new("有限会社仕入先")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let other = CounterpartyBuilder::new("株式会社別会社")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let (_, january) = post(
+        &mut connection,
+        date(2024, 1, 10),
+        ("501", "202"),
+        11_000,
+        supplier,
+    );
+    let (_, february) = post(
+        &mut connection,
+        date(2024, 2, 20),
+        ("501", "202"),
+        5_500,
+        supplier,
+    );
+    let (_, accrued) = post(
+        &mut connection,
+        date(2024, 2, 25),
+        ("515", "212"),
+        3_300,
+        supplier,
+    );
+    let (payment, _) = post(
+        &mut connection,
+        date(2024, 3, 5),
+        ("202", "111"),
+        13_000,
+        supplier,
+    );
+    let (_, elsewhere) = post(
+        &mut connection,
+        date(2024, 3, 1),
+        ("501", "202"),
+        1_000,
+        other,
+    );
+
+    let mut repository = OpenItemRepository::new(&mut connection);
+    for (item_line_id, payment_line_id, amount) in [
+        // An item against an item, and the wrong way round.
+        (january, february, None),
+        (payment, january, None),
+        // Another account, and another counterparty.
+        (accrued, payment, None),
+        (elsewhere, payment, None),
+        // More than is left, and nothing at all.
+        (january, payment, Some(11_001)),
+        (january, payment, Some(0)),
+    ] {
+        assert!(matches!(
+            repository.match_payment(&NewOpenItemMatch {
+                item_line_id,
+                payment_line_id,
+                amount,
+            }),
+            Err(Error::Validation(_))
+        ));
+    }
+    assert!(matches!(repository.find(999), Err(Error::NotFound(_))));
+
+    let payables = OpenItemQuery {
+        kind: OpenItemKind::Payable,
+        counterparty_id: Some(supplier),
+        as_of: None,
+    };
+    let matches = repository.auto_match(&payables).unwrap();
+    let applied = matches
+        .iter()
+        .map(|m| (m.item_line_id, m.payment_line_id, m.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        applied,
+        vec![(january, payment, 11_000), (february, payment, 2_000)]
+    );
+    let open = repository.outstanding(&payables).unwrap();
+    assert_eq!(open.len(), 1);
+    let remaining = open[0]
+        .items
+        .iter()
+        .map(|item| (item.line_id, item.remaining))
+        .collect::<Vec<_>>();
+    assert_eq!(remaining, vec![(february, 3_500), (accrued, 3_300)]);
+    assert!(open[0].payments.is_empty());
+    assert_eq!(open[0].balance, 6_800);
+    // Nothing is left to match.
+    assert!(repository.auto_match(&payables).unwrap().is_empty());
+    assert!(matches!(
+        repository.match_payment(&NewOpenItemMatch {
+            item_line_id: february,
+            payment_line_id: payment,
+            amount: None,
+        }),
+        Err(Error::Validation(_))
+    ));
+
+    // A voided payment no longer settles anything.
+    let entry_id = repository.find(payment).unwrap().entry_id;
+    JournalRepository::new(&mut connection)
+        .void(entry_id)
+        .unwrap();
+    let open = OpenItemRepository::new(&mut connection)
+        .outstanding(&payables)
+        .unwrap();
+    assert_eq!(open[0].outstanding, 11_000 + 5_500 + 3_300);
+}
+
+#[test]
+fn test_aging() {
+    let mut connection = test_util::connection();
+    let early = CounterpartyBuilder::new("A商事")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let late = CounterpartyBuilder::new("B商事")
+        .insert(&mut connection)
+        .unwrap()
+        .id;
+    let as_of = date(2024, 6, 30);
+    for (on, amount) in [
+        (date(2024, 6, 30), 1_000),
+        (date(2024, 5, 31), 2_000),
+        (date(2024, 5, 31), 10),
+        (date(2024, 5, 30), 3_000),
+        (date(2024, 4, 1), 4_000),
+        (date(2024, 3, 1), 5_000),
+    ] {
+        post(&mut connection, on, ("122", "401"), amount, late);
+    }
+    let (early_item, _) = post(
+        &mut connection,
+        date(2024, 1, 15),
+        ("122", "401"),
+        50_000,
+        early,
+    );
+    let (_, receipt) = post(
+        &mut connection,
+        date(2024, 2, 15),
+        ("111", "122"),
+        20_000,
+        early,
+    );
+    // Received ahead of any work.
+    post(
+        &mut connection,
+        date(2024, 6, 20),
+        ("111", "122"),
+        700,
+        late,
+    );
+    OpenItemRepository::new(&mut connection)
+        .match_payment(&NewOpenItemMatch {
+            item_line_id: early_item,
+            payment_line_id: receipt,
+            amount: None,
+        })
+        .unwrap();
+
+    let report = AgingReport::generate(&mut connection, OpenItemKind::Receivable, as_of).unwrap();
+    let rows = report
+        .rows
+        .iter()
+        .map(|row| {
+            (
+                row.name.as_str(),
+                row.buckets,
+                row.outstanding,
+                row.unapplied,
+                row.balance,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            ("A商事", [0, 0, 0, 30_000], 30_000, 0, 30_000),
+            ("B商事", [3_010, 3_000, 4_000, 5_000], 15_010, 700, 14_310),
+        ]
+    );
+    assert_eq!(report.total_buckets, [3_010, 3_000, 4_000, 35_000]);
+    assert_eq!(report.total_balance, 44_310);
+    let receivable = account_id(&mut connection, "122");
+    let ledger = LedgerRepository::new(&mut connection)
+        .ledger(receivable, date(2024, 1, 1), as_of)
+        .unwrap();
+    assert_eq!(report.total_balance, ledger.closing_balance);
+
+    // Earlier, the receipt had not come in and everything was younger.
+    let report =
+        AgingReport::generate(&mut connection, OpenItemKind::Receivable, date(2024, 2, 1)).unwrap();
+    assert_eq!(report.rows.len(), 1);
+    assert_eq!(report.rows[0].buckets, [50_000, 0, 0, 0]);
+    assert!(report.to_table().contains("売掛金年齢表 2024-02-01現在"));
+
+    let payables = AgingReport::generate(&mut connection, OpenItemKind::Payable, as_of).unwrap();
+    assert!(payables.rows.is_empty());
+}
+
+#[tokio::test]
+async fn test_http_open_items() {
+    let mut db = TestDb::temp_file();
+    let supplier = CounterpartyBuilder::new("有限会社仕入先")
+        .insert(db.conn())
+        .unwrap()
+        .id;
+    let (_, bill) = post(
+        db.conn(),
+        date(2024, 1, 10),
+        ("501", "202"),
+        11_000,
+        supplier,
+    );
+    let (payment, _) = post(db.conn(), date(2024, 2, 5), ("202", "111"), 4_000, supplier);
+    let app = http::router(db.pool());
+
+    let response = send(&app, request("GET", "/open-items?kind=payable", None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json();
+    assert_eq!(body[0]["outstanding"], 11_000);
+    assert_eq!(body[0]["unapplied"], 4_000);
+
+    let new_match = json!({ "item_line_id": bill, "payment_line_id": payment });
+    let response = send(
+        &app,
+        request("POST", "/open-items/matches", Some(new_match)),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let matched = response.json();
+    assert_eq!(matched["amount"], 4_000);
+
+    let uri = format!("/open-items/lines/{}", bill);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["remaining"], 7_000);
+
+    let uri = "/reports/aging?as_of=2024-02-20&kind=payable";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(
+        response.json()["rows"][0]["buckets"],
+        json!([0, 7_000, 0, 0])
+    );
+
+    let uri = format!("/open-items/matches/{}", matched["id"]);
+    let response = send(&app, request("DELETE", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    let uri = format!("/open-items/lines/{}/matches", bill);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.json(), json!([]));
+}
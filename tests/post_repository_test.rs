@@ -0,0 +1,240 @@
+use diesel::prelude::*;
+
+use new_tax_account_backend::models::{NewPost, PostChanges, PostTag, Tag};
+use new_tax_account_backend::repository::post::BULK_LIMIT;
+use new_tax_account_backend::repository::{BulkItem, BulkStatus, PostFilter, PostRepository};
+use new_tax_account_backend::schema::{post_tags, tags};
+use new_tax_account_backend::test_util;
+use new_tax_account_backend::Error;
+
+fn new_post(title: &str) -> NewPost {
+    NewPost {
+        title: title.to_string(),
+        body: "body".to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_update_increments_version() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+    let post = repository.create(&new_post("title1")).unwrap();
+    assert_eq!(post.version, 0);
+
+    let changes = PostChanges {
+        body: Some("new body".to_string()),
+        author: Some(Some("alice".to_string())),
+        ..Default::default()
+    };
+    let updated = repository.update(post.id.unwrap(), 0, &changes).unwrap();
+
+    assert_eq!(updated.version, 1);
+    assert_eq!(updated.title, "title1");
+    assert_eq!(updated.body, "new body");
+    assert_eq!(updated.author.as_deref(), Some("alice"));
+}
+
+#[test]
+fn test_concurrent_update_conflicts() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+    let id = repository.create(&new_post("title1")).unwrap().id.unwrap();
+
+    // Both editors loaded version 0; the first one wins.
+    let first = PostChanges {
+        body: Some("first".to_string()),
+        ..Default::default()
+    };
+    let second = PostChanges {
+        body: Some("second".to_string()),
+        ..Default::default()
+    };
+    repository.update(id, 0, &first).unwrap();
+    let result = repository.update(id, 0, &second);
+
+    match result {
+        Err(Error::Conflict {
+            resource,
+            id: conflicted,
+            expected,
+            current,
+        }) => {
+            assert_eq!((resource, conflicted), ("post", id));
+            assert_eq!((expected, current), (0, 1));
+        }
+        _ => panic!("expected a conflict"),
+    }
+    assert_eq!(repository.find(id).unwrap().body, "first");
+}
+
+#[test]
+fn test_update_missing_post() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+
+    let result = repository.update(1, 0, &PostChanges::default());
+    assert!(matches!(result, Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_delete_requires_version() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+    let id = repository.create(&new_post("title1")).unwrap().id.unwrap();
+
+    assert!(matches!(
+        repository.delete(id, 5),
+        Err(Error::Conflict { .. })
+    ));
+    repository.delete(id, 0).unwrap();
+    assert!(matches!(repository.find(id), Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_bulk_create() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+
+    let new_posts = (1..=3)
+        .map(|i| new_post(&format!("title{}", i)))
+        .collect::<Vec<_>>();
+    let items = repository.bulk_create(&new_posts).unwrap();
+
+    assert_eq!(items.len(), 3);
+    assert!(items.iter().all(|item| item.status == BulkStatus::Created));
+    assert_eq!(repository.find(items[2].id).unwrap().title, "title3");
+
+    // Several statements' worth, reusing the id of the deleted last post.
+    repository.delete(items[2].id, 0).unwrap();
+    let new_posts = (0..BULK_LIMIT)
+        .map(|i| NewPost {
+            author: (i % 2 == 0).then(|| "bob".to_string()),
+            ..new_post(&format!("bulk{}", i))
+        })
+        .collect::<Vec<_>>();
+    let items = repository.bulk_create(&new_posts).unwrap();
+    assert_eq!(items.len(), BULK_LIMIT);
+    for i in [0, 1, 41, BULK_LIMIT - 1] {
+        let post = repository.find(items[i].id).unwrap();
+        assert_eq!(post.title, format!("bulk{}", i));
+        assert_eq!(post.author.is_some(), i % 2 == 0);
+    }
+}
+
+#[test]
+fn test_bulk_create_is_atomic() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+
+    let too_many = vec![new_post("title"); BULK_LIMIT + 1];
+    assert!(matches!(
+        repository.bulk_create(&too_many),
+        Err(Error::Validation(_))
+    ));
+    assert!(repository.list(None, 10, 0).unwrap().is_empty());
+}
+
+#[test]
+fn test_bulk_publish_and_recategorize() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+    let items = repository
+        .bulk_create(&[new_post("title1"), new_post("title2")])
+        .unwrap();
+    let ids = vec![items[0].id, items[1].id, 999];
+
+    let published = repository.bulk_set_published(&ids, true).unwrap();
+    assert_eq!(
+        published,
+        vec![
+            BulkItem {
+                id: ids[0],
+                status: BulkStatus::Updated
+            },
+            BulkItem {
+                id: ids[1],
+                status: BulkStatus::Updated
+            },
+            BulkItem {
+                id: 999,
+                status: BulkStatus::NotFound
+            },
+        ]
+    );
+    assert_eq!(repository.list(Some(true), 10, 0).unwrap().len(), 2);
+
+    repository.bulk_recategorize(&ids[..1], Some(5)).unwrap();
+    let post = repository.find(ids[0]).unwrap();
+    assert_eq!(post.category_id, Some(5));
+    assert_eq!(post.version, 2);
+}
+
+#[test]
+fn test_bulk_delete_by_filter() {
+    let mut connection = test_util::connection();
+    let mut repository = PostRepository::new(&mut connection);
+    let mut posts = vec![new_post("title1"), new_post("title2"), new_post("title3")];
+    posts[0].author = Some("bob".to_string());
+    posts[2].author = Some("bob".to_string());
+    repository.bulk_create(&posts).unwrap();
+
+    assert!(matches!(
+        repository.bulk_delete(&PostFilter::default()),
+        Err(Error::Validation(_))
+    ));
+
+    let filter = PostFilter {
+        author: Some("bob".to_string()),
+        ..Default::default()
+    };
+    let deleted = repository.bulk_delete(&filter).unwrap();
+    assert_eq!(deleted.len(), 2);
+
+    let remaining = repository.list(None, 10, 0).unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].title, "title2");
+}
+
+#[test]
+fn test_delete_removes_tags() {
+    let mut connection = test_util::connection();
+    let tag_id = diesel::insert_into(tags::table)
+        .values(&Tag {
+            id: None,
+            name: "rust".to_string(),
+        })
+        .returning(tags::id.assume_not_null())
+        .get_result::<i32>(&mut connection)
+        .unwrap();
+    let mut repository = PostRepository::new(&mut connection);
+    let posts = vec![new_post("title1"), new_post("title2"), new_post("title3")];
+    let ids: Vec<i32> = repository
+        .bulk_create(&posts)
+        .unwrap()
+        .iter()
+        .map(|item| item.id)
+        .collect();
+    let new_post_tags: Vec<PostTag> = ids
+        .iter()
+        .map(|&post_id| PostTag { post_id, tag_id })
+        .collect();
+    diesel::insert_into(post_tags::table)
+        .values(&new_post_tags)
+        .execute(&mut connection)
+        .unwrap();
+
+    let mut repository = PostRepository::new(&mut connection);
+    repository.delete(ids[0], 0).unwrap();
+    let filter = PostFilter {
+        ids: Some(ids[1..].to_vec()),
+        ..Default::default()
+    };
+    assert_eq!(repository.bulk_delete(&filter).unwrap().len(), 2);
+
+    let left: i64 = post_tags::table
+        .count()
+        .get_result(&mut connection)
+        .unwrap();
+    assert_eq!(left, 0);
+}
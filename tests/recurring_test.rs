@@ -0,0 +1,346 @@
+use axum::http::StatusCode;
+use chrono::Utc;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::models::{
+    NewJournalLine, NewRecurringEntry, OccurrenceChanges, OccurrenceStatus, RecurringEntry,
+    Schedule, Side,
+};
+use new_tax_account_backend::recurring::due_dates;
+use new_tax_account_backend::repository::{
+    AccountRepository, JournalRepository, RecurringEntryRepository,
+};
+use new_tax_account_backend::test_util::{self, account_id, date, request, send, TestDb};
+use new_tax_account_backend::{http, Error};
+
+fn schedule(schedule: Schedule, day: Option<i32>, month: Option<i32>) -> RecurringEntry {
+    let now = Utc::now().naive_utc();
+    RecurringEntry {
+        id: 1,
+        name: String::new(),
+        memo: String::new(),
+        schedule,
+        day,
+        month,
+        start_date: date(2024, 1, 1),
+        end_date: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// Rent of `amount` paid from the bank on day 25 of every month of 2024.
+fn rent(connection: &mut SqliteConnection, amount: i64) -> NewRecurringEntry {
+    NewRecurringEntry {
+        name: "家賃".to_string(),
+        memo: String::new(),
+        schedule: Schedule::Monthly,
+        day: Some(25),
+        month: None,
+        start_date: date(2024, 1, 1),
+        end_date: Some(date(2024, 12, 31)),
+        lines: vec![
+            NewJournalLine::debit(account_id(connection, "526"), amount),
+            NewJournalLine::credit(account_id(connection, "111"), amount),
+        ],
+    }
+}
+
+#[test]
+fn test_due_dates() {
+    let (from, to) = (date(2024, 1, 1), date(2025, 12, 31));
+
+    let monthly = schedule(Schedule::Monthly, Some(31), None);
+    let dates = due_dates(&monthly, from, date(2024, 4, 30));
+    assert_eq!(
+        dates,
+        [
+            date(2024, 1, 31),
+            date(2024, 2, 29),
+            date(2024, 3, 31),
+            date(2024, 4, 30)
+        ]
+    );
+
+    let end_of_month = schedule(Schedule::EndOfMonth, None, None);
+    let dates = due_dates(&end_of_month, date(2025, 1, 15), date(2025, 3, 30));
+    assert_eq!(dates, [date(2025, 1, 31), date(2025, 2, 28)]);
+
+    let yearly = schedule(Schedule::Yearly, Some(29), Some(2));
+    assert_eq!(
+        due_dates(&yearly, from, to),
+        [date(2024, 2, 29), date(2025, 2, 28)]
+    );
+
+    let bounded = RecurringEntry {
+        start_date: date(2024, 3, 10),
+        end_date: Some(date(2024, 6, 9)),
+        ..schedule(Schedule::Monthly, Some(10), None)
+    };
+    assert_eq!(
+        due_dates(&bounded, from, to),
+        [date(2024, 3, 10), date(2024, 4, 10), date(2024, 5, 10)]
+    );
+}
+
+#[test]
+fn test_materialize_is_idempotent() {
+    let mut connection = test_util::connection();
+    let new_entry = rent(&mut connection, 110_000);
+    let template = RecurringEntryRepository::new(&mut connection)
+        .create(&new_entry)
+        .unwrap();
+    let id = template.recurring_entry.id;
+    assert_eq!(template.lines.len(), 2);
+
+    let mut repository = RecurringEntryRepository::new(&mut connection);
+    let posted = repository.materialize(date(2024, 3, 24)).unwrap();
+    assert_eq!(
+        posted.iter().map(|o| o.due_date).collect::<Vec<_>>(),
+        [date(2024, 1, 25), date(2024, 2, 25)]
+    );
+    assert!(repository
+        .materialize(date(2024, 3, 24))
+        .unwrap()
+        .is_empty());
+
+    let posted = repository.materialize(date(2024, 3, 25)).unwrap();
+    assert_eq!(posted.len(), 1);
+    let entry_id = posted[0].entry_id.unwrap();
+    let entry = JournalRepository::new(&mut connection)
+        .find(entry_id)
+        .unwrap();
+    assert_eq!(entry.entry.entry_date, date(2024, 3, 25));
+    assert_eq!(entry.entry.memo, "家賃");
+
+    let mut repository = RecurringEntryRepository::new(&mut connection);
+    let occurrences = repository.occurrences(id, None, date(2024, 4, 30)).unwrap();
+    assert_eq!(
+        occurrences.iter().map(|o| o.status).collect::<Vec<_>>(),
+        [
+            OccurrenceStatus::Posted,
+            OccurrenceStatus::Posted,
+            OccurrenceStatus::Posted,
+            OccurrenceStatus::Due
+        ]
+    );
+
+    // Past the end date nothing more falls due.
+    assert_eq!(repository.materialize(date(2025, 6, 30)).unwrap().len(), 9);
+    assert!(repository
+        .materialize(date(2025, 6, 30))
+        .unwrap()
+        .is_empty());
+
+    // Posted templates are ended rather than deleted.
+    assert!(matches!(repository.delete(id), Err(Error::Validation(_))));
+}
+
+#[test]
+fn test_skip_and_modify_occurrences() {
+    let mut connection = test_util::connection();
+    let new_entry = rent(&mut connection, 110_000);
+    let mut raised = rent(&mut connection, 121_000);
+    raised.lines[0].description = "家賃改定".to_string();
+    let mut repository = RecurringEntryRepository::new(&mut connection);
+    let id = repository.create(&new_entry).unwrap().recurring_entry.id;
+
+    let skipped = repository.skip(id, date(2024, 2, 25)).unwrap();
+    assert_eq!(skipped.status, OccurrenceStatus::Skipped);
+    let modified = repository
+        .modify(
+            id,
+            date(2024, 3, 25),
+            &OccurrenceChanges {
+                entry_date: Some(date(2024, 3, 27)),
+                lines: Some(raised.lines.clone()),
+            },
+        )
+        .unwrap();
+    assert!(modified.modified);
+    assert_eq!(modified.entry_date, date(2024, 3, 27));
+
+    // Only scheduled dates that are not posted can be changed.
+    assert!(matches!(
+        repository.skip(id, date(2024, 2, 24)),
+        Err(Error::Validation(_))
+    ));
+    let unbalanced = OccurrenceChanges {
+        entry_date: None,
+        lines: Some(vec![raised.lines[0].clone()]),
+    };
+    assert!(matches!(
+        repository.modify(id, date(2024, 4, 25), &unbalanced),
+        Err(Error::Validation(_))
+    ));
+
+    let posted = repository.materialize(date(2024, 3, 31)).unwrap();
+    assert_eq!(
+        posted
+            .iter()
+            .map(|o| (o.due_date, o.entry_date))
+            .collect::<Vec<_>>(),
+        [
+            (date(2024, 1, 25), date(2024, 1, 25)),
+            (date(2024, 3, 25), date(2024, 3, 27))
+        ]
+    );
+    assert!(matches!(
+        repository.skip(id, date(2024, 3, 25)),
+        Err(Error::Validation(_))
+    ));
+    let entry = JournalRepository::new(&mut connection)
+        .find(posted[1].entry_id.unwrap())
+        .unwrap();
+    let debits = entry
+        .lines
+        .iter()
+        .filter(|line| line.side == Side::Debit)
+        .map(|line| line.amount)
+        .sum::<i64>();
+    assert_eq!(debits, 121_000);
+    assert!(entry
+        .lines
+        .iter()
+        .any(|line| line.description == "家賃改定"));
+
+    // A skipped occurrence restored is posted on the next run.
+    let mut repository = RecurringEntryRepository::new(&mut connection);
+    let restored = repository.restore(id, date(2024, 2, 25)).unwrap();
+    assert_eq!(restored.status, OccurrenceStatus::Due);
+    let posted = repository.materialize(date(2024, 3, 31)).unwrap();
+    assert_eq!(
+        posted.iter().map(|o| o.due_date).collect::<Vec<_>>(),
+        [date(2024, 2, 25)]
+    );
+}
+
+#[test]
+fn test_template_validation() {
+    let mut connection = test_util::connection();
+    let valid = rent(&mut connection, 110_000);
+    let mut repository = RecurringEntryRepository::new(&mut connection);
+    let invalid = [
+        NewRecurringEntry {
+            name: " ".to_string(),
+            ..valid.clone()
+        },
+        NewRecurringEntry {
+            month: Some(4),
+            ..valid.clone()
+        },
+        NewRecurringEntry {
+            schedule: Schedule::Yearly,
+            ..valid.clone()
+        },
+        NewRecurringEntry {
+            day: Some(32),
+            ..valid.clone()
+        },
+        NewRecurringEntry {
+            end_date: Some(date(2023, 12, 31)),
+            ..valid.clone()
+        },
+        NewRecurringEntry {
+            lines: valid.lines[..1].to_vec(),
+            ..valid.clone()
+        },
+    ];
+    for new_entry in &invalid {
+        assert!(
+            matches!(repository.create(new_entry), Err(Error::Validation(_))),
+            "{:?}",
+            new_entry
+        );
+    }
+    assert!(repository.list().unwrap().is_empty());
+
+    let id = repository.create(&valid).unwrap().recurring_entry.id;
+    let end_of_month = NewRecurringEntry {
+        schedule: Schedule::EndOfMonth,
+        day: None,
+        ..valid.clone()
+    };
+    let updated = repository.update(id, &end_of_month).unwrap();
+    assert_eq!(updated.recurring_entry.schedule, Schedule::EndOfMonth);
+    repository.delete(id).unwrap();
+    assert!(matches!(repository.find(id), Err(Error::NotFound(_))));
+}
+
+#[test]
+fn test_accounts_used_by_templates_cannot_be_deleted() {
+    let mut connection = test_util::connection();
+    let new_entry = rent(&mut connection, 110_000);
+    let id = RecurringEntryRepository::new(&mut connection)
+        .create(&new_entry)
+        .unwrap()
+        .recurring_entry
+        .id;
+
+    let rent = new_entry.lines[0].account_id;
+    assert!(AccountRepository::new(&mut connection)
+        .delete(rent)
+        .unwrap_err()
+        .is_foreign_key_violation());
+    RecurringEntryRepository::new(&mut connection)
+        .delete(id)
+        .unwrap();
+    AccountRepository::new(&mut connection)
+        .delete(rent)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_http_recurring_entries() {
+    let mut db = TestDb::temp_file();
+    let new_entry = rent(db.conn(), 55_000);
+    let app = http::router(db.pool());
+
+    let response = send(
+        &app,
+        request("POST", "/recurring-entries", Some(json!(new_entry))),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let template = response.json();
+    let id = template["id"].as_i64().unwrap();
+    assert_eq!(template["schedule"], "monthly");
+
+    let uri = format!("/recurring-entries/{}/occurrences/2024-01-25/skip", id);
+    let response = send(&app, request("POST", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let uri = format!("/recurring-entries/{}/occurrences/2024-02-25", id);
+    let changes = json!({ "entry_date": "2024-02-26" });
+    let response = send(&app, request("PUT", &uri, Some(changes))).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["modified"], true);
+
+    let uri = "/recurring-entries/run?to=2024-03-31";
+    let response = send(&app, request("POST", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let posted = response.json();
+    let dates = posted
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| o["entry_date"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(dates, ["2024-02-26", "2024-03-25"]);
+
+    let uri = format!("/recurring-entries/{}/occurrences?to=2024-04-30", id);
+    let response = send(&app, request("GET", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let occurrences = response.json();
+    let statuses = occurrences
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|o| o["status"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(statuses, ["skipped", "posted", "posted", "due"]);
+
+    let uri = format!("/recurring-entries/{}", id);
+    let response = send(&app, request("DELETE", &uri, None)).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
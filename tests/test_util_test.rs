@@ -0,0 +1,135 @@
+use axum::http::StatusCode;
+use diesel::prelude::*;
+use serde_json::json;
+
+use self::models::*;
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, year, CategoryBuilder, CounterpartyBuilder,
+    EntryBuilder, PostBuilder, TestDb,
+};
+use new_tax_account_backend::*;
+
+fn count_posts(connection: &mut SqliteConnection) -> i64 {
+    use self::schema::posts;
+    posts::table.count().get_result(connection).unwrap()
+}
+
+#[test]
+fn test_databases_are_isolated() {
+    let mut first = TestDb::in_memory();
+    let mut second = TestDb::temp_file();
+
+    PostBuilder::new("title1").insert(first.conn()).unwrap();
+
+    assert_eq!(count_posts(first.conn()), 1);
+    assert_eq!(count_posts(second.conn()), 0);
+    assert!(first.path().is_none());
+    assert!(second.path().unwrap().is_file());
+}
+
+#[test]
+fn test_rollback() {
+    let mut connection = test_util::connection();
+    PostBuilder::new("kept").insert(&mut connection).unwrap();
+
+    let inserted = test_util::rollback(&mut connection, |connection| {
+        PostBuilder::new("discarded").insert(connection).unwrap();
+        count_posts(connection)
+    });
+
+    assert_eq!(inserted, 2);
+    assert_eq!(count_posts(&mut connection), 1);
+}
+
+#[test]
+fn test_builders() {
+    let mut connection = test_util::connection();
+    let category: Category = CategoryBuilder::new("Tech")
+        .description("technology")
+        .insert(&mut connection)
+        .unwrap();
+    let post: Post = PostBuilder::new("title1")
+        .category_id(category.id.unwrap())
+        .author("Alice")
+        .published(true)
+        .good_count(10)
+        .insert(&mut connection)
+        .unwrap();
+
+    assert!(post.id.is_some());
+    assert_eq!(post.body, "title1 body");
+    assert_eq!(post.category_id, category.id);
+    assert_eq!(post.author.as_deref(), Some("Alice"));
+    assert!(post.published);
+    assert_eq!(post.good_count, 10);
+}
+
+#[tokio::test]
+async fn test_http_helpers() {
+    let db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_post = json!({ "title": "title1", "body": "body1" });
+    let response = send(&app, request("POST", "/posts", Some(new_post))).await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.header("etag"), Some("\"0\""));
+    assert_eq!(response.json()["title"], "title1");
+
+    let response = send(&app, request("GET", "/posts/0", None)).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_date() {
+    assert_eq!(date(2024, 2, 29).to_string(), "2024-02-29");
+}
+
+#[test]
+fn test_entry_and_counterparty_builders() {
+    let mut connection = test_util::connection();
+    let landlord = CounterpartyBuilder::new("大家")
+        .address("東京都千代田区1-1")
+        .insert(&mut connection)
+        .unwrap();
+    let bank = account_id(&mut connection, "111");
+    let entry = EntryBuilder::new(date(2024, 1, 25))
+        .memo("家賃")
+        .debit("526", 100_000)
+        .counterparty(landlord.id)
+        .line(models::NewJournalLine::credit(bank, 100_000))
+        .insert(&mut connection)
+        .unwrap();
+
+    assert_eq!(entry.entry.memo, "家賃");
+    assert_eq!(
+        entry.lines[0].account_id,
+        account_id(&mut connection, "526")
+    );
+    assert_eq!(entry.lines[0].counterparty_id, Some(landlord.id));
+    assert_eq!(
+        (entry.lines[1].account_id, entry.lines[1].counterparty_id),
+        (bank, None)
+    );
+    assert!(EntryBuilder::new(date(2024, 1, 25))
+        .debit("526", 1)
+        .insert(&mut connection)
+        .is_err());
+
+    let entry = EntryBuilder::new(date(2024, 1, 26))
+        .debit("101", 10_800)
+        .credit("401", 10_800)
+        .tax(models::TaxCategory::Reduced8)
+        .insert(&mut connection)
+        .unwrap();
+    assert_eq!(entry.lines[1].tax_category, models::TaxCategory::Reduced8);
+    assert_eq!(entry.lines[1].tax_amount, 800);
+}
+
+#[test]
+fn test_year() {
+    let period = year(2024);
+    assert_eq!(
+        (period.from, period.to),
+        (date(2024, 1, 1), date(2024, 12, 31))
+    );
+}
@@ -0,0 +1,118 @@
+use axum::http::StatusCode;
+use diesel::SqliteConnection;
+
+use new_tax_account_backend::models::AccountType;
+use new_tax_account_backend::report::{Period, TrialBalance};
+use new_tax_account_backend::test_util::{self, date, request, send, year, EntryBuilder, TestDb};
+use new_tax_account_backend::{http, Error};
+
+/// Capital paid in last year, then a sale and rent in January.
+fn sample_entries(connection: &mut SqliteConnection) {
+    for (entry_date, debit, credit, amount) in [
+        (date(2023, 12, 1), "111", "301", 1_000_000),
+        (date(2024, 1, 10), "111", "401", 330_000),
+        (date(2024, 1, 31), "526", "111", 80_000),
+    ] {
+        EntryBuilder::new(entry_date)
+            .debit(debit, amount)
+            .credit(credit, amount)
+            .insert(connection)
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_trial_balance_groups_and_totals() {
+    let mut connection = test_util::connection();
+    sample_entries(&mut connection);
+
+    let report = TrialBalance::generate(
+        &mut connection,
+        Period::new(date(2024, 1, 1), date(2024, 1, 31)).unwrap(),
+    )
+    .unwrap();
+    assert!(report.balanced);
+    assert_eq!(
+        (report.debit_total, report.credit_total),
+        (410_000, 410_000)
+    );
+
+    let types = report
+        .groups
+        .iter()
+        .map(|group| group.account_type)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            AccountType::Asset,
+            AccountType::Equity,
+            AccountType::Revenue,
+            AccountType::Expense
+        ]
+    );
+
+    let bank = &report.groups[0].rows[0];
+    assert_eq!(bank.code, "111");
+    assert_eq!(bank.opening_balance, 1_000_000);
+    assert_eq!((bank.debit, bank.credit), (330_000, 80_000));
+    assert_eq!(bank.closing_balance, 1_250_000);
+
+    // Capital has no January activity but still carries its balance.
+    let capital = &report.groups[1];
+    assert_eq!(capital.rows[0].opening_balance, 1_000_000);
+    assert_eq!(capital.rows[0].closing_balance, 1_000_000);
+    assert_eq!(capital.debit + capital.credit, 0);
+
+    assert_eq!(report.groups[2].closing_balance, 330_000);
+    assert_eq!(report.groups[3].closing_balance, 80_000);
+}
+
+#[test]
+fn test_trial_balance_csv_and_table() {
+    let mut connection = test_util::connection();
+    sample_entries(&mut connection);
+    let report = TrialBalance::generate(&mut connection, year(2024)).unwrap();
+
+    let mut csv = Vec::new();
+    report.write_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        "account_type,code,name,opening_balance,debit,credit,closing_balance"
+    );
+    assert_eq!(lines[1], "asset,111,普通預金,1000000,330000,80000,1250000");
+    assert_eq!(lines.len(), 5);
+
+    let table = report.to_table();
+    assert!(table.contains("資産計"));
+    assert!(table.contains("1,250,000"));
+    assert!(!table.contains("warning"));
+}
+
+#[test]
+fn test_trial_balance_rejects_inverted_period() {
+    let result = Period::new(date(2024, 2, 1), date(2024, 1, 1));
+    assert!(matches!(result, Err(Error::Validation(_))));
+}
+
+#[tokio::test]
+async fn test_trial_balance_over_http() {
+    let mut db = TestDb::temp_file();
+    sample_entries(db.conn());
+    let app = http::router(db.pool());
+
+    let uri = "/reports/trial-balance?from=2024-01-01&to=2024-01-31";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let report = response.json();
+    assert_eq!(report["balanced"], true);
+    assert_eq!(report["debit_total"], 410_000);
+    assert_eq!(report["groups"][0]["account_type"], "asset");
+    assert_eq!(report["groups"][0]["rows"][0]["closing_balance"], 1_250_000);
+
+    let uri = "/reports/trial-balance?from=2024-02-01&to=2024-01-01";
+    let response = send(&app, request("GET", uri, None)).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -0,0 +1,281 @@
+use axum::http::StatusCode;
+use chrono::NaiveDate;
+use diesel::SqliteConnection;
+use serde_json::json;
+
+use new_tax_account_backend::models::{
+    JournalEntryWithLines, Rounding, Side, TaxMethod, TaxSettingsChanges, Withholding,
+};
+use new_tax_account_backend::report::{PaymentRecordSummary, WithholdingLedger};
+use new_tax_account_backend::repository::TaxSettingsRepository;
+use new_tax_account_backend::test_util::{
+    self, account_id, date, request, send, year, CounterpartyBuilder, EntryBuilder, TestDb,
+};
+use new_tax_account_backend::withholding::withholding_tax;
+use new_tax_account_backend::{http, Error};
+
+fn counterparty(connection: &mut SqliteConnection, name: &str, withholding: Withholding) -> i32 {
+    CounterpartyBuilder::new(name)
+        .withholding(withholding)
+        .insert(connection)
+        .unwrap()
+        .id
+}
+
+/// Pays `amount` from 普通預金 for 外注工賃 to `payee`.
+fn pay(entry_date: NaiveDate, amount: i64, payee: i32) -> EntryBuilder {
+    EntryBuilder::new(entry_date)
+        .debit("524", amount)
+        .counterparty(payee)
+        .credit("111", amount)
+}
+
+/// (account id, side, amount) of each line.
+fn lines(entry: &JournalEntryWithLines) -> Vec<(i32, Side, i64)> {
+    entry
+        .lines
+        .iter()
+        .map(|line| (line.account_id, line.side, line.amount))
+        .collect()
+}
+
+#[test]
+fn test_withholding_tax() {
+    assert_eq!(withholding_tax(0), 0);
+    assert_eq!(withholding_tax(10_000), 1_021);
+    assert_eq!(withholding_tax(110_000), 11_231);
+    assert_eq!(withholding_tax(1_000_000), 102_100);
+    // 102,100 + 20.42% of 210,000
+    assert_eq!(withholding_tax(1_210_000), 144_982);
+}
+
+#[test]
+fn test_payment_withholds_to_deposits() {
+    let mut connection = test_util::connection();
+    let writer = counterparty(&mut connection, "山田 花子", Withholding::Payments);
+    let (bank, outsourcing, deposits) = (
+        account_id(&mut connection, "111"),
+        account_id(&mut connection, "524"),
+        account_id(&mut connection, "214"),
+    );
+
+    let entry = pay(date(2024, 1, 10), 110_000, writer)
+        .insert(&mut connection)
+        .unwrap();
+    assert_eq!(
+        lines(&entry),
+        vec![
+            (outsourcing, Side::Debit, 110_000),
+            (bank, Side::Credit, 98_769),
+            (deposits, Side::Credit, 11_231),
+        ]
+    );
+    assert_eq!(entry.lines[2].counterparty_id, Some(writer));
+
+    // Under 税抜経理 tax is still worked out on the payment with
+    // consumption tax.
+    TaxSettingsRepository::new(&mut connection)
+        .update(&TaxSettingsChanges {
+            method: Some(TaxMethod::Exclusive),
+            rounding: Some(Rounding::Floor),
+        })
+        .unwrap();
+    let entry = pay(date(2024, 1, 20), 110_000, writer)
+        .insert(&mut connection)
+        .unwrap();
+    assert_eq!(
+        lines(&entry)[2..],
+        [
+            (bank, Side::Credit, 98_769),
+            (deposits, Side::Credit, 11_231)
+        ]
+    );
+
+    // Without a rule nothing is withheld.
+    let shop = counterparty(&mut connection, "文具店", Withholding::None);
+    let entry = EntryBuilder::new(date(2024, 1, 25))
+        .debit("520", 11_000)
+        .counterparty(shop)
+        .credit("111", 11_000)
+        .insert(&mut connection)
+        .unwrap();
+    assert!(lines(&entry).iter().all(|line| line.0 != deposits));
+}
+
+#[test]
+fn test_receipt_withholds_to_drawings() {
+    let mut connection = test_util::connection();
+    let client = counterparty(&mut connection, "株式会社出版", Withholding::Receipts);
+    let (receivable, drawings) = (
+        account_id(&mut connection, "122"),
+        account_id(&mut connection, "191"),
+    );
+
+    let entry = EntryBuilder::new(date(2024, 2, 29))
+        .debit("122", 110_000)
+        .credit("401", 110_000)
+        .counterparty(client)
+        .insert(&mut connection)
+        .unwrap();
+    assert_eq!(
+        lines(&entry)[..2],
+        [
+            (receivable, Side::Debit, 98_769),
+            (drawings, Side::Debit, 11_231)
+        ]
+    );
+}
+
+#[test]
+fn test_manual_withholding_and_missing_payment() {
+    let mut connection = test_util::connection();
+    let writer = counterparty(&mut connection, "山田 花子", Withholding::Payments);
+
+    // Withheld by hand: left as entered.
+    let entry = EntryBuilder::new(date(2024, 1, 10))
+        .debit("524", 110_000)
+        .counterparty(writer)
+        .credit("111", 99_790)
+        .credit("214", 10_210)
+        .counterparty(writer)
+        .insert(&mut connection)
+        .unwrap();
+    assert_eq!(entry.lines.len(), 3);
+    assert_eq!(entry.lines[2].amount, 10_210);
+
+    // Paid privately: there is no payment line to withhold from.
+    assert!(matches!(
+        EntryBuilder::new(date(2024, 1, 10))
+            .debit("524", 110_000)
+            .counterparty(writer)
+            .credit("291", 110_000)
+            .insert(&mut connection),
+        Err(Error::Validation(_))
+    ));
+}
+
+/// Two payments to one writer, one small payment to another and the
+/// January tax paid to the tax office.
+fn sample_year(connection: &mut SqliteConnection) -> (i32, i32) {
+    let writer = counterparty(connection, "山田 花子", Withholding::Payments);
+    let designer = counterparty(connection, "佐藤 一郎", Withholding::Payments);
+    for (day, amount, payee) in [
+        (date(2024, 1, 10), 110_000, writer),
+        (date(2024, 2, 10), 1_210_000, writer),
+        (date(2024, 3, 5), 33_000, designer),
+    ] {
+        pay(day, amount, payee).insert(connection).unwrap();
+    }
+    EntryBuilder::new(date(2024, 2, 9))
+        .debit("214", 11_231)
+        .credit("111", 11_231)
+        .insert(connection)
+        .unwrap();
+    (writer, designer)
+}
+
+#[test]
+fn test_withholding_ledger() {
+    let mut connection = test_util::connection();
+    let (writer, _) = sample_year(&mut connection);
+
+    let ledger = WithholdingLedger::generate(&mut connection, year(2024)).unwrap();
+    let rows = ledger
+        .rows
+        .iter()
+        .map(|row| (row.payment, row.withheld, row.remitted, row.balance))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            (110_000, 11_231, 0, 11_231),
+            (0, 0, 11_231, 0),
+            (1_210_000, 144_982, 0, 144_982),
+            (33_000, 3_369, 0, 148_351),
+        ]
+    );
+    assert_eq!(ledger.rows[0].counterparty_id, Some(writer));
+    assert_eq!(
+        ledger.rows[0].counterparty_name.as_deref(),
+        Some("山田 花子")
+    );
+    assert_eq!(ledger.opening_balance, 0);
+    assert_eq!(
+        (ledger.total_withheld, ledger.total_remitted),
+        (159_582, 11_231)
+    );
+    assert_eq!(ledger.closing_balance, 148_351);
+    assert!(ledger.to_table().contains("前期繰越"));
+
+    // The next year starts from what is still held.
+    let next = WithholdingLedger::generate(&mut connection, year(2025)).unwrap();
+    assert_eq!(next.opening_balance, 148_351);
+    assert!(next.rows.is_empty());
+}
+
+#[test]
+fn test_payment_records() {
+    let mut connection = test_util::connection();
+    let (writer, designer) = sample_year(&mut connection);
+
+    let summary = PaymentRecordSummary::generate(&mut connection, year(2024)).unwrap();
+    let record = |id: i32| {
+        summary
+            .records
+            .iter()
+            .find(|record| record.counterparty_id == id)
+            .unwrap()
+    };
+    assert_eq!(summary.records.len(), 2);
+    assert_eq!(
+        (
+            record(writer).payments,
+            record(writer).amount,
+            record(writer).withheld
+        ),
+        (2, 1_320_000, 156_213)
+    );
+    assert!(record(writer).filing_required);
+    assert_eq!(
+        (record(designer).amount, record(designer).withheld),
+        (33_000, 3_369)
+    );
+    assert!(!record(designer).filing_required);
+    assert_eq!(
+        (summary.total_amount, summary.total_withheld),
+        (1_353_000, 159_582)
+    );
+
+    let other_year = PaymentRecordSummary::generate(&mut connection, year(2023)).unwrap();
+    assert!(other_year.records.is_empty());
+}
+
+#[tokio::test]
+async fn test_http_withholding() {
+    let mut db = TestDb::temp_file();
+    let app = http::router(db.pool());
+
+    let new_counterparty = json!({ "name": "山田 花子", "withholding": "payments" });
+    let response = send(
+        &app,
+        request("POST", "/counterparties", Some(new_counterparty)),
+    )
+    .await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    let created = response.json();
+    assert_eq!(created["withholding"], "payments");
+    let writer = created["id"].as_i64().unwrap() as i32;
+    pay(date(2024, 1, 10), 110_000, writer)
+        .insert(db.conn())
+        .unwrap();
+
+    for (path, field, expected) in [
+        ("/reports/withholding", "closing_balance", 11_231),
+        ("/reports/payment-records", "total_amount", 110_000),
+    ] {
+        let uri = format!("{}?from=2024-01-01&to=2024-12-31", path);
+        let response = send(&app, request("GET", &uri, None)).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.json()[field], expected, "{}", path);
+    }
+}